@@ -9,7 +9,7 @@ use serde::{Deserialize, Serialize};
 use crate::config::AddCli;
 use crate::git::ViewKind;
 use crate::prompt_code::{LineSnippetResolver, language_for_path};
-use crate::review::{LineKey, LineSide, PromptSnippet, Review};
+use crate::review::{LineKey, LineSide, PromptSnippet, Reply, Review, Suggestion};
 
 const DRAFT_DIR: &str = "remark";
 const DRAFT_FILENAME: &str = "draft.md";
@@ -38,19 +38,23 @@ pub fn run(
     side = Some(LineSide::New);
   }
 
+  crate::reanchor::reanchor_notes(repo, notes_ref, base_ref.as_deref())?;
   let existing = load_file_review(repo, notes_ref, &file)?;
+  let key = (!file_comment).then(|| LineKey {
+    side: side.unwrap_or(LineSide::New),
+    line: cmd.line.map_or(1, |span| span.start),
+  });
+  // Replies start from a blank editor; edits start from the current body.
   let initial = existing
     .as_ref()
+    .filter(|_| !cmd.reply)
     .and_then(|f| {
       if file_comment {
-        f.file_comment.as_ref().map(|c| c.body.clone())
+        f.file_comment.as_ref().map(crate::review::body_with_tags)
       } else {
-        f.comments
-          .get(&crate::review::LineKey {
-            side: side.unwrap_or(LineSide::New),
-            line: cmd.line.unwrap_or(1),
-          })
-          .map(|c| c.body.clone())
+        key
+          .and_then(|key| f.comments.get(&key))
+          .map(crate::review::body_with_suggestion)
       }
     })
     .unwrap_or_default();
@@ -62,6 +66,31 @@ pub fn run(
   } else {
     anyhow::bail!("missing comment body (use --message or --edit)");
   };
+  if cmd.reply {
+    return append_reply(repo, notes_ref, base_ref.as_deref(), &file, key, body);
+  }
+  let author = crate::git::author_identity(repo);
+
+  let (body, fenced) = crate::review::take_suggestion(&body);
+  let (body, mut tags) = crate::review::take_tags(&body);
+  if cmd.severity.is_some() {
+    tags.severity = cmd.severity;
+  }
+  for label in &cmd.label {
+    let label = label.trim_start_matches('#');
+    if !label.is_empty() && !tags.labels.iter().any(|l| l == label) {
+      tags.labels.push(label.to_string());
+    }
+  }
+  // Without an editor or explicit tags, a new message keeps the comment's existing tags.
+  let tags = (cmd.edit || !tags.is_empty()).then_some(tags);
+  let replacement = cmd
+    .suggestion
+    .map(|text| text.lines().map(str::to_string).collect())
+    .or(fenced);
+  if file_comment && replacement.is_some() {
+    anyhow::bail!("suggestions need --line");
+  }
 
   let mut review = Review::new();
   if let Some(file_review) = existing {
@@ -71,48 +100,123 @@ pub fn run(
   if file_comment {
     review.set_file_comment(&file, body);
   } else {
-    let line = cmd.line.context("missing --line <n>")?;
+    let span = cmd.line.context("missing --line <n>")?;
+    let line = span.start;
     let side = side.context("missing --side <old|new>")?;
     review.set_line_comment(&file, side, line, body);
-    if let Some(hash) =
-      current_snippet_hash(repo, base_ref.as_deref(), &file, LineKey { side, line })
-    {
+    review.set_line_comment_end(&file, side, line, Some(span.end));
+    let suggestion = match replacement {
+      Some(_) if side == LineSide::Old => {
+        anyhow::bail!("suggestions apply to new-side lines only")
+      }
+      Some(replacement) => Some(crate::apply_suggestion_cmd::new_suggestion(
+        repo,
+        &file,
+        line,
+        span.end,
+        replacement,
+      )?),
+      None => None,
+    };
+    if let Some(comment) = review.files.get_mut(&file).and_then(|f| f.comment_mut(key)) {
+      comment.suggestion = suggestion;
+    }
+    if let Some(hash) = current_snippet_hash(
+      repo,
+      base_ref.as_deref(),
+      &file,
+      LineKey { side, line },
+      span.end,
+    ) {
       review.set_line_comment_snippet_hash(&file, side, line, Some(hash));
     }
   }
 
+  if let Some(comment) = review.files.get_mut(&file).and_then(|f| f.comment_mut(key)) {
+    comment.stamp_author(&author);
+    if let Some(tags) = tags {
+      tags.apply(comment);
+    }
+  }
+
   persist_file_review(repo, notes_ref, &file, review.files.get(&file))?;
   sync_draft_notes(repo, notes_ref, base_ref.as_deref())?;
   Ok(())
 }
 
-fn load_file_review(
+/// Adds `body` to the thread of the comment at `key` (the file comment when `None`).
+pub(crate) fn append_reply(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<&str>,
+  file: &str,
+  key: Option<LineKey>,
+  body: String,
+) -> Result<()> {
+  if body.trim().is_empty() {
+    anyhow::bail!("reply body is empty");
+  }
+  let mut file_review = load_file_review(repo, notes_ref, file)?.unwrap_or_default();
+  let comment = file_review.comment_mut(key).with_context(|| match key {
+    None => format!("no file comment on {file} to reply to"),
+    Some(key) => format!("no comment on {file}:{} to reply to", key.line),
+  })?;
+  comment.push_reply(crate::git::author_identity(repo), body);
+  persist_file_review(repo, notes_ref, file, Some(&file_review))?;
+  sync_draft_notes(repo, notes_ref, base_ref)?;
+  Ok(())
+}
+
+pub(crate) fn load_file_review(
   repo: &gix::Repository,
   notes_ref: &str,
   path: &str,
 ) -> Result<Option<crate::review::FileReview>> {
-  let head = crate::git::head_commit_oid(repo)?;
-  let oid = crate::git::note_file_key_oid(repo, head, ViewKind::All, None, path)?;
+  let oid = file_note_key(repo, path)?;
   let note = crate::notes::read(repo, notes_ref, &oid)?;
   Ok(note.as_deref().and_then(crate::review::decode_file_note))
 }
 
-fn persist_file_review(
+/// [`load_file_review`] as `tx` will leave it.
+fn read_file_review(
+  tx: &crate::notes::Transaction<'_>,
+  repo: &gix::Repository,
+  path: &str,
+) -> Result<Option<crate::review::FileReview>> {
+  let oid = file_note_key(repo, path)?;
+  let note = tx.read(&oid)?;
+  Ok(note.as_deref().and_then(crate::review::decode_file_note))
+}
+
+fn file_note_key(repo: &gix::Repository, path: &str) -> Result<gix::ObjectId> {
+  let head = crate::git::head_commit_oid(repo)?;
+  crate::git::note_file_key_oid(repo, head, ViewKind::All, None, path)
+}
+
+pub(crate) fn persist_file_review(
   repo: &gix::Repository,
   notes_ref: &str,
   path: &str,
   file: Option<&crate::review::FileReview>,
 ) -> Result<()> {
-  let head = crate::git::head_commit_oid(repo)?;
-  let oid = crate::git::note_file_key_oid(repo, head, ViewKind::All, None, path)?;
+  let mut tx = crate::notes::Transaction::new(repo, notes_ref)?;
+  stage_file_review(&mut tx, repo, path, file)?;
+  tx.commit()?;
+  Ok(())
+}
+
+fn stage_file_review(
+  tx: &mut crate::notes::Transaction<'_>,
+  repo: &gix::Repository,
+  path: &str,
+  file: Option<&crate::review::FileReview>,
+) -> Result<()> {
+  let oid = file_note_key(repo, path)?;
   match file {
-    Some(file) if !file.comments.is_empty() || file.file_comment.is_some() || file.reviewed => {
-      let note = crate::review::encode_file_note(file);
-      crate::notes::write(repo, notes_ref, &oid, Some(&note))?;
-    }
-    _ => {
-      crate::notes::write(repo, notes_ref, &oid, None)?;
+    Some(file) if !file.is_empty() => {
+      tx.upsert(&oid, &crate::review::encode_file_note(file));
     }
+    _ => tx.remove(&oid),
   }
   Ok(())
 }
@@ -155,6 +259,12 @@ struct DraftReview {
 struct DraftFileReview {
   file_comment: Option<String>,
   comments: BTreeMap<LineKey, String>,
+  /// Last line of range comments, keyed like `comments`.
+  ranges: BTreeMap<LineKey, u32>,
+  suggestions: BTreeMap<LineKey, Suggestion>,
+  // Thread replies are rendered for reading only; the notes stay authoritative for them.
+  file_replies: Vec<Reply>,
+  replies: BTreeMap<LineKey, Vec<Reply>>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -201,6 +311,7 @@ impl DraftReview {
       return false;
     };
     let removed = f.file_comment.take().is_some();
+    f.file_replies.clear();
     if f.file_comment.is_none() && f.comments.is_empty() {
       self.files.remove(path);
     }
@@ -211,6 +322,9 @@ impl DraftReview {
     let Some(f) = self.files.get_mut(path) else {
       return false;
     };
+    f.replies.remove(&LineKey { side, line });
+    f.ranges.remove(&LineKey { side, line });
+    f.suggestions.remove(&LineKey { side, line });
     let removed = f.comments.remove(&LineKey { side, line }).is_some();
     if f.file_comment.is_none() && f.comments.is_empty() {
       self.files.remove(path);
@@ -304,7 +418,7 @@ pub(crate) fn write_draft_placeholder(
       .or_else(|| {
         existing
           .as_ref()
-          .and_then(|f| f.file_comment.as_ref().map(|c| c.body.clone()))
+          .and_then(|f| f.file_comment.as_ref().map(crate::review::body_with_tags))
       })
       .unwrap_or_default()
   } else {
@@ -317,7 +431,7 @@ pub(crate) fn write_draft_placeholder(
         existing.as_ref().and_then(|f| {
           f.comments
             .get(&LineKey { side, line })
-            .map(|c| c.body.clone())
+            .map(crate::review::body_with_tags)
         })
       })
       .unwrap_or_default()
@@ -413,6 +527,56 @@ fn sync_draft_notes_with_mode(
   notes_ref: &str,
   base_ref: Option<&str>,
 ) -> Result<SyncReport> {
+  crate::reanchor::reanchor_notes(repo, notes_ref, base_ref)?;
+  let mut tx = crate::notes::Transaction::new(repo, notes_ref)?;
+  let sync = stage_draft_sync(&mut tx, repo, notes_ref, base_ref)?;
+  tx.commit()?;
+  sync.finish(repo, notes_ref, base_ref)
+}
+
+/// The draft side of a sync whose notes changes are staged in a transaction; written out by
+/// [`StagedSync::finish`] once that transaction is committed.
+pub(crate) struct StagedSync {
+  draft_path: std::path::PathBuf,
+  draft_review: DraftReview,
+  draft_updated: bool,
+  preferred_hashes: HashMap<String, String>,
+}
+
+impl StagedSync {
+  pub(crate) fn finish(
+    self,
+    repo: &gix::Repository,
+    notes_ref: &str,
+    base_ref: Option<&str>,
+  ) -> Result<SyncReport> {
+    if self.draft_updated {
+      let draft = render_prompt_draft(repo, base_ref, &self.draft_review);
+      std::fs::write(&self.draft_path, draft).context("write draft file")?;
+    }
+
+    rebuild_draft_meta(
+      repo,
+      notes_ref,
+      base_ref,
+      &self.draft_review,
+      Some(&self.preferred_hashes),
+    )?;
+
+    Ok(SyncReport {
+      draft_updated: self.draft_updated,
+    })
+  }
+}
+
+/// Syncs the draft with the notes as `tx` sees them, staging the notes side into `tx`. Notes
+/// under earlier HEADs are expected to be re-anchored already.
+pub(crate) fn stage_draft_sync(
+  tx: &mut crate::notes::Transaction<'_>,
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<&str>,
+) -> Result<StagedSync> {
   ensure_draft_exists(repo, notes_ref, base_ref)?;
 
   let draft_path = draft_path(repo)?;
@@ -441,10 +605,7 @@ fn sync_draft_notes_with_mode(
     None
   };
 
-  let diff_context = prompt_diff_context(repo);
-  let base_tree = base_ref.and_then(|b| crate::git::merge_base_tree(repo, b).ok());
-  let view_order = prompt_view_order(base_ref.is_some());
-  let mut resolver = LineSnippetResolver::new(repo, base_tree, diff_context, view_order);
+  let mut resolver = snippet_resolver(repo, base_ref);
   let mut preferred_hashes = HashMap::new();
 
   let mut paths = BTreeSet::new();
@@ -454,13 +615,20 @@ fn sync_draft_notes_with_mode(
   paths.extend(draft_review.files.keys().cloned());
 
   let mut draft_updated = false;
+  let author = crate::git::author_identity(repo);
   for path in paths {
-    let mut notes_file = load_file_review(repo, notes_ref, &path)?.unwrap_or_default();
+    let mut notes_file = read_file_review(tx, repo, &path)?.unwrap_or_default();
+    let mut draft_file = draft_review.files.get(&path).cloned().unwrap_or_default();
+    let (mut notes_dirty, relocated_draft) =
+      relocate_comments(&mut resolver, &path, &mut notes_file, &mut draft_file);
+    draft_updated |= relocated_draft;
+
     let mut notes_unresolved = notes_file.clone();
     prune_resolved(&mut notes_unresolved);
-    let mut notes_dirty = false;
-
-    let mut draft_file = draft_review.files.get(&path).cloned().unwrap_or_default();
+    // Outdated comments stay in the notes but have no line to render in the draft.
+    notes_unresolved
+      .comments
+      .retain(|_, comment| !comment.outdated);
 
     // File comment sync (change-biased).
     match (
@@ -468,7 +636,7 @@ fn sync_draft_notes_with_mode(
       notes_unresolved
         .file_comment
         .as_ref()
-        .map(|c| c.body.clone()),
+        .map(crate::review::body_with_tags),
     ) {
       (Some(draft_body), Some(notes_body)) => {
         if draft_body != notes_body {
@@ -476,11 +644,12 @@ fn sync_draft_notes_with_mode(
             .as_ref()
             .map_or(draft_newer, |b| matches!(b, SyncAction::NotesFromDraft));
           if prefer_draft {
-            notes_file.file_comment = Some(crate::review::Comment {
-              body: draft_body,
-              resolved: false,
-              snippet_hash: None,
-            });
+            let comment = notes_file.file_comment.get_or_insert_default();
+            let (body, tags) = crate::review::take_tags(&draft_body);
+            comment.body = body;
+            tags.apply(comment);
+            comment.unresolve();
+            comment.stamp_author(&author);
             notes_dirty = true;
           } else {
             draft_file.file_comment = Some(notes_body);
@@ -493,11 +662,13 @@ fn sync_draft_notes_with_mode(
           .as_ref()
           .map_or(draft_newer, |b| matches!(b, SyncAction::NotesFromDraft));
         if prefer_draft {
-          notes_file.file_comment = Some(crate::review::Comment {
-            body: draft_body,
-            resolved: false,
-            snippet_hash: None,
-          });
+          // A resolved file comment may still be in the notes; reopen it with its thread.
+          let comment = notes_file.file_comment.get_or_insert_default();
+          let (body, tags) = crate::review::take_tags(&draft_body);
+          comment.body = body;
+          tags.apply(comment);
+          comment.unresolve();
+          comment.stamp_author(&author);
           notes_dirty = true;
         } else {
           draft_file.file_comment = None;
@@ -525,8 +696,12 @@ fn sync_draft_notes_with_mode(
 
     for key in keys {
       let draft_body = draft_file.comments.get(&key).cloned();
+      let draft_end = draft_file.ranges.get(&key).copied();
+      let draft_suggestion = draft_file.suggestions.get(&key).cloned();
       let notes_comment = notes_unresolved.comments.get(&key);
-      let notes_body = notes_comment.map(|c| c.body.clone());
+      let notes_body = notes_comment.map(crate::review::body_with_tags);
+      let notes_end = notes_comment.and_then(|c| c.end_line);
+      let notes_suggestion = notes_comment.and_then(|c| c.suggestion.clone());
       let notes_hash = notes_comment.and_then(|c| c.snippet_hash.clone());
 
       if draft_body.is_none()
@@ -543,27 +718,35 @@ fn sync_draft_notes_with_mode(
 
       let meta_key = meta_key(&path, side_label(key.side), key.line);
       let draft_hash = meta_map.get(&meta_key).cloned();
-      let current_hash = resolver
-        .snippet(&path, key)
-        .and_then(|snippet| snippet_hash(&path, key, &snippet).ok());
+      let last_line = draft_end
+        .or(notes_end)
+        .filter(|end| *end > key.line)
+        .unwrap_or(key.line);
+      let current_hash = range_snippet_hash(&mut resolver, &path, key, last_line);
       let snippet_present = current_hash.is_some();
       let stored_hash = notes_hash.clone().or(draft_hash.clone());
+      // Anchored comments were already checked by content when relocating them.
+      let anchored = notes_comment.is_some_and(|c| c.anchor.is_some());
       let invalid = if snippet_present {
-        stored_hash
-          .as_ref()
-          .is_some_and(|stored| Some(stored.as_str()) != current_hash.as_deref())
+        !anchored
+          && stored_hash
+            .as_ref()
+            .is_some_and(|stored| Some(stored.as_str()) != current_hash.as_deref())
       } else {
         draft_body.is_some() || notes_body.is_some()
       };
       if invalid {
+        // The code moved on under the comment: keep it as history rather than dropping it.
         if notes_body.is_some()
           && let Some(comment) = notes_file.comments.get_mut(&key)
         {
-          comment.resolved = true;
+          comment.outdated = true;
           notes_dirty = true;
         }
         if draft_body.is_some() {
           draft_file.comments.remove(&key);
+          draft_file.ranges.remove(&key);
+          draft_file.suggestions.remove(&key);
           draft_updated = true;
         }
         continue;
@@ -591,7 +774,10 @@ fn sync_draft_notes_with_mode(
       } else if notes_valid && !draft_valid {
         SyncAction::DraftFromNotes
       } else if draft_valid && notes_valid {
-        if draft_body == notes_body {
+        if draft_body == notes_body
+          && draft_end == notes_end
+          && draft_suggestion == notes_suggestion
+        {
           SyncAction::None
         } else if let Some(bias) = change_bias {
           bias
@@ -612,14 +798,19 @@ fn sync_draft_notes_with_mode(
         SyncAction::NotesFromDraft => {
           if let Some(body) = draft_body {
             let hash = draft_hash.or(current_hash.clone());
-            notes_file.comments.insert(
-              key,
-              crate::review::Comment {
-                body,
-                resolved: false,
-                snippet_hash: hash,
-              },
-            );
+            let comment = notes_file.comments.entry(key).or_default();
+            if comment.outdated {
+              // The outdated comment parked here belongs to other code; this is a new thread.
+              *comment = crate::review::Comment::default();
+            }
+            let (body, tags) = crate::review::take_tags(&body);
+            comment.body = body;
+            tags.apply(comment);
+            comment.end_line = draft_end;
+            comment.suggestion = draft_suggestion;
+            comment.unresolve();
+            comment.snippet_hash = hash;
+            comment.stamp_author(&author);
             notes_dirty = true;
           } else if notes_body.is_some() {
             notes_file.comments.remove(&key);
@@ -629,9 +820,19 @@ fn sync_draft_notes_with_mode(
         SyncAction::DraftFromNotes => {
           if let Some(body) = notes_body {
             draft_file.comments.insert(key, body);
+            match notes_end {
+              Some(end) => draft_file.ranges.insert(key, end),
+              None => draft_file.ranges.remove(&key),
+            };
+            match notes_suggestion {
+              Some(suggestion) => draft_file.suggestions.insert(key, suggestion),
+              None => draft_file.suggestions.remove(&key),
+            };
             draft_updated = true;
           } else if draft_body.is_some() {
             draft_file.comments.remove(&key);
+            draft_file.ranges.remove(&key);
+            draft_file.suggestions.remove(&key);
             draft_updated = true;
           }
         }
@@ -639,6 +840,32 @@ fn sync_draft_notes_with_mode(
       }
     }
 
+    // Replies are only ever added to the notes, so mirror them into the draft as-is.
+    let file_replies = match &draft_file.file_comment {
+      Some(_) => notes_file
+        .file_comment
+        .as_ref()
+        .map(|c| c.replies.clone())
+        .unwrap_or_default(),
+      None => Vec::new(),
+    };
+    if draft_file.file_replies != file_replies {
+      draft_file.file_replies = file_replies;
+      draft_updated = true;
+    }
+    let line_replies: BTreeMap<LineKey, Vec<Reply>> = draft_file
+      .comments
+      .keys()
+      .filter_map(|key| {
+        let replies = &notes_file.comments.get(key)?.replies;
+        (!replies.is_empty()).then(|| (*key, replies.clone()))
+      })
+      .collect();
+    if draft_file.replies != line_replies {
+      draft_file.replies = line_replies;
+      draft_updated = true;
+    }
+
     if draft_file.file_comment.is_none() && draft_file.comments.is_empty() {
       if draft_review.files.remove(&path).is_some() {
         draft_updated = true;
@@ -659,25 +886,18 @@ fn sync_draft_notes_with_mode(
       }
     }
 
+    notes_dirty |= anchor_new_comments(&mut resolver, &path, &mut notes_file);
     if notes_dirty {
-      persist_file_review(repo, notes_ref, &path, Some(&notes_file))?;
+      stage_file_review(tx, repo, &path, Some(&notes_file))?;
     }
   }
 
-  if draft_updated {
-    let draft = render_prompt_draft(repo, base_ref, &draft_review);
-    std::fs::write(&draft_path, draft).context("write draft file")?;
-  }
-
-  rebuild_draft_meta(
-    repo,
-    notes_ref,
-    base_ref,
-    &draft_review,
-    Some(&preferred_hashes),
-  )?;
-
-  Ok(SyncReport { draft_updated })
+  Ok(StagedSync {
+    draft_path,
+    draft_review,
+    draft_updated,
+    preferred_hashes,
+  })
 }
 
 fn load_draft_meta(repo: &gix::Repository) -> Result<DraftMeta> {
@@ -726,10 +946,7 @@ fn rebuild_draft_meta(
     .and_then(|content| draft_content_hash(&content).ok());
   meta.draft_hash = draft_hash;
 
-  let diff_context = prompt_diff_context(repo);
-  let base_tree = base_ref.and_then(|b| crate::git::merge_base_tree(repo, b).ok());
-  let view_order = prompt_view_order(base_ref.is_some());
-  let mut resolver = LineSnippetResolver::new(repo, base_tree, diff_context, view_order);
+  let mut resolver = snippet_resolver(repo, base_ref);
 
   for (path, file) in &draft_review.files {
     for key in file.comments.keys() {
@@ -739,9 +956,8 @@ fn rebuild_draft_meta(
         .cloned()
         .or_else(|| preferred_hashes.and_then(|h| h.get(&meta_key).cloned()))
         .or_else(|| {
-          resolver
-            .snippet(path, *key)
-            .and_then(|snippet| snippet_hash(path, *key, &snippet).ok())
+          let last_line = file.ranges.get(key).copied().unwrap_or(key.line);
+          range_snippet_hash(&mut resolver, path, *key, last_line)
         });
       if let Some(hash) = hash {
         meta.lines.push(DraftLineMeta {
@@ -808,7 +1024,7 @@ fn draft_content_hash(content: &str) -> Result<String> {
   Ok(oid.to_string())
 }
 
-fn list_tracked_paths(repo: &gix::Repository) -> Result<Vec<String>> {
+pub(crate) fn list_tracked_paths(repo: &gix::Repository) -> Result<Vec<String>> {
   let index = repo.index_or_empty().context("open index")?;
   let mut options = repo.dirwalk_options().context("init dirwalk options")?;
   options.set_emit_tracked(true);
@@ -872,17 +1088,162 @@ fn snippet_hash(path: &str, key: LineKey, snippet: &str) -> Result<String> {
   Ok(oid.to_string())
 }
 
+/// Moves anchored comments in the notes to wherever their code is now, and mirrors the moves
+/// into the draft. Returns whether the notes and the draft changed.
+fn relocate_comments(
+  resolver: &mut LineSnippetResolver<'_>,
+  path: &str,
+  notes_file: &mut crate::review::FileReview,
+  draft_file: &mut DraftFileReview,
+) -> (bool, bool) {
+  if !notes_file.comments.values().any(|c| c.anchor.is_some()) {
+    return (false, false);
+  }
+  let mut notes_changed = false;
+  let mut draft_changed = false;
+  let mut unedited = Vec::new();
+  for side in [LineSide::Old, LineSide::New] {
+    let Some(lines) = resolver.lines(path, side) else {
+      continue;
+    };
+    let relocated = notes_file.relocate(side, lines);
+    if relocated.is_empty() {
+      continue;
+    }
+    notes_changed = true;
+    unedited.extend(
+      relocated
+        .moved
+        .iter()
+        .map(|(_, to)| *to)
+        .filter(|to| !relocated.edited.contains(to))
+        .chain(relocated.revived.iter().copied()),
+    );
+
+    for key in &relocated.outdated {
+      if draft_file.comments.remove(key).is_some() {
+        draft_file.ranges.remove(key);
+        draft_file.suggestions.remove(key);
+        draft_file.replies.remove(key);
+        draft_changed = true;
+      }
+    }
+    // Take every moved entry out before reinserting, so swapped comments don't clobber each other.
+    let taken: Vec<_> = relocated
+      .moved
+      .iter()
+      .filter(|(from, to)| from != to)
+      .filter_map(|(from, to)| {
+        let body = draft_file.comments.remove(from)?;
+        draft_file.ranges.remove(from);
+        draft_file.replies.remove(from);
+        Some((*to, body, draft_file.suggestions.remove(from)))
+      })
+      .collect();
+    for (to, body, suggestion) in taken {
+      draft_file.comments.insert(to, body);
+      if let Some(end) = notes_file.comments.get(&to).and_then(|c| c.end_line) {
+        draft_file.ranges.insert(to, end);
+      }
+      if let Some(suggestion) = suggestion {
+        draft_file.suggestions.insert(to, suggestion);
+      }
+      draft_changed = true;
+    }
+    for key in &relocated.revived {
+      if draft_file.comments.contains_key(key) {
+        continue;
+      }
+      let Some(comment) = notes_file.comments.get(key).filter(|c| !c.resolved) else {
+        continue;
+      };
+      draft_file
+        .comments
+        .insert(*key, crate::review::body_with_tags(comment));
+      if let Some(end) = comment.end_line {
+        draft_file.ranges.insert(*key, end);
+      }
+      if let Some(suggestion) = &comment.suggestion {
+        draft_file.suggestions.insert(*key, suggestion.clone());
+      }
+      draft_changed = true;
+    }
+  }
+
+  // The snippet hash follows comments whose code only moved. After an edit it keeps describing the
+  // code as it read when the comment was written, which is how `remark autoresolve` spots fixes.
+  for key in unedited {
+    let Some(comment) = notes_file.comments.get_mut(&key) else {
+      continue;
+    };
+    let hash = range_snippet_hash(resolver, path, key, comment.last_line(key));
+    if hash.is_some() && comment.snippet_hash != hash {
+      comment.snippet_hash = hash;
+      notes_changed = true;
+    }
+  }
+  (notes_changed, draft_changed)
+}
+
+/// Anchors open comments that don't have an anchor yet to the current code. Returns whether any
+/// comment changed.
+fn anchor_new_comments(
+  resolver: &mut LineSnippetResolver<'_>,
+  path: &str,
+  notes_file: &mut crate::review::FileReview,
+) -> bool {
+  let mut changed = false;
+  for side in [LineSide::Old, LineSide::New] {
+    let unanchored = |key: &LineKey, c: &crate::review::Comment| {
+      key.side == side && c.anchor.is_none() && !c.outdated && !c.resolved
+    };
+    if !notes_file.comments.iter().any(|(k, c)| unanchored(k, c)) {
+      continue;
+    }
+    let Some(lines) = resolver.lines(path, side) else {
+      continue;
+    };
+    for (key, comment) in notes_file.comments.iter_mut() {
+      if unanchored(key, &*comment) {
+        comment.anchor = crate::review::Anchor::capture(lines, key.line, comment.last_line(*key));
+        changed |= comment.anchor.is_some();
+      }
+    }
+  }
+  changed
+}
+
+/// Hash of the code a comment at `key` covers through `last_line`, as it reads now.
 pub(crate) fn current_snippet_hash(
   repo: &gix::Repository,
   base_ref: Option<&str>,
   path: &str,
   key: LineKey,
+  last_line: u32,
 ) -> Option<String> {
+  range_snippet_hash(&mut snippet_resolver(repo, base_ref), path, key, last_line)
+}
+
+/// Resolves current snippets for [`range_snippet_hash`]; build one for many lookups.
+pub(crate) fn snippet_resolver<'repo>(
+  repo: &'repo gix::Repository,
+  base_ref: Option<&str>,
+) -> LineSnippetResolver<'repo> {
   let diff_context = prompt_diff_context(repo);
   let base_tree = base_ref.and_then(|b| crate::git::merge_base_tree(repo, b).ok());
   let view_order = prompt_view_order(base_ref.is_some());
-  let mut resolver = LineSnippetResolver::new(repo, base_tree, diff_context, view_order);
-  let snippet = resolver.snippet(path, key)?;
+  LineSnippetResolver::new(repo, base_tree, diff_context, view_order)
+}
+
+/// Hash of `key.line..=last_line` plus context, which is what a comment's `snippet_hash`
+/// records.
+pub(crate) fn range_snippet_hash(
+  resolver: &mut LineSnippetResolver<'_>,
+  path: &str,
+  key: LineKey,
+  last_line: u32,
+) -> Option<String> {
+  let snippet = resolver.snippet_range(path, key, last_line)?;
   snippet_hash(path, key, &snippet).ok()
 }
 
@@ -973,16 +1334,15 @@ fn render_prompt_draft(
   base_ref: Option<&str>,
   review: &DraftReview,
 ) -> String {
-  let diff_context = prompt_diff_context(repo);
-  let base_tree = base_ref.and_then(|b| crate::git::merge_base_tree(repo, b).ok());
-  let view_order = prompt_view_order(base_ref.is_some());
-  let mut resolver = LineSnippetResolver::new(repo, base_tree, diff_context, view_order);
+  let mut resolver = snippet_resolver(repo, base_ref);
   let review = draft_to_review(review);
-  crate::review::render_prompt(&review, |path, key| {
-    resolver.snippet(path, key).map(|code| PromptSnippet {
-      code,
-      lang: language_for_path(path),
-    })
+  crate::review::render_prompt(&review, |path, key, last| {
+    resolver
+      .snippet_range(path, key, last)
+      .map(|code| PromptSnippet {
+        code,
+        lang: language_for_path(path),
+      })
   })
 }
 
@@ -990,10 +1350,44 @@ fn draft_to_review(draft: &DraftReview) -> Review {
   let mut review = Review::new();
   for (path, file) in &draft.files {
     if let Some(comment) = file.file_comment.as_ref() {
-      review.set_file_comment(path, comment.clone());
+      let (body, tags) = crate::review::take_tags(comment);
+      review.set_file_comment(path, body);
+      if let Some(comment) = review.files.get_mut(path).and_then(|f| f.comment_mut(None)) {
+        tags.apply(comment);
+      }
     }
     for (line_key, comment) in &file.comments {
-      review.set_line_comment(path, line_key.side, line_key.line, comment.clone());
+      let (body, tags) = crate::review::take_tags(comment);
+      review.set_line_comment(path, line_key.side, line_key.line, body);
+      if let Some(comment) = review
+        .files
+        .get_mut(path)
+        .and_then(|f| f.comment_mut(Some(*line_key)))
+      {
+        tags.apply(comment);
+      }
+      review.set_line_comment_end(
+        path,
+        line_key.side,
+        line_key.line,
+        file.ranges.get(line_key).copied(),
+      );
+    }
+    let Some(review_file) = review.files.get_mut(path) else {
+      continue;
+    };
+    if let Some(comment) = review_file.file_comment.as_mut() {
+      comment.replies = file.file_replies.clone();
+    }
+    for (line_key, suggestion) in &file.suggestions {
+      if let Some(comment) = review_file.comments.get_mut(line_key) {
+        comment.suggestion = Some(suggestion.clone());
+      }
+    }
+    for (line_key, replies) in &file.replies {
+      if let Some(comment) = review_file.comments.get_mut(line_key) {
+        comment.replies = replies.clone();
+      }
     }
   }
   review
@@ -1005,21 +1399,46 @@ fn review_to_draft(review: &Review) -> DraftReview {
     if let Some(comment) = file
       .file_comment
       .as_ref()
-      .filter(|c| !c.resolved)
-      .map(|c| c.body.trim_end())
-      .filter(|body| !body.is_empty())
+      .filter(|c| !c.resolved && !c.body.trim().is_empty())
+      .map(crate::review::body_with_tags)
     {
-      draft.set_file_comment(path, comment.to_string());
+      draft.set_file_comment(path, comment);
+      if let Some(f) = draft.files.get_mut(path) {
+        f.file_replies = file
+          .file_comment
+          .as_ref()
+          .map(|c| c.replies.clone())
+          .unwrap_or_default();
+      }
     }
     for (key, comment) in &file.comments {
-      if comment.resolved {
+      if comment.resolved || comment.outdated {
         continue;
       }
-      let body = comment.body.trim_end();
-      if body.is_empty() {
+      if comment.body.trim().is_empty() {
         continue;
       }
-      draft.set_line_comment(path, key.side, key.line, body.to_string());
+      draft.set_line_comment(
+        path,
+        key.side,
+        key.line,
+        crate::review::body_with_tags(comment),
+      );
+      if let Some(end) = comment.end_line
+        && let Some(f) = draft.files.get_mut(path)
+      {
+        f.ranges.insert(*key, end);
+      }
+      if let Some(suggestion) = &comment.suggestion
+        && let Some(f) = draft.files.get_mut(path)
+      {
+        f.suggestions.insert(*key, suggestion.clone());
+      }
+      if !comment.replies.is_empty()
+        && let Some(f) = draft.files.get_mut(path)
+      {
+        f.replies.insert(*key, comment.replies.clone());
+      }
     }
   }
   draft
@@ -1029,28 +1448,59 @@ fn parse_prompt_draft(content: &str) -> Result<DraftReview> {
   let mut review = DraftReview::default();
   let mut current_file: Option<String> = None;
   let mut mode = DraftSection::None;
-  let mut pending_line: Option<(u32, LineSide)> = None;
+  let mut pending_line: Option<(u32, Option<u32>, LineSide)> = None;
   let mut text_fence: Option<String> = None;
   let mut skip_fence: Option<String> = None;
   let mut pending_target: Option<DraftTarget> = None;
+  // The comment the most recent text block belonged to, which any following replies attach to.
+  let mut last_target: Option<DraftTarget> = None;
+  let mut pending_reply: Option<(String, i64)> = None;
+  let mut reply_target: Option<(DraftTarget, String, i64)> = None;
+  let mut pending_suggestion = false;
+  let mut suggestion_target: Option<DraftTarget> = None;
   let mut body = Vec::new();
 
   for line in content.lines() {
     let trimmed = line.trim_start();
     if let Some(fence) = text_fence.as_ref() {
       if trimmed == fence.as_str() {
-        if let Some(target) = pending_target.take() {
-          let text = body.join("\n").trim_end().to_string();
+        let text = body.join("\n").trim_end().to_string();
+        if let Some(target) = suggestion_target.take() {
+          if let DraftTarget::Line(path, line, _, side) = target
+            && let Some(f) = review.files.get_mut(&path)
+          {
+            f.suggestions
+              .insert(LineKey { side, line }, Suggestion::from_preview(&text));
+          }
+        } else if let Some((target, author, at)) = reply_target.take() {
+          if !text.trim().is_empty() {
+            review.push_reply(
+              &target,
+              Reply {
+                author,
+                at,
+                body: text,
+              },
+            );
+          }
+        } else if let Some(target) = pending_target.take() {
           let trimmed = text.trim();
           if trimmed.is_empty() || trimmed == DRAFT_PLACEHOLDER {
             body.clear();
             text_fence = None;
+            last_target = None;
             continue;
           }
+          last_target = Some(target.clone());
           match target {
             DraftTarget::File(path) => review.set_file_comment(&path, text),
-            DraftTarget::Line(path, line, side) => {
+            DraftTarget::Line(path, line, end, side) => {
               review.set_line_comment(&path, side, line, text);
+              if let Some(end) = end
+                && let Some(f) = review.files.get_mut(&path)
+              {
+                f.ranges.insert(LineKey { side, line }, end);
+              }
             }
           }
         }
@@ -1073,12 +1523,25 @@ fn parse_prompt_draft(content: &str) -> Result<DraftReview> {
       let ticks = trimmed.chars().take_while(|c| *c == '`').count();
       let fence = "`".repeat(ticks.max(3));
       let lang = rest.trim();
-      if lang.starts_with("text") {
+      if lang == crate::review::REPLY_FENCE_LANG
+        && let (Some(target), Some((author, at))) = (last_target.clone(), pending_reply.take())
+      {
+        reply_target = Some((target, author, at));
+        body.clear();
+        text_fence = Some(fence);
+      } else if lang == "diff"
+        && std::mem::take(&mut pending_suggestion)
+        && let Some(target) = last_target.clone()
+      {
+        suggestion_target = Some(target);
+        body.clear();
+        text_fence = Some(fence);
+      } else if lang.starts_with("text") {
         pending_target = match mode {
           DraftSection::FileComment => current_file.clone().map(DraftTarget::File),
-          DraftSection::LineComments => current_file
-            .clone()
-            .and_then(|path| pending_line.map(|(line, side)| DraftTarget::Line(path, line, side))),
+          DraftSection::LineComments => current_file.clone().and_then(|path| {
+            pending_line.map(|(line, end, side)| DraftTarget::Line(path, line, end, side))
+          }),
           DraftSection::None => None,
         };
         if mode == DraftSection::LineComments {
@@ -1096,19 +1559,32 @@ fn parse_prompt_draft(content: &str) -> Result<DraftReview> {
       current_file = Some(path.trim().to_string());
       mode = DraftSection::None;
       pending_line = None;
+      last_target = None;
       continue;
     }
     if trimmed.starts_with("### File comment") {
       mode = DraftSection::FileComment;
       pending_line = None;
+      last_target = None;
       continue;
     }
     if trimmed.starts_with("### Line comments") {
       mode = DraftSection::LineComments;
+      last_target = None;
+      continue;
+    }
+    if let Some(rest) = trimmed.strip_prefix(crate::review::REPLY_MARKER) {
+      pending_reply = parse_reply_marker(rest);
+      continue;
+    }
+    if trimmed.trim_end() == crate::review::SUGGESTION_MARKER {
+      pending_suggestion = true;
       continue;
     }
     if mode == DraftSection::LineComments
-      && let Some(rest) = trimmed.strip_prefix("- line ")
+      && let Some(rest) = trimmed
+        .strip_prefix("- lines ")
+        .or_else(|| trimmed.strip_prefix("- line "))
     {
       pending_line = parse_line_marker(rest);
     }
@@ -1135,24 +1611,61 @@ enum DraftSection {
   LineComments,
 }
 
+#[derive(Clone)]
 enum DraftTarget {
   File(String),
-  Line(String, u32, LineSide),
+  Line(String, u32, Option<u32>, LineSide),
+}
+
+impl DraftReview {
+  fn push_reply(&mut self, target: &DraftTarget, reply: Reply) {
+    match target {
+      DraftTarget::File(path) => {
+        let f = self.files.entry(path.clone()).or_default();
+        f.file_replies.push(reply);
+      }
+      DraftTarget::Line(path, line, _, side) => {
+        let f = self.files.entry(path.clone()).or_default();
+        f.replies
+          .entry(LineKey {
+            side: *side,
+            line: *line,
+          })
+          .or_default()
+          .push(reply);
+      }
+    }
+  }
+}
+
+/// Parses `<author> at <timestamp>` following the reply marker.
+fn parse_reply_marker(input: &str) -> Option<(String, i64)> {
+  let (author, at) = input.trim_end().rsplit_once(" at ")?;
+  Some((author.to_string(), crate::review::parse_timestamp(at)?))
 }
 
-fn parse_line_marker(input: &str) -> Option<(u32, LineSide)> {
+fn parse_line_marker(input: &str) -> Option<(u32, Option<u32>, LineSide)> {
   let digits: String = input.chars().take_while(|c| c.is_ascii_digit()).collect();
   if digits.is_empty() {
     None
   } else {
     let line = digits.parse().ok()?;
-    let rest = &input[digits.len()..];
+    let mut rest = &input[digits.len()..];
+    let mut end = None;
+    if let Some(after_dash) = rest.strip_prefix('-') {
+      let end_digits: String = after_dash
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+      end = end_digits.parse::<u32>().ok().filter(|end| *end > line);
+      rest = &after_dash[end_digits.len()..];
+    }
     let side = if rest.contains("old") {
       LineSide::Old
     } else {
       LineSide::New
     };
-    Some((line, side))
+    Some((line, end, side))
   }
 }
 
@@ -1261,6 +1774,168 @@ Old note
     assert!(draft.line_comment("src/lib.rs", LineSide::New, 3).is_none());
   }
 
+  #[test]
+  fn parse_prompt_draft_reads_thread_replies() {
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", LineSide::New, 5, "Line note".to_string());
+    review
+      .files
+      .get_mut("src/lib.rs")
+      .and_then(|f| {
+        f.comment_mut(Some(LineKey {
+          side: LineSide::New,
+          line: 5,
+        }))
+      })
+      .expect("comment")
+      .replies
+      .push(Reply {
+        author: "Bob <bob@example.com>".to_string(),
+        at: 1_700_000_000,
+        body: "Agreed".to_string(),
+      });
+
+    let content = crate::review::render_prompt(&review, |_, _, _| None);
+    let draft = parse_prompt_draft(&content).unwrap();
+    assert_eq!(
+      draft.line_comment("src/lib.rs", LineSide::New, 5),
+      Some("Line note")
+    );
+    let file = draft.files.get("src/lib.rs").expect("draft file");
+    let replies = &file.replies[&LineKey {
+      side: LineSide::New,
+      line: 5,
+    }];
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].author, "Bob <bob@example.com>");
+    assert_eq!(replies[0].at, 1_700_000_000);
+    assert_eq!(replies[0].body, "Agreed");
+  }
+
+  #[test]
+  fn parse_prompt_draft_reads_range_comments() {
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", LineSide::Old, 10, "Range note".to_string());
+    review.set_line_comment_end("src/lib.rs", LineSide::Old, 10, Some(24));
+
+    let content = crate::review::render_prompt(&review, |_, _, _| None);
+    assert!(content.contains("- lines 10-24 (old)"));
+    let draft = parse_prompt_draft(&content).unwrap();
+    assert_eq!(
+      draft.line_comment("src/lib.rs", LineSide::Old, 10),
+      Some("Range note")
+    );
+    let key = LineKey {
+      side: LineSide::Old,
+      line: 10,
+    };
+    assert_eq!(draft.files["src/lib.rs"].ranges.get(&key), Some(&24));
+    let roundtrip = draft_to_review(&draft);
+    assert_eq!(
+      roundtrip
+        .line_comment("src/lib.rs", LineSide::Old, 10)
+        .and_then(|c| c.end_line),
+      Some(24)
+    );
+  }
+
+  #[test]
+  fn parse_prompt_draft_reads_suggestions() {
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", LineSide::New, 3, "Inline it".to_string());
+    let suggestion = Suggestion {
+      original: vec!["let y = x;".to_string()],
+      replacement: vec![],
+    };
+    if let Some(comment) = review
+      .files
+      .get_mut("src/lib.rs")
+      .and_then(|f| f.comments.values_mut().next())
+    {
+      comment.suggestion = Some(suggestion.clone());
+    }
+
+    let content = crate::review::render_prompt(&review, |_, _, _| None);
+    assert!(content.contains("- suggested change\n```diff\n-let y = x;\n```"));
+    let draft = parse_prompt_draft(&content).unwrap();
+    let key = LineKey {
+      side: LineSide::New,
+      line: 3,
+    };
+    assert_eq!(
+      draft.files["src/lib.rs"].suggestions.get(&key),
+      Some(&suggestion)
+    );
+    assert_eq!(
+      draft.line_comment("src/lib.rs", LineSide::New, 3),
+      Some("Inline it")
+    );
+  }
+
+  #[test]
+  fn parse_prompt_draft_reads_tags() {
+    let mut review = Review::new();
+    review.set_line_comment(
+      "src/lib.rs",
+      LineSide::New,
+      5,
+      "Leaks the handle".to_string(),
+    );
+    if let Some(comment) = review
+      .files
+      .get_mut("src/lib.rs")
+      .and_then(|f| f.comments.values_mut().next())
+    {
+      comment.severity = Some(crate::review::Severity::Blocker);
+      comment.labels = vec!["io".to_string()];
+    }
+
+    let content = crate::review::render_prompt(&review, |_, _, _| None);
+    assert!(content.contains("```text\n[blocker] #io\nLeaks the handle\n```"));
+    let draft = parse_prompt_draft(&content).unwrap();
+    let roundtrip = draft_to_review(&draft);
+    let comment = roundtrip
+      .line_comment("src/lib.rs", LineSide::New, 5)
+      .expect("comment");
+    assert_eq!(comment.body, "Leaks the handle");
+    assert_eq!(comment.severity, Some(crate::review::Severity::Blocker));
+    assert_eq!(comment.labels, ["io"]);
+  }
+
+  #[test]
+  fn add_reply_appends_to_thread() {
+    let (_td, repo) = init_repo_with_commit("src/lib.rs", "fn main() {}\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    write_notes_file_comment(&repo, notes_ref, "src/lib.rs", "Why a main in a lib?");
+
+    let cmd = AddCli {
+      file: Some("src/lib.rs".to_string()),
+      line: None,
+      side: None,
+      file_comment: true,
+      reply: true,
+      message: Some("It's a fixture".to_string()),
+      suggestion: None,
+      edit: false,
+      editor: None,
+      severity: None,
+      label: Vec::new(),
+    };
+    run(&repo, notes_ref, None, cmd).expect("reply");
+
+    let notes = load_file_review(&repo, notes_ref, "src/lib.rs")
+      .expect("load notes")
+      .expect("notes present");
+    let comment = notes.file_comment.expect("file comment");
+    assert_eq!(comment.body, "Why a main in a lib?");
+    assert_eq!(comment.replies.len(), 1);
+    assert_eq!(comment.replies[0].body, "It's a fixture");
+    assert!(!comment.replies[0].author.is_empty());
+
+    let draft = std::fs::read_to_string(draft_path(&repo).expect("draft path")).expect("draft");
+    assert!(draft.contains("It's a fixture"));
+  }
+
   fn init_repo_with_commit(path: &str, contents: &str) -> (tempfile::TempDir, gix::Repository) {
     ensure_git_identity();
     let td = tempfile::tempdir().expect("tempdir");
@@ -1326,8 +2001,7 @@ Old note
     crate::review::FileReview {
       file_comment: Some(crate::review::Comment {
         body: body.to_string(),
-        resolved: false,
-        snippet_hash: None,
+        ..Default::default()
       }),
       ..Default::default()
     }
@@ -1398,6 +2072,28 @@ Old note
     assert_eq!(comment.body, "draft");
   }
 
+  #[test]
+  fn sync_copies_draft_tags_into_notes() {
+    let (_td, repo) = init_repo_with_commit("src/lib.rs", "fn main() {}\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+
+    write_notes_file_comment(&repo, notes_ref, "src/lib.rs", "notes");
+    let mut review = Review::new();
+    review.set_file_comment("src/lib.rs", "notes".to_string());
+    write_draft_from_review(&repo, notes_ref, None, &review).expect("write draft");
+
+    write_draft_file_comment(&repo, None, "src/lib.rs", "[nit] #style\nnotes");
+
+    sync_draft_notes_with_mode(&repo, notes_ref, None).expect("sync");
+    let notes = load_file_review(&repo, notes_ref, "src/lib.rs")
+      .expect("load notes")
+      .expect("notes present");
+    let comment = notes.file_comment.expect("file comment");
+    assert_eq!(comment.body, "notes");
+    assert_eq!(comment.severity, Some(crate::review::Severity::Nit));
+    assert_eq!(comment.labels, ["style"]);
+  }
+
   #[test]
   fn sync_prefers_notes_when_only_notes_changed() {
     let (_td, repo) = init_repo_with_commit("src/lib.rs", "fn main() {}\n");
@@ -1549,7 +2245,7 @@ Old note
   }
 
   #[test]
-  fn sync_line_comment_removes_when_invalidated() {
+  fn sync_line_comment_marks_outdated_when_invalidated() {
     let (_td, repo) = init_repo_with_commit(
       "src/lib.rs",
       "fn main() {}
@@ -1586,7 +2282,119 @@ Old note
           side: LineSide::New,
           line: 1
         })
-        .is_some_and(|c| c.resolved)
+        .is_some_and(|c| c.outdated && !c.resolved && c.body == "note")
+    );
+  }
+
+  #[test]
+  fn sync_moves_anchored_comment_with_its_code() {
+    let (_td, repo) = init_repo_with_commit("src/lib.rs", "fn a() {}\nfn b() {}\nfn c() {}\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", LineSide::New, 2, "about b".to_string());
+    let notes_file = review.files.get("src/lib.rs").expect("notes file");
+    persist_file_review(&repo, notes_ref, "src/lib.rs", Some(notes_file)).expect("note");
+    write_draft_from_review(&repo, notes_ref, None, &review).expect("write draft");
+    sync_draft_notes_with_mode(&repo, notes_ref, None).expect("anchor");
+
+    let workdir = repo.workdir().expect("workdir");
+    std::fs::write(
+      workdir.join("src/lib.rs"),
+      "use std::fmt;\n\nfn a() {}\nfn b() {}\nfn c() {}\n",
+    )
+    .expect("write updated file");
+    sync_draft_notes_with_mode(&repo, notes_ref, None).expect("sync");
+
+    let notes_review = load_file_review(&repo, notes_ref, "src/lib.rs")
+      .expect("load notes")
+      .expect("notes review");
+    let moved = notes_review
+      .comments
+      .get(&LineKey {
+        side: LineSide::New,
+        line: 4,
+      })
+      .expect("comment followed its code");
+    assert!(!moved.outdated);
+    assert_eq!(moved.body, "about b");
+    let draft_review =
+      load_draft_review(&draft_path(&repo).expect("draft path")).expect("load draft");
+    assert_eq!(
+      draft_review.line_comment("src/lib.rs", LineSide::New, 4),
+      Some("about b")
+    );
+    assert!(
+      draft_review
+        .line_comment("src/lib.rs", LineSide::New, 2)
+        .is_none()
+    );
+  }
+
+  #[test]
+  fn sync_keeps_snippet_hash_when_anchored_code_is_edited() {
+    let (_td, repo) = init_repo_with_commit(
+      "src/lib.rs",
+      "fn a() {}\nfn count(items: &[u32]) -> usize {\n  items.iter().count()\n}\n",
+    );
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    let key = LineKey {
+      side: LineSide::New,
+      line: 3,
+    };
+
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", key.side, key.line, "use len()".to_string());
+    let notes_file = review.files.get("src/lib.rs").expect("notes file");
+    persist_file_review(&repo, notes_ref, "src/lib.rs", Some(notes_file)).expect("note");
+    write_draft_from_review(&repo, notes_ref, None, &review).expect("write draft");
+    sync_draft_notes_with_mode(&repo, notes_ref, None).expect("anchor");
+    let comment_at = |repo: &gix::Repository| {
+      load_file_review(repo, notes_ref, "src/lib.rs")
+        .expect("load notes")
+        .and_then(|f| f.comments.get(&key).cloned())
+        .expect("comment")
+    };
+    let written = comment_at(&repo).snippet_hash.expect("snippet hash");
+
+    let workdir = repo.workdir().expect("workdir");
+    std::fs::write(
+      workdir.join("src/lib.rs"),
+      "fn a() {}\nfn count(items: &[u32]) -> usize {\n  items.iter().copied().count()\n}\n",
+    )
+    .expect("write updated file");
+    sync_draft_notes_with_mode(&repo, notes_ref, None).expect("sync");
+
+    let edited = comment_at(&repo);
+    assert!(!edited.outdated);
+    assert_eq!(edited.snippet_hash.as_deref(), Some(written.as_str()));
+    assert_ne!(
+      current_snippet_hash(&repo, None, "src/lib.rs", key, key.line),
+      Some(written)
+    );
+  }
+
+  #[test]
+  fn changed_comments_notice_edits_past_the_first_line() {
+    let original: String = (1..=20).map(|n| format!("let v{n} = {n};\n")).collect();
+    let (_td, repo) = init_repo_with_commit("src/lib.rs", &original);
+    let key = LineKey {
+      side: LineSide::New,
+      line: 2,
+    };
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", key.side, key.line, "check these".to_string());
+    review.set_line_comment_end("src/lib.rs", key.side, key.line, Some(15));
+    let hash = current_snippet_hash(&repo, None, "src/lib.rs", key, 15);
+    review.set_line_comment_snippet_hash("src/lib.rs", key.side, key.line, hash);
+    assert!(crate::autoresolve_cmd::changed_comments(&repo, None, &review).is_empty());
+
+    let edited = original.replace("let v15 = 15;", "let v15 = 16;");
+    let workdir = repo.workdir().expect("workdir");
+    std::fs::write(workdir.join("src/lib.rs"), edited).expect("write updated file");
+    assert_eq!(
+      crate::autoresolve_cmd::changed_comments(&repo, None, &review).len(),
+      1
     );
   }
 
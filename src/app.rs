@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
@@ -9,16 +9,20 @@ use gix::ObjectId;
 use gix_hash::{Kind, hasher};
 use ratatui_textarea::TextArea;
 
+use crate::file_filter::FileFilter;
 use crate::file_tree::FileTreeView;
 use crate::git::ViewKind;
 use crate::highlight::Highlighter;
-use crate::review::{FileReview, LineKey, LineSide, Review};
+use crate::keymap::{Action, Context as KeyContext, Keymap};
+use crate::review::{CommentState, FileReview, LineKey, LineSide, Review, merge_file_review};
+use crate::theme::{Palette, Theme};
 use unicode_width::UnicodeWidthStr;
 
 const CONFIG_DIFF_CONTEXT_KEY: &str = "remark.diffContext";
 const DEFAULT_DIFF_CONTEXT: u32 = 3;
 const MIN_DIFF_CONTEXT: u32 = 0;
 const MAX_DIFF_CONTEXT: u32 = 20;
+const NO_REVISIONS: &str = "No commit selected (pass --rev or --range)";
 
 #[derive(Debug, Clone)]
 pub(crate) struct JumpTarget {
@@ -32,8 +36,12 @@ pub(crate) struct UiOptions {
   pub(crate) base_ref: Option<String>,
   pub(crate) show_ignored: bool,
   pub(crate) view: ViewKind,
+  pub(crate) revisions: Option<crate::git::Revisions>,
   pub(crate) jump_target: Option<JumpTarget>,
   pub(crate) tab_width: usize,
+  pub(crate) prompt_template: Option<String>,
+  pub(crate) keymap: Keymap,
+  pub(crate) theme: Theme,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -42,6 +50,31 @@ pub(crate) enum Mode {
   EditComment,
   EditPrompt,
   CommentList,
+  /// Typing a search pattern after `/` or `?`.
+  Search,
+  /// The fuzzy file finder (`f`).
+  FileFinder,
+  /// The file list filter menu (`F`).
+  FileFilter,
+}
+
+/// A text search over the diff rows.
+#[derive(Debug, Clone)]
+struct Search {
+  pattern: String,
+  /// Started with `?`: `n` moves up and `N` down.
+  backward: bool,
+  /// Started from the file list: stepping past the last match moves on to other files.
+  all_files: bool,
+}
+
+/// Fuzzy finder over all files of the view, filtered or not.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileFinder {
+  pub(crate) query: String,
+  /// Paths matching the query, best first.
+  pub(crate) matches: Vec<String>,
+  pub(crate) selected: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -67,7 +100,14 @@ pub(crate) struct CommentListEntry {
   pub(crate) path: String,
   pub(crate) locator: CommentLocator,
   pub(crate) body: String,
+  /// Severity and labels as shown in the list, e.g. `[issue] #perf`; empty when untagged.
+  pub(crate) tags: String,
   pub(crate) resolved: bool,
+  /// How the comment was resolved, e.g. `fixed the loop, abc1234`; empty when not recorded.
+  pub(crate) resolution: String,
+  pub(crate) replies: usize,
+  pub(crate) has_suggestion: bool,
+  pub(crate) outdated: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -128,9 +168,13 @@ pub fn run(repo: gix::Repository, options: UiOptions) -> Result<()> {
       options.base_ref,
       options.show_ignored,
       options.view,
+      options.revisions,
       options.jump_target,
-      options.tab_width,
+      Highlighter::new(options.tab_width, options.theme.syntax)?,
     )?;
+    app.prompt_template = options.prompt_template;
+    app.keymap = options.keymap;
+    app.palette = options.theme.palette;
     app.run_loop(&mut ui)
   })();
 
@@ -185,8 +229,16 @@ struct App {
   base_ref: Option<String>,
   show_ignored: bool,
   jump_target: Option<JumpTarget>,
+  /// Template the prompt editor renders with instead of showing the draft as-is.
+  prompt_template: Option<String>,
+  keymap: Keymap,
+  palette: Palette,
 
   view: ViewKind,
+  /// Commit or range passed with `--rev`/`--range`, reviewed by the `commit`/`range` views.
+  revisions: Option<crate::git::Revisions>,
+  /// Index into `revisions.commits` shown by the `commit` view.
+  rev_step: usize,
   focus: Focus,
   mode: Mode,
   diff_view_mode: DiffViewMode,
@@ -195,8 +247,18 @@ struct App {
   head_commit_oid: Option<ObjectId>,
   review: Review,
 
+  /// Every file of the view; `files` holds those the file filter lets through.
+  listed_files: Vec<FileEntry>,
   files: Vec<FileEntry>,
   file_tree: FileTreeView,
+  /// Directories folded away in the file tree, by path.
+  collapsed_dirs: HashSet<String>,
+  /// Directory row the file list cursor is on; the diff keeps showing the last selected file.
+  selected_dir: Option<String>,
+  file_filter: FileFilter,
+  /// Glob being typed in the filter menu.
+  filter_glob_input: Option<String>,
+  file_finder: Option<FileFinder>,
   file_selected: usize,
   file_scroll: u16,
   files_viewport_height: u16,
@@ -210,10 +272,31 @@ struct App {
   diff_row_offsets: Vec<u32>,
   diff_row_heights: Vec<u16>,
   diff_total_visual_lines: u32,
+  /// Diff row where a visual (`V`) line selection started.
+  visual_anchor: Option<usize>,
+  /// Outdated comment waiting for a new line (picked with `o` in the comment list).
+  reanchor_source: Option<(String, LineKey)>,
 
   reviewed_files: HashSet<String>,
+  /// Diffs compare against the content each file had when it was last marked reviewed (`S`).
+  since_review: bool,
+  /// The shown diff's old side is such a snapshot, which has no lines to comment on.
+  interdiff: bool,
+  /// Hunks of the shown diff, in order.
+  current_hunks: Vec<crate::diff::Hunk>,
+  /// Index into `current_hunks` of each diff row inside a hunk.
+  row_hunks: Vec<Option<usize>>,
+  /// Whether each diff row belongs to a hunk marked viewed.
+  viewed_rows: Vec<bool>,
+  /// Viewed and total hunks of the partly viewed files, shown in the file list.
+  hunk_progress: HashMap<String, (usize, usize)>,
 
   editor_target: Option<CommentTarget>,
+  editor_reply: bool,
+  /// The editor holds a resolution message rather than a comment body.
+  editor_resolve: bool,
+  /// Last line of the range being commented on, when the editor was opened from a selection.
+  editor_range_end: Option<u32>,
   editor_buffer: TextArea<'static>,
   prompt_buffer: TextArea<'static>,
 
@@ -221,9 +304,15 @@ struct App {
   show_help: bool,
   show_prompt: bool,
   show_diff_popup: bool,
+  /// The search being typed or stepped through with `n`/`N`.
+  search: Option<Search>,
+  /// File and diff cursor to go back to when a search being typed is canceled.
+  search_origin: (usize, usize),
   comment_list: Vec<CommentListEntry>,
   comment_list_selected: usize,
   comment_list_marked: HashSet<usize>,
+  /// Hides comments less severe than this from the comment list.
+  comment_list_severity: Option<crate::review::Severity>,
 
   // Store diff data for popup display
   current_before: Option<String>,
@@ -293,22 +382,53 @@ impl App {
   }
 
   fn merge_draft_into_notes(&self, mut notes: Review, draft: Review) -> Review {
+    // Outdated comments only live in the notes; everything else comes from the draft.
     for file in notes.files.values_mut() {
       file.file_comment = None;
-      file.comments.clear();
+      file
+        .comments
+        .retain(|_, comment| comment.outdated && !comment.resolved);
     }
 
     for (path, draft_file) in draft.files {
       let entry = notes.files.entry(path).or_default();
       entry.file_comment = draft_file.file_comment;
-      entry.comments = draft_file.comments;
+      entry.comments.extend(draft_file.comments);
     }
 
     notes
   }
 
+  fn build_rev_notes_review(&self) -> Result<Review> {
+    let mut review = Review::new();
+    for e in &self.files {
+      let Some(oid) = self.note_key_oid(&e.path)? else {
+        continue;
+      };
+      let note = crate::notes::read(&self.repo, &self.notes_ref, &oid)
+        .with_context(|| format!("read file note for '{}'", e.path))?;
+      if let Some(fr) = note.as_deref().and_then(crate::review::decode_file_note) {
+        review.files.insert(e.path.clone(), fr);
+      }
+    }
+    Ok(review)
+  }
+
   fn refresh_review_from_sources(&mut self) -> Result<()> {
+    if self.in_rev_view() {
+      // Commit reviews are keyed to immutable commits: no draft, nothing to re-anchor.
+      self.review = self.build_rev_notes_review()?;
+      return Ok(());
+    }
     if self.head_commit_oid.is_some() {
+      let report =
+        crate::reanchor::reanchor_notes(&self.repo, &self.notes_ref, self.base_ref.as_deref())?;
+      if report.copied_notes > 0 {
+        self.status = format!(
+          "Re-anchored {} note(s) to HEAD ({} outdated comment(s))",
+          report.copied_notes, report.outdated_comments
+        );
+      }
       crate::add_cmd::sync_draft_notes(&self.repo, &self.notes_ref, self.base_ref.as_deref())?;
     }
     let draft_review = crate::add_cmd::load_review_from_draft(
@@ -346,6 +466,12 @@ impl App {
         return "No comments.\n".to_string();
       }
     };
+    if let Some(name) = self.prompt_template.clone() {
+      match self.render_prompt_template(&name) {
+        Ok(prompt) => return prompt,
+        Err(e) => self.status = format!("Prompt template failed, showing the draft: {e:#}"),
+      }
+    }
     match std::fs::read_to_string(&path) {
       Ok(content) => {
         if content.trim().is_empty() {
@@ -361,6 +487,29 @@ impl App {
     }
   }
 
+  fn render_prompt_template(&self, name: &str) -> Result<String> {
+    let review = crate::add_cmd::load_review_from_draft(
+      &self.repo,
+      &self.notes_ref,
+      self.base_ref.as_deref(),
+    )?;
+    let base_tree = self
+      .base_ref
+      .as_deref()
+      .and_then(|b| crate::git::merge_base_tree(&self.repo, b).ok());
+    let mut view_order = vec![ViewKind::All, ViewKind::Staged, ViewKind::Unstaged];
+    if base_tree.is_some() {
+      view_order.push(ViewKind::Base);
+    }
+    let mut resolver = crate::prompt_code::LineSnippetResolver::new(
+      &self.repo,
+      base_tree,
+      self.diff_context,
+      view_order,
+    );
+    crate::prompt_template::render(&self.repo, name, &review, false, &mut resolver)
+  }
+
   fn refresh_prompt_buffer_from_draft(&mut self) {
     let prompt = self.read_prompt_from_draft();
     self.prompt_buffer = crate::ui::textarea_from_string(&prompt);
@@ -372,10 +521,10 @@ impl App {
     base_ref: Option<String>,
     show_ignored: bool,
     view: ViewKind,
+    revisions: Option<crate::git::Revisions>,
     jump_target: Option<JumpTarget>,
-    tab_width: usize,
+    highlighter: Highlighter,
   ) -> Result<Self> {
-    let highlighter = Highlighter::new(tab_width)?;
     // Always default to Decorated view; users can switch with 'd' key
     let diff_view_mode = DiffViewMode::Decorated;
     let diff_context = match crate::git::read_local_config_value(&repo, CONFIG_DIFF_CONTEXT_KEY)
@@ -392,15 +541,26 @@ impl App {
       base_ref,
       show_ignored,
       jump_target,
+      prompt_template: None,
+      keymap: Keymap::default(),
+      palette: Theme::default().palette,
       view,
+      revisions,
+      rev_step: 0,
       focus: Focus::Files,
       mode: Mode::Browse,
       diff_view_mode,
       diff_context,
       head_commit_oid: None,
       review: Review::new(),
+      listed_files: Vec::new(),
       files: Vec::new(),
       file_tree: FileTreeView::default(),
+      collapsed_dirs: HashSet::new(),
+      selected_dir: None,
+      file_filter: FileFilter::default(),
+      filter_glob_input: None,
+      file_finder: None,
       file_selected: 0,
       file_scroll: 0,
       files_viewport_height: 1,
@@ -413,17 +573,31 @@ impl App {
       diff_row_offsets: Vec::new(),
       diff_row_heights: Vec::new(),
       diff_total_visual_lines: 0,
+      visual_anchor: None,
+      reanchor_source: None,
       reviewed_files: HashSet::new(),
+      since_review: false,
+      interdiff: false,
+      current_hunks: Vec::new(),
+      row_hunks: Vec::new(),
+      viewed_rows: Vec::new(),
+      hunk_progress: HashMap::new(),
       editor_target: None,
+      editor_reply: false,
+      editor_resolve: false,
+      editor_range_end: None,
       editor_buffer: crate::ui::empty_textarea(),
       prompt_buffer: crate::ui::empty_textarea(),
       status: String::new(),
       show_help: false,
       show_prompt: false,
       show_diff_popup: false,
+      search: None,
+      search_origin: (0, 0),
       comment_list: Vec::new(),
       comment_list_selected: 0,
       comment_list_marked: HashSet::new(),
+      comment_list_severity: None,
       current_before: None,
       current_after: None,
       current_diff_lines: Vec::new(),
@@ -509,15 +683,20 @@ impl App {
             crate::ui::DrawState {
               view: self.view,
               base_ref: self.base_ref.as_deref(),
+              rev_label: self.rev_label(),
               focus: self.focus,
               mode: self.mode,
               diff_view_mode: self.effective_diff_view_mode(),
               diff_context: self.diff_context,
               review: &self.review,
               files: &self.files,
+              listed_files: self.listed_files.len(),
+              file_filter: &self.file_filter,
+              filter_glob_input: self.filter_glob_input.as_deref(),
+              file_finder: self.file_finder.as_ref(),
               file_rows: &self.file_tree.rows,
               file_selected: self.file_selected,
-              file_row_selected: self.file_tree.selected_row(self.file_selected),
+              file_row_selected: self.selected_file_row(),
               file_scroll: self.file_scroll,
               diff_rows: &self.diff_rows,
               diff_cursor: self.diff_cursor,
@@ -527,17 +706,41 @@ impl App {
                 .get(self.diff_cursor)
                 .copied()
                 .unwrap_or(0),
+              visual_range: self.visual_range(),
+              reanchoring: self.reanchor_source.is_some(),
               reviewed_files: &self.reviewed_files,
+              interdiff: self.interdiff,
+              viewed_rows: &self.viewed_rows,
+              search_pattern: self
+                .search
+                .as_ref()
+                .map(|s| s.pattern.as_str())
+                .filter(|p| !p.is_empty()),
+              search_prompt: (self.mode == Mode::Search)
+                .then(|| self.search.as_ref())
+                .flatten()
+                .map(|s| {
+                  let prefix = if s.backward { '?' } else { '/' };
+                  let scope = if s.all_files { " (all files)" } else { "" };
+                  format!("{prefix}{}{scope}", s.pattern)
+                }),
+              hunk_progress: &self.hunk_progress,
               editor_target: self.editor_target.as_ref(),
+              editor_reply: self.editor_reply,
+              editor_resolve: self.editor_resolve,
+              editor_range_end: self.editor_range_end,
               editor_buffer: &self.editor_buffer,
               prompt_buffer: &self.prompt_buffer,
               status: &self.status,
               show_help: self.show_help,
+              keymap: &self.keymap,
+              palette: &self.palette,
               show_prompt: self.show_prompt,
               show_diff_popup: self.show_diff_popup,
               comment_list: &self.comment_list,
               comment_list_selected: self.comment_list_selected,
               comment_list_marked: &self.comment_list_marked,
+              comment_list_severity: self.comment_list_severity,
               current_diff_lines: &self.current_diff_lines,
               diff_cursor_line: self.get_current_line_number(),
             },
@@ -570,6 +773,9 @@ impl App {
       Mode::EditComment => self.handle_edit_key(key),
       Mode::EditPrompt => self.handle_prompt_key(key),
       Mode::CommentList => self.handle_comment_list_key(key),
+      Mode::Search => self.handle_search_key(key),
+      Mode::FileFinder => self.handle_finder_key(key),
+      Mode::FileFilter => self.handle_filter_key(key),
     }
   }
 
@@ -577,155 +783,145 @@ impl App {
     // Keyboard interaction implies "cursor-following" again.
     self.manual_scroll = false;
 
-    let no_ctrl_alt =
-      !key.modifiers.contains(KeyModifiers::CONTROL) && !key.modifiers.contains(KeyModifiers::ALT);
-    if no_ctrl_alt {
-      match key.code {
-        KeyCode::Char('Q') => return Ok(true),
-        KeyCode::Char('q') => {
-          return Ok(true);
-        }
-        KeyCode::Char('I') => {
-          self.show_ignored = !self.show_ignored;
-          self.reload_file_list()?;
-          self.status = if self.show_ignored {
-            "Showing ignored files".to_string()
-          } else {
-            "Hiding ignored files".to_string()
-          };
-          return Ok(false);
-        }
-        _ => {}
+    let context = match self.focus {
+      Focus::Files => KeyContext::Files,
+      Focus::Diff => KeyContext::Diff,
+    };
+    let Some(action) = self.keymap.lookup(context, key) else {
+      return Ok(false);
+    };
+
+    match action {
+      Action::Quit => return Ok(true),
+      Action::ToggleIgnored => {
+        self.show_ignored = !self.show_ignored;
+        self.reload_file_list()?;
+        self.status = if self.show_ignored {
+          "Showing ignored files".to_string()
+        } else {
+          "Hiding ignored files".to_string()
+        };
+        return Ok(false);
       }
+      _ => {}
     }
 
     if self.show_help {
-      // While help is open, treat most keys as inert.
-      let is_plain_qmark = key.code == KeyCode::Char('?')
-        && !key.modifiers.contains(KeyModifiers::CONTROL)
-        && !key.modifiers.contains(KeyModifiers::ALT);
-      if key.code == KeyCode::Esc || is_plain_qmark {
+      // While help is open, treat most keys as inert. The help key closes it from either pane.
+      if matches!(
+        self.keymap.action(KeyContext::Global, key),
+        Some(Action::Help | Action::Cancel)
+      ) {
         self.show_help = false;
       }
       return Ok(false);
     }
 
-    if key.code == KeyCode::Char('?')
-      && !key.modifiers.contains(KeyModifiers::CONTROL)
-      && !key.modifiers.contains(KeyModifiers::ALT)
-    {
-      self.show_help = !self.show_help;
-      return Ok(false);
-    }
-
-    if key.code == KeyCode::Esc {
-      if self.show_diff_popup {
-        self.show_diff_popup = false;
-        self.status.clear();
-        return Ok(false);
+    match action {
+      Action::Help => self.show_help = !self.show_help,
+      Action::Search => self.begin_search(false),
+      Action::SearchBackward => self.begin_search(true),
+      Action::Cancel => return Ok(self.cancel()),
+      Action::CommentList => return self.open_comment_list(),
+      Action::PlaceComment if self.reanchor_source.is_some() => self.revive_at_cursor()?,
+      Action::Prompt => {
+        if self.show_prompt {
+          self.show_prompt = false;
+          self.mode = Mode::Browse;
+        } else {
+          self.show_prompt = true;
+          self.refresh_prompt_buffer_from_draft();
+          self.mode = Mode::EditPrompt;
+        }
       }
-      return Ok(true);
-    }
-
-    let is_shift_c = matches!(key.code, KeyCode::Char('C'))
-      || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::SHIFT));
-    if is_shift_c && no_ctrl_alt {
-      return self.open_comment_list();
-    }
-
-    if key.code == KeyCode::Char('p') && key.modifiers.is_empty() {
-      if self.show_prompt {
-        self.show_prompt = false;
-        self.mode = Mode::Browse;
-      } else {
-        self.show_prompt = true;
-        self.refresh_prompt_buffer_from_draft();
-        self.mode = Mode::EditPrompt;
+      Action::ViewAll => self.try_set_view(ViewKind::All)?,
+      Action::ViewUnstaged => self.try_set_view(ViewKind::Unstaged)?,
+      Action::ViewStaged => self.try_set_view(ViewKind::Staged)?,
+      Action::ViewBase => self.try_set_view(ViewKind::Base)?,
+      Action::ViewRev => self.set_rev_view()?,
+      Action::PrevCommit => self.step_commit(-1)?,
+      Action::NextCommit => self.step_commit(1)?,
+      Action::CycleDiffMode => self.toggle_diff_view_mode()?,
+      Action::Reload => self.reload_file_list()?,
+      Action::FocusFiles => self.focus = Focus::Files,
+      Action::FocusDiff => self.focus = Focus::Diff,
+      Action::ToggleReviewed => self.toggle_reviewed_for_selected(),
+      Action::SinceReview => self.toggle_since_review()?,
+      Action::FindFile => self.open_file_finder(),
+      Action::FilterFiles => self.mode = Mode::FileFilter,
+      Action::Stage => self.stage_selection(true)?,
+      Action::Unstage => self.stage_selection(false)?,
+      Action::MoveUp | Action::MoveDown | Action::PageUp | Action::PageDown => {
+        let height = match self.focus {
+          Focus::Files => self.files_viewport_height,
+          Focus::Diff => self.diff_viewport_height,
+        };
+        let page = height.saturating_sub(1).max(1) as i32;
+        let delta = match action {
+          Action::MoveUp => -1,
+          Action::MoveDown => 1,
+          Action::PageUp => -page,
+          _ => page,
+        };
+        match self.focus {
+          Focus::Files => self.select_file(delta)?,
+          Focus::Diff => self.move_diff_cursor(delta),
+        }
       }
-      return Ok(false);
-    }
-
-    match key.code {
-      KeyCode::Char('1') => self.try_set_view(ViewKind::All)?,
-      KeyCode::Char('2') => self.try_set_view(ViewKind::Unstaged)?,
-      KeyCode::Char('3') => self.try_set_view(ViewKind::Staged)?,
-      KeyCode::Char('4') => self.try_set_view(ViewKind::Base)?,
-      KeyCode::Char('i') if key.modifiers.is_empty() => self.toggle_diff_view_mode()?,
-      KeyCode::Char('R') if no_ctrl_alt => self.reload_file_list()?,
-      KeyCode::Char('h') if key.modifiers.is_empty() => self.focus = Focus::Files,
-      KeyCode::Char('l') if key.modifiers.is_empty() => self.focus = Focus::Diff,
-      KeyCode::Left if no_ctrl_alt => self.focus = Focus::Files,
-      KeyCode::Right if no_ctrl_alt => self.focus = Focus::Diff,
-      KeyCode::Char('v') if key.modifiers.is_empty() => self.toggle_reviewed_for_selected(),
-      _ => match self.focus {
-        Focus::Files => self.handle_files_key(key)?,
-        Focus::Diff => self.handle_diff_key(key)?,
+      Action::Comment | Action::Reply
+        if self.focus == Focus::Files && self.selected_dir.is_some() =>
+      {
+        self.status = "Select a file to comment on".to_string();
+      }
+      Action::Comment => match self.focus {
+        Focus::Files => self.begin_file_comment()?,
+        Focus::Diff => self.begin_comment()?,
       },
+      Action::Reply => match self.focus {
+        Focus::Files => self.begin_file_reply()?,
+        Focus::Diff => self.begin_reply()?,
+      },
+      Action::Open if self.selected_dir.is_some() => self.toggle_collapsed_dir(),
+      Action::Open => self.focus = Focus::Diff,
+      Action::ToggleDir => self.toggle_collapsed_dir(),
+      Action::ResolveAll => self.resolve_selected_files()?,
+      Action::NextUnreviewed => self.select_next_unreviewed(1)?,
+      Action::PrevUnreviewed => self.select_next_unreviewed(-1)?,
+      Action::Next if self.search.is_some() => self.step_search(true)?,
+      Action::Next => self.jump_next_hunk(),
+      Action::PrevMatch if self.search.is_some() => self.step_search(false)?,
+      Action::MarkViewed => self.toggle_hunk_viewed()?,
+      Action::Visual => self.toggle_visual_selection(),
+      Action::ApplySuggestion => self.apply_suggestion()?,
+      Action::DeleteComment => self.delete_comment()?,
+      Action::DiffPopup => self.toggle_diff_popup()?,
+      Action::ToggleResolved => self.toggle_resolved()?,
+      Action::ResolveWithNote => self.begin_resolve()?,
+      Action::LessContext => self.adjust_diff_context(-1)?,
+      Action::MoreContext => self.adjust_diff_context(1)?,
+      _ => {}
     }
 
     Ok(false)
   }
 
-  fn handle_files_key(&mut self, key: KeyEvent) -> Result<()> {
-    let page = self.files_viewport_height.saturating_sub(1).max(1) as i32;
-    match key.code {
-      KeyCode::Up => self.select_file(-1)?,
-      KeyCode::Down => self.select_file(1)?,
-      KeyCode::Char('k') if key.modifiers.is_empty() => self.select_file(-1)?,
-      KeyCode::Char('j') if key.modifiers.is_empty() => self.select_file(1)?,
-      KeyCode::PageUp => self.select_file(-page)?,
-      KeyCode::PageDown => self.select_file(page)?,
-      KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-        self.select_file(-page)?
-      }
-      KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-        self.select_file(page)?
-      }
-      KeyCode::Enter => {
-        self.focus = Focus::Diff;
-      }
-      KeyCode::Char('c') if key.modifiers.is_empty() => self.begin_file_comment()?,
-      _ => {}
+  /// Backs out of the innermost thing going on: a search, a selection, a re-anchor or the diff
+  /// popup. Returns true to quit when there is none.
+  fn cancel(&mut self) -> bool {
+    if self.search.take().is_some() {
+      self.status.clear();
+      return false;
     }
-    Ok(())
-  }
-
-  fn handle_diff_key(&mut self, key: KeyEvent) -> Result<()> {
-    let page = self.diff_viewport_height.saturating_sub(1).max(1) as i32;
-    match key.code {
-      KeyCode::Up => self.move_diff_cursor(-1),
-      KeyCode::Down => self.move_diff_cursor(1),
-      KeyCode::Char('k') if key.modifiers.is_empty() => self.move_diff_cursor(-1),
-      KeyCode::Char('j') if key.modifiers.is_empty() => self.move_diff_cursor(1),
-      KeyCode::PageUp => self.move_diff_cursor(-page),
-      KeyCode::PageDown => self.move_diff_cursor(page),
-      KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-        self.move_diff_cursor(-page)
-      }
-      KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-        self.move_diff_cursor(page)
-      }
-      KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-        self.select_next_unreviewed(1)?;
-      }
-      KeyCode::Char('n') if key.modifiers.is_empty() => {
-        self.jump_next_hunk();
-      }
-      KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-        self.select_next_unreviewed(-1)?;
-      }
-      KeyCode::Char('c') if key.modifiers.is_empty() => self.begin_comment()?,
-      KeyCode::Char('d') if key.modifiers.is_empty() => self.delete_comment()?,
-      KeyCode::Char('H') => self.toggle_diff_popup()?,
-      KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::SHIFT) => {
-        self.toggle_diff_popup()?
-      }
-      KeyCode::Char('r') if key.modifiers.is_empty() => self.toggle_resolved()?,
-      KeyCode::Char('[') if key.modifiers.is_empty() => self.adjust_diff_context(-1)?,
-      KeyCode::Char(']') if key.modifiers.is_empty() => self.adjust_diff_context(1)?,
-      _ => {}
+    if self.visual_anchor.take().is_some() || self.reanchor_source.take().is_some() {
+      self.status.clear();
+      return false;
     }
-    Ok(())
+    if self.show_diff_popup {
+      self.show_diff_popup = false;
+      self.status.clear();
+      return false;
+    }
+    true
   }
 
   fn get_current_line_number(&self) -> u32 {
@@ -743,8 +939,10 @@ impl App {
     self.show_diff_popup = !self.show_diff_popup;
     if self.show_diff_popup {
       self.status = format!(
-        "Diff popup open ({} lines), press ESC or 'H' to close",
-        self.current_diff_lines.len()
+        "Diff popup open ({} lines), press {} or {} to close",
+        self.current_diff_lines.len(),
+        self.keymap.keys(Action::Cancel),
+        self.keymap.keys(Action::DiffPopup)
       );
     } else {
       self.status.clear();
@@ -784,108 +982,81 @@ impl App {
   }
 
   fn handle_edit_key(&mut self, key: KeyEvent) -> Result<bool> {
-    if key.code == KeyCode::Esc {
-      self.mode = Mode::Browse;
-      self.editor_target = None;
-      self.editor_buffer = crate::ui::empty_textarea();
-      self.status = "Canceled".to_string();
-      return Ok(false);
-    }
-
-    if key.code == KeyCode::Char('s')
-      && key.modifiers.contains(KeyModifiers::CONTROL)
-      && !key.modifiers.contains(KeyModifiers::ALT)
-    {
-      self.accept_comment_and_move_on()?;
-      return Ok(false);
-    }
-
-    if key.code == KeyCode::Enter && key.modifiers.contains(KeyModifiers::SHIFT) {
-      self.accept_comment_and_move_on()?;
-      return Ok(false);
+    match self.keymap.action(KeyContext::CommentEditor, key) {
+      Some(Action::EditorCancel) => {
+        self.mode = Mode::Browse;
+        self.editor_target = None;
+        self.editor_reply = false;
+        self.editor_resolve = false;
+        self.editor_range_end = None;
+        self.editor_buffer = crate::ui::empty_textarea();
+        self.status = "Canceled".to_string();
+      }
+      Some(Action::EditorAccept) => self.accept_comment_and_move_on()?,
+      _ => {
+        self.editor_buffer.input(key);
+      }
     }
-
-    self.editor_buffer.input(key);
     Ok(false)
   }
 
   fn handle_prompt_key(&mut self, key: KeyEvent) -> Result<bool> {
-    if key.code == KeyCode::Esc {
-      self.mode = Mode::Browse;
-      self.show_prompt = false;
-      self.status = "Closed prompt preview".to_string();
-      return Ok(false);
-    }
-
-    if key.code == KeyCode::Char('s')
-      && key.modifiers.contains(KeyModifiers::CONTROL)
-      && !key.modifiers.contains(KeyModifiers::ALT)
-    {
-      let prompt = self.read_prompt_from_draft();
-      match crate::clipboard::copy(&prompt) {
-        Ok(method) => self.status = format!("Copied prompt to clipboard ({method})"),
-        Err(e) => self.status = format!("Clipboard failed: {e}"),
+    match self.keymap.action(KeyContext::PromptEditor, key) {
+      Some(Action::PromptClose) => {
+        self.mode = Mode::Browse;
+        self.show_prompt = false;
+        self.status = "Closed prompt preview".to_string();
       }
-      self.mode = Mode::Browse;
-      self.show_prompt = false;
-      return Ok(false);
-    }
-
-    if key.code == KeyCode::Enter && key.modifiers.contains(KeyModifiers::SHIFT) {
-      let prompt = self.read_prompt_from_draft();
-      match crate::clipboard::copy(&prompt) {
-        Ok(method) => self.status = format!("Copied prompt to clipboard ({method})"),
-        Err(e) => self.status = format!("Clipboard failed: {e}"),
+      Some(Action::PromptCopy) => {
+        let prompt = self.read_prompt_from_draft();
+        match crate::clipboard::copy(&prompt) {
+          Ok(method) => self.status = format!("Copied prompt to clipboard ({method})"),
+          Err(e) => self.status = format!("Clipboard failed: {e}"),
+        }
+        self.mode = Mode::Browse;
+        self.show_prompt = false;
+      }
+      _ => {
+        self.prompt_buffer.input(key);
       }
-      self.mode = Mode::Browse;
-      self.show_prompt = false;
-      return Ok(false);
     }
-
-    self.prompt_buffer.input(key);
     Ok(false)
   }
 
   fn handle_comment_list_key(&mut self, key: KeyEvent) -> Result<bool> {
-    if key.code == KeyCode::Esc {
-      self.close_comment_list();
+    let Some(action) = self.keymap.action(KeyContext::CommentList, key) else {
       return Ok(false);
+    };
+    match action {
+      Action::ListClose => {
+        self.close_comment_list();
+        return Ok(false);
+      }
+      Action::ListFilter => {
+        self.cycle_comment_list_severity();
+        return Ok(false);
+      }
+      _ => {}
     }
 
     if self.comment_list.is_empty() {
       return Ok(false);
     }
 
-    let no_ctrl_alt =
-      !key.modifiers.contains(KeyModifiers::CONTROL) && !key.modifiers.contains(KeyModifiers::ALT);
-
-    if key.code == KeyCode::Enter && key.modifiers.contains(KeyModifiers::SHIFT) {
-      self.jump_to_comment_list_entry()?;
-      return Ok(false);
-    }
-
-    let is_shift_r = matches!(key.code, KeyCode::Char('R'))
-      || (key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::SHIFT));
-    if is_shift_r && no_ctrl_alt {
-      self.resolve_comment_list_selection()?;
-      return Ok(false);
-    }
-
-    if key.code == KeyCode::Delete && no_ctrl_alt {
-      self.discard_comment_list_selection()?;
-      return Ok(false);
-    }
-
-    match key.code {
-      KeyCode::Up | KeyCode::Char('k') => self.move_comment_list_selection(-1),
-      KeyCode::Down | KeyCode::Char('j') => self.move_comment_list_selection(1),
-      KeyCode::PageUp => self.move_comment_list_selection(-10),
-      KeyCode::PageDown => self.move_comment_list_selection(10),
-      KeyCode::Home => self.comment_list_selected = 0,
-      KeyCode::End => self.comment_list_selected = self.comment_list.len().saturating_sub(1),
-      KeyCode::Enter if no_ctrl_alt => {
-        self.toggle_comment_list_mark();
-      }
+    match action {
+      Action::ListJump => self.jump_to_comment_list_entry()?,
+      Action::ListResolveFiles => self.resolve_comment_list_selection()?,
+      Action::ListDiscard => self.discard_comment_list_selection()?,
+      Action::ListReanchor => self.pick_outdated_for_reanchor()?,
+      Action::ListMarkChanged => self.mark_changed_comments()?,
+      Action::ListResolveSelected => self.resolve_marked_comments()?,
+      Action::ListUp => self.move_comment_list_selection(-1),
+      Action::ListDown => self.move_comment_list_selection(1),
+      Action::ListPageUp => self.move_comment_list_selection(-10),
+      Action::ListPageDown => self.move_comment_list_selection(10),
+      Action::ListFirst => self.comment_list_selected = 0,
+      Action::ListLast => self.comment_list_selected = self.comment_list.len().saturating_sub(1),
+      Action::ListMark => self.toggle_comment_list_mark(),
       _ => {}
     }
 
@@ -894,7 +1065,8 @@ impl App {
 
   fn open_comment_list(&mut self) -> Result<bool> {
     self.comment_list = self.build_comment_list();
-    if self.comment_list.is_empty() {
+    // With a severity filter on, open anyway so the filter can be loosened.
+    if self.comment_list.is_empty() && self.comment_list_severity.is_none() {
       self.status = "No comments".to_string();
       return Ok(false);
     }
@@ -906,6 +1078,24 @@ impl App {
     Ok(false)
   }
 
+  /// Steps the comment list filter through all → suggestion → issue → blocker and back.
+  fn cycle_comment_list_severity(&mut self) {
+    use crate::review::Severity;
+    self.comment_list_severity = match self.comment_list_severity {
+      None => Some(Severity::Suggestion),
+      Some(Severity::Nit | Severity::Suggestion) => Some(Severity::Issue),
+      Some(Severity::Issue) => Some(Severity::Blocker),
+      Some(Severity::Blocker) => None,
+    };
+    self.comment_list = self.build_comment_list();
+    self.comment_list_selected = 0;
+    self.comment_list_marked.clear();
+    self.status = match self.comment_list_severity {
+      Some(min) => format!("Showing {} and above", min.as_str()),
+      None => "Showing all comments".to_string(),
+    };
+  }
+
   fn close_comment_list(&mut self) {
     self.mode = Mode::Browse;
     self.comment_list_marked.clear();
@@ -949,6 +1139,89 @@ impl App {
     paths.into_iter().collect()
   }
 
+  /// Marks the comments whose code changed since they were written, so `y` can resolve them.
+  fn mark_changed_comments(&mut self) -> Result<()> {
+    let Some(head) = self.head_commit_oid.filter(|_| !self.in_rev_view()) else {
+      self.status = "Auto-resolve needs a worktree view".to_string();
+      return Ok(());
+    };
+    // The draft doesn't carry snippet hashes, so look at the notes.
+    let notes = self.build_notes_review(head)?;
+    let changed =
+      crate::autoresolve_cmd::changed_comments(&self.repo, self.base_ref.as_deref(), &notes);
+    self.comment_list_marked = self
+      .comment_list
+      .iter()
+      .enumerate()
+      .filter(|(_, entry)| {
+        let CommentLocator::Line { side, line } = entry.locator else {
+          return false;
+        };
+        changed
+          .iter()
+          .any(|c| c.file == entry.path && c.side == Some(side) && c.line == Some(line))
+      })
+      .map(|(idx, _)| idx)
+      .collect();
+    self.status = match self.comment_list_marked.len() {
+      0 => "No open comments on changed code".to_string(),
+      n => format!(
+        "Marked {n} comment(s) on changed code ({})",
+        self.keymap.hint(&[
+          (Action::ListMark, "toggles"),
+          (Action::ListResolveSelected, "resolves"),
+        ])
+      ),
+    };
+    Ok(())
+  }
+
+  /// Resolves the marked comments (or the selected one) one by one, unlike `Shift+R`.
+  fn resolve_marked_comments(&mut self) -> Result<()> {
+    let indices: Vec<usize> = if self.comment_list_marked.is_empty() {
+      vec![self.comment_list_selected]
+    } else {
+      self.comment_list_marked.iter().copied().collect()
+    };
+    let author = crate::git::author_identity(&self.repo);
+    let mut paths = std::collections::BTreeSet::new();
+    let mut resolved = 0;
+    for idx in indices {
+      let Some(entry) = self.comment_list.get(idx) else {
+        continue;
+      };
+      let key = match entry.locator {
+        CommentLocator::File => None,
+        CommentLocator::Line { side, line } => Some(LineKey { side, line }),
+      };
+      if let Some(comment) = self
+        .review
+        .files
+        .get_mut(&entry.path)
+        .and_then(|f| f.comment_mut(key))
+        .filter(|c| !c.resolved)
+      {
+        comment.resolve(
+          author.clone(),
+          Some(crate::autoresolve_cmd::DEFAULT_MESSAGE.to_string()),
+          None,
+        );
+        paths.insert(entry.path.clone());
+        resolved += 1;
+      }
+    }
+    let paths: Vec<String> = paths.into_iter().collect();
+    self.persist_file_notes(&paths)?;
+
+    self.comment_list = self.build_comment_list();
+    self.comment_list_selected = self
+      .comment_list_selected
+      .min(self.comment_list.len().saturating_sub(1));
+    self.comment_list_marked.clear();
+    self.status = format!("Resolved {resolved} comment(s)");
+    Ok(())
+  }
+
   fn resolve_comment_list_selection(&mut self) -> Result<()> {
     let paths = self.comment_list_selected_paths();
     if paths.is_empty() {
@@ -962,12 +1235,12 @@ impl App {
         for c in file.comments.values_mut() {
           c.resolved = true;
         }
-        if file.file_comment.is_none() && file.comments.is_empty() && !file.reviewed {
+        if file.is_empty() {
           self.review.files.remove(path);
         }
       }
-      self.persist_file_note(path)?;
     }
+    self.persist_file_notes(&paths)?;
 
     self.comment_list = self.build_comment_list();
     self.comment_list_selected = self
@@ -991,8 +1264,8 @@ impl App {
           self.review.files.remove(path);
         }
       }
-      self.persist_file_note(path)?;
     }
+    self.persist_file_notes(&paths)?;
 
     self.comment_list = self.build_comment_list();
     self.comment_list_selected = self
@@ -1012,6 +1285,7 @@ impl App {
       self.status = format!("File not found: {}", entry.path);
       return Ok(());
     };
+    self.selected_dir = None;
     self.file_selected = idx;
     self.reload_diff_for_selected()?;
     match entry.locator {
@@ -1040,37 +1314,153 @@ impl App {
 
   fn build_comment_list(&self) -> Vec<CommentListEntry> {
     let mut out = Vec::new();
+    let mut outdated = Vec::new();
+    let shown = |c: &crate::review::Comment| {
+      !c.body.trim().is_empty() && self.comment_list_severity.is_none_or(|min| c.rank() >= min)
+    };
     for (path, file) in &self.review.files {
-      if let Some(c) = file
-        .file_comment
-        .as_ref()
-        .filter(|c| !c.body.trim().is_empty())
-      {
+      if let Some(c) = file.file_comment.as_ref().filter(|c| shown(c)) {
         out.push(CommentListEntry {
           path: path.clone(),
           locator: CommentLocator::File,
           body: c.body.clone(),
+          tags: crate::review::Tags::of(c).to_string(),
           resolved: c.resolved,
+          resolution: c
+            .resolution
+            .as_ref()
+            .map(crate::review::Resolution::summary)
+            .unwrap_or_default(),
+          replies: c.replies.len(),
+          has_suggestion: c.suggestion.is_some(),
+          outdated: false,
         });
       }
       for (k, c) in &file.comments {
-        if c.body.trim().is_empty() {
+        if !shown(c) {
           continue;
         }
-        out.push(CommentListEntry {
+        let entry = CommentListEntry {
           path: path.clone(),
           locator: CommentLocator::Line {
             side: k.side,
             line: k.line,
           },
           body: c.body.clone(),
+          tags: crate::review::Tags::of(c).to_string(),
           resolved: c.resolved,
-        });
+          resolution: c
+            .resolution
+            .as_ref()
+            .map(crate::review::Resolution::summary)
+            .unwrap_or_default(),
+          replies: c.replies.len(),
+          has_suggestion: c.suggestion.is_some(),
+          outdated: c.outdated,
+        };
+        if c.outdated {
+          outdated.push(entry);
+        } else {
+          out.push(entry);
+        }
       }
     }
+    // Outdated comments are listed after the live ones, in their own section.
+    out.extend(outdated);
     out
   }
 
+  fn pick_outdated_for_reanchor(&mut self) -> Result<()> {
+    let Some(entry) = self.comment_list.get(self.comment_list_selected).cloned() else {
+      return Ok(());
+    };
+    let CommentLocator::Line { side, line } = entry.locator else {
+      self.status = "Only outdated line comments can be re-anchored".to_string();
+      return Ok(());
+    };
+    if !entry.outdated {
+      self.status = "Comment is not outdated".to_string();
+      return Ok(());
+    }
+    let Some(idx) = self.files.iter().position(|e| e.path == entry.path) else {
+      self.status = format!("File not found: {}", entry.path);
+      return Ok(());
+    };
+    self.selected_dir = None;
+    self.file_selected = idx;
+    self.reload_diff_for_selected()?;
+    let keep = match side {
+      LineSide::Old => KeepLine::Old(line),
+      LineSide::New => KeepLine::New(line),
+    };
+    self.diff_cursor = self.find_row_for_keep_line(keep).unwrap_or(0);
+    self.focus = Focus::Diff;
+    self.close_comment_list();
+    self.reanchor_source = Some((entry.path, LineKey { side, line }));
+    self.status = format!(
+      "Move to the new line and press {} to re-anchor ({} cancels)",
+      self.keymap.keys(Action::PlaceComment),
+      self.keymap.keys(Action::Cancel)
+    );
+    Ok(())
+  }
+
+  /// Moves the outdated comment picked for re-anchoring onto the line under the cursor.
+  fn revive_at_cursor(&mut self) -> Result<()> {
+    let Some((path, from)) = self.reanchor_source.clone() else {
+      return Ok(());
+    };
+    let Some(CommentTarget {
+      path: cursor_path,
+      locator: CommentLocator::Line { side, line },
+    }) = self.current_comment_target()
+    else {
+      self.status = "Not a commentable line".to_string();
+      return Ok(());
+    };
+    if cursor_path != path || side != from.side {
+      self.status = format!("Pick a line on the same side of {path}");
+      return Ok(());
+    }
+    let Some(oid) = self.note_key_oid(&path)? else {
+      self.status = "No HEAD commit — notes disabled".to_string();
+      return Ok(());
+    };
+
+    // Outdated comments live in the notes only, so re-anchor them there directly.
+    let mut tx = crate::notes::Transaction::new(&self.repo, &self.notes_ref)?;
+    let note = tx
+      .read(&oid)
+      .with_context(|| format!("read file note for '{path}'"))?;
+    let Some(mut file) = note.as_deref().and_then(crate::review::decode_file_note) else {
+      self.status = "Outdated comment not found".to_string();
+      return Ok(());
+    };
+    match file.revive(from, line) {
+      Ok(comment) => {
+        let key = LineKey { side, line };
+        comment.snippet_hash = crate::add_cmd::current_snippet_hash(
+          &self.repo,
+          self.base_ref.as_deref(),
+          &path,
+          key,
+          comment.last_line(key),
+        );
+      }
+      Err(err) => {
+        self.status = format!("{err:#}");
+        return Ok(());
+      }
+    }
+    tx.upsert(&oid, &crate::review::encode_file_note(&file));
+    tx.commit().context("write re-anchored comment")?;
+
+    self.reanchor_source = None;
+    self.refresh_review_from_sources()?;
+    self.status = format!("Re-anchored comment to line {line}");
+    Ok(())
+  }
+
   fn handle_mouse(&mut self, m: MouseEvent, rects: crate::ui::LayoutRects) -> Result<()> {
     if self.mode != Mode::Browse {
       return Ok(());
@@ -1084,10 +1474,7 @@ impl App {
           self.focus = Focus::Files;
           let inner_y = m.row.saturating_sub(rects.files.y + 1) as usize;
           let row_idx = self.file_scroll as usize + inner_y;
-          if let Some(file_index) = self.file_tree.file_at_row(row_idx) {
-            self.file_selected = file_index;
-            self.reload_diff_for_selected()?;
-          }
+          self.select_file_row(row_idx)?;
         } else if rects.diff.contains(pos) {
           self.focus = Focus::Diff;
           let inner_y = m.row.saturating_sub(rects.diff.y + 1) as usize;
@@ -1159,6 +1546,50 @@ impl App {
     Ok(())
   }
 
+  fn set_rev_view(&mut self) -> Result<()> {
+    let Some(view) = self.revisions.as_ref().map(|r| r.view) else {
+      self.status = NO_REVISIONS.to_string();
+      return Ok(());
+    };
+    if view == ViewKind::Commit {
+      self.rev_step = 0;
+    }
+    self.try_set_view(view)
+  }
+
+  /// Steps through the commits of a range; stepping past either end returns to the whole range.
+  fn step_commit(&mut self, delta: isize) -> Result<()> {
+    let Some(revs) = &self.revisions else {
+      self.status = NO_REVISIONS.to_string();
+      return Ok(());
+    };
+    if revs.view != ViewKind::Range {
+      self.status = "Not reviewing a range (pass --range a..b)".to_string();
+      return Ok(());
+    }
+    if revs.commits.is_empty() {
+      self.status = "No commits in range".to_string();
+      return Ok(());
+    }
+    let last = revs.commits.len() - 1;
+    let next = match self.view {
+      ViewKind::Commit => self
+        .rev_step
+        .checked_add_signed(delta)
+        .filter(|&i| i <= last),
+      _ if delta > 0 => Some(0),
+      _ => Some(last),
+    };
+    match next {
+      Some(i) => {
+        self.rev_step = i;
+        self.view = ViewKind::Commit;
+      }
+      None => self.view = ViewKind::Range,
+    }
+    self.reload_view()
+  }
+
   fn reload_view(&mut self) -> Result<()> {
     self.mode = Mode::Browse;
     self.focus = Focus::Files;
@@ -1169,10 +1600,14 @@ impl App {
     self.reviewed_files.clear();
     self.status.clear();
     self.file_selected = 0;
+    self.selected_dir = None;
     self.file_scroll = 0;
     self.diff_cursor = 0;
     self.diff_scroll = 0;
     self.editor_target = None;
+    self.editor_reply = false;
+    self.editor_resolve = false;
+    self.editor_range_end = None;
     self.editor_buffer = crate::ui::empty_textarea();
 
     if self.view == ViewKind::Base {
@@ -1181,16 +1616,17 @@ impl App {
         .clone()
         .or_else(|| crate::git::default_base_ref(&self.repo));
       self.base_ref = base;
-      if self.base_ref.is_none() {
-        self.status = "No base ref set (pass --base <ref>)".to_string();
-      }
+    }
+    if let Some(missing) = self.missing_view_ref() {
+      self.status = missing.to_string();
     }
 
     self.head_commit_oid = crate::git::head_commit_oid(&self.repo).ok();
 
-    self.files = self.list_files_for_view()?;
-    self.file_tree = FileTreeView::build(&self.files);
-    if self.view == ViewKind::Base && self.base_ref.is_none() {
+    self.listed_files = self.list_files_for_view()?;
+    self.files = self.listed_files.clone();
+    self.file_tree = FileTreeView::build(&self.files, &self.collapsed_dirs);
+    if self.missing_view_ref().is_some() {
       self.diff_rows.clear();
       self.review = Review::new();
       self.review.files.clear();
@@ -1229,6 +1665,8 @@ impl App {
       .filter(|(_, f)| f.reviewed)
       .map(|(path, _)| path.clone())
       .collect();
+    self.refresh_hunk_progress()?;
+    self.apply_file_filter(None);
 
     self.reload_diff_for_selected()?;
     Ok(())
@@ -1241,14 +1679,15 @@ impl App {
     self.reviewed_files.clear();
 
     self.head_commit_oid = crate::git::head_commit_oid(&self.repo).ok();
-    self.files = self.list_files_for_view()?;
-    self.file_tree = FileTreeView::build(&self.files);
+    self.listed_files = self.list_files_for_view()?;
+    self.files = self.listed_files.clone();
+    self.file_tree = FileTreeView::build(&self.files, &self.collapsed_dirs);
 
-    if self.view == ViewKind::Base && self.base_ref.is_none() {
+    if let Some(missing) = self.missing_view_ref() {
       self.diff_rows.clear();
       self.review = Review::new();
       self.review.files.clear();
-      self.status = "No base ref set (pass --base <ref>)".to_string();
+      self.status = missing.to_string();
       self.file_selected = 0;
       self.file_scroll = 0;
       self.file_tree = FileTreeView::default();
@@ -1277,7 +1716,7 @@ impl App {
       self.file_selected = self.file_selected.min(self.files.len().saturating_sub(1));
     }
     // Ensure the tree mapping is up-to-date for the selected file.
-    self.file_tree = FileTreeView::build(&self.files);
+    self.file_tree = FileTreeView::build(&self.files, &self.collapsed_dirs);
 
     self.refresh_review_from_sources()?;
 
@@ -1298,6 +1737,8 @@ impl App {
       .filter(|(_, f)| f.reviewed)
       .map(|(path, _)| path.clone())
       .collect();
+    self.refresh_hunk_progress()?;
+    self.apply_file_filter(keep_path);
 
     self.reload_diff_for_selected()?;
     if let Some(k) = keep_line
@@ -1315,15 +1756,7 @@ impl App {
     }
 
     let review_checks = {
-      let base_tree = if self.view == ViewKind::Base {
-        self
-          .base_ref
-          .as_deref()
-          .map(|b| crate::git::merge_base_tree(&self.repo, b))
-          .transpose()?
-      } else {
-        None
-      };
+      let base_tree = self.view_base_tree()?;
 
       let mut checks = Vec::new();
       for (path, file) in self.review.files.iter() {
@@ -1368,9 +1801,7 @@ impl App {
     }
 
     if !changed.is_empty() && self.head_commit_oid.is_some() {
-      for path in changed {
-        self.persist_file_note(&path)?;
-      }
+      self.persist_file_notes(&changed)?;
     }
 
     Ok(())
@@ -1417,6 +1848,93 @@ impl App {
     }
   }
 
+  fn in_rev_view(&self) -> bool {
+    matches!(self.view, ViewKind::Commit | ViewKind::Range)
+  }
+
+  /// `(parent, commit)` diffed by the `commit`/`range` view; `None` in other views.
+  fn rev_pair(&self) -> Option<(Option<ObjectId>, ObjectId)> {
+    let revs = self.revisions.as_ref()?;
+    match self.view {
+      ViewKind::Range => Some((revs.from, revs.to)),
+      ViewKind::Commit => revs.commits.get(self.rev_step).map(|c| (c.parent, c.id)),
+      _ => None,
+    }
+  }
+
+  fn rev_label(&self) -> Option<String> {
+    let revs = self.revisions.as_ref()?;
+    match self.view {
+      ViewKind::Range => Some(format!("{} ({} commits)", revs.label, revs.commits.len())),
+      ViewKind::Commit => {
+        let c = revs.commits.get(self.rev_step)?;
+        let id = c.id.to_hex_with_len(7);
+        Some(if revs.view == ViewKind::Range {
+          format!(
+            "{}/{} {id} {}",
+            self.rev_step + 1,
+            revs.commits.len(),
+            c.summary
+          )
+        } else {
+          format!("{id} {}", c.summary)
+        })
+      }
+      _ => None,
+    }
+  }
+
+  /// Status shown when the current view needs a ref that was never given.
+  fn missing_view_ref(&self) -> Option<&'static str> {
+    match self.view {
+      ViewKind::Base if self.base_ref.is_none() => Some("No base ref set (pass --base <ref>)"),
+      ViewKind::Commit | ViewKind::Range if self.rev_pair().is_none() => Some(NO_REVISIONS),
+      _ => None,
+    }
+  }
+
+  /// Old side of the diff when it is a tree: the merge base, or the parent of the reviewed commit.
+  fn view_base_tree(&self) -> Result<Option<gix::Tree<'_>>> {
+    match self.view {
+      ViewKind::Base => self
+        .base_ref
+        .as_deref()
+        .map(|b| crate::git::merge_base_tree(&self.repo, b))
+        .transpose(),
+      ViewKind::Commit | ViewKind::Range => match self.rev_pair() {
+        Some((Some(from), _)) => crate::git::commit_tree(&self.repo, from).map(Some),
+        _ => Ok(None),
+      },
+      _ => Ok(None),
+    }
+  }
+
+  /// Note key for `path` in the current view, or `None` when notes are unavailable.
+  fn note_key_oid(&self, path: &str) -> Result<Option<ObjectId>> {
+    if self.in_rev_view() {
+      let Some((from, to)) = self.rev_pair() else {
+        return Ok(None);
+      };
+      // A stepped commit shares its notes with `--rev <commit>`; a range is keyed by both ends.
+      let from = from.map(|id| id.to_string());
+      let base = match self.view {
+        ViewKind::Range => from.as_deref(),
+        _ => None,
+      };
+      return crate::git::note_file_key_oid(&self.repo, to, self.view, base, path).map(Some);
+    }
+    let Some(head) = self.head_commit_oid else {
+      return Ok(None);
+    };
+    // Worktree review notes are view-agnostic: always store them under the `all` key.
+    // Base-diff notes stay under the `base` key because line anchors are relative to that diff.
+    let (view_for_key, base_for_key) = match self.view {
+      ViewKind::Base => (ViewKind::Base, self.base_ref_for_key()),
+      _ => (ViewKind::All, None),
+    };
+    crate::git::note_file_key_oid(&self.repo, head, view_for_key, base_for_key, path).map(Some)
+  }
+
   fn list_files_for_view(&self) -> Result<Vec<FileEntry>> {
     let (paths, staged_status, unstaged_status) = match self.view {
       ViewKind::All => {
@@ -1464,17 +1982,19 @@ impl App {
           Default::default(),
         )
       }
+      ViewKind::Commit | ViewKind::Range => {
+        let Some((from, to)) = self.rev_pair() else {
+          return Ok(Vec::new());
+        };
+        (
+          crate::git::list_commit_paths(&self.repo, from, to)?,
+          Default::default(),
+          Default::default(),
+        )
+      }
     };
 
-    let base_tree = if self.view == ViewKind::Base {
-      self
-        .base_ref
-        .as_deref()
-        .map(|b| crate::git::merge_base_tree(&self.repo, b))
-        .transpose()?
-    } else {
-      None
-    };
+    let base_tree = self.view_base_tree()?;
 
     let mut out = Vec::with_capacity(paths.len());
     for path in paths {
@@ -1505,7 +2025,7 @@ impl App {
           let x = staged_status.get(&path).copied().unwrap_or('-');
           [x, '-']
         }
-        ViewKind::Base => {
+        ViewKind::Base | ViewKind::Commit | ViewKind::Range => {
           let x = match change {
             FileChangeKind::Added => 'A',
             FileChangeKind::Deleted => 'D',
@@ -1529,20 +2049,24 @@ impl App {
       return Ok(());
     };
 
-    let base_tree = if self.view == ViewKind::Base {
-      self
-        .base_ref
-        .as_deref()
-        .map(|b| crate::git::merge_base_tree(&self.repo, b))
-        .transpose()?
+    let base_tree = self.view_base_tree()?;
+
+    let (mut before, after) = self.read_before_after(base_tree.as_ref(), &path)?;
+    drop(base_tree);
+
+    let snapshot = if self.since_review {
+      self.reviewed_snapshot(&path)?
     } else {
       None
     };
+    self.interdiff = snapshot.is_some();
+    if self.interdiff {
+      before = snapshot;
+    }
 
-    let (before, after) = self.read_before_after(base_tree.as_ref(), &path)?;
-    drop(base_tree);
-
-    let before_label = if before.is_some() {
+    let before_label = if self.interdiff {
+      format!("reviewed/{path}")
+    } else if before.is_some() {
       format!("a/{path}")
     } else {
       "/dev/null".to_string()
@@ -1567,26 +2091,17 @@ impl App {
       .cloned()
       .collect();
 
-    let mut valid_old = HashSet::new();
-    let mut valid_new = HashSet::new();
-    for line in &diff_lines {
-      match line.kind {
-        crate::diff::Kind::Remove => {
-          if let Some(n) = line.old_line {
-            valid_old.insert(n);
-          }
-        }
-        crate::diff::Kind::Add | crate::diff::Kind::Context => {
-          if let Some(n) = line.new_line {
-            valid_new.insert(n);
-          }
-        }
-        _ => {}
-      }
-    }
-    if self
-      .review
-      .prune_line_comments(&path, &valid_old, &valid_new)
+    // Lines past the end of either side can't be shown; their comments become outdated. A
+    // snapshot isn't the old side comments refer to, so nothing is checked against it.
+    let line_numbers = |text: Option<&str>| -> HashSet<u32> {
+      (1..=text.map_or(0, |t| t.lines().count() as u32)).collect()
+    };
+    let valid_old = line_numbers(before.as_deref());
+    let valid_new = line_numbers(after.as_deref());
+    if !self.interdiff
+      && self
+        .review
+        .mark_outdated_line_comments(&path, &valid_old, &valid_new)
       && self.head_commit_oid.is_some()
     {
       self.persist_file_note(&path)?;
@@ -1609,7 +2124,14 @@ impl App {
 
     rows.insert(0, RenderRow::FileHeader { path: path.clone() });
 
+    self.current_hunks = crate::diff::hunks(&diff_lines);
+    self.row_hunks = row_hunks(&rows, &self.current_hunks);
     self.diff_rows = rows;
+    self.refresh_viewed_rows();
+    if !self.interdiff {
+      self.update_hunk_progress(&path);
+    }
+    self.visual_anchor = None;
     // Store diff data for popup display
     self.current_before = before;
     self.current_after = after;
@@ -1628,41 +2150,94 @@ impl App {
     self.diff_scroll = 0;
     self.needs_clear = true;
     self.recompute_diff_metrics(self.diff_viewport_width);
-    self.status = path;
+    self.status = if self.since_review && !self.interdiff {
+      format!("{path} (never reviewed; showing the full diff)")
+    } else {
+      path
+    };
+    Ok(())
+  }
+
+  fn toggle_since_review(&mut self) -> Result<()> {
+    self.since_review = !self.since_review;
+    self.reload_diff_for_selected()?;
+    if !self.since_review {
+      self.status = "Showing full diffs".to_string();
+    } else if self.interdiff {
+      self.status = "Showing changes since last review".to_string();
+    }
     Ok(())
   }
 
+  /// What `path` looked like when it was last marked reviewed, if that snapshot still exists.
+  fn reviewed_snapshot(&self, path: &str) -> Result<Option<String>> {
+    let Some(id) = self
+      .review
+      .files
+      .get(path)
+      .and_then(|f| f.reviewed_blob.as_deref())
+    else {
+      return Ok(None);
+    };
+    crate::git::try_read_snapshot(&self.repo, id)
+  }
+
   fn select_file(&mut self, delta: i32) -> Result<()> {
     if self.files.is_empty() {
       self.file_selected = 0;
       return Ok(());
     }
 
-    // Get current row from file index
-    let current_row = self.file_tree.selected_row(self.file_selected).unwrap_or(0);
+    // Directory rows are stops too, so they can be folded and acted on.
+    let current_row = self.selected_file_row().unwrap_or(0) as i32;
+    let last_row = self.file_tree.rows.len().saturating_sub(1) as i32;
+    let new_row = (current_row + delta).clamp(0, last_row) as usize;
+    self.select_file_row(new_row)
+  }
 
-    // Find next/previous file row
-    let new_row = if delta > 0 {
-      // Navigate forward - use saturating to stay at last file if we go past it
-      (0..delta).fold(current_row, |row, _| {
-        self.file_tree.next_file_row(row).unwrap_or(row)
-      })
-    } else {
-      // Navigate backward - use saturating to stay at first file if we go before it
-      (0..delta.abs()).fold(current_row, |row, _| {
-        self.file_tree.prev_file_row(row).unwrap_or(row)
-      })
-    };
+  /// Row of the file list cursor: the selected directory, or else the selected file (or the
+  /// collapsed directory holding it).
+  fn selected_file_row(&self) -> Option<usize> {
+    match self.selected_dir.as_deref() {
+      Some(dir) => self.file_tree.dir_row(dir),
+      None => self.file_tree.selected_row(self.file_selected),
+    }
+  }
 
-    // Update file_selected from the new row
-    if let Some(file_idx) = self.file_tree.file_at_row(new_row) {
+  fn select_file_row(&mut self, row: usize) -> Result<()> {
+    if let Some(file_idx) = self.file_tree.file_at_row(row) {
+      self.selected_dir = None;
       self.file_selected = file_idx;
       self.reload_diff_for_selected()?;
+    } else if let Some(dir) = self.file_tree.rows.get(row).and_then(|r| r.dir.clone()) {
+      self.selected_dir = Some(dir);
     }
-
     Ok(())
   }
 
+  /// Folds or unfolds the directory under the cursor.
+  fn toggle_collapsed_dir(&mut self) {
+    let Some(dir) = self.selected_dir.clone() else {
+      return;
+    };
+    if !self.collapsed_dirs.remove(&dir) {
+      self.collapsed_dirs.insert(dir);
+    }
+    self.file_tree = FileTreeView::build(&self.files, &self.collapsed_dirs);
+  }
+
+  /// Paths of the files below the selected directory, or else of the selected file.
+  fn selected_paths(&self) -> Vec<String> {
+    let indices = match self.selected_file_row() {
+      Some(row) if self.selected_dir.is_some() => self.file_tree.rows[row].dir_files.clone(),
+      _ => vec![self.file_selected],
+    };
+    indices
+      .into_iter()
+      .filter_map(|idx| self.files.get(idx).map(|e| e.path.clone()))
+      .collect()
+  }
+
   fn ensure_file_visible(&mut self, viewport_height: u16) {
     if self.files.is_empty() {
       self.file_scroll = 0;
@@ -1671,7 +2246,7 @@ impl App {
       return;
     }
     let viewport_height = viewport_height.max(1) as usize;
-    let selected_row = self.file_tree.selected_row(self.file_selected).unwrap_or(0);
+    let selected_row = self.selected_file_row().unwrap_or(0);
     let scroll = self.file_scroll as usize;
     if selected_row < scroll {
       self.file_scroll = selected_row as u16;
@@ -1720,6 +2295,7 @@ impl App {
       }
       let path = &self.files[idx as usize].path;
       if !self.reviewed_files.contains(path) {
+        self.selected_dir = None;
         self.file_selected = idx as usize;
         self.reload_diff_for_selected()?;
         return Ok(());
@@ -1789,45 +2365,127 @@ impl App {
   }
 
   fn toggle_reviewed_for_selected(&mut self) {
+    if self.selected_dir.is_some() {
+      self.toggle_reviewed_for_dir();
+      return;
+    }
     let Some(path) = self.files.get(self.file_selected).map(|e| e.path.clone()) else {
       self.status = "No file selected".to_string();
       return;
     };
-    let was_reviewed = self
-      .review
-      .files
-      .get(&path)
-      .map(|f| f.reviewed)
-      .unwrap_or(false);
-    let new_reviewed = !was_reviewed;
-    let hash = if new_reviewed {
-      match self.review_hash_for_current_view(&path) {
-        Ok(v) => v,
-        Err(e) => {
-          self.status = format!("Failed to hash reviewed file: {e}");
-          return;
-        }
+    let new_reviewed = !self.reviewed_files.contains(&path);
+    if let Err(e) = self.set_reviewed(&path, new_reviewed) {
+      self.status = format!("Failed to hash reviewed file: {e}");
+      return;
+    }
+    self.status = if new_reviewed {
+      "Marked reviewed".to_string()
+    } else {
+      "Marked unreviewed".to_string()
+    };
+    self.refresh_viewed_rows();
+    if let Err(e) = self.persist_file_note(&path) {
+      self.status = format!("Failed to save reviewed state: {e}");
+    }
+  }
+
+  /// Marks every file below the selected directory reviewed, or unmarks them all when they
+  /// already are.
+  fn toggle_reviewed_for_dir(&mut self) {
+    let paths = self.selected_paths();
+    if paths.is_empty() {
+      return;
+    }
+    let new_reviewed = !paths.iter().all(|p| self.reviewed_files.contains(p));
+    let mut changed = Vec::new();
+    for path in paths {
+      if self.reviewed_files.contains(&path) == new_reviewed {
+        continue;
       }
+      if let Err(e) = self.set_reviewed(&path, new_reviewed) {
+        self.status = format!("Failed to hash reviewed file {path}: {e}");
+        return;
+      }
+      changed.push(path);
+    }
+    self.refresh_viewed_rows();
+    if let Err(e) = self.persist_file_notes(&changed) {
+      self.status = format!("Failed to save reviewed state: {e}");
+      return;
+    }
+    let dir = self.selected_dir.as_deref().unwrap_or_default();
+    self.status = if new_reviewed {
+      format!("Marked {} file(s) in {dir}/ reviewed", changed.len())
     } else {
-      None
+      format!("Marked {} file(s) in {dir}/ unreviewed", changed.len())
+    };
+  }
+
+  /// Records `path` as reviewed as the view shows it now, or as not reviewed. Nothing is
+  /// persisted.
+  fn set_reviewed(&mut self, path: &str, reviewed: bool) -> Result<()> {
+    // Marking a file reviewed marks all its hunks viewed, so after an edit only the hunks that
+    // changed need another look.
+    let (hash, snapshot, hunks) = if reviewed {
+      let base_tree = self.view_base_tree()?;
+      let hash = self.review_hash_for_current_view_with_base(base_tree.as_ref(), path)?;
+      let hunks = self.hunk_hashes_for_view(self.review_view(), base_tree.as_ref(), path)?;
+      drop(base_tree);
+      (hash, self.snapshot_for_current_view(path)?, hunks)
+    } else {
+      (None, None, Vec::new())
     };
 
-    let entry = self.review.files.entry(path.clone()).or_default();
-    entry.reviewed = new_reviewed;
+    let entry = self.review.files.entry(path.to_string()).or_default();
+    entry.reviewed = reviewed;
     entry.reviewed_hash = hash;
+    if snapshot.is_some() {
+      entry.reviewed_blob = snapshot;
+    }
+    entry.viewed_hunks = hunks.into_iter().collect();
     if entry.reviewed {
-      self.reviewed_files.insert(path.clone());
-      self.status = "Marked reviewed".to_string();
+      self.reviewed_files.insert(path.to_string());
     } else {
-      self.reviewed_files.remove(&path);
-      if entry.file_comment.is_none() && entry.comments.is_empty() {
-        self.review.files.remove(&path);
+      self.reviewed_files.remove(path);
+      if entry.is_empty() {
+        self.review.files.remove(path);
       }
-      self.status = "Marked unreviewed".to_string();
     }
-    if let Err(e) = self.persist_file_note(&path) {
-      self.status = format!("Failed to save reviewed state: {e}");
+    self.hunk_progress.remove(path);
+    Ok(())
+  }
+
+  /// Resolves every open comment of the selected file, or of all files below the selected
+  /// directory.
+  fn resolve_selected_files(&mut self) -> Result<()> {
+    let author = crate::git::author_identity(&self.repo);
+    let mut paths = Vec::new();
+    let mut resolved = 0;
+    for path in self.selected_paths() {
+      let Some(file) = self.review.files.get_mut(&path) else {
+        continue;
+      };
+      let open = file
+        .file_comment
+        .iter_mut()
+        .chain(file.comments.values_mut())
+        .filter(|c| !c.resolved);
+      let before = resolved;
+      for comment in open {
+        comment.resolve(author.clone(), None, None);
+        resolved += 1;
+      }
+      if resolved > before {
+        paths.push(path);
+      }
+    }
+    if paths.is_empty() {
+      self.status = "No open comments".to_string();
+      return Ok(());
     }
+    self.persist_file_notes(&paths)?;
+    self.status = format!("Resolved {resolved} comment(s) in {} file(s)", paths.len());
+    Ok(())
   }
 
   fn diff_row_at_visual_line(&self, visual: u32) -> usize {
@@ -1953,11 +2611,15 @@ impl App {
   }
 
   fn current_comment_target(&self) -> Option<CommentTarget> {
+    self.comment_target_at(self.diff_cursor)
+  }
+
+  fn comment_target_at(&self, row_idx: usize) -> Option<CommentTarget> {
     let path = self
       .files
       .get(self.file_selected)
       .map(|e| e.path.as_str())?;
-    let row = self.diff_rows.get(self.diff_cursor)?;
+    let row = self.diff_rows.get(row_idx)?;
     let locator = match row {
       RenderRow::FileHeader { .. } => CommentLocator::File,
       RenderRow::Unified(r) => match r.kind {
@@ -2006,6 +2668,17 @@ impl App {
       }
       RenderRow::Section { .. } => return None,
     };
+    if self.interdiff
+      && matches!(
+        locator,
+        CommentLocator::Line {
+          side: LineSide::Old,
+          ..
+        }
+      )
+    {
+      return None;
+    }
     Some(CommentTarget {
       path: path.to_string(),
       locator,
@@ -2013,6 +2686,9 @@ impl App {
   }
 
   fn begin_comment(&mut self) -> Result<()> {
+    if self.visual_anchor.is_some() {
+      return self.begin_range_comment();
+    }
     let Some(target) = self.current_comment_target() else {
       self.status = "Not a commentable line".to_string();
       return Ok(());
@@ -2020,6 +2696,62 @@ impl App {
     self.begin_comment_for_target(target)
   }
 
+  fn visual_range(&self) -> Option<(usize, usize)> {
+    let anchor = self.visual_anchor?;
+    Some((anchor.min(self.diff_cursor), anchor.max(self.diff_cursor)))
+  }
+
+  fn toggle_visual_selection(&mut self) {
+    if self.visual_anchor.take().is_some() {
+      self.status.clear();
+    } else {
+      self.visual_anchor = Some(self.diff_cursor);
+      self.status = format!(
+        "Visual selection: move to extend, {} to comment",
+        self.keymap.keys(Action::Comment)
+      );
+    }
+  }
+
+  /// Opens the editor for a comment spanning the selected rows. The range takes the side of the
+  /// row where the selection started and covers that side's lines within the selection.
+  fn begin_range_comment(&mut self) -> Result<()> {
+    let Some((start_row, end_row)) = self.visual_range() else {
+      return Ok(());
+    };
+    let anchor = self.visual_anchor.take().unwrap_or(start_row);
+    let side = match self.comment_target_at(anchor).map(|t| t.locator) {
+      Some(CommentLocator::Line { side, .. }) => side,
+      _ => match self.current_comment_target().map(|t| t.locator) {
+        Some(CommentLocator::Line { side, .. }) => side,
+        _ => {
+          self.status = "Select commentable lines for a range comment".to_string();
+          return Ok(());
+        }
+      },
+    };
+    let lines: Vec<u32> = (start_row..=end_row)
+      .filter_map(|row| match self.comment_target_at(row)?.locator {
+        CommentLocator::Line { side: s, line } if s == side => Some(line),
+        _ => None,
+      })
+      .collect();
+    let (Some(&first), Some(&last)) = (lines.iter().min(), lines.iter().max()) else {
+      self.status = "Select commentable lines for a range comment".to_string();
+      return Ok(());
+    };
+    let Some(path) = self.files.get(self.file_selected).map(|e| e.path.clone()) else {
+      self.status = "No file selected".to_string();
+      return Ok(());
+    };
+    self.begin_comment_for_target(CommentTarget {
+      path,
+      locator: CommentLocator::Line { side, line: first },
+    })?;
+    self.editor_range_end = Some(last);
+    Ok(())
+  }
+
   fn begin_file_comment(&mut self) -> Result<()> {
     let Some(path) = self.files.get(self.file_selected).map(|e| e.path.clone()) else {
       self.status = "No file selected".to_string();
@@ -2031,60 +2763,226 @@ impl App {
     })
   }
 
-  fn begin_comment_for_target(&mut self, target: CommentTarget) -> Result<()> {
-    let existing = match target.locator {
-      CommentLocator::File => self
-        .review
-        .file_comment(&target.path)
-        .map(|c| c.body.as_str())
-        .unwrap_or(""),
-      CommentLocator::Line { side, line } => self
-        .review
-        .line_comment(&target.path, side, line)
-        .map(|c| c.body.as_str())
-        .unwrap_or(""),
+  fn begin_reply(&mut self) -> Result<()> {
+    let Some(target) = self.current_comment_target() else {
+      self.status = "Not a commentable line".to_string();
+      return Ok(());
+    };
+    self.begin_reply_for_target(target)
+  }
+
+  fn begin_file_reply(&mut self) -> Result<()> {
+    let Some(path) = self.files.get(self.file_selected).map(|e| e.path.clone()) else {
+      self.status = "No file selected".to_string();
+      return Ok(());
+    };
+    self.begin_reply_for_target(CommentTarget {
+      path,
+      locator: CommentLocator::File,
+    })
+  }
+
+  fn begin_reply_for_target(&mut self, target: CommentTarget) -> Result<()> {
+    let exists = match target.locator {
+      CommentLocator::File => self.review.file_comment(&target.path).is_some(),
+      CommentLocator::Line { side, line } => {
+        self.review.line_comment(&target.path, side, line).is_some()
+      }
     };
+    if !exists {
+      self.status = "No comment to reply to".to_string();
+      return Ok(());
+    }
     self.editor_target = Some(target);
-    self.editor_buffer = crate::ui::textarea_from_string(existing);
+    self.editor_reply = true;
+    self.editor_buffer = crate::ui::empty_textarea();
     self.show_help = false;
     self.show_prompt = false;
     self.mode = Mode::EditComment;
     Ok(())
   }
 
-  fn accept_comment_and_move_on(&mut self) -> Result<()> {
-    let Some(target) = self.editor_target.clone() else {
-      self.mode = Mode::Browse;
+  fn accept_reply(&mut self, target: CommentTarget) -> Result<()> {
+    let body = crate::ui::textarea_contents(&self.editor_buffer);
+    self.mode = Mode::Browse;
+    self.editor_target = None;
+    self.editor_reply = false;
+    self.editor_resolve = false;
+    self.editor_range_end = None;
+    self.editor_buffer = crate::ui::empty_textarea();
+    if body.trim().is_empty() {
+      self.status = "Canceled".to_string();
+      return Ok(());
+    }
+
+    let key = match target.locator {
+      CommentLocator::File => None,
+      CommentLocator::Line { side, line } => Some(crate::review::LineKey { side, line }),
+    };
+    let Some(comment) = self
+      .review
+      .files
+      .get_mut(&target.path)
+      .and_then(|f| f.comment_mut(key))
+    else {
+      self.status = "No comment to reply to".to_string();
       return Ok(());
     };
+    comment.push_reply(
+      crate::git::author_identity(&self.repo),
+      body.trim_end().to_string(),
+    );
+    self.persist_file_note(&target.path)?;
+    self.status = "Replied".to_string();
+    Ok(())
+  }
 
-    let comment = crate::ui::textarea_contents(&self.editor_buffer);
-    if comment.trim().is_empty() {
-      let removed = match target.locator {
-        CommentLocator::File => self.review.remove_file_comment(&target.path),
-        CommentLocator::Line { side, line } => {
-          self.review.remove_line_comment(&target.path, side, line)
-        }
-      };
-      if removed {
-        self.persist_file_note(&target.path)?;
-        self.status = "Saved".to_string();
-      }
-    } else {
-      match target.locator {
-        CommentLocator::File => self.review.set_file_comment(&target.path, comment),
-        CommentLocator::Line { side, line } => {
-          self
-            .review
-            .set_line_comment(&target.path, side, line, comment)
-        }
+  /// Opens the editor for a message saying how the comment under the cursor was addressed.
+  fn begin_resolve(&mut self) -> Result<()> {
+    let Some(target) = self.current_comment_target() else {
+      self.status = "Not a commentable line".to_string();
+      return Ok(());
+    };
+    let comment = match target.locator {
+      CommentLocator::File => self.review.file_comment(&target.path),
+      CommentLocator::Line { side, line } => self.review.line_comment(&target.path, side, line),
+    };
+    let Some(comment) = comment else {
+      self.status = "No comment to resolve".to_string();
+      return Ok(());
+    };
+    let message = comment
+      .resolution
+      .as_ref()
+      .and_then(|r| r.message.clone())
+      .unwrap_or_default();
+    self.editor_target = Some(target);
+    self.editor_resolve = true;
+    self.editor_buffer = crate::ui::textarea_from_string(&message);
+    self.show_help = false;
+    self.show_prompt = false;
+    self.mode = Mode::EditComment;
+    Ok(())
+  }
+
+  fn accept_resolve(&mut self, target: CommentTarget) -> Result<()> {
+    let message = crate::ui::textarea_contents(&self.editor_buffer);
+    self.mode = Mode::Browse;
+    self.editor_target = None;
+    self.editor_resolve = false;
+    self.editor_range_end = None;
+    self.editor_buffer = crate::ui::empty_textarea();
+
+    let key = match target.locator {
+      CommentLocator::File => None,
+      CommentLocator::Line { side, line } => Some(crate::review::LineKey { side, line }),
+    };
+    let Some(comment) = self
+      .review
+      .files
+      .get_mut(&target.path)
+      .and_then(|f| f.comment_mut(key))
+    else {
+      self.status = "No comment to resolve".to_string();
+      return Ok(());
+    };
+    let message = Some(message.trim().to_string()).filter(|m| !m.is_empty());
+    comment.resolve(crate::git::author_identity(&self.repo), message, None);
+    self.persist_file_note(&target.path)?;
+    self.status = "Resolved".to_string();
+    Ok(())
+  }
+
+  fn begin_comment_for_target(&mut self, target: CommentTarget) -> Result<()> {
+    let existing = match target.locator {
+      CommentLocator::File => self
+        .review
+        .file_comment(&target.path)
+        .map(crate::review::body_with_tags)
+        .unwrap_or_default(),
+      CommentLocator::Line { side, line } => self
+        .review
+        .line_comment(&target.path, side, line)
+        .map(crate::review::body_with_suggestion)
+        .unwrap_or_default(),
+    };
+    self.editor_target = Some(target);
+    self.editor_range_end = None;
+    self.editor_buffer = crate::ui::textarea_from_string(&existing);
+    self.show_help = false;
+    self.show_prompt = false;
+    self.mode = Mode::EditComment;
+    Ok(())
+  }
+
+  fn accept_comment_and_move_on(&mut self) -> Result<()> {
+    let Some(target) = self.editor_target.clone() else {
+      self.mode = Mode::Browse;
+      return Ok(());
+    };
+    if self.editor_reply {
+      return self.accept_reply(target);
+    }
+    if self.editor_resolve {
+      return self.accept_resolve(target);
+    }
+
+    let comment = crate::ui::textarea_contents(&self.editor_buffer);
+    if comment.trim().is_empty() {
+      let removed = match target.locator {
+        CommentLocator::File => self.review.remove_file_comment(&target.path),
+        CommentLocator::Line { side, line } => {
+          self.review.remove_line_comment(&target.path, side, line)
+        }
+      };
+      if removed {
+        self.persist_file_note(&target.path)?;
+        self.status = "Saved".to_string();
+      }
+    } else {
+      let (comment, tags) = crate::review::take_tags(&comment);
+      let key = match target.locator {
+        CommentLocator::File => {
+          self.review.set_file_comment(&target.path, comment);
+          None
+        }
+        CommentLocator::Line { side, line } => {
+          self
+            .review
+            .set_line_comment(&target.path, side, line, comment);
+          Some(crate::review::LineKey { side, line })
+        }
+      };
+      if let Some(comment) = self
+        .review
+        .files
+        .get_mut(&target.path)
+        .and_then(|f| f.comment_mut(key))
+      {
+        tags.apply(comment);
+      }
+      if let CommentLocator::Line { side, line } = target.locator
+        && let Some(end) = self.editor_range_end
+      {
+        self
+          .review
+          .set_line_comment_end(&target.path, side, line, Some(end));
+      }
+      if let CommentLocator::Line { side, line } = target.locator {
+        self.set_suggestion_from_body(&target.path, side, line)?;
       }
       if let CommentLocator::Line { side, line } = target.locator
+        && !self.in_rev_view()
+        && let Some(last_line) = self
+          .review
+          .line_comment(&target.path, side, line)
+          .map(|c| c.last_line(crate::review::LineKey { side, line }))
         && let Some(hash) = crate::add_cmd::current_snippet_hash(
           &self.repo,
           self.base_ref.as_deref(),
           &target.path,
           crate::review::LineKey { side, line },
+          last_line,
         )
       {
         self
@@ -2097,6 +2995,9 @@ impl App {
 
     self.mode = Mode::Browse;
     self.editor_target = None;
+    self.editor_reply = false;
+    self.editor_resolve = false;
+    self.editor_range_end = None;
     self.editor_buffer = crate::ui::empty_textarea();
 
     for i in (self.diff_cursor + 1)..self.diff_rows.len() {
@@ -2129,6 +3030,74 @@ impl App {
     Ok(())
   }
 
+  /// Moves a ```suggestion block typed into the comment body into the comment's suggestion.
+  fn set_suggestion_from_body(&mut self, path: &str, side: LineSide, line: u32) -> Result<()> {
+    let key = crate::review::LineKey { side, line };
+    let Some(comment) = self
+      .review
+      .files
+      .get_mut(path)
+      .and_then(|f| f.comments.get_mut(&key))
+    else {
+      return Ok(());
+    };
+    let (body, replacement) = crate::review::take_suggestion(&comment.body);
+    comment.body = body;
+    comment.suggestion = match replacement {
+      Some(_) if side == LineSide::Old => {
+        self.status = "Suggestions apply to new-side lines only".to_string();
+        None
+      }
+      Some(replacement) => {
+        let last = comment.last_line(key);
+        Some(crate::apply_suggestion_cmd::new_suggestion(
+          &self.repo,
+          path,
+          line,
+          last,
+          replacement,
+        )?)
+      }
+      None => None,
+    };
+    Ok(())
+  }
+
+  fn apply_suggestion(&mut self) -> Result<()> {
+    let Some(CommentTarget {
+      path,
+      locator: CommentLocator::Line { side, line },
+    }) = self.current_comment_target()
+    else {
+      self.status = "Not a commentable line".to_string();
+      return Ok(());
+    };
+    let Some((key, _)) = self
+      .review
+      .line_comment_covering(&path, side, line)
+      .filter(|(_, c)| c.suggestion.is_some())
+    else {
+      self.status = "No suggestion here".to_string();
+      return Ok(());
+    };
+    let Some(comment) = self
+      .review
+      .files
+      .get_mut(&path)
+      .and_then(|f| f.comments.get_mut(&key))
+    else {
+      return Ok(());
+    };
+    if let Err(err) = crate::apply_suggestion_cmd::apply(&self.repo, &path, key, comment) {
+      self.status = format!("{err:#}");
+      return Ok(());
+    }
+    self.persist_file_note(&path)?;
+    self.reload_file_list()?;
+    self.status = "Applied suggestion".to_string();
+    Ok(())
+  }
+
   fn delete_comment(&mut self) -> Result<()> {
     let Some(target) = self.current_comment_target() else {
       self.status = "Not a commentable line".to_string();
@@ -2150,6 +3119,18 @@ impl App {
   }
 
   fn persist_file_note(&mut self, path: &str) -> Result<()> {
+    self.persist_file_notes(&[path.to_string()])
+  }
+
+  /// Persists several files' notes (resolved state, the draft sync and reviewed state) as a
+  /// single notes commit.
+  fn persist_file_notes(&mut self, paths: &[String]) -> Result<()> {
+    for path in paths {
+      self.prune_viewed_hunks(path)?;
+    }
+    if self.in_rev_view() {
+      return self.persist_rev_notes(paths);
+    }
     crate::add_cmd::write_draft_from_review_no_meta(
       &self.repo,
       self.base_ref.as_deref(),
@@ -2161,279 +3142,978 @@ impl App {
       return Ok(());
     }
 
-    self.persist_resolved_state(path)?;
+    crate::reanchor::reanchor_notes(&self.repo, &self.notes_ref, self.base_ref.as_deref())?;
+    let mut tx = crate::notes::Transaction::new(&self.repo, &self.notes_ref)?;
+    for path in paths {
+      self.stage_resolved_state(&mut tx, path)?;
+    }
+    let sync = crate::add_cmd::stage_draft_sync(
+      &mut tx,
+      &self.repo,
+      &self.notes_ref,
+      self.base_ref.as_deref(),
+    )?;
+    for path in paths {
+      self.stage_reviewed_state(&mut tx, path)?;
+    }
+    tx.commit().context("write review state")?;
 
-    let report =
-      crate::add_cmd::sync_draft_notes(&self.repo, &self.notes_ref, self.base_ref.as_deref())?;
+    let report = sync.finish(&self.repo, &self.notes_ref, self.base_ref.as_deref())?;
     if report.draft_updated {
       self.refresh_review_from_sources()?;
     }
+    Ok(())
+  }
 
-    self.persist_reviewed_state(path)?;
+  /// Commit reviews have no draft: the review is written to the commit's notes as-is.
+  fn persist_rev_notes(&mut self, paths: &[String]) -> Result<()> {
+    let mut tx = crate::notes::Transaction::new(&self.repo, &self.notes_ref)?;
+    for path in paths {
+      let Some(oid) = self.note_key_oid(path)? else {
+        continue;
+      };
+      match self.review.files.get(path) {
+        Some(file) if !file.is_empty() => {
+          tx.upsert(&oid, &crate::review::encode_file_note(file));
+        }
+        _ => {
+          if tx
+            .read(&oid)
+            .with_context(|| format!("read file note for '{path}'"))?
+            .is_some()
+          {
+            tx.remove(&oid);
+          }
+        }
+      }
+    }
+    tx.commit().context("write commit review")?;
     Ok(())
   }
 
-  fn persist_resolved_state(&mut self, path: &str) -> Result<()> {
-    let Some(head) = self.head_commit_oid else {
+  fn stage_resolved_state(&self, tx: &mut crate::notes::Transaction<'_>, path: &str) -> Result<()> {
+    let Some(oid) = self.note_key_oid(path)? else {
       return Ok(());
     };
-    let (view_for_key, base_for_key) = match self.view {
-      ViewKind::Base => (ViewKind::Base, self.base_ref_for_key()),
-      _ => (ViewKind::All, None),
-    };
-
-    let oid = crate::git::note_file_key_oid(&self.repo, head, view_for_key, base_for_key, path)?;
-    let note = crate::notes::read(&self.repo, &self.notes_ref, &oid)
+    let note = tx
+      .read(&oid)
       .with_context(|| format!("read file note for '{path}'"))?;
     let Some(text) = note.as_deref() else {
       return Ok(());
     };
     let mut file = crate::review::decode_file_note(text).unwrap_or_default();
 
+    // The draft carries bodies only; resolved state and new replies go straight to the notes.
     if let Some(review_file) = self.review.files.get(path) {
       if let (Some(src), Some(dest)) = (&review_file.file_comment, &mut file.file_comment) {
         dest.resolved = src.resolved;
+        dest.resolution = src.resolution.clone();
+        crate::review::merge_replies(&mut dest.replies, &src.replies);
       }
       for (key, comment) in &review_file.comments {
         if let Some(existing) = file.comments.get_mut(key) {
           existing.resolved = comment.resolved;
+          existing.resolution = comment.resolution.clone();
+          existing.outdated |= comment.outdated;
+          crate::review::merge_replies(&mut existing.replies, &comment.replies);
         }
       }
     }
+    // Outdated comments never reach the draft, so deleting one only shows up as its absence here.
+    let review_file = self.review.files.get(path);
+    file.comments.retain(|key, comment| {
+      !comment.outdated
+        || comment.resolved
+        || review_file.is_some_and(|f| f.comments.contains_key(key))
+    });
 
-    if !file.comments.is_empty() || file.file_comment.is_some() || file.reviewed {
-      let note = crate::review::encode_file_note(&file);
-      crate::notes::write(&self.repo, &self.notes_ref, &oid, Some(&note))
-        .with_context(|| format!("write file note for '{path}'"))?;
+    if !file.is_empty() {
+      tx.upsert(&oid, &crate::review::encode_file_note(&file));
     }
     Ok(())
   }
 
-  fn persist_reviewed_state(&mut self, path: &str) -> Result<()> {
-    let Some(head) = self.head_commit_oid else {
+  fn stage_reviewed_state(&self, tx: &mut crate::notes::Transaction<'_>, path: &str) -> Result<()> {
+    let Some(oid) = self.note_key_oid(path)? else {
       return Ok(());
     };
-    // Worktree review notes are view-agnostic: always store them under the `all` key.
-    // Base-diff notes stay under the `base` key because line anchors are relative to that diff.
-    let (view_for_key, base_for_key) = match self.view {
-      ViewKind::Base => (ViewKind::Base, self.base_ref_for_key()),
-      _ => (ViewKind::All, None),
-    };
-
-    let oid = crate::git::note_file_key_oid(&self.repo, head, view_for_key, base_for_key, path)?;
-
-    let note = crate::notes::read(&self.repo, &self.notes_ref, &oid)
+    let note = tx
+      .read(&oid)
       .with_context(|| format!("read file note for '{path}'"))?;
     let mut file = note
       .as_deref()
       .and_then(crate::review::decode_file_note)
       .unwrap_or_default();
 
-    let (reviewed, reviewed_hash) = self
-      .review
-      .files
-      .get(path)
-      .map(|f| (f.reviewed, f.reviewed_hash.clone()))
-      .unwrap_or((false, None));
+    let current = self.review.files.get(path);
+    file.reviewed = current.is_some_and(|f| f.reviewed);
+    file.reviewed_hash = current.and_then(|f| f.reviewed_hash.clone());
+    file.reviewed_blob = current.and_then(|f| f.reviewed_blob.clone());
+    file.viewed_hunks = current.map(|f| f.viewed_hunks.clone()).unwrap_or_default();
+
+    if !file.is_empty() {
+      tx.upsert(&oid, &crate::review::encode_file_note(&file));
+    } else if note.is_some() {
+      tx.remove(&oid);
+    }
+    Ok(())
+  }
+}
+
+fn parse_diff_context(raw: &str) -> Option<u32> {
+  let parsed = raw.trim().parse::<u32>().ok()?;
+  Some(parsed.clamp(MIN_DIFF_CONTEXT, MAX_DIFF_CONTEXT))
+}
+
+impl App {
+  fn read_before_after(
+    &self,
+    base_tree: Option<&gix::Tree<'_>>,
+    path: &str,
+  ) -> Result<(Option<String>, Option<String>)> {
+    self.read_before_after_for_view(self.view, base_tree, path)
+  }
+
+  fn read_before_after_for_view(
+    &self,
+    view: ViewKind,
+    base_tree: Option<&gix::Tree<'_>>,
+    path: &str,
+  ) -> Result<(Option<String>, Option<String>)> {
+    let out = match view {
+      ViewKind::All => (
+        crate::git::try_read_head(&self.repo, path)?,
+        crate::git::try_read_worktree(&self.repo, path)?,
+      ),
+      ViewKind::Unstaged => (
+        crate::git::try_read_index(&self.repo, path)?,
+        crate::git::try_read_worktree(&self.repo, path)?,
+      ),
+      ViewKind::Staged => (
+        crate::git::try_read_head(&self.repo, path)?,
+        crate::git::try_read_index(&self.repo, path)?,
+      ),
+      ViewKind::Base => (
+        match base_tree {
+          Some(t) => crate::git::try_read_tree(t, path)?,
+          None => None,
+        },
+        crate::git::try_read_head(&self.repo, path)?,
+      ),
+      ViewKind::Commit | ViewKind::Range => (
+        match base_tree {
+          Some(t) => crate::git::try_read_tree(t, path)?,
+          None => None,
+        },
+        match self.rev_pair() {
+          Some((_, to)) => crate::git::try_read_at(&self.repo, to, path)?,
+          None => None,
+        },
+      ),
+    };
+    Ok(out)
+  }
+
+  fn review_hash_for_view(
+    &self,
+    view: ViewKind,
+    base_tree: Option<&gix::Tree<'_>>,
+    path: &str,
+  ) -> Result<Option<String>> {
+    let (before, after) = self.read_before_after_for_view(view, base_tree, path)?;
+    if before.is_none() && after.is_none() {
+      return Ok(None);
+    }
+    let hash = review_fingerprint(before.as_deref(), after.as_deref())?;
+    Ok(Some(hash))
+  }
+
+  fn review_hash_for_current_view_with_base(
+    &self,
+    base_tree: Option<&gix::Tree<'_>>,
+    path: &str,
+  ) -> Result<Option<String>> {
+    self.review_hash_for_view(self.review_view(), base_tree, path)
+  }
+
+  /// The view reviewed state is tracked against: the staging views count as `All`.
+  fn review_view(&self) -> ViewKind {
+    match self.view {
+      ViewKind::Base | ViewKind::Commit | ViewKind::Range => self.view,
+      _ => ViewKind::All,
+    }
+  }
+
+  /// Stores the content `path` has now, returning the blob id kept as the review snapshot.
+  fn snapshot_for_current_view(&self, path: &str) -> Result<Option<String>> {
+    let base_tree = self.view_base_tree()?;
+    let (_, after) =
+      self.read_before_after_for_view(self.review_view(), base_tree.as_ref(), path)?;
+    let Some(after) = after else {
+      return Ok(None);
+    };
+    let id = crate::git::write_snapshot(&self.repo, &after)?;
+    Ok(Some(id.to_string()))
+  }
 
-    file.reviewed = reviewed;
-    file.reviewed_hash = reviewed_hash;
+  fn toggle_diff_view_mode(&mut self) -> Result<()> {
+    let keep_new_line = self.diff_rows.get(self.diff_cursor).and_then(|r| match r {
+      RenderRow::Unified(r) => r.new_line,
+      RenderRow::SideBySide(r) => r.new_line,
+      RenderRow::Decorated(r) => Some(r.line_number),
+      RenderRow::Section { .. } => None,
+      RenderRow::FileHeader { .. } => None,
+    });
+
+    self.diff_view_mode = match self.diff_view_mode {
+      DiffViewMode::Decorated => DiffViewMode::SideBySide,
+      DiffViewMode::SideBySide => DiffViewMode::Unified,
+      DiffViewMode::Unified => DiffViewMode::Decorated,
+    };
+
+    self.reload_diff_for_selected()?;
+
+    if let Some(n) = keep_new_line
+      && let Some(idx) = self.diff_rows.iter().position(|r| match r {
+        RenderRow::Unified(r) => r.new_line == Some(n),
+        RenderRow::SideBySide(r) => r.new_line == Some(n),
+        RenderRow::Decorated(r) => r.line_number == n,
+        RenderRow::Section { .. } => false,
+        RenderRow::FileHeader { .. } => false,
+      })
+    {
+      self.diff_cursor = idx;
+    }
+
+    Ok(())
+  }
+
+  fn adjust_diff_context(&mut self, delta: i32) -> Result<()> {
+    let cur = i32::try_from(self.diff_context).unwrap_or(i32::MAX);
+    let min = i32::try_from(MIN_DIFF_CONTEXT).unwrap_or(i32::MIN);
+    let max = i32::try_from(MAX_DIFF_CONTEXT).unwrap_or(i32::MAX);
+    let next = (cur + delta).clamp(min, max);
+    let next = u32::try_from(next).unwrap_or(self.diff_context);
+    if next == self.diff_context {
+      return Ok(());
+    }
+    let keep_line = self.keep_cursor_line();
+    self.diff_context = next;
+    self.reload_diff_for_selected()?;
+    if let Some(k) = keep_line
+      && let Some(idx) = self.find_row_for_keep_line(k)
+    {
+      self.diff_cursor = idx;
+    }
+    if let Err(e) = crate::git::write_local_config_value(
+      &self.repo,
+      CONFIG_DIFF_CONTEXT_KEY,
+      &self.diff_context.to_string(),
+    ) {
+      self.status = format!("Failed to save diff context: {e}");
+    }
+    Ok(())
+  }
 
-    if !file.comments.is_empty() || file.file_comment.is_some() || file.reviewed {
-      let note = crate::review::encode_file_note(&file);
-      crate::notes::write(&self.repo, &self.notes_ref, &oid, Some(&note))
-        .with_context(|| format!("write file note for '{path}'"))?;
+  /// Narrows `files` to the listed files the file filter lets through, keeping `keep_path`
+  /// selected while it is shown. Marking files reviewed or resolving comments doesn't hide them
+  /// until the list is filtered again.
+  fn apply_file_filter(&mut self, keep_path: Option<String>) {
+    let filter = &self.file_filter;
+    self.files = self
+      .listed_files
+      .iter()
+      .filter(|e| {
+        let unresolved = self.review.comment_state(&e.path) == CommentState::HasUnresolved;
+        filter.matches(e, self.reviewed_files.contains(&e.path), unresolved)
+      })
+      .cloned()
+      .collect();
+    self.file_tree = FileTreeView::build(&self.files, &self.collapsed_dirs);
+    self.file_selected = keep_path
+      .and_then(|path| self.files.iter().position(|e| e.path == path))
+      .unwrap_or(self.file_selected)
+      .min(self.files.len().saturating_sub(1));
+    self.file_scroll = 0;
+    if let Some(dir) = self.selected_dir.as_deref()
+      && self.file_tree.dir_row(dir).is_none()
+    {
+      self.selected_dir = None;
+    }
+    if self.files.is_empty() && !self.listed_files.is_empty() {
+      self.status = format!(
+        "No files match the filter ({} to change it)",
+        self.keymap.keys(Action::FilterFiles)
+      );
+    }
+  }
+
+  /// Re-filters the file list after the filter changed, reloading the diff if another file ends
+  /// up selected.
+  fn refilter_files(&mut self) -> Result<()> {
+    let current = self.files.get(self.file_selected).map(|e| e.path.clone());
+    self.apply_file_filter(current.clone());
+    let selected = self.files.get(self.file_selected).map(|e| e.path.clone());
+    if selected != current || selected.is_none() {
+      self.reload_diff_for_selected()?;
+    }
+    if !self.files.is_empty() {
+      self.status = if self.file_filter.is_active() {
+        format!(
+          "Showing {} of {} files ({})",
+          self.files.len(),
+          self.listed_files.len(),
+          self.file_filter.describe()
+        )
+      } else {
+        "Showing all files".to_string()
+      };
+    }
+    Ok(())
+  }
+
+  fn handle_filter_key(&mut self, key: KeyEvent) -> Result<bool> {
+    if let Some(input) = self.filter_glob_input.as_mut() {
+      match self.keymap.action(KeyContext::Input, key) {
+        Some(Action::InputCancel) => self.filter_glob_input = None,
+        Some(Action::InputAccept) => {
+          let input = self.filter_glob_input.take().unwrap_or_default();
+          match self.file_filter.set_glob(&input) {
+            Ok(()) => self.refilter_files()?,
+            Err(e) => self.status = format!("{e:#}"),
+          }
+        }
+        _ => match key.code {
+          KeyCode::Backspace => {
+            input.pop();
+          }
+          KeyCode::Char(c) if !has_ctrl_alt(key) => input.push(c),
+          _ => {}
+        },
+      }
+      return Ok(false);
+    }
+
+    match self.keymap.action(KeyContext::FileFilter, key) {
+      Some(Action::FilterClose) => self.mode = Mode::Browse,
+      Some(Action::FilterUnreviewed) => {
+        self.file_filter.unreviewed = !self.file_filter.unreviewed;
+        self.refilter_files()?;
+      }
+      Some(Action::FilterUnresolved) => {
+        self.file_filter.unresolved = !self.file_filter.unresolved;
+        self.refilter_files()?;
+      }
+      Some(Action::FilterChange) => {
+        self.file_filter.cycle_change();
+        self.refilter_files()?;
+      }
+      Some(Action::FilterGlob) => {
+        self.filter_glob_input = Some(self.file_filter.glob().unwrap_or_default().to_string());
+      }
+      Some(Action::FilterClear) => {
+        self.file_filter = FileFilter::default();
+        self.refilter_files()?;
+      }
+      _ => {}
+    }
+    Ok(false)
+  }
+
+  fn open_file_finder(&mut self) {
+    self.file_finder = Some(FileFinder {
+      matches: finder_matches(&self.listed_files, ""),
+      ..Default::default()
+    });
+    self.mode = Mode::FileFinder;
+    self.status.clear();
+  }
+
+  fn handle_finder_key(&mut self, key: KeyEvent) -> Result<bool> {
+    let Some(finder) = self.file_finder.as_mut() else {
+      self.mode = Mode::Browse;
+      return Ok(false);
+    };
+    let last = finder.matches.len().saturating_sub(1);
+    match self.keymap.action(KeyContext::FileFinder, key) {
+      Some(Action::FinderCancel) => {
+        self.file_finder = None;
+        self.mode = Mode::Browse;
+      }
+      Some(Action::FinderOpen) => {
+        let path = finder.matches.get(finder.selected).cloned();
+        self.file_finder = None;
+        self.mode = Mode::Browse;
+        if let Some(path) = path {
+          self.open_listed_file(&path)?;
+        }
+      }
+      Some(Action::FinderUp) => finder.selected = finder.selected.saturating_sub(1),
+      Some(Action::FinderDown) => finder.selected = (finder.selected + 1).min(last),
+      _ => match key.code {
+        KeyCode::Backspace => {
+          finder.query.pop();
+          finder.matches = finder_matches(&self.listed_files, &finder.query);
+          finder.selected = 0;
+        }
+        KeyCode::Char(c) if !has_ctrl_alt(key) => {
+          finder.query.push(c);
+          finder.matches = finder_matches(&self.listed_files, &finder.query);
+          finder.selected = 0;
+        }
+        _ => {}
+      },
+    }
+    Ok(false)
+  }
+
+  /// Selects `path` and moves focus to its diff, clearing the file filter if it hides the file.
+  fn open_listed_file(&mut self, path: &str) -> Result<()> {
+    if !self.files.iter().any(|e| e.path == path) {
+      self.file_filter = FileFilter::default();
+      self.apply_file_filter(None);
+      self.status = format!("Cleared the file filter to show {path}");
+    }
+    let Some(idx) = self.files.iter().position(|e| e.path == path) else {
+      return Ok(());
+    };
+    self.selected_dir = None;
+    self.file_selected = idx;
+    self.reload_diff_for_selected()?;
+    self.focus = Focus::Diff;
+    Ok(())
+  }
+
+  fn begin_search(&mut self, backward: bool) {
+    self.search = Some(Search {
+      pattern: String::new(),
+      backward,
+      all_files: self.focus == Focus::Files,
+    });
+    self.search_origin = (self.file_selected, self.diff_cursor);
+    self.visual_anchor = None;
+    self.mode = Mode::Search;
+    self.status.clear();
+  }
+
+  fn handle_search_key(&mut self, key: KeyEvent) -> Result<bool> {
+    let Some(search) = self.search.as_mut() else {
+      self.mode = Mode::Browse;
+      return Ok(false);
+    };
+    match self.keymap.action(KeyContext::Input, key) {
+      Some(Action::InputCancel) => {
+        self.search = None;
+        self.mode = Mode::Browse;
+        let (file, cursor) = self.search_origin;
+        if file != self.file_selected {
+          self.selected_dir = None;
+          self.file_selected = file;
+          self.reload_diff_for_selected()?;
+        }
+        self.diff_cursor = cursor.min(self.diff_rows.len().saturating_sub(1));
+        self.status.clear();
+      }
+      Some(Action::InputAccept) => {
+        self.mode = Mode::Browse;
+        if search.pattern.is_empty() {
+          self.search = None;
+          return Ok(false);
+        }
+        let search = search.clone();
+        if self.search_preview() {
+          self.focus = Focus::Diff;
+        } else if !(search.all_files
+          && self.search_other_files(&search.pattern, !search.backward)?)
+        {
+          self.status = format!("Pattern not found: {}", search.pattern);
+        }
+      }
+      _ => match key.code {
+        KeyCode::Backspace => {
+          search.pattern.pop();
+          self.search_preview();
+        }
+        KeyCode::Char(c) if !has_ctrl_alt(key) => {
+          search.pattern.push(c);
+          self.search_preview();
+        }
+        _ => {}
+      },
+    }
+    Ok(false)
+  }
+
+  /// Diff rows matching `pattern`, top to bottom.
+  fn search_matches(&self, pattern: &str) -> Vec<usize> {
+    self
+      .diff_rows
+      .iter()
+      .enumerate()
+      .filter(|(_, row)| crate::search::row_matches(row, pattern))
+      .map(|(idx, _)| idx)
+      .collect()
+  }
+
+  /// Moves the cursor to the first match from where the search started, wrapping around the
+  /// diff. Returns whether there was one.
+  fn search_preview(&mut self) -> bool {
+    let Some(search) = self.search.as_ref() else {
+      return false;
+    };
+    let (file, cursor) = self.search_origin;
+    let origin = if file == self.file_selected {
+      cursor
+    } else {
+      0
+    };
+    let matches = self.search_matches(&search.pattern);
+    let found = if search.backward {
+      matches
+        .iter()
+        .rev()
+        .find(|&&row| row <= origin)
+        .or(matches.last())
+    } else {
+      matches
+        .iter()
+        .find(|&&row| row >= origin)
+        .or(matches.first())
+    };
+    match found {
+      Some(&row) => {
+        self.diff_cursor = row;
+        self.status = format!("{} match(es)", matches.len());
+        true
+      }
+      None => {
+        self.diff_cursor = origin.min(self.diff_rows.len().saturating_sub(1));
+        self.status.clear();
+        false
+      }
+    }
+  }
+
+  /// `n` (`next`) and `N`: the next or previous match in the search's direction. Searches
+  /// started from the file list continue in the other files of the view; others wrap around.
+  fn step_search(&mut self, next: bool) -> Result<()> {
+    let Some(search) = self.search.clone() else {
+      return Ok(());
+    };
+    let down = next != search.backward;
+    let matches = self.search_matches(&search.pattern);
+    let found = if down {
+      matches.iter().find(|&&row| row > self.diff_cursor)
+    } else {
+      matches.iter().rev().find(|&&row| row < self.diff_cursor)
+    };
+    if let Some(&row) = found {
+      self.diff_cursor = row;
+      let idx = matches.iter().position(|&r| r == row).unwrap_or(0);
+      self.status = format!("Match {} of {}", idx + 1, matches.len());
+      return Ok(());
+    }
+    if search.all_files && self.search_other_files(&search.pattern, down)? {
+      return Ok(());
+    }
+    let wrapped = if down {
+      matches.first()
+    } else {
+      matches.last()
+    };
+    match wrapped {
+      Some(&row) => {
+        self.diff_cursor = row;
+        self.status = "Search wrapped".to_string();
+      }
+      None => self.status = format!("Pattern not found: {}", search.pattern),
+    }
+    Ok(())
+  }
+
+  /// Opens the next file of the view (wrapping around) whose diff matches `pattern`, on its first
+  /// match, or its last one when searching up. Returns whether one was found.
+  fn search_other_files(&mut self, pattern: &str, down: bool) -> Result<bool> {
+    let (start, cursor) = (self.file_selected, self.diff_cursor);
+    let len = self.files.len();
+    for step in 1..len {
+      let idx = if down {
+        (start + step) % len
+      } else {
+        (start + len - step) % len
+      };
+      let path = self.files[idx].path.clone();
+      let (before, after) = {
+        let base_tree = self.view_base_tree()?;
+        self.read_before_after(base_tree.as_ref(), &path)?
+      };
+      let in_text =
+        |text: Option<String>| text.is_some_and(|t| crate::search::contains(&t, pattern));
+      if !in_text(before) && !in_text(after) {
+        continue;
+      }
+      self.selected_dir = None;
+      self.file_selected = idx;
+      self.reload_diff_for_selected()?;
+      let matches = self.search_matches(pattern);
+      let row = if down {
+        matches.first()
+      } else {
+        matches.last()
+      };
+      if let Some(&row) = row {
+        self.diff_cursor = row;
+        self.focus = Focus::Diff;
+        self.status = format!("{path}: {} match(es)", matches.len());
+        return Ok(true);
+      }
+    }
+    if self.file_selected != start {
+      self.selected_dir = None;
+      self.file_selected = start;
+      self.reload_diff_for_selected()?;
+      self.diff_cursor = cursor.min(self.diff_rows.len().saturating_sub(1));
+    }
+    Ok(false)
+  }
+
+  /// Moves to the start of the next hunk not marked viewed.
+  fn jump_next_hunk(&mut self) {
+    let current = self.row_hunks.get(self.diff_cursor).copied().flatten();
+    let next = (self.diff_cursor + 1..self.diff_rows.len()).find(|&i| {
+      let hunk = self.row_hunks.get(i).copied().flatten();
+      hunk.is_some() && hunk != current && !self.viewed_rows.get(i).copied().unwrap_or(false)
+    });
+    match next {
+      Some(i) => self.diff_cursor = i,
+      None => self.status = "No unviewed hunks below".to_string(),
+    }
+  }
+
+  fn toggle_hunk_viewed(&mut self) -> Result<()> {
+    if self.interdiff {
+      self.status = "Hunks can't be marked in the since-last-review diff".to_string();
+      return Ok(());
+    }
+    let Some(path) = self.files.get(self.file_selected).map(|e| e.path.clone()) else {
+      return Ok(());
+    };
+    let Some(hash) = self
+      .row_hunks
+      .get(self.diff_cursor)
+      .copied()
+      .flatten()
+      .and_then(|i| self.current_hunks.get(i))
+      .map(|h| h.hash.clone())
+    else {
+      self.status = "Not in a hunk".to_string();
+      return Ok(());
+    };
+
+    let file = self.review.files.entry(path.clone()).or_default();
+    let viewed = !file.viewed_hunks.remove(&hash);
+    if viewed {
+      file.viewed_hunks.insert(hash);
+    } else if file.is_empty() {
+      self.review.files.remove(&path);
+    }
+    self.refresh_viewed_rows();
+    self.update_hunk_progress(&path);
+    self.persist_file_note(&path)?;
+    if viewed {
+      self.status = "Hunk viewed".to_string();
+      self.jump_next_hunk();
+    } else {
+      self.status = "Hunk not viewed".to_string();
+    }
+    Ok(())
+  }
+
+  /// Stages (or unstages) the selected file when the file list has focus, otherwise the selected
+  /// lines or the hunk under the cursor, then reloads the view.
+  fn stage_selection(&mut self, stage: bool) -> Result<()> {
+    if !matches!(
+      self.view,
+      ViewKind::All | ViewKind::Unstaged | ViewKind::Staged
+    ) {
+      self.status = "Staging works in the all, unstaged and staged views".to_string();
+      return Ok(());
+    }
+    let Some(path) = self.files.get(self.file_selected).map(|e| e.path.clone()) else {
+      self.status = "No file selected".to_string();
+      return Ok(());
+    };
+
+    let selected_dir = self.selected_dir.clone();
+    let what = if self.focus == Focus::Files {
+      let paths = self.selected_paths();
+      for path in &paths {
+        if stage {
+          crate::git::stage_file(&self.repo, path)?;
+        } else {
+          crate::git::unstage_file(&self.repo, path)?;
+        }
+      }
+      if selected_dir.is_some() {
+        "directory"
+      } else {
+        "file"
+      }
     } else {
-      crate::notes::write(&self.repo, &self.notes_ref, &oid, None)
-        .with_context(|| format!("delete file note for '{path}'"))?;
-    }
-    Ok(())
-  }
-}
+      if self.visual_range().is_some() && self.effective_diff_view_mode() == DiffViewMode::Decorated
+      {
+        // Removed lines are not shown there, so a selection cannot say which of them go along.
+        self.status = "Stage lines from the unified or side-by-side view".to_string();
+        return Ok(());
+      }
+      let Some((picked, what)) = self.picked_changes() else {
+        self.status = "No changed lines here".to_string();
+        return Ok(());
+      };
+      if !self.stage_lines(&path, stage, &picked)? {
+        return Ok(());
+      }
+      what
+    };
 
-fn merge_file_review(target: &mut FileReview, incoming: FileReview) {
-  if incoming.reviewed {
-    target.reviewed = true;
-    if target.reviewed_hash.is_none() {
-      target.reviewed_hash = incoming.reviewed_hash;
+    let focus = self.focus;
+    let cursor = self.diff_cursor;
+    self.visual_anchor = None;
+    self.reload_view()?;
+    if let Some(idx) = self.files.iter().position(|e| e.path == path) {
+      self.selected_dir = None;
+      self.file_selected = idx;
+      self.reload_diff_for_selected()?;
+      self.focus = focus;
+      self.diff_cursor = cursor.min(self.diff_rows.len().saturating_sub(1));
     }
-  }
-  match (&target.file_comment, &incoming.file_comment) {
-    (None, Some(_)) => target.file_comment = incoming.file_comment,
-    (Some(t), Some(i)) if t.resolved && !i.resolved => target.file_comment = incoming.file_comment,
-    _ => {}
+    if let Some(dir) = selected_dir
+      && self.file_tree.dir_row(&dir).is_some()
+    {
+      self.selected_dir = Some(dir);
+    }
+    self.status = format!("{} {what}", if stage { "Staged" } else { "Unstaged" });
+    Ok(())
   }
 
-  for (k, v) in incoming.comments {
-    match target.comments.get(&k) {
-      None => {
-        target.comments.insert(k, v);
-      }
-      Some(existing) if existing.resolved && !v.resolved => {
-        target.comments.insert(k, v);
+  /// The changed lines of the visual selection, or else of the hunk under the cursor.
+  fn picked_changes(&self) -> Option<(HashSet<(crate::diff::Kind, u32)>, &'static str)> {
+    use crate::diff::Kind;
+
+    if let Some((start, end)) = self.visual_range() {
+      let mut picked = HashSet::new();
+      for row in self.diff_rows.get(start..=end).unwrap_or_default() {
+        match row {
+          RenderRow::Unified(r) => match r.kind {
+            Kind::Remove => picked.extend(r.old_line.map(|n| (Kind::Remove, n))),
+            Kind::Add => picked.extend(r.new_line.map(|n| (Kind::Add, n))),
+            _ => {}
+          },
+          RenderRow::SideBySide(r) => {
+            if r.left_kind == Some(Kind::Remove) {
+              picked.extend(r.old_line.map(|n| (Kind::Remove, n)));
+            }
+            if r.right_kind == Some(Kind::Add) {
+              picked.extend(r.new_line.map(|n| (Kind::Add, n)));
+            }
+          }
+          RenderRow::Decorated(_) | RenderRow::FileHeader { .. } | RenderRow::Section { .. } => {}
+        }
       }
-      _ => {}
+      return (!picked.is_empty()).then_some((picked, "lines"));
     }
-  }
-}
-
-fn parse_diff_context(raw: &str) -> Option<u32> {
-  let parsed = raw.trim().parse::<u32>().ok()?;
-  Some(parsed.clamp(MIN_DIFF_CONTEXT, MAX_DIFF_CONTEXT))
-}
 
-impl App {
-  fn read_before_after(
-    &self,
-    base_tree: Option<&gix::Tree<'_>>,
-    path: &str,
-  ) -> Result<(Option<String>, Option<String>)> {
-    self.read_before_after_for_view(self.view, base_tree, path)
+    let hunk = self
+      .row_hunks
+      .get(self.diff_cursor)
+      .copied()
+      .flatten()
+      .and_then(|i| self.current_hunks.get(i))?;
+    Some((hunk.changes.iter().copied().collect(), "hunk"))
   }
 
-  fn read_before_after_for_view(
-    &self,
-    view: ViewKind,
-    base_tree: Option<&gix::Tree<'_>>,
+  /// Writes the index with just the `picked` lines staged or unstaged. Staging reads the
+  /// unstaged diff, unstaging the staged one. Returns whether anything was written.
+  fn stage_lines(
+    &mut self,
     path: &str,
-  ) -> Result<(Option<String>, Option<String>)> {
-    let out = match view {
-      ViewKind::All => (
-        crate::git::try_read_head(&self.repo, path)?,
-        crate::git::try_read_worktree(&self.repo, path)?,
-      ),
-      ViewKind::Unstaged => (
-        crate::git::try_read_index(&self.repo, path)?,
-        crate::git::try_read_worktree(&self.repo, path)?,
-      ),
-      ViewKind::Staged => (
-        crate::git::try_read_head(&self.repo, path)?,
-        crate::git::try_read_index(&self.repo, path)?,
-      ),
-      ViewKind::Base => (
-        match base_tree {
-          Some(t) => crate::git::try_read_tree(t, path)?,
-          None => None,
-        },
-        crate::git::try_read_head(&self.repo, path)?,
-      ),
+    stage: bool,
+    picked: &HashSet<(crate::diff::Kind, u32)>,
+  ) -> Result<bool> {
+    if self.interdiff {
+      self.status = format!(
+        "Leave the since-last-review diff ({}) to stage lines",
+        self.keymap.keys(Action::SinceReview)
+      );
+      return Ok(false);
+    }
+    let (before, after) = match (self.view, stage) {
+      (ViewKind::Unstaged, true) | (ViewKind::Staged, false) => {
+        self.read_before_after(None, path)?
+      }
+      _ => {
+        self.status = if stage {
+          "Stage hunks and lines from the unstaged view".to_string()
+        } else {
+          "Unstage hunks and lines from the staged view".to_string()
+        };
+        return Ok(false);
+      }
     };
-    Ok(out)
-  }
-
-  fn review_hash_for_view(
-    &self,
-    view: ViewKind,
-    base_tree: Option<&gix::Tree<'_>>,
-    path: &str,
-  ) -> Result<Option<String>> {
-    let (before, after) = self.read_before_after_for_view(view, base_tree, path)?;
-    if before.is_none() && after.is_none() {
-      return Ok(None);
+    // The picked line numbers are those of the diff on screen; a file changed since is
+    // reloaded first.
+    let shown = self.current_file_path.as_deref() == Some(path)
+      && self.current_before == before
+      && self.current_after == after;
+    let content = shown
+      .then(|| {
+        crate::diff::apply_picked(
+          before.as_deref().unwrap_or(""),
+          after.as_deref().unwrap_or(""),
+          &self.current_diff_lines,
+          |kind, line| {
+            if stage {
+              picked.contains(&(kind, line))
+            } else {
+              !picked.contains(&(kind, line))
+            }
+          },
+        )
+      })
+      .flatten();
+    let Some(content) = content else {
+      self.status = format!(
+        "{path} changed since it was shown; reload ({}) and try again",
+        self.keymap.keys(Action::Reload)
+      );
+      return Ok(false);
+    };
+    // Taking every line away from a file the other side doesn't have deletes it.
+    let gone = if stage {
+      after.is_none()
+    } else {
+      before.is_none()
+    };
+    if gone && content.is_empty() {
+      crate::git::write_index_entry(&self.repo, path, None)?;
+    } else {
+      crate::git::write_index_entry(&self.repo, path, Some(content.as_bytes()))?;
     }
-    let hash = review_fingerprint(before.as_deref(), after.as_deref())?;
-    Ok(Some(hash))
+    Ok(true)
   }
 
-  fn review_hash_for_current_view(&self, path: &str) -> Result<Option<String>> {
-    let base_tree = if self.view == ViewKind::Base {
-      self
-        .base_ref
-        .as_deref()
-        .map(|b| crate::git::merge_base_tree(&self.repo, b))
-        .transpose()?
-    } else {
-      None
-    };
-    self.review_hash_for_current_view_with_base(base_tree.as_ref(), path)
+  fn refresh_viewed_rows(&mut self) {
+    let viewed = self
+      .files
+      .get(self.file_selected)
+      .and_then(|e| self.review.files.get(&e.path))
+      .map(|f| &f.viewed_hunks);
+    let rows = self
+      .row_hunks
+      .iter()
+      .map(|hunk| {
+        hunk
+          .and_then(|i| self.current_hunks.get(i))
+          .is_some_and(|h| viewed.is_some_and(|v| v.contains(&h.hash)))
+      })
+      .collect();
+    self.viewed_rows = rows;
   }
 
-  fn review_hash_for_current_view_with_base(
-    &self,
-    base_tree: Option<&gix::Tree<'_>>,
-    path: &str,
-  ) -> Result<Option<String>> {
-    let view = if self.view == ViewKind::Base {
-      ViewKind::Base
+  /// Recounts the viewed hunks of `path` from the shown diff.
+  fn update_hunk_progress(&mut self, path: &str) {
+    let viewed = self
+      .review
+      .files
+      .get(path)
+      .filter(|f| !f.reviewed)
+      .map_or(0, |f| {
+        self
+          .current_hunks
+          .iter()
+          .filter(|h| f.viewed_hunks.contains(&h.hash))
+          .count()
+      });
+    if viewed == 0 {
+      self.hunk_progress.remove(path);
     } else {
-      ViewKind::All
-    };
-    self.review_hash_for_view(view, base_tree, path)
+      self
+        .hunk_progress
+        .insert(path.to_string(), (viewed, self.current_hunks.len()));
+    }
   }
 
-  fn toggle_diff_view_mode(&mut self) -> Result<()> {
-    let keep_new_line = self.diff_rows.get(self.diff_cursor).and_then(|r| match r {
-      RenderRow::Unified(r) => r.new_line,
-      RenderRow::SideBySide(r) => r.new_line,
-      RenderRow::Decorated(r) => Some(r.line_number),
-      RenderRow::Section { .. } => None,
-      RenderRow::FileHeader { .. } => None,
-    });
-
-    self.diff_view_mode = match self.diff_view_mode {
-      DiffViewMode::Decorated => DiffViewMode::SideBySide,
-      DiffViewMode::SideBySide => DiffViewMode::Unified,
-      DiffViewMode::Unified => DiffViewMode::Decorated,
-    };
-
-    self.reload_diff_for_selected()?;
-
-    if let Some(n) = keep_new_line
-      && let Some(idx) = self.diff_rows.iter().position(|r| match r {
-        RenderRow::Unified(r) => r.new_line == Some(n),
-        RenderRow::SideBySide(r) => r.new_line == Some(n),
-        RenderRow::Decorated(r) => r.line_number == n,
-        RenderRow::Section { .. } => false,
-        RenderRow::FileHeader { .. } => false,
-      })
-    {
-      self.diff_cursor = idx;
+  /// Recounts the viewed hunks of every listed file that has some.
+  fn refresh_hunk_progress(&mut self) -> Result<()> {
+    let base_tree = self.view_base_tree()?;
+    let mut progress = HashMap::new();
+    for entry in &self.files {
+      let Some(file) = self.review.files.get(&entry.path) else {
+        continue;
+      };
+      if file.reviewed || file.viewed_hunks.is_empty() {
+        continue;
+      }
+      let hunks = self.hunk_hashes_for_view(self.view, base_tree.as_ref(), &entry.path)?;
+      let viewed = hunks
+        .iter()
+        .filter(|h| file.viewed_hunks.contains(*h))
+        .count();
+      if viewed > 0 {
+        progress.insert(entry.path.clone(), (viewed, hunks.len()));
+      }
     }
-
+    drop(base_tree);
+    self.hunk_progress = progress;
     Ok(())
   }
 
-  fn adjust_diff_context(&mut self, delta: i32) -> Result<()> {
-    let cur = i32::try_from(self.diff_context).unwrap_or(i32::MAX);
-    let min = i32::try_from(MIN_DIFF_CONTEXT).unwrap_or(i32::MIN);
-    let max = i32::try_from(MAX_DIFF_CONTEXT).unwrap_or(i32::MAX);
-    let next = (cur + delta).clamp(min, max);
-    let next = u32::try_from(next).unwrap_or(self.diff_context);
-    if next == self.diff_context {
+  /// Forgets the viewed hunks `path` no longer has, in the shown view or the view reviews are
+  /// kept for. They can't be viewed again, and the note would grow with every edit.
+  fn prune_viewed_hunks(&mut self, path: &str) -> Result<()> {
+    if self
+      .review
+      .files
+      .get(path)
+      .is_none_or(|f| f.viewed_hunks.is_empty())
+    {
       return Ok(());
     }
-    let keep_line = self.keep_cursor_line();
-    self.diff_context = next;
-    self.reload_diff_for_selected()?;
-    if let Some(k) = keep_line
-      && let Some(idx) = self.find_row_for_keep_line(k)
-    {
-      self.diff_cursor = idx;
+    let base_tree = self.view_base_tree()?;
+    let mut live: HashSet<String> = self
+      .hunk_hashes_for_view(self.view, base_tree.as_ref(), path)?
+      .into_iter()
+      .collect();
+    if self.review_view() != self.view {
+      live.extend(self.hunk_hashes_for_view(self.review_view(), base_tree.as_ref(), path)?);
     }
-    if let Err(e) = crate::git::write_local_config_value(
-      &self.repo,
-      CONFIG_DIFF_CONTEXT_KEY,
-      &self.diff_context.to_string(),
-    ) {
-      self.status = format!("Failed to save diff context: {e}");
+    drop(base_tree);
+    if let Some(file) = self.review.files.get_mut(path) {
+      file.viewed_hunks.retain(|h| live.contains(h));
     }
     Ok(())
   }
 
-  fn jump_next_hunk(&mut self) {
-    if self.diff_rows.is_empty() {
-      return;
-    }
-    let start = self.diff_cursor.saturating_add(1);
-    if start >= self.diff_rows.len() {
-      return;
-    }
-    if let Some(rel) = self.diff_rows[start..]
-      .iter()
-      .position(|r| matches!(r, RenderRow::Section { .. }))
-    {
-      self.diff_cursor = start + rel;
-    }
+  fn hunk_hashes_for_view(
+    &self,
+    view: ViewKind,
+    base_tree: Option<&gix::Tree<'_>>,
+    path: &str,
+  ) -> Result<Vec<String>> {
+    let (before, after) = self.read_before_after_for_view(view, base_tree, path)?;
+    let lines = crate::diff::unified_file_diff(
+      "a",
+      "b",
+      before.as_deref(),
+      after.as_deref(),
+      self.diff_context,
+    )?;
+    Ok(
+      crate::diff::hunks(&lines)
+        .into_iter()
+        .map(|h| h.hash)
+        .collect(),
+    )
   }
 
   fn build_unified_rows(
@@ -2874,6 +4554,52 @@ fn word_wrap_line_count(s: &str, width: u16) -> usize {
   lines
 }
 
+/// Whether `key` is a Ctrl or Alt chord rather than text typed into a prompt.
+fn has_ctrl_alt(key: KeyEvent) -> bool {
+  key
+    .modifiers
+    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+/// Paths of `files` matching the fuzzy `query`, best first; all of them in list order when
+/// the query is empty.
+fn finder_matches(files: &[FileEntry], query: &str) -> Vec<String> {
+  let mut scored: Vec<(i64, &str)> = files
+    .iter()
+    .filter_map(|e| {
+      crate::search::fuzzy_score(&e.path, query).map(|score| (score, e.path.as_str()))
+    })
+    .collect();
+  if !query.is_empty() {
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+  }
+  scored
+    .into_iter()
+    .map(|(_, path)| path.to_string())
+    .collect()
+}
+
+/// Maps diff rows to the hunk they show. Hunk headers start a hunk in the unified and
+/// side-by-side modes; decorated rows are matched by their new-file line.
+fn row_hunks(rows: &[RenderRow], hunks: &[crate::diff::Hunk]) -> Vec<Option<usize>> {
+  let mut current = None;
+  rows
+    .iter()
+    .map(|row| match row {
+      RenderRow::FileHeader { .. } => None,
+      RenderRow::Section { .. } => {
+        current = Some(current.map_or(0, |i| i + 1));
+        current
+      }
+      RenderRow::Unified(_) | RenderRow::SideBySide(_) => current,
+      RenderRow::Decorated(r) => hunks.iter().position(|h| {
+        h.new_lines
+          .is_some_and(|(start, end)| (start..=end).contains(&r.line_number))
+      }),
+    })
+    .collect()
+}
+
 fn review_fingerprint(before: Option<&str>, after: Option<&str>) -> Result<String> {
   let mut h = hasher(Kind::Sha1);
   h.update(b"remark-review-v1\0");
@@ -2911,15 +4637,26 @@ mod tests {
       base_ref: None,
       show_ignored: false,
       jump_target: None,
+      prompt_template: None,
+      keymap: Keymap::default(),
+      palette: Theme::default().palette,
       view: ViewKind::All,
+      revisions: None,
+      rev_step: 0,
       focus: Focus::Files,
       mode: Mode::Browse,
       diff_view_mode: DiffViewMode::Decorated,
       diff_context: DEFAULT_DIFF_CONTEXT,
       head_commit_oid: None,
       review: Review::new(),
+      listed_files: Vec::new(),
       files: Vec::new(),
       file_tree: FileTreeView::default(),
+      collapsed_dirs: HashSet::new(),
+      selected_dir: None,
+      file_filter: FileFilter::default(),
+      filter_glob_input: None,
+      file_finder: None,
       file_selected: 0,
       file_scroll: 0,
       files_viewport_height: 1,
@@ -2932,22 +4669,36 @@ mod tests {
       diff_row_offsets: Vec::new(),
       diff_row_heights: Vec::new(),
       diff_total_visual_lines: 0,
+      visual_anchor: None,
+      reanchor_source: None,
       reviewed_files: HashSet::new(),
+      since_review: false,
+      interdiff: false,
+      current_hunks: Vec::new(),
+      row_hunks: Vec::new(),
+      viewed_rows: Vec::new(),
+      hunk_progress: HashMap::new(),
       editor_target: None,
+      editor_reply: false,
+      editor_resolve: false,
+      editor_range_end: None,
       editor_buffer: crate::ui::empty_textarea(),
       prompt_buffer: crate::ui::empty_textarea(),
       status: String::new(),
       show_help: false,
       show_prompt: false,
       show_diff_popup: false,
+      search: None,
+      search_origin: (0, 0),
       comment_list: Vec::new(),
       comment_list_selected: 0,
       comment_list_marked: HashSet::new(),
+      comment_list_severity: None,
       current_before: None,
       current_after: None,
       current_diff_lines: Vec::new(),
       current_file_path: None,
-      highlighter: Highlighter::new(2).expect("highlighter"),
+      highlighter: Highlighter::new(2, Theme::default().syntax).expect("highlighter"),
       needs_clear: false,
     }
   }
@@ -2989,6 +4740,81 @@ mod tests {
     assert!(!rendered.iter().any(|line| line.contains('\t')));
   }
 
+  #[test]
+  fn next_hunk_skips_viewed_hunks() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let mut app = test_app(repo);
+    let section = || RenderRow::Section {
+      text: "@@".to_string(),
+    };
+    let line = |n| {
+      RenderRow::Unified(DiffRow {
+        kind: crate::diff::Kind::Add,
+        old_line: None,
+        new_line: Some(n),
+        spans: Vec::new(),
+      })
+    };
+    app.diff_rows = vec![
+      RenderRow::FileHeader {
+        path: "a.rs".to_string(),
+      },
+      section(),
+      line(1),
+      line(2),
+      section(),
+      line(5),
+      section(),
+      line(9),
+    ];
+    let hunk = |hash: &str| crate::diff::Hunk {
+      hash: hash.to_string(),
+      new_lines: None,
+      changes: Vec::new(),
+    };
+    app.current_hunks = vec![hunk("a"), hunk("b"), hunk("c")];
+    app.row_hunks = row_hunks(&app.diff_rows, &app.current_hunks);
+    assert_eq!(
+      app.row_hunks,
+      [
+        None,
+        Some(0),
+        Some(0),
+        Some(0),
+        Some(1),
+        Some(1),
+        Some(2),
+        Some(2)
+      ]
+    );
+    app.viewed_rows = app.row_hunks.iter().map(|h| *h == Some(1)).collect();
+
+    app.diff_cursor = 2;
+    app.jump_next_hunk();
+    assert_eq!(app.diff_cursor, 6);
+    app.jump_next_hunk();
+    assert_eq!(app.diff_cursor, 6);
+    assert_eq!(app.status, "No unviewed hunks below");
+
+    // Decorated rows belong to the hunk covering their line.
+    let decorated = |n| {
+      RenderRow::Decorated(DecoratedRow {
+        status: crate::diff::LineStatus::Unchanged,
+        line_number: n,
+        old_line_number: None,
+        spans: Vec::new(),
+      })
+    };
+    let rows: Vec<_> = (1..=4).map(decorated).collect();
+    let hunks = [crate::diff::Hunk {
+      hash: "a".to_string(),
+      new_lines: Some((2, 3)),
+      changes: Vec::new(),
+    }];
+    assert_eq!(row_hunks(&rows, &hunks), [None, Some(0), Some(0), None]);
+  }
+
   #[test]
   fn diff_metrics_account_for_marker_width() {
     let td = tempfile::tempdir().expect("tempdir");
@@ -3007,4 +4833,78 @@ mod tests {
     assert_eq!(app.diff_row_heights, vec![2]);
     assert_eq!(app.diff_total_visual_lines, 2);
   }
+
+  #[test]
+  fn staging_takes_the_shown_lines_and_whole_deletions() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    std::fs::write(td.path().join("t.txt"), "x\na\nb\nc\nd\nx\ne\n").expect("write");
+    crate::test_support::run_git(td.path(), &["add", "t.txt"]);
+    let mut app = test_app(repo);
+    app.view = ViewKind::Unstaged;
+    let show = |app: &mut App| {
+      let (before, after) = app.read_before_after(None, "t.txt").expect("read");
+      app.current_diff_lines = crate::diff::unified_file_diff(
+        "a/t.txt",
+        "b/t.txt",
+        before.as_deref(),
+        after.as_deref(),
+        1,
+      )
+      .expect("diff");
+      app.current_before = before;
+      app.current_after = after;
+      app.current_file_path = Some("t.txt".to_string());
+    };
+
+    // Both hunks make the same change; only the second one's lines are staged.
+    std::fs::write(td.path().join("t.txt"), "y\na\nb\nc\nd\ny\ne\n").expect("write");
+    show(&mut app);
+    let hunks = crate::diff::hunks(&app.current_diff_lines);
+    assert_eq!(hunks.len(), 2);
+    let picked = hunks[1].changes.iter().copied().collect();
+    assert!(app.stage_lines("t.txt", true, &picked).expect("stage"));
+    assert_eq!(
+      crate::git::try_read_index(&app.repo, "t.txt")
+        .expect("index")
+        .as_deref(),
+      Some("x\na\nb\nc\nd\ny\ne\n")
+    );
+
+    // A diff that no longer matches the file stages nothing.
+    assert!(!app.stage_lines("t.txt", true, &picked).expect("stage"));
+
+    // Removing every line of a deleted file stages the deletion.
+    std::fs::remove_file(td.path().join("t.txt")).expect("remove");
+    show(&mut app);
+    let picked = crate::diff::hunks(&app.current_diff_lines)
+      .into_iter()
+      .flat_map(|h| h.changes)
+      .collect();
+    assert!(app.stage_lines("t.txt", true, &picked).expect("stage"));
+    assert_eq!(
+      crate::git::try_read_index(&app.repo, "t.txt").expect("index"),
+      None
+    );
+  }
+
+  #[test]
+  fn writing_notes_forgets_hunks_the_diff_no_longer_has() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    std::fs::write(td.path().join("t.txt"), "a\nb\n").expect("write");
+    let mut app = test_app(repo);
+    let live = app
+      .hunk_hashes_for_view(ViewKind::All, None, "t.txt")
+      .expect("hunks");
+    assert_eq!(live.len(), 1);
+    let file = app.review.files.entry("t.txt".to_string()).or_default();
+    file.viewed_hunks = std::collections::BTreeSet::from([live[0].clone(), "gone".to_string()]);
+
+    app.prune_viewed_hunks("t.txt").expect("prune");
+    assert_eq!(
+      app.review.files["t.txt"].viewed_hunks,
+      std::collections::BTreeSet::from([live[0].clone()])
+    );
+  }
 }
@@ -0,0 +1,150 @@
+use anyhow::{Context, Result};
+
+use crate::config::{ApplySuggestionCli, ResolveCli};
+use crate::review::{Comment, LineKey, LineSide, Suggestion};
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: ApplySuggestionCli,
+) -> Result<()> {
+  let file = crate::git::normalize_repo_path(repo, &cmd.file);
+  let key = LineKey {
+    side: LineSide::New,
+    line: cmd.line,
+  };
+
+  // The comment may have been written in any view (staged, unstaged, base...).
+  let mut file_review = crate::lsp::load_file_review(repo, notes_ref, &file, base_ref.as_deref())?
+    .with_context(|| format!("no comments on {file}"))?;
+  let comment = file_review
+    .comments
+    .get_mut(&key)
+    .with_context(|| format!("no comment on {file}:{}", cmd.line))?;
+  let was_resolved = comment.resolved;
+  apply(repo, &file, key, comment)?;
+  if !was_resolved {
+    // Resolves the comment in whichever view's note holds it, and syncs the draft.
+    let resolve = ResolveCli {
+      file: Some(file.clone()),
+      line: Some(cmd.line),
+      side: Some(LineSide::New),
+      file_comment: false,
+      unresolve: false,
+      message: None,
+      commit: None,
+    };
+    crate::resolve_cmd::run(repo, notes_ref, base_ref, resolve)?;
+  }
+  println!("Applied suggestion to {file}:{}.", cmd.line);
+  Ok(())
+}
+
+/// Builds a suggestion for `start..=last`, recording the worktree lines it replaces.
+pub(crate) fn new_suggestion(
+  repo: &gix::Repository,
+  path: &str,
+  start: u32,
+  last: u32,
+  replacement: Vec<String>,
+) -> Result<Suggestion> {
+  let content = crate::git::try_read_worktree(repo, path)?.unwrap_or_default();
+  let original = content
+    .lines()
+    .skip(start.saturating_sub(1) as usize)
+    .take((last.max(start) - start + 1) as usize)
+    .map(str::to_string)
+    .collect();
+  Ok(Suggestion {
+    original,
+    replacement,
+  })
+}
+
+/// Writes the comment's suggestion into the worktree file and marks the comment resolved.
+pub(crate) fn apply(
+  repo: &gix::Repository,
+  path: &str,
+  key: LineKey,
+  comment: &mut Comment,
+) -> Result<()> {
+  if key.side != LineSide::New {
+    anyhow::bail!("suggestions apply to new-side lines only");
+  }
+  let suggestion = comment
+    .suggestion
+    .as_ref()
+    .with_context(|| format!("comment on {path}:{} has no suggestion", key.line))?;
+  let workdir = repo.workdir().context("repository has no worktree")?;
+  let content = crate::git::try_read_worktree(repo, path)?
+    .with_context(|| format!("{path} is not in the worktree"))?;
+  let updated = suggestion
+    .apply_to(&content, key.line, comment.last_line(key))
+    .with_context(|| format!("apply suggestion to {path}"))?;
+  std::fs::write(workdir.join(path), updated).with_context(|| format!("write {path}"))?;
+  comment.resolved = true;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn init_repo_with_file(path: &str, contents: &str) -> (tempfile::TempDir, gix::Repository) {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let full = td.path().join(path);
+    std::fs::create_dir_all(full.parent().expect("parent")).expect("mkdir");
+    std::fs::write(&full, contents).expect("write file");
+    (td, repo)
+  }
+
+  #[test]
+  fn apply_replaces_range_and_resolves() {
+    let (td, repo) = init_repo_with_file("src/lib.rs", "a\nb\nc\nd\n");
+    let key = LineKey {
+      side: LineSide::New,
+      line: 2,
+    };
+    let mut comment = Comment {
+      body: "Collapse these".to_string(),
+      end_line: Some(3),
+      ..Default::default()
+    };
+    comment.suggestion =
+      Some(new_suggestion(&repo, "src/lib.rs", 2, 3, vec!["bc".to_string()]).expect("suggest"));
+    assert_eq!(
+      comment.suggestion.as_ref().map(|s| s.original.clone()),
+      Some(vec!["b".to_string(), "c".to_string()])
+    );
+
+    apply(&repo, "src/lib.rs", key, &mut comment).expect("apply");
+
+    let updated = std::fs::read_to_string(td.path().join("src/lib.rs")).expect("read");
+    assert_eq!(updated, "a\nbc\nd\n");
+    assert!(comment.resolved);
+  }
+
+  #[test]
+  fn apply_refuses_when_lines_changed() {
+    let (td, repo) = init_repo_with_file("src/lib.rs", "a\nb\n");
+    let mut comment = Comment {
+      body: "Rename".to_string(),
+      suggestion: Some(Suggestion {
+        original: vec!["old".to_string()],
+        replacement: vec!["new".to_string()],
+      }),
+      ..Default::default()
+    };
+    let key = LineKey {
+      side: LineSide::New,
+      line: 2,
+    };
+
+    assert!(apply(&repo, "src/lib.rs", key, &mut comment).is_err());
+    let untouched = std::fs::read_to_string(td.path().join("src/lib.rs")).expect("read");
+    assert_eq!(untouched, "a\nb\n");
+    assert!(!comment.resolved);
+  }
+}
@@ -0,0 +1,158 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use anyhow::{Context, Result};
+
+use crate::config::{AutoresolveCli, ResolveCli};
+use crate::list_cmd::CommentEntry;
+use crate::review::Review;
+
+/// Resolution message recorded when none is given.
+pub(crate) const DEFAULT_MESSAGE: &str = "The commented code changed after the review.";
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: AutoresolveCli,
+) -> Result<()> {
+  let paths = crate::check_cmd::check_paths(repo, cmd.filter, base_ref.as_deref())?;
+  let review = crate::list_cmd::load_notes_review(repo, notes_ref, base_ref.as_deref(), &paths)?;
+  let changed = changed_comments(repo, base_ref.as_deref(), &review);
+  if changed.is_empty() {
+    println!("No open comments on changed code.");
+    return Ok(());
+  }
+
+  if cmd.dry_run {
+    println!("{} open comment(s) on changed code:", changed.len());
+    for entry in &changed {
+      println!("  {}", entry.summary_line());
+    }
+    return Ok(());
+  }
+
+  let chosen = if cmd.yes {
+    changed.iter().collect()
+  } else {
+    if !std::io::stdin().is_terminal() {
+      anyhow::bail!("not a terminal; pass --yes to resolve them all or --dry-run to list them");
+    }
+    confirm(&changed, std::io::stdin().lock(), std::io::stdout())?
+  };
+
+  let message = cmd.message.unwrap_or_else(|| DEFAULT_MESSAGE.to_string());
+  for entry in &chosen {
+    let resolve = ResolveCli {
+      file: Some(entry.file.to_string()),
+      line: entry.line,
+      side: entry.side,
+      file_comment: false,
+      unresolve: false,
+      message: Some(message.clone()),
+      commit: cmd.commit.clone(),
+    };
+    crate::resolve_cmd::run(repo, notes_ref, base_ref.clone(), resolve)?;
+  }
+  println!("Resolved {} of {} comment(s).", chosen.len(), changed.len());
+  Ok(())
+}
+
+/// Open line comments whose code no longer matches the snippet hash stored when they were
+/// written. Outdated comments and comments without a hash are left out.
+pub(crate) fn changed_comments<'a>(
+  repo: &gix::Repository,
+  base_ref: Option<&str>,
+  review: &'a Review,
+) -> Vec<CommentEntry<'a>> {
+  let mut out = Vec::new();
+  let mut resolver = crate::add_cmd::snippet_resolver(repo, base_ref);
+  for (path, file) in &review.files {
+    for (key, comment) in &file.comments {
+      if comment.resolved || comment.outdated {
+        continue;
+      }
+      let Some(stored) = comment.snippet_hash.as_deref() else {
+        continue;
+      };
+      let last_line = comment.last_line(*key);
+      let current = crate::add_cmd::range_snippet_hash(&mut resolver, path, *key, last_line);
+      if current.is_some_and(|hash| hash != stored) {
+        out.push(CommentEntry::new(path, Some(*key), comment));
+      }
+    }
+  }
+  out
+}
+
+/// Asks about each entry in turn: `y` picks it, `a` picks it and all that follow, `q` stops.
+/// Anything else skips it.
+fn confirm<'e, 'a>(
+  entries: &'e [CommentEntry<'a>],
+  mut input: impl BufRead,
+  mut output: impl Write,
+) -> Result<Vec<&'e CommentEntry<'a>>> {
+  let mut chosen = Vec::new();
+  let mut rest = entries.iter();
+  while let Some(entry) = rest.next() {
+    write!(output, "Resolve {}? [y/N/a/q] ", entry.summary_line()).context("write prompt")?;
+    output.flush().context("flush prompt")?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer).context("read answer")? == 0 {
+      break;
+    }
+    match answer.trim().to_ascii_lowercase().as_str() {
+      "y" | "yes" => chosen.push(entry),
+      "a" | "all" => {
+        chosen.push(entry);
+        chosen.extend(rest.by_ref());
+        break;
+      }
+      "q" | "quit" => break,
+      _ => {}
+    }
+  }
+  Ok(chosen)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::review::{Comment, LineKey, LineSide};
+
+  fn bodies<'a>(chosen: Vec<&CommentEntry<'a>>) -> Vec<&'a str> {
+    chosen.iter().map(|e| e.body).collect()
+  }
+
+  #[test]
+  fn confirm_follows_answers() {
+    let comments: Vec<_> = (1..=4)
+      .map(|n| Comment {
+        body: format!("comment {n}"),
+        ..Default::default()
+      })
+      .collect();
+    let entries: Vec<_> = comments
+      .iter()
+      .enumerate()
+      .map(|(i, c)| {
+        let key = LineKey {
+          side: LineSide::New,
+          line: i as u32 + 1,
+        };
+        CommentEntry::new("src/lib.rs", Some(key), c)
+      })
+      .collect();
+    let mut prompts = Vec::new();
+    let chosen = confirm(&entries, "n\ny\na\n".as_bytes(), &mut prompts).expect("confirm");
+    assert_eq!(bodies(chosen), ["comment 2", "comment 3", "comment 4"]);
+    let prompts = String::from_utf8(prompts).expect("utf8");
+    assert!(prompts.starts_with("Resolve src/lib.rs:1 [open] comment 1? [y/N/a/q] "));
+
+    let chosen = confirm(&entries, "y\nq\ny\n".as_bytes(), std::io::sink()).expect("confirm");
+    assert_eq!(bodies(chosen), ["comment 1"]);
+
+    // Running out of input stops asking.
+    let chosen = confirm(&entries, "y\n".as_bytes(), std::io::sink()).expect("confirm");
+    assert_eq!(bodies(chosen), ["comment 1"]);
+  }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+use std::io::BufRead;
+
+use anyhow::{Context, Result};
+use gix::ObjectId;
+
+use crate::config::{CheckCli, PromptFilter};
+use crate::list_cmd::{CommentEntry, comment_entries};
+use crate::review::Review;
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: CheckCli,
+) -> Result<()> {
+  let paths = if cmd.pre_push {
+    pushed_paths(repo, std::io::stdin().lock(), base_ref.as_deref())?
+  } else {
+    check_paths(repo, cmd.filter, base_ref.as_deref())?
+  };
+  let review = crate::list_cmd::load_notes_review(repo, notes_ref, base_ref.as_deref(), &paths)?;
+  let mut unresolved = unresolved_entries(&review, cmd.ignore_outdated);
+  if let Some(min) = cmd.severity {
+    unresolved.retain(|e| e.rank >= min);
+  }
+  if unresolved.is_empty() {
+    println!("No unresolved comments.");
+    return Ok(());
+  }
+
+  let files: BTreeSet<_> = unresolved.iter().map(|e| e.file).collect();
+  println!(
+    "{} unresolved comment(s) in {} file(s):",
+    unresolved.len(),
+    files.len()
+  );
+  for entry in &unresolved {
+    println!("  {}", entry.summary_line());
+  }
+  anyhow::bail!("{} unresolved review comment(s)", unresolved.len())
+}
+
+/// Worktree changes plus, when a base ref is known, everything the branch changed since it.
+pub(crate) fn check_paths(
+  repo: &gix::Repository,
+  filter: Option<PromptFilter>,
+  base_ref: Option<&str>,
+) -> Result<Vec<String>> {
+  if let Some(filter) = filter {
+    return crate::prompt_cmd::view_paths(repo, filter, base_ref);
+  }
+  let mut paths = crate::prompt_cmd::view_paths(repo, PromptFilter::All, base_ref)?;
+  if base_ref.is_some() {
+    paths.extend(crate::prompt_cmd::view_paths(
+      repo,
+      PromptFilter::Base,
+      base_ref,
+    )?);
+    paths.sort();
+    paths.dedup();
+  }
+  Ok(paths)
+}
+
+/// Files changed by the pushes git lists on a pre-push hook's stdin, one
+/// `<local ref> <local sha> <remote ref> <remote sha>` line per ref. Deletions change nothing;
+/// a ref new to the remote counts from the base ref's merge base when there is one, or else
+/// from the commits no remote-tracking ref has.
+fn pushed_paths(
+  repo: &gix::Repository,
+  input: impl BufRead,
+  base_ref: Option<&str>,
+) -> Result<Vec<String>> {
+  let mut paths = BTreeSet::new();
+  for line in input.lines() {
+    let line = line.context("read pushed refs")?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [_, local, _, remote] = fields[..] else {
+      continue;
+    };
+    let local = ObjectId::from_hex(local.as_bytes())
+      .with_context(|| format!("parse pushed commit '{local}'"))?;
+    if local.is_null() {
+      continue;
+    }
+    let remote = ObjectId::from_hex(remote.as_bytes())
+      .ok()
+      .filter(|id| !id.is_null() && repo.has_object(id));
+    let changed = match (remote, base_ref) {
+      (Some(remote), _) => crate::git::list_commit_paths(repo, Some(remote), local)?,
+      (None, Some(base_ref)) => {
+        let base_tree = crate::git::merge_base_tree_for(repo, local, base_ref)?;
+        let tree = crate::git::commit_tree(repo, local)?;
+        crate::git::changed_paths_between(repo, &base_tree, &tree)?
+      }
+      (None, None) => crate::git::unpushed_paths(repo, local)?,
+    };
+    paths.extend(changed);
+  }
+  Ok(paths.into_iter().collect())
+}
+
+fn unresolved_entries(review: &Review, ignore_outdated: bool) -> Vec<CommentEntry<'_>> {
+  review
+    .files
+    .iter()
+    .flat_map(|(path, file)| comment_entries(path, file))
+    .filter(|e| !e.resolved && !(ignore_outdated && e.outdated))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::review::{Comment, FileReview, LineKey, LineSide};
+  use crate::test_support::run_git;
+
+  fn comment(body: &str, resolved: bool, outdated: bool) -> Comment {
+    Comment {
+      body: body.to_string(),
+      resolved,
+      outdated,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn unresolved_entries_skip_resolved_and_optionally_outdated() {
+    let mut file = FileReview {
+      file_comment: Some(comment("done", true, false)),
+      ..Default::default()
+    };
+    for (line, c) in [
+      (1, comment("open", false, false)),
+      (2, comment("moved away", false, true)),
+      (3, comment("fixed", true, false)),
+    ] {
+      file.comments.insert(
+        LineKey {
+          side: LineSide::New,
+          line,
+        },
+        c,
+      );
+    }
+    let mut review = Review::new();
+    review.files.insert("src/lib.rs".to_string(), file);
+
+    let bodies = |ignore| {
+      unresolved_entries(&review, ignore)
+        .into_iter()
+        .map(|e| e.body)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(bodies(false), ["open", "moved away"]);
+    assert_eq!(bodies(true), ["open"]);
+  }
+
+  #[test]
+  fn pushed_paths_cover_the_pushed_range() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let git = |args: &[&str]| run_git(td.path(), args).trim().to_string();
+    git(&["init", "--quiet"]);
+    let commit = |path: &str| {
+      std::fs::write(td.path().join(path), path).expect("write file");
+      git(&["add", path]);
+      git(&["commit", "--quiet", "--no-gpg-sign", "-m", path]);
+      git(&["rev-parse", "HEAD"])
+    };
+    let first = commit("a.txt");
+    let second = commit("b.txt");
+    let repo = gix::open(td.path()).expect("open repo");
+    let zero = "0".repeat(40);
+
+    let input = format!("refs/heads/main {second} refs/heads/main {first}\n");
+    assert_eq!(
+      pushed_paths(&repo, input.as_bytes(), None).expect("paths"),
+      ["b.txt"]
+    );
+    // Nothing is on a remote yet, so a new branch brings both commits.
+    let input = format!("refs/heads/topic {second} refs/heads/topic {zero}\n");
+    assert_eq!(
+      pushed_paths(&repo, input.as_bytes(), None).expect("paths"),
+      ["a.txt", "b.txt"]
+    );
+    let input = format!("(delete) {zero} refs/heads/old {first}\n");
+    assert!(
+      pushed_paths(&repo, input.as_bytes(), None)
+        .expect("paths")
+        .is_empty()
+    );
+  }
+}
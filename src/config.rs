@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
@@ -6,7 +7,9 @@ use confique::Config as _;
 use confique::Layer as _;
 
 use crate::git::ViewKind;
-use crate::review::LineSide;
+use crate::keymap::KeyBinding;
+use crate::review::{LineSide, Severity};
+use crate::theme::{SyntaxTheme, Theme, UiTheme};
 
 #[derive(confique::Config, Debug, Clone)]
 pub struct AppConfig {
@@ -20,6 +23,17 @@ pub struct AppConfig {
   pub fetch_notes: bool,
   #[config(default = 2, env = "REMARK_TAB_WIDTH")]
   pub tab_width: usize,
+  /// Prompt template used by `remark prompt` and the TUI prompt editor.
+  #[config(env = "REMARK_PROMPT_TEMPLATE")]
+  pub prompt_template: Option<String>,
+  /// Keys of TUI actions by action name, e.g. `quit = ["ctrl+q"]`; unlisted actions keep their
+  /// defaults.
+  pub keymap: Option<BTreeMap<String, KeyBinding>>,
+  /// TUI colours: `dark`, `light` or `mono`. `NO_COLOR` forces `mono`.
+  pub theme: Option<UiTheme>,
+  /// Syntax highlighting theme, any of verdant-themes' by name, e.g. `catppuccin-latte`; defaults
+  /// to one that suits `theme`.
+  pub syntax_theme: Option<SyntaxTheme>,
 }
 
 #[derive(Parser)]
@@ -67,11 +81,21 @@ pub struct GlobalArgs {
 #[derive(Subcommand)]
 pub enum Command {
   Prompt(PromptCli),
+  List(ListCli),
+  Show(ShowCli),
+  Check(CheckCli),
+  Hook(HookCli),
   Resolve(ResolveCli),
+  Autoresolve(AutoresolveCli),
+  Revive(ReviveCli),
   Add(AddCli),
+  ApplySuggestion(ApplySuggestionCli),
   New(NewCli),
   Purge(PurgeCli),
+  Push(SyncCli),
+  Pull(SyncCli),
   Lsp(LspCli),
+  Mcp(McpCli),
 }
 
 #[derive(Args, Debug, Clone, Default)]
@@ -85,10 +109,18 @@ pub struct UiArgs {
     )]
   pub show_ignored: Option<bool>,
 
-  /// Start in view (all/unstaged/staged/base).
+  /// Start in view (all/unstaged/staged/base/commit/range).
   #[arg(long = "view", value_enum)]
   pub view: Option<ViewKind>,
 
+  /// Review a single commit against its parent (implies --view commit).
+  #[arg(long = "rev", conflicts_with = "range")]
+  pub rev: Option<String>,
+
+  /// Review a commit range `a..b` (implies --view range; step through commits with < and >).
+  #[arg(long = "range")]
+  pub range: Option<String>,
+
   /// Preselect a file when launching the UI.
   #[arg(long = "file")]
   pub file: Option<String>,
@@ -100,6 +132,15 @@ pub struct UiArgs {
   /// Which side for line (default: new).
   #[arg(long = "side", value_enum)]
   pub side: Option<LineSide>,
+
+  /// TUI colours (default: dark; NO_COLOR forces mono).
+  #[arg(long = "theme", value_enum)]
+  pub theme: Option<UiTheme>,
+
+  /// Syntax highlighting theme, any verdant-themes theme (default: catppuccin-mocha, or
+  /// catppuccin-latte with --theme light).
+  #[arg(long = "syntax-theme")]
+  pub syntax_theme: Option<SyntaxTheme>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -107,6 +148,95 @@ pub struct PromptCli {
   /// Filter files (default: all).
   #[arg(long = "filter", value_enum, default_value_t = PromptFilter::All)]
   pub filter: PromptFilter,
+
+  /// Also list comments whose code has changed or disappeared.
+  #[arg(long = "include-outdated", action = ArgAction::SetTrue)]
+  pub include_outdated: bool,
+
+  /// Render with this template instead of the built-in layout (a name or a path).
+  #[arg(long = "template")]
+  pub template: Option<String>,
+
+  /// Output format; json and xml give every comment a stable id.
+  #[arg(long = "format", value_enum, default_value_t = PromptFormat::Markdown)]
+  pub format: PromptFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptFormat {
+  Markdown,
+  Json,
+  Xml,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ListCli {
+  /// Filter files (default: all).
+  #[arg(long = "filter", value_enum, default_value_t = PromptFilter::All)]
+  pub filter: PromptFilter,
+
+  /// Output format.
+  #[arg(long = "format", value_enum, default_value_t = OutputFormat::Text)]
+  pub format: OutputFormat,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ShowCli {
+  /// File to show comments for.
+  pub file: String,
+
+  /// Output format.
+  #[arg(long = "format", value_enum, default_value_t = OutputFormat::Text)]
+  pub format: OutputFormat,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CheckCli {
+  /// Files to check (default: worktree changes plus the base range when a base ref is known).
+  #[arg(long = "filter", value_enum)]
+  pub filter: Option<PromptFilter>,
+
+  /// Don't count unresolved comments whose code has changed or disappeared.
+  #[arg(long = "ignore-outdated", action = ArgAction::SetTrue)]
+  pub ignore_outdated: bool,
+
+  /// Only count comments at least this severe (untagged comments count as suggestions).
+  #[arg(long = "severity", value_enum)]
+  pub severity: Option<Severity>,
+
+  /// Check the files changed by the refs being pushed, read from stdin the way git feeds a
+  /// pre-push hook.
+  #[arg(long = "pre-push", action = ArgAction::SetTrue, conflicts_with = "filter")]
+  pub pre_push: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct HookCli {
+  #[command(subcommand)]
+  pub command: HookCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HookCommand {
+  /// Run `remark check` from the pre-push hook.
+  Install(HookInstallCli),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct HookInstallCli {
+  /// Also report unresolved comments after amend/rebase (post-rewrite hook, never blocks).
+  #[arg(long = "post-rewrite", action = ArgAction::SetTrue)]
+  pub post_rewrite: bool,
+
+  /// Overwrite hooks that were not installed by remark.
+  #[arg(long = "force", action = ArgAction::SetTrue)]
+  pub force: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+  Text,
+  Json,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
@@ -138,6 +268,57 @@ pub struct ResolveCli {
   /// Mark comment as unresolved.
   #[arg(long = "unresolve", action = ArgAction::SetTrue)]
   pub unresolve: bool,
+
+  /// How the comment was addressed, e.g. "won't fix: intentional".
+  #[arg(long = "message", short = 'm', conflicts_with = "unresolve")]
+  pub message: Option<String>,
+
+  /// Commit that addressed the comment (any revision, e.g. HEAD).
+  #[arg(long = "commit", conflicts_with = "unresolve")]
+  pub commit: Option<String>,
+}
+
+/// Resolve open comments whose code changed since they were written.
+#[derive(Args, Debug, Clone)]
+pub struct AutoresolveCli {
+  /// Files to look at (default: worktree changes plus the base range when a base ref is known).
+  #[arg(long = "filter", value_enum)]
+  pub filter: Option<PromptFilter>,
+
+  /// Resolve every changed comment without asking.
+  #[arg(long = "yes", short = 'y', action = ArgAction::SetTrue)]
+  pub yes: bool,
+
+  /// Only list the changed comments.
+  #[arg(long = "dry-run", action = ArgAction::SetTrue, conflicts_with = "yes")]
+  pub dry_run: bool,
+
+  /// Resolution message recorded on each comment.
+  #[arg(long = "message", short = 'm')]
+  pub message: Option<String>,
+
+  /// Commit that addressed the comments (any revision, e.g. HEAD).
+  #[arg(long = "commit")]
+  pub commit: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReviveCli {
+  /// File the outdated comment is on.
+  #[arg(long = "file")]
+  pub file: String,
+
+  /// Line the outdated comment is parked on (1-based).
+  #[arg(long = "line", value_parser = clap::value_parser!(u32).range(1..))]
+  pub line: u32,
+
+  /// Which side the comment is on (default: new).
+  #[arg(long = "side", value_enum)]
+  pub side: Option<LineSide>,
+
+  /// Line to re-anchor the comment to (default: where it is parked).
+  #[arg(long = "to", value_parser = clap::value_parser!(u32).range(1..))]
+  pub to: Option<u32>,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -146,9 +327,9 @@ pub struct AddCli {
   #[arg(long = "file")]
   pub file: Option<String>,
 
-  /// Line number (1-based).
-  #[arg(long = "line", value_parser = clap::value_parser!(u32).range(1..))]
-  pub line: Option<u32>,
+  /// Line number or range (e.g. 10 or 10-24, 1-based).
+  #[arg(long = "line")]
+  pub line: Option<LineSpan>,
 
   /// Which side for line comments (default: new).
   #[arg(long = "side", value_enum)]
@@ -158,10 +339,18 @@ pub struct AddCli {
   #[arg(long = "file-comment", action = ArgAction::SetTrue)]
   pub file_comment: bool,
 
+  /// Reply to the existing comment's thread instead of replacing its body.
+  #[arg(long = "reply", action = ArgAction::SetTrue)]
+  pub reply: bool,
+
   /// Comment body (ignored when using --edit).
   #[arg(long = "message", short = 'm')]
   pub message: Option<String>,
 
+  /// Suggested replacement for the commented line(s); an empty string suggests deleting them.
+  #[arg(long = "suggestion")]
+  pub suggestion: Option<String>,
+
   /// Edit the comment body in $VISUAL/$EDITOR.
   #[arg(long = "edit", action = ArgAction::SetTrue)]
   pub edit: bool,
@@ -169,6 +358,66 @@ pub struct AddCli {
   /// Editor command to use with --edit (overrides $VISUAL/$EDITOR).
   #[arg(long = "editor")]
   pub editor: Option<String>,
+
+  /// Comment severity.
+  #[arg(long = "severity", value_enum)]
+  pub severity: Option<Severity>,
+
+  /// Label to attach to the comment (repeatable).
+  #[arg(long = "label", value_name = "LABEL")]
+  pub label: Vec<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ApplySuggestionCli {
+  /// File the suggestion is on.
+  #[arg(long = "file")]
+  pub file: String,
+
+  /// Line the commented range starts on (1-based).
+  #[arg(long = "line", value_parser = clap::value_parser!(u32).range(1..))]
+  pub line: u32,
+}
+
+/// A 1-based line or inclusive line range, written `N` or `N-M`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSpan {
+  pub start: u32,
+  pub end: u32,
+}
+
+impl std::str::FromStr for LineSpan {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let parse = |value: &str| -> Result<u32, String> {
+      match value.trim().parse::<u32>() {
+        Ok(line) if line >= 1 => Ok(line),
+        _ => Err(format!("invalid line number: {value:?}")),
+      }
+    };
+    let (start, end) = match s.split_once('-') {
+      Some((start, end)) => (parse(start)?, parse(end)?),
+      None => {
+        let line = parse(s)?;
+        (line, line)
+      }
+    };
+    if end < start {
+      return Err(format!("line range ends before it starts: {s}"));
+    }
+    Ok(Self { start, end })
+  }
+}
+
+impl std::fmt::Display for LineSpan {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if self.end > self.start {
+      write!(f, "{}-{}", self.start, self.end)
+    } else {
+      write!(f, "{}", self.start)
+    }
+  }
 }
 
 #[derive(Args, Debug, Clone, Default)]
@@ -181,6 +430,13 @@ pub struct PurgeCli {
   pub yes: bool,
 }
 
+#[derive(Args, Debug, Clone, Default)]
+pub struct SyncCli {
+  /// Remote to sync notes with (default: remark.notesRemote, then the branch remote, then origin).
+  #[arg(long = "remote")]
+  pub remote: Option<String>,
+}
+
 #[derive(Args, Debug, Clone, Default)]
 pub struct LspCli {
   /// Include resolved comments in diagnostics/hover output.
@@ -196,6 +452,10 @@ pub struct LspCli {
   pub no_diagnostics: bool,
 }
 
+/// Serves the review comments to coding agents over the Model Context Protocol (stdio).
+#[derive(Args, Debug, Clone, Default)]
+pub struct McpCli {}
+
 pub fn load_config(global: &GlobalArgs, ui: &UiArgs) -> Result<AppConfig> {
   let mut cli_layer = <AppConfig as confique::Config>::Layer::empty();
   cli_layer.notes_ref = global.notes_ref.clone();
@@ -234,11 +494,13 @@ fn add_if_exists(
 
 pub fn validate_ui_args(cli: &Cli) -> Result<()> {
   if cli.command.is_none() {
-    return Ok(());
+    return cli.ui.validate_view();
   }
 
   if cli.ui.has_any() {
-    anyhow::bail!("UI-only flags (--ignored/--view/--file/--line/--side) require no subcommand");
+    anyhow::bail!(
+      "UI-only flags (--ignored/--view/--rev/--range/--file/--line/--side/--theme/--syntax-theme) require no subcommand"
+    );
   }
 
   Ok(())
@@ -275,16 +537,90 @@ pub fn resolve_show_ignored(config: &AppConfig, cli_show_ignored: Option<bool>)
   cli_show_ignored.unwrap_or(config.show_ignored)
 }
 
+pub fn resolve_prompt_template(config: &AppConfig, cli_template: Option<String>) -> Option<String> {
+  cli_template.or_else(|| config.prompt_template.clone())
+}
+
+pub fn resolve_theme(
+  config: &AppConfig,
+  cli_theme: Option<UiTheme>,
+  cli_syntax_theme: Option<SyntaxTheme>,
+) -> Theme {
+  Theme::new(
+    cli_theme.or(config.theme).unwrap_or_default(),
+    cli_syntax_theme.or(config.syntax_theme),
+    crate::theme::no_color_requested(),
+  )
+}
+
 pub fn resolve_fetch_notes(config: &AppConfig, cli_fetch_notes: Option<bool>) -> bool {
   cli_fetch_notes.unwrap_or(config.fetch_notes)
 }
 
 impl UiArgs {
+  /// `--view commit` and `--view range` need the matching revisions; the other views ignore them.
+  fn validate_view(&self) -> Result<()> {
+    let has_revisions = self.rev.is_some() || self.range.is_some();
+    match self.view {
+      Some(ViewKind::Range) if self.range.is_none() => {
+        anyhow::bail!("--view range requires --range <a..b>")
+      }
+      Some(ViewKind::Commit) if self.range.is_some() => {
+        anyhow::bail!("--view commit takes --rev, not --range")
+      }
+      Some(ViewKind::All | ViewKind::Unstaged | ViewKind::Staged | ViewKind::Base)
+        if has_revisions =>
+      {
+        anyhow::bail!("--rev and --range open the commit and range views; drop --view")
+      }
+      _ => Ok(()),
+    }
+  }
+
   fn has_any(&self) -> bool {
     self.show_ignored.is_some()
       || self.view.is_some()
+      || self.rev.is_some()
+      || self.range.is_some()
       || self.file.is_some()
       || self.line.is_some()
       || self.side.is_some()
+      || self.theme.is_some()
+      || self.syntax_theme.is_some()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn line_span_parses_single_lines_and_ranges() {
+    assert_eq!("7".parse::<LineSpan>(), Ok(LineSpan { start: 7, end: 7 }));
+    assert_eq!(
+      "10-24".parse::<LineSpan>(),
+      Ok(LineSpan { start: 10, end: 24 })
+    );
+    assert!("0".parse::<LineSpan>().is_err());
+    assert!("24-10".parse::<LineSpan>().is_err());
+    assert!("x-3".parse::<LineSpan>().is_err());
+    assert_eq!(LineSpan { start: 10, end: 24 }.to_string(), "10-24");
+  }
+
+  #[test]
+  fn view_must_agree_with_rev_and_range() {
+    let validate = |args: &[&str]| {
+      let cli =
+        Cli::try_parse_from(std::iter::once("remark").chain(args.iter().copied())).expect("parse");
+      validate_ui_args(&cli)
+    };
+    assert!(validate(&["--view", "range"]).is_err());
+    assert!(validate(&["--view", "range", "--rev", "HEAD"]).is_err());
+    assert!(validate(&["--view", "commit", "--range", "a..b"]).is_err());
+    assert!(validate(&["--view", "staged", "--rev", "HEAD"]).is_err());
+    assert!(validate(&["--view", "range", "--range", "a..b"]).is_ok());
+    assert!(validate(&["--view", "commit"]).is_ok());
+    assert!(validate(&["--view", "commit", "--rev", "HEAD"]).is_ok());
+    assert!(validate(&["--rev", "HEAD"]).is_ok());
   }
 }
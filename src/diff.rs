@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use similar::{ChangeTag, TextDiff};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Kind {
   FileHeader,
   HunkHeader,
@@ -135,6 +137,141 @@ pub fn unified_file_diff(
   Ok(out)
 }
 
+/// Applies part of the change `lines` shows from `before` to `after`: removed lines are dropped
+/// and added lines kept only where `pick` says so. `pick` gets the kind (`Remove` or `Add`) and
+/// the line's number on its side, as in [`Hunk::changes`]. `lines` must be the diff of `before`
+/// against `after` (as returned by [`unified_file_diff`]); `None` when a line it shows doesn't
+/// match the text.
+pub fn apply_picked(
+  before: &str,
+  after: &str,
+  lines: &[Line],
+  pick: impl Fn(Kind, u32) -> bool,
+) -> Option<String> {
+  let old: Vec<&str> = before.split_inclusive('\n').collect();
+  let new: Vec<&str> = after.split_inclusive('\n').collect();
+  let same = |side: &[&str], n: u32, line: &Line| {
+    let text = line.text.get(1..).unwrap_or_default();
+    n.checked_sub(1)
+      .and_then(|i| side.get(i as usize))
+      .is_some_and(|s| s.trim_end_matches(['\n', '\r']) == text.trim_end_matches(['\n', '\r']))
+  };
+
+  let mut out = String::with_capacity(after.len().max(before.len()));
+  let mut next_old = 0;
+  for line in lines {
+    match line.kind {
+      Kind::Context | Kind::Remove => {
+        let n = line.old_line?;
+        if !same(&old, n, line) || (n as usize) <= next_old {
+          return None;
+        }
+        out.extend(old[next_old..n as usize - 1].iter().copied());
+        if line.kind == Kind::Context || !pick(Kind::Remove, n) {
+          out.push_str(old[n as usize - 1]);
+        }
+        next_old = n as usize;
+      }
+      Kind::Add => {
+        let n = line.new_line?;
+        if !same(&new, n, line) {
+          return None;
+        }
+        if pick(Kind::Add, n) {
+          // A kept last line without a newline gets one when a line follows it.
+          if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+          }
+          out.push_str(new[n as usize - 1]);
+        }
+      }
+      Kind::FileHeader | Kind::HunkHeader => {}
+    }
+  }
+  out.extend(old[next_old..].iter().copied());
+  Some(out)
+}
+
+/// A hunk of a unified diff, identified by what it changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+  /// Hash of the removed and added lines. Context and line numbers are left out, so the hunk
+  /// keeps its identity when edits elsewhere in the file shift it; a hunk repeating an earlier
+  /// hunk's change also hashes how many came before it, so the two stay apart.
+  pub hash: String,
+  /// First and last new-file line the hunk shows; `None` when it shows none (a deletion
+  /// without context).
+  pub new_lines: Option<(u32, u32)>,
+  /// The removed (old line numbers) and added (new line numbers) lines.
+  pub changes: Vec<(Kind, u32)>,
+}
+
+/// The hunks of `lines` (as returned by [`unified_file_diff`]), in order.
+pub fn hunks(lines: &[Line]) -> Vec<Hunk> {
+  let mut out = Vec::new();
+  let mut cur: Option<(gix_hash::Hasher, Hunk)> = None;
+  let mut seen: HashMap<String, usize> = HashMap::new();
+  let mut finish = |cur: Option<(gix_hash::Hasher, Hunk)>, out: &mut Vec<Hunk>| {
+    let Some((h, mut hunk)) = cur else {
+      return;
+    };
+    let Ok(oid) = h.try_finalize() else {
+      return;
+    };
+    let hash = oid.to_string();
+    let earlier = seen.entry(hash.clone()).or_default();
+    hunk.hash = match *earlier {
+      0 => hash,
+      n => occurrence_hash(&hash, n),
+    };
+    *earlier += 1;
+    out.push(hunk);
+  };
+  for line in lines {
+    match line.kind {
+      Kind::HunkHeader => {
+        finish(cur.take(), &mut out);
+        let mut h = gix_hash::hasher(gix_hash::Kind::Sha1);
+        h.update(b"remark-hunk-v1\0");
+        let hunk = Hunk {
+          hash: String::new(),
+          new_lines: None,
+          changes: Vec::new(),
+        };
+        cur = Some((h, hunk));
+      }
+      Kind::Context | Kind::Add | Kind::Remove => {
+        let Some((h, hunk)) = cur.as_mut() else {
+          continue;
+        };
+        if line.kind != Kind::Context {
+          h.update(line.text.trim_end_matches(['\n', '\r']).as_bytes());
+          h.update(b"\n");
+          if let Some(n) = line.old_line.or(line.new_line) {
+            hunk.changes.push((line.kind, n));
+          }
+        }
+        if let Some(n) = line.new_line {
+          hunk.new_lines = Some(hunk.new_lines.map_or((n, n), |(start, _)| (start, n)));
+        }
+      }
+      Kind::FileHeader => {}
+    }
+  }
+  finish(cur, &mut out);
+  out
+}
+
+/// Hash of the `n`th repeat of a hunk whose changes hash to `hash`.
+fn occurrence_hash(hash: &str, n: usize) -> String {
+  let mut h = gix_hash::hasher(gix_hash::Kind::Sha1);
+  h.update(b"remark-hunk-repeat-v1\0");
+  h.update(hash.as_bytes());
+  h.update(format!("\0{n}").as_bytes());
+  h.try_finalize()
+    .map_or_else(|_| format!("{hash}-{n}"), |oid| oid.to_string())
+}
+
 fn calculate_hunk_bounds(group: &[similar::DiffOp]) -> (u32, u32, u32, u32) {
   let before_start = group
     .first()
@@ -377,6 +514,99 @@ mod tests {
     assert!(header.contains("one") || header.contains("two"), "{header}");
   }
 
+  #[test]
+  fn hunk_hash_survives_unrelated_edits() {
+    let before = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let after = "a\nB\nc\nd\ne\nf\ng\nh\nI\nj\n";
+    let hunks_of = |before, after| {
+      let lines = unified_file_diff("a/t", "b/t", Some(before), Some(after), 1).unwrap();
+      hunks(&lines)
+    };
+
+    let two = hunks_of(before, after);
+    assert_eq!(two.len(), 2);
+    assert_eq!(two[0].new_lines, Some((1, 3)));
+    assert_eq!(two[1].new_lines, Some((8, 10)));
+    assert_eq!(two[1].changes, [(Kind::Remove, 9), (Kind::Add, 9)]);
+    assert_ne!(two[0].hash, two[1].hash);
+
+    // Dropping the first change and inserting a line on top moves the second hunk but keeps
+    // its hash.
+    let shifted = hunks_of(before, "z\na\nb\nc\nd\ne\nf\ng\nh\nI\nj\n");
+    assert_eq!(shifted.len(), 2);
+    assert_eq!(shifted[1].hash, two[1].hash);
+    assert_eq!(shifted[1].new_lines, Some((9, 11)));
+  }
+
+  #[test]
+  fn identical_hunks_get_distinct_hashes() {
+    let before = "use foo;\na\nb\nc\nd\nuse foo;\ne\n";
+    let after = "a\nb\nc\nd\ne\n";
+    let lines = unified_file_diff("a/t", "b/t", Some(before), Some(after), 1).unwrap();
+    let found = hunks(&lines);
+
+    assert_eq!(found.len(), 2);
+    assert_ne!(found[0].hash, found[1].hash);
+
+    // The first occurrence keeps the hash a lone hunk with the same change has.
+    let lone = unified_file_diff("a/t", "b/t", Some("use foo;\na\n"), Some("a\n"), 1).unwrap();
+    assert_eq!(hunks(&lone)[0].hash, found[0].hash);
+  }
+
+  #[test]
+  fn apply_picked_takes_only_picked_lines() {
+    let before = "a\nb\nc\n";
+    let after = "a\nB\nc\nd\n";
+    let lines = unified_file_diff("a/t", "b/t", Some(before), Some(after), 0).unwrap();
+    let apply = |pick: &dyn Fn(Kind, u32) -> bool| apply_picked(before, after, &lines, pick);
+
+    assert_eq!(apply(&|_, _| true).as_deref(), Some(after));
+    assert_eq!(apply(&|_, _| false).as_deref(), Some(before));
+    // Only the appended line.
+    assert_eq!(
+      apply(&|kind, line| kind == Kind::Add && line == 4).as_deref(),
+      Some("a\nb\nc\nd\n")
+    );
+    // Only the removal of `b`, without adding `B`.
+    assert_eq!(
+      apply(&|kind, line| kind == Kind::Remove && line == 2).as_deref(),
+      Some("a\nc\n")
+    );
+    // Every removal of a deleted file leaves nothing.
+    let gone = unified_file_diff("a/t", "b/t", Some(before), None, 3).unwrap();
+    assert_eq!(
+      apply_picked(before, "", &gone, |_, _| true).as_deref(),
+      Some("")
+    );
+    // A diff of other text is refused.
+    assert_eq!(apply_picked("x\nb\nc\n", after, &lines, |_, _| true), None);
+    // Appending after a last line without a newline keeps the lines apart.
+    let lines = unified_file_diff("a/t", "b/t", Some("a\nb"), Some("a\nb\nc\n"), 0).unwrap();
+    assert_eq!(
+      apply_picked("a\nb", "a\nb\nc\n", &lines, |kind, line| kind == Kind::Add
+        && line == 3)
+      .as_deref(),
+      Some("a\nb\nc\n")
+    );
+  }
+
+  #[test]
+  fn apply_picked_follows_the_shown_hunks() {
+    // The two hunks change identical lines; picking the second one's changes must stage the
+    // second change and leave the first alone.
+    let before = "x\na\nb\nc\nd\nx\ne\n";
+    let after = "y\na\nb\nc\nd\ny\ne\n";
+    let lines = unified_file_diff("a/t", "b/t", Some(before), Some(after), 1).unwrap();
+    let found = hunks(&lines);
+    assert_eq!(found.len(), 2);
+    let picked = &found[1].changes;
+
+    assert_eq!(
+      apply_picked(before, after, &lines, |kind, n| picked.contains(&(kind, n))).as_deref(),
+      Some("x\na\nb\nc\nd\ny\ne\n")
+    );
+  }
+
   #[test]
   fn decorated_returns_full_file() {
     let before = "line1\nline2\nline3\n";
@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ignore::Match;
+use ignore::overrides::{Override, OverrideBuilder};
+
+use crate::app::{FileChangeKind, FileEntry};
+
+/// Narrows the file list. It is kept when switching views; with nothing set every file shows.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileFilter {
+  pub(crate) unreviewed: bool,
+  /// Only files with unresolved comments.
+  pub(crate) unresolved: bool,
+  pub(crate) change: Option<FileChangeKind>,
+  glob: Option<(String, Override)>,
+}
+
+impl FileFilter {
+  pub(crate) fn is_active(&self) -> bool {
+    self.unreviewed || self.unresolved || self.change.is_some() || self.glob.is_some()
+  }
+
+  pub(crate) fn glob(&self) -> Option<&str> {
+    self.glob.as_ref().map(|(text, _)| text.as_str())
+  }
+
+  /// Sets the glob filter from whitespace-separated gitignore-style patterns; `!` excludes.
+  /// Blank input clears it.
+  pub(crate) fn set_glob(&mut self, globs: &str) -> Result<()> {
+    let globs = globs.trim();
+    if globs.is_empty() {
+      self.glob = None;
+      return Ok(());
+    }
+    let mut builder = OverrideBuilder::new("");
+    for glob in globs.split_whitespace() {
+      builder
+        .add(glob)
+        .with_context(|| format!("invalid glob '{glob}'"))?;
+    }
+    let overrides = builder.build().context("build glob filter")?;
+    self.glob = Some((globs.to_string(), overrides));
+    Ok(())
+  }
+
+  /// Steps the change kind filter through any → added → modified → deleted and back.
+  pub(crate) fn cycle_change(&mut self) {
+    self.change = match self.change {
+      None => Some(FileChangeKind::Added),
+      Some(FileChangeKind::Added) => Some(FileChangeKind::Modified),
+      Some(FileChangeKind::Modified) => Some(FileChangeKind::Deleted),
+      Some(FileChangeKind::Deleted) => None,
+    };
+  }
+
+  pub(crate) fn matches(&self, entry: &FileEntry, reviewed: bool, unresolved: bool) -> bool {
+    if self.unreviewed && reviewed {
+      return false;
+    }
+    if self.unresolved && !unresolved {
+      return false;
+    }
+    if self.change.is_some_and(|kind| kind != entry.change) {
+      return false;
+    }
+    let Some((_, overrides)) = &self.glob else {
+      return true;
+    };
+    let path = Path::new(&entry.path);
+    match overrides.matched(path, false) {
+      Match::None | Match::Whitelist(_) => true,
+      // A pattern naming a directory takes in the files below it.
+      Match::Ignore(_) => path
+        .ancestors()
+        .skip(1)
+        .any(|dir| overrides.matched(dir, true).is_whitelist()),
+    }
+  }
+
+  /// The active filters, comma separated, e.g. `unreviewed, added, *.rs`.
+  pub(crate) fn describe(&self) -> String {
+    let mut parts = Vec::new();
+    if self.unreviewed {
+      parts.push("unreviewed");
+    }
+    if self.unresolved {
+      parts.push("unresolved");
+    }
+    if let Some(kind) = self.change {
+      parts.push(change_label(kind));
+    }
+    if let Some(glob) = self.glob() {
+      parts.push(glob);
+    }
+    parts.join(", ")
+  }
+}
+
+pub(crate) fn change_label(kind: FileChangeKind) -> &'static str {
+  match kind {
+    FileChangeKind::Added => "added",
+    FileChangeKind::Modified => "modified",
+    FileChangeKind::Deleted => "deleted",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(path: &str, change: FileChangeKind) -> FileEntry {
+    FileEntry {
+      path: path.to_string(),
+      change,
+      git_xy: [' ', 'M'],
+    }
+  }
+
+  #[test]
+  fn filters_combine() {
+    let rs = entry("src/app.rs", FileChangeKind::Modified);
+    let new_rs = entry("src/file_filter.rs", FileChangeKind::Added);
+    let doc = entry("docs/guide/intro.md", FileChangeKind::Modified);
+
+    let mut filter = FileFilter::default();
+    assert!(!filter.is_active());
+    assert!(filter.matches(&doc, true, false));
+
+    filter.set_glob("*.rs").expect("glob");
+    assert!(filter.matches(&rs, false, false));
+    assert!(!filter.matches(&doc, false, false));
+
+    filter.cycle_change();
+    assert_eq!(filter.change, Some(FileChangeKind::Added));
+    assert!(filter.matches(&new_rs, false, false));
+    assert!(!filter.matches(&rs, false, false));
+
+    filter.unreviewed = true;
+    filter.unresolved = true;
+    assert!(!filter.matches(&new_rs, true, true));
+    assert!(!filter.matches(&new_rs, false, false));
+    assert!(filter.matches(&new_rs, false, true));
+    assert_eq!(filter.describe(), "unreviewed, unresolved, added, *.rs");
+
+    let mut filter = FileFilter::default();
+    filter.set_glob("docs !*.rs").expect("glob");
+    assert!(filter.matches(&doc, false, false));
+    assert!(!filter.matches(&rs, false, false));
+    filter.set_glob("  ").expect("clear");
+    assert!(!filter.is_active());
+  }
+}
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
 use crate::app::FileEntry;
 
@@ -7,6 +7,11 @@ pub(crate) struct FileTreeRow {
   pub(crate) label: String,
   pub(crate) file_index: Option<usize>,
   pub(crate) is_dir: bool,
+  /// Path of a directory row, without the trailing slash.
+  pub(crate) dir: Option<String>,
+  pub(crate) collapsed: bool,
+  /// Every file below a directory row, including those inside collapsed subdirectories.
+  pub(crate) dir_files: Vec<usize>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -22,7 +27,9 @@ struct Node {
 }
 
 impl FileTreeView {
-  pub(crate) fn build(files: &[FileEntry]) -> Self {
+  /// Lays out `files` as a tree. Directories in `collapsed` get a row but hide their contents;
+  /// their files map to that row.
+  pub(crate) fn build(files: &[FileEntry], collapsed: &HashSet<String>) -> Self {
     if files.is_empty() {
       return Self::default();
     }
@@ -44,10 +51,17 @@ impl FileTreeView {
       rows: Vec::new(),
       file_to_row: vec![0; files.len()],
     };
-    walk_node(&root, &mut Vec::new(), true, &mut out);
+    walk_node(&root, "", collapsed, &mut Vec::new(), true, &mut out);
     out
   }
 
+  pub(crate) fn dir_row(&self, dir: &str) -> Option<usize> {
+    self
+      .rows
+      .iter()
+      .position(|row| row.dir.as_deref() == Some(dir))
+  }
+
   pub(crate) fn selected_row(&self, file_index: usize) -> Option<usize> {
     self.file_to_row.get(file_index).copied()
   }
@@ -55,19 +69,16 @@ impl FileTreeView {
   pub(crate) fn file_at_row(&self, row: usize) -> Option<usize> {
     self.rows.get(row)?.file_index
   }
-
-  pub(crate) fn next_file_row(&self, current_row: usize) -> Option<usize> {
-    (current_row + 1..self.rows.len()).find(|&row| self.rows[row].file_index.is_some())
-  }
-
-  pub(crate) fn prev_file_row(&self, current_row: usize) -> Option<usize> {
-    (0..current_row)
-      .rev()
-      .find(|&row| self.rows[row].file_index.is_some())
-  }
 }
 
-fn walk_node(node: &Node, prefix_stack: &mut Vec<bool>, is_root: bool, out: &mut FileTreeView) {
+fn walk_node(
+  node: &Node,
+  path: &str,
+  collapsed: &HashSet<String>,
+  prefix_stack: &mut Vec<bool>,
+  is_root: bool,
+  out: &mut FileTreeView,
+) {
   // Collect directories separately from files so we can sort them independently
   let mut dirs: Vec<(String, EntryRef<'_>)> = node
     .dirs
@@ -119,6 +130,9 @@ fn walk_node(node: &Node, prefix_stack: &mut Vec<bool>, is_root: bool, out: &mut
           label,
           file_index: Some(file_index),
           is_dir: false,
+          dir: None,
+          collapsed: false,
+          dir_files: Vec::new(),
         });
         if let Some(slot) = out.file_to_row.get_mut(file_index) {
           *slot = row_idx;
@@ -130,17 +144,39 @@ fn walk_node(node: &Node, prefix_stack: &mut Vec<bool>, is_root: bool, out: &mut
         } else {
           format!("{}{name}/", tree_prefix(prefix_stack, is_last))
         };
+        let dir = if path.is_empty() {
+          name.to_string()
+        } else {
+          format!("{path}/{name}")
+        };
+        let mut dir_files = Vec::new();
+        collect_files(child, &mut dir_files);
+        let is_collapsed = collapsed.contains(&dir);
+        let row_idx = out.rows.len();
+        if is_collapsed {
+          for &file_index in &dir_files {
+            if let Some(slot) = out.file_to_row.get_mut(file_index) {
+              *slot = row_idx;
+            }
+          }
+        }
         out.rows.push(FileTreeRow {
           label,
           file_index: None,
           is_dir: true,
+          dir: Some(dir.clone()),
+          collapsed: is_collapsed,
+          dir_files,
         });
+        if is_collapsed {
+          continue;
+        }
 
         // Don't draw root-level vertical connector columns; root has no "├/└" lines.
         if !is_root {
           prefix_stack.push(is_last);
         }
-        walk_node(child, prefix_stack, false, out);
+        walk_node(child, &dir, collapsed, prefix_stack, false, out);
         if !is_root {
           prefix_stack.pop();
         }
@@ -149,6 +185,13 @@ fn walk_node(node: &Node, prefix_stack: &mut Vec<bool>, is_root: bool, out: &mut
   }
 }
 
+fn collect_files(node: &Node, out: &mut Vec<usize>) {
+  for child in node.dirs.values() {
+    collect_files(child, out);
+  }
+  out.extend(node.files.values());
+}
+
 fn tree_prefix(prefix_stack: &[bool], is_last: bool) -> String {
   let mut out = String::new();
   for &ancestor_is_last in prefix_stack {
@@ -188,7 +231,7 @@ mod tests {
   #[test]
   fn builds_flat_list() {
     let files = vec![fe("a.rs"), fe("b.rs")];
-    let view = FileTreeView::build(&files);
+    let view = FileTreeView::build(&files, &HashSet::new());
     assert_eq!(view.rows.len(), 2);
     assert_eq!(view.rows[0].label, "a.rs");
     assert_eq!(view.rows[1].label, "b.rs");
@@ -200,7 +243,7 @@ mod tests {
   #[test]
   fn builds_tree_with_dirs() {
     let files = vec![fe("README.md"), fe("src/app.rs"), fe("src/ui.rs")];
-    let view = FileTreeView::build(&files);
+    let view = FileTreeView::build(&files, &HashSet::new());
 
     let labels: Vec<&str> = view.rows.iter().map(|r| r.label.as_str()).collect();
     // Directories now come before files: src/ comes before README.md
@@ -213,29 +256,40 @@ mod tests {
     assert_eq!(view.file_at_row(3), Some(0)); // README.md
   }
 
+  #[test]
+  fn collapsed_dirs_hide_their_files() {
+    let files = vec![
+      fe("README.md"),
+      fe("src/app.rs"),
+      fe("src/ui/draw.rs"),
+      fe("src/ui/help.rs"),
+    ];
+    let collapsed = HashSet::from(["src/ui".to_string()]);
+    let view = FileTreeView::build(&files, &collapsed);
+
+    let labels: Vec<&str> = view.rows.iter().map(|r| r.label.as_str()).collect();
+    assert_eq!(labels, vec!["src/", "├─ ui/", "└─ app.rs", "README.md"]);
+    assert_eq!(view.dir_row("src/ui"), Some(1));
+    assert!(view.rows[1].collapsed);
+    assert!(!view.rows[0].collapsed);
+    // Hidden files map to the collapsed directory's row.
+    assert_eq!(view.file_to_row, vec![3, 2, 1, 1]);
+    let mut under_src = view.rows[0].dir_files.clone();
+    under_src.sort();
+    assert_eq!(under_src, vec![1, 2, 3]);
+    assert_eq!(view.rows[1].dir_files, vec![2, 3]);
+  }
+
   #[test]
   fn navigation_through_directories() {
     let files = vec![fe("abc/first.rs"), fe("plo/second.rs"), fe("mod.rs")];
-    let view = FileTreeView::build(&files);
-
-    eprintln!(
-      "Labels: {:?}",
-      view
-        .rows
-        .iter()
-        .map(|r| r.label.as_str())
-        .collect::<Vec<_>>()
-    );
-    eprintln!("file_to_row: {:?}", view.file_to_row);
-
-    // Test navigation order
-    let first_row = view.file_to_row[0]; // first.rs
-    let next1 = view.next_file_row(first_row);
-    let next2 = next1.and_then(|r| view.next_file_row(r));
-
-    eprintln!(
-      "From first.rs (row {}): next is {:?}, then {:?}",
-      first_row, next1, next2
-    );
+    let view = FileTreeView::build(&files, &HashSet::new());
+
+    // Stepping row by row passes each directory before its files.
+    let order: Vec<Option<usize>> = (0..view.rows.len()).map(|r| view.file_at_row(r)).collect();
+    assert_eq!(order, vec![None, Some(0), None, Some(1), Some(2)]);
+    assert_eq!(view.dir_row("abc"), Some(0));
+    assert_eq!(view.dir_row("plo"), Some(2));
+    assert_eq!(view.rows[2].dir_files, vec![1]);
   }
 }
@@ -5,6 +5,7 @@ use anyhow::{Context, Result};
 use gix::bstr::{BStr, ByteSlice};
 use gix::{ObjectId, Repository};
 use gix_dir::walk::EmissionMode;
+use gix_ref::transaction::PreviousValue;
 
 pub const DEFAULT_NOTES_REF: &str = "refs/notes/remark";
 pub const CONFIG_NOTES_REF_KEY: &str = "remark.notesRef";
@@ -16,6 +17,30 @@ pub enum ViewKind {
   Unstaged,
   Staged,
   Base,
+  Commit,
+  Range,
+}
+
+/// A commit under review, with the parent it is diffed against.
+#[derive(Debug, Clone)]
+pub struct RevCommit {
+  pub id: ObjectId,
+  /// First parent; `None` for a root commit.
+  pub parent: Option<ObjectId>,
+  pub summary: String,
+}
+
+/// The commit or commit range reviewed by the `commit` and `range` views.
+#[derive(Debug, Clone)]
+pub struct Revisions {
+  /// View these revisions were requested for (`--rev` or `--range`).
+  pub view: ViewKind,
+  /// The revision spec as given on the command line.
+  pub label: String,
+  pub from: Option<ObjectId>,
+  pub to: ObjectId,
+  /// Commits in `from..to` along the first-parent chain, oldest first.
+  pub commits: Vec<RevCommit>,
 }
 
 pub fn default_base_ref(repo: &Repository) -> Option<String> {
@@ -64,6 +89,14 @@ pub fn read_notes_ref(repo: &Repository) -> String {
     .unwrap_or_else(|| DEFAULT_NOTES_REF.to_string())
 }
 
+/// The git author identity (`Name <email>`) used to attribute comments and replies.
+pub fn author_identity(repo: &Repository) -> String {
+  if let Some(Ok(sig)) = repo.author() {
+    return format!("{} <{}>", sig.name, sig.email);
+  }
+  std::env::var("USER").unwrap_or_else(|_| "remark".to_string())
+}
+
 pub fn ensure_notes_ref(repo: &Repository, notes_ref: &str) -> Result<()> {
   if repo
     .try_find_reference(notes_ref)
@@ -77,17 +110,25 @@ pub fn ensure_notes_ref(repo: &Repository, notes_ref: &str) -> Result<()> {
     return Ok(());
   };
 
-  if !remote_has_notes_ref(repo, &remote, notes_ref)? {
+  let Some(fetched) = fetch_notes_ref(repo, &remote, notes_ref)
+    .with_context(|| format!("fetch {notes_ref} from {remote}"))?
+  else {
     return Ok(());
-  }
+  };
 
-  fetch_notes_ref(repo, &remote, notes_ref)
-    .with_context(|| format!("fetch {notes_ref} from {remote}"))?;
+  repo
+    .reference(
+      notes_ref,
+      fetched,
+      PreviousValue::MustNotExist,
+      "remark: fetch notes",
+    )
+    .context("create notes ref")?;
 
   Ok(())
 }
 
-fn select_notes_remote(repo: &Repository) -> Result<Option<String>> {
+pub fn select_notes_remote(repo: &Repository) -> Result<Option<String>> {
   if let Some(remote) = read_local_config_value(repo, CONFIG_NOTES_REMOTE_KEY)
     .ok()
     .flatten()
@@ -150,18 +191,38 @@ fn list_remotes(repo: &Repository) -> Result<Vec<String>> {
   Ok(remotes)
 }
 
-fn fetch_notes_ref(repo: &Repository, remote: &str, notes_ref: &str) -> Result<()> {
+/// Local ref mirroring `notes_ref` as last fetched from `remote`.
+pub fn remote_notes_ref(remote: &str, notes_ref: &str) -> String {
+  let name = notes_ref.strip_prefix("refs/").unwrap_or(notes_ref);
+  format!("refs/remark/remotes/{remote}/{name}")
+}
+
+/// Fetches `notes_ref` from `remote` into its remote-tracking ref and returns the fetched commit.
+///
+/// The local notes ref is never touched here; callers merge the fetched commit into it.
+pub fn fetch_notes_ref(
+  repo: &Repository,
+  remote: &str,
+  notes_ref: &str,
+) -> Result<Option<ObjectId>> {
+  if !remote_has_notes_ref(repo, remote, notes_ref)? {
+    return Ok(None);
+  }
+
   let workdir = repo
     .workdir()
     .map(ToOwned::to_owned)
     .unwrap_or_else(|| repo.git_dir().to_path_buf());
 
-  let refspec = format!("+{notes_ref}:{notes_ref}");
+  // Forcing is fine: the tracking ref only ever mirrors the remote.
+  let tracking_ref = remote_notes_ref(remote, notes_ref);
+  let refspec = format!("+{notes_ref}:{tracking_ref}");
   let status = Command::new("git")
     .arg("-C")
     .arg(workdir)
     .arg("fetch")
     .arg("--no-tags")
+    .arg("--quiet")
     .arg(remote)
     .arg(refspec)
     .status()
@@ -171,6 +232,37 @@ fn fetch_notes_ref(repo: &Repository, remote: &str, notes_ref: &str) -> Result<(
     anyhow::bail!("git fetch failed");
   }
 
+  let fetched = repo
+    .try_find_reference(tracking_ref.as_str())
+    .context("find remote notes ref")?
+    .with_context(|| format!("{tracking_ref} missing after fetch"))?
+    .id()
+    .detach();
+  Ok(Some(fetched))
+}
+
+/// Pushes `notes_ref` to `remote` without forcing, so a remote that moved on is never overwritten.
+pub fn push_notes_ref(repo: &Repository, remote: &str, notes_ref: &str) -> Result<()> {
+  let workdir = repo
+    .workdir()
+    .map(ToOwned::to_owned)
+    .unwrap_or_else(|| repo.git_dir().to_path_buf());
+
+  let refspec = format!("{notes_ref}:{notes_ref}");
+  let status = Command::new("git")
+    .arg("-C")
+    .arg(workdir)
+    .arg("push")
+    .arg("--quiet")
+    .arg(remote)
+    .arg(refspec)
+    .status()
+    .context("spawn git push")?;
+
+  if !status.success() {
+    anyhow::bail!("git push failed (the remote notes may have moved on; run `remark pull` first)");
+  }
+
   Ok(())
 }
 
@@ -234,6 +326,187 @@ pub fn head_commit_oid(repo: &Repository) -> Result<ObjectId> {
   Ok(repo.head_commit().context("read HEAD commit")?.id)
 }
 
+pub fn commit_tree(repo: &Repository, commit_id: ObjectId) -> Result<gix::Tree<'_>> {
+  repo
+    .find_object(commit_id)
+    .with_context(|| format!("find commit {commit_id}"))?
+    .peel_to_commit()
+    .context("object is not a commit")?
+    .tree()
+    .context("commit tree")
+}
+
+fn rev_commit(repo: &Repository, id: ObjectId) -> Result<RevCommit> {
+  let commit = repo
+    .find_object(id)
+    .with_context(|| format!("find commit {id}"))?
+    .peel_to_commit()
+    .context("object is not a commit")?;
+  let parent = commit.parent_ids().next().map(|p| p.detach());
+  let summary = commit
+    .message()
+    .map(|m| m.summary().to_str_lossy().into_owned())
+    .unwrap_or_default();
+  Ok(RevCommit {
+    id,
+    parent,
+    summary,
+  })
+}
+
+/// Resolves `rev` to the id of the commit it names.
+pub fn resolve_commit_id(repo: &Repository, rev: &str) -> Result<ObjectId> {
+  Ok(
+    repo
+      .rev_parse_single(rev.as_bytes().as_bstr())
+      .with_context(|| format!("resolve revision '{rev}'"))?
+      .object()
+      .context("peel revision object")?
+      .peel_to_commit()
+      .with_context(|| format!("'{rev}' is not a commit"))?
+      .id,
+  )
+}
+
+/// Resolves `rev` to a single commit reviewed against its first parent.
+pub fn resolve_commit(repo: &Repository, rev: &str) -> Result<Revisions> {
+  let commit = rev_commit(repo, resolve_commit_id(repo, rev)?)?;
+  Ok(Revisions {
+    view: ViewKind::Commit,
+    label: rev.to_string(),
+    from: commit.parent,
+    to: commit.id,
+    commits: vec![commit],
+  })
+}
+
+/// Resolves an `a..b` range. Either side may be omitted and defaults to `HEAD`, like git.
+pub fn resolve_range(repo: &Repository, spec: &str) -> Result<Revisions> {
+  let (from, to) = parse_range_spec(spec)?;
+  let from = resolve_commit_id(repo, from)?;
+  let to = resolve_commit_id(repo, to)?;
+  let range = format!("{from}..{to}");
+  let commits = git_output_oids(repo, &["rev-list", "--first-parent", "--reverse", &range])
+    .into_iter()
+    .map(|id| rev_commit(repo, id))
+    .collect::<Result<Vec<_>>>()?;
+  Ok(Revisions {
+    view: ViewKind::Range,
+    label: spec.to_string(),
+    from: Some(from),
+    to,
+    commits,
+  })
+}
+
+fn parse_range_spec(spec: &str) -> Result<(&str, &str)> {
+  if spec.contains("...") {
+    anyhow::bail!("symmetric ranges are not supported: '{spec}' (use a..b)");
+  }
+  let Some((from, to)) = spec.split_once("..") else {
+    anyhow::bail!("invalid range '{spec}' (expected a..b)");
+  };
+  let or_head = |s: &str| if s.is_empty() { "HEAD" } else { s };
+  let (from, to) = (or_head(from), or_head(to));
+  if from == to {
+    anyhow::bail!("empty range '{spec}'");
+  }
+  Ok((from, to))
+}
+
+/// Commits HEAD pointed at before `head` whose notes belong to it, most recent first.
+///
+/// Those are ancestors of `head` (plain commits, and first-parent history for clones without a
+/// reflog) and what amends, rebases and resets replaced. The reflog is followed back only until
+/// the first other move (e.g. a checkout), so another branch's history is never picked up.
+/// Best-effort: git failures yield fewer candidates.
+pub fn previous_head_commits(repo: &Repository, head: ObjectId, limit: usize) -> Vec<ObjectId> {
+  // One more entry than needed: each move's old value is the next entry's commit.
+  let max_count = format!("--max-count={}", limit + 1);
+  let reflog = git_output(
+    repo,
+    &[
+      "log",
+      "--walk-reflogs",
+      "--format=%H %gs",
+      max_count.as_str(),
+      "HEAD",
+    ],
+  );
+  let entries: Vec<(ObjectId, &str)> = reflog
+    .lines()
+    .filter_map(|line| {
+      let (hex, subject) = line.split_once(' ').unwrap_or((line, ""));
+      Some((ObjectId::from_hex(hex.as_bytes()).ok()?, subject))
+    })
+    .collect();
+
+  let mut out = Vec::new();
+  for pair in entries.windows(2) {
+    let (subject, old) = (pair[0].1, pair[1].0);
+    if !rewrites_head(subject) && !is_ancestor(repo, old, head) {
+      break;
+    }
+    if old != head && !out.contains(&old) {
+      out.push(old);
+    }
+  }
+  let max_count = format!("--max-count={limit}");
+  let ancestors = git_output_oids(
+    repo,
+    &["rev-list", "--first-parent", max_count.as_str(), "HEAD"],
+  );
+  for oid in ancestors {
+    if oid != head && !out.contains(&oid) {
+      out.push(oid);
+    }
+  }
+  out.truncate(limit);
+  out
+}
+
+/// Whether a HEAD reflog subject is an amend, rebase or reset, which replace commits in place.
+fn rewrites_head(subject: &str) -> bool {
+  ["commit (amend)", "rebase", "reset"]
+    .iter()
+    .any(|prefix| subject.starts_with(prefix))
+}
+
+/// Whether `commit` is `head` or one of its ancestors.
+pub fn is_ancestor(repo: &Repository, commit: ObjectId, head: ObjectId) -> bool {
+  commit == head
+    || repo
+      .merge_base(commit, head)
+      .is_ok_and(|base| base.detach() == commit)
+}
+
+fn git_output_oids(repo: &Repository, args: &[&str]) -> Vec<ObjectId> {
+  git_output(repo, args)
+    .lines()
+    .filter_map(|line| ObjectId::from_hex(line.trim().as_bytes()).ok())
+    .collect()
+}
+
+fn git_output(repo: &Repository, args: &[&str]) -> String {
+  let workdir = repo
+    .workdir()
+    .map(ToOwned::to_owned)
+    .unwrap_or_else(|| repo.git_dir().to_path_buf());
+
+  let Ok(output) = Command::new("git")
+    .arg("-C")
+    .arg(workdir)
+    .args(args)
+    .output()
+  else {
+    return String::new();
+  };
+  if !output.status.success() {
+    return String::new();
+  }
+  String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
 pub fn normalize_repo_path(repo: &Repository, path: &str) -> String {
   let mut out = path.to_string();
   if let Some(stripped) = out.strip_prefix("./") {
@@ -268,6 +541,8 @@ pub fn note_file_key_oid(
     ViewKind::Unstaged => "unstaged",
     ViewKind::Staged => "staged",
     ViewKind::Base => "base",
+    ViewKind::Commit => "commit",
+    ViewKind::Range => "range",
   });
   key.push('\n');
   if let Some(b) = base_ref {
@@ -476,9 +751,61 @@ pub fn list_base_paths(repo: &Repository, base_ref: &str) -> Result<Vec<String>>
   };
 
   let base_tree = merge_base_tree(repo, base_ref)?;
+  changed_paths_between(repo, &base_tree, &head_tree)
+}
+
+/// Paths changed by `to` relative to `from`, or to the empty tree for a root commit.
+pub fn list_commit_paths(
+  repo: &Repository,
+  from: Option<ObjectId>,
+  to: ObjectId,
+) -> Result<Vec<String>> {
+  let old_tree = match from {
+    Some(id) => commit_tree(repo, id)?,
+    None => repo.empty_tree(),
+  };
+  let new_tree = commit_tree(repo, to)?;
+  changed_paths_between(repo, &old_tree, &new_tree)
+}
 
+/// Paths changed by the commits reachable from `tip` that no remote-tracking ref has.
+pub fn unpushed_paths(repo: &Repository, tip: ObjectId) -> Result<Vec<String>> {
+  let workdir = repo
+    .workdir()
+    .map(ToOwned::to_owned)
+    .unwrap_or_else(|| repo.git_dir().to_path_buf());
+  let tip = tip.to_string();
+  let output = Command::new("git")
+    .arg("-C")
+    .arg(workdir)
+    .args(["rev-list", tip.as_str(), "--not", "--remotes"])
+    .output()
+    .context("spawn git rev-list")?;
+  if !output.status.success() {
+    anyhow::bail!("git rev-list failed");
+  }
+
+  let mut out = BTreeSet::new();
+  for line in output.stdout.lines() {
+    let id = ObjectId::from_hex(line.trim()).context("parse git rev-list output")?;
+    let parent = repo
+      .find_commit(id)
+      .context("read pushed commit")?
+      .parent_ids()
+      .next()
+      .map(|parent| parent.detach());
+    out.extend(list_commit_paths(repo, parent, id)?);
+  }
+  Ok(out.into_iter().collect())
+}
+
+pub fn changed_paths_between(
+  repo: &Repository,
+  old_tree: &gix::Tree<'_>,
+  new_tree: &gix::Tree<'_>,
+) -> Result<Vec<String>> {
   let changes = repo
-    .diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)
+    .diff_tree_to_tree(Some(old_tree), Some(new_tree), None)
     .context("diff trees")?;
 
   let mut out = BTreeSet::<String>::new();
@@ -497,9 +824,16 @@ pub fn list_base_paths(repo: &Repository, base_ref: &str) -> Result<Vec<String>>
 }
 
 pub fn merge_base_tree<'repo>(repo: &'repo Repository, base_ref: &str) -> Result<gix::Tree<'repo>> {
-  let head_commit = repo.head_commit().context("read HEAD commit")?;
-  let head_id = head_commit.id;
+  let head_id = head_commit_oid(repo)?;
+  merge_base_tree_for(repo, head_id, base_ref)
+}
 
+/// Like [`merge_base_tree`], but relative to `commit_id` instead of the current HEAD.
+pub fn merge_base_tree_for<'repo>(
+  repo: &'repo Repository,
+  commit_id: ObjectId,
+  base_ref: &str,
+) -> Result<gix::Tree<'repo>> {
   let base_commit = repo
     .rev_parse_single(base_ref.as_bytes().as_bstr())
     .with_context(|| format!("resolve base ref '{base_ref}'"))?
@@ -510,7 +844,7 @@ pub fn merge_base_tree<'repo>(repo: &'repo Repository, base_ref: &str) -> Result
   let base_id = base_commit.id;
 
   let merge_base = repo
-    .merge_base(head_id, base_id)
+    .merge_base(commit_id, base_id)
     .context("find merge base")?;
   merge_base
     .object()
@@ -571,6 +905,10 @@ pub fn try_read_head(repo: &Repository, path: &str) -> Result<Option<String>> {
   ))
 }
 
+pub fn try_read_at(repo: &Repository, commit_id: ObjectId, path: &str) -> Result<Option<String>> {
+  try_read_tree(&commit_tree(repo, commit_id)?, path)
+}
+
 pub fn try_read_tree(tree: &gix::Tree<'_>, path: &str) -> Result<Option<String>> {
   let Some(entry) = tree
     .lookup_entry_by_path(path)
@@ -588,9 +926,305 @@ pub fn try_read_tree(tree: &gix::Tree<'_>, path: &str) -> Result<Option<String>>
   ))
 }
 
+/// Stages the worktree version of `path`, or its removal when the file is gone. Symlinks are
+/// staged as links and the executable bit follows the worktree.
+pub fn stage_file(repo: &Repository, path: &str) -> Result<()> {
+  use gix::index::entry::Mode;
+
+  let Some(wt) = repo.workdir() else {
+    anyhow::bail!("no worktree to stage from");
+  };
+  let abs = wt.join(path);
+  let meta = match std::fs::symlink_metadata(&abs) {
+    Ok(meta) => meta,
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+      return set_index_entry(repo, path, None);
+    }
+    Err(err) => return Err(err).with_context(|| format!("stat worktree file '{}'", abs.display())),
+  };
+  let (content, mode) = if meta.file_type().is_symlink() {
+    let target =
+      std::fs::read_link(&abs).with_context(|| format!("read link '{}'", abs.display()))?;
+    let target = gix::path::into_bstr(target.as_path()).into_owned();
+    (Vec::from(target), Mode::SYMLINK)
+  } else if meta.is_file() {
+    let content =
+      std::fs::read(&abs).with_context(|| format!("read worktree file '{}'", abs.display()))?;
+    let executable = is_executable(&meta).unwrap_or_else(|| head_entry_is_executable(repo, path));
+    let mode = if executable {
+      Mode::FILE_EXECUTABLE
+    } else {
+      Mode::FILE
+    };
+    (content, mode)
+  } else {
+    anyhow::bail!("'{path}' is not a file");
+  };
+  let id = repo
+    .write_blob(content)
+    .context("write staged blob")?
+    .detach();
+  set_index_entry(repo, path, Some((id, mode)))
+}
+
+/// Resets the index entry of `path` to its `HEAD` version, mode included, dropping it when
+/// `HEAD` has none.
+pub fn unstage_file(repo: &Repository, path: &str) -> Result<()> {
+  use gix::index::entry::Mode;
+  use gix::object::tree::EntryKind;
+
+  let head_entry = match repo.head_tree() {
+    Ok(tree) => tree
+      .lookup_entry_by_path(path)
+      .with_context(|| format!("lookup '{path}' in HEAD tree"))?
+      .map(|entry| (entry.object_id(), entry.mode().kind())),
+    Err(e) if is_empty_repo_error(&e) => None,
+    Err(e) => return Err(e).context("read HEAD tree"),
+  };
+  let entry = match head_entry {
+    None => None,
+    Some((id, kind)) => {
+      let mode = match kind {
+        EntryKind::Blob => Mode::FILE,
+        EntryKind::BlobExecutable => Mode::FILE_EXECUTABLE,
+        EntryKind::Link => Mode::SYMLINK,
+        EntryKind::Commit => Mode::COMMIT,
+        EntryKind::Tree => anyhow::bail!("'{path}' is a directory in HEAD"),
+      };
+      Some((id, mode))
+    }
+  };
+  set_index_entry(repo, path, entry)
+}
+
+/// Points the index entry of `path` at `content`, adding the entry if needed; `None` removes it.
+/// An existing entry keeps its mode; a new one gets the executable bit of the worktree file or
+/// the `HEAD` entry.
+pub fn write_index_entry(repo: &Repository, path: &str, content: Option<&[u8]>) -> Result<()> {
+  use gix::index::entry::Mode;
+
+  let Some(content) = content else {
+    return set_index_entry(repo, path, None);
+  };
+  let id = repo
+    .write_blob(content)
+    .context("write staged blob")?
+    .detach();
+  let staged_mode = repo
+    .index_or_empty()
+    .context("open index")?
+    .entry_by_path(path.as_bytes().as_bstr())
+    .map(|entry| entry.mode);
+  let mode = staged_mode.unwrap_or_else(|| {
+    if head_entry_is_executable(repo, path) || worktree_is_executable(repo, path) {
+      Mode::FILE_EXECUTABLE
+    } else {
+      Mode::FILE
+    }
+  });
+  set_index_entry(repo, path, Some((id, mode)))
+}
+
+/// Points the index entry of `path` at blob `id` with `mode`; `None` removes it. Refuses paths
+/// with unresolved conflicts, whose stages only a merge tool should collapse. The entry's stat
+/// data is cleared so git re-checks the worktree file.
+fn set_index_entry(
+  repo: &Repository,
+  path: &str,
+  entry: Option<(ObjectId, gix::index::entry::Mode)>,
+) -> Result<()> {
+  use gix::index::entry::{Flags, Stat};
+
+  let mut index = repo.open_index().context("open index")?;
+  let key = path.as_bytes().as_bstr();
+  let conflicted = index
+    .entries()
+    .iter()
+    .any(|e| e.flags.intersects(Flags::STAGE_MASK) && e.path(&index) == key);
+  if conflicted {
+    anyhow::bail!("'{path}' has unresolved conflicts; resolve them first");
+  }
+  match entry {
+    None => index.remove_entries(|_, entry_path, _| entry_path == key),
+    Some((id, mode)) => match index.entry_index_by_path(key) {
+      Ok(idx) => {
+        let entry = &mut index.entries_mut()[idx];
+        entry.id = id;
+        entry.mode = mode;
+        entry.stat = Stat::default();
+      }
+      Err(_) => {
+        index.dangerously_push_entry(Stat::default(), id, Flags::empty(), mode, key);
+        index.sort_entries();
+      }
+    },
+  }
+  // The cached trees no longer match the entries.
+  index.remove_tree();
+  index
+    .write(gix::index::write::Options::default())
+    .context("write index")
+}
+
+fn head_entry_is_executable(repo: &Repository, path: &str) -> bool {
+  repo
+    .head_tree()
+    .ok()
+    .and_then(|tree| tree.lookup_entry_by_path(path).ok().flatten())
+    .is_some_and(|entry| entry.mode().kind() == gix::object::tree::EntryKind::BlobExecutable)
+}
+
+fn worktree_is_executable(repo: &Repository, path: &str) -> bool {
+  repo
+    .workdir()
+    .and_then(|wt| std::fs::metadata(wt.join(path)).ok())
+    .and_then(|meta| is_executable(&meta))
+    .unwrap_or(false)
+}
+
+/// The executable bit of a file; `None` where the filesystem has none.
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> Option<bool> {
+  use std::os::unix::fs::PermissionsExt;
+
+  Some(meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &std::fs::Metadata) -> Option<bool> {
+  None
+}
+
+/// Stores `content` as a blob so it can be read back with [`try_read_snapshot`].
+pub fn write_snapshot(repo: &Repository, content: &str) -> Result<ObjectId> {
+  Ok(
+    repo
+      .write_blob(content.as_bytes())
+      .context("write snapshot blob")?
+      .detach(),
+  )
+}
+
+/// Reads a blob written by [`write_snapshot`]; `None` when the id is malformed or the blob has
+/// been pruned since.
+pub fn try_read_snapshot(repo: &Repository, id: &str) -> Result<Option<String>> {
+  let Ok(id) = ObjectId::from_hex(id.as_bytes()) else {
+    return Ok(None);
+  };
+  let Some(object) = repo.try_find_object(id).context("find snapshot blob")? else {
+    return Ok(None);
+  };
+  let blob = object.try_into_blob().context("snapshot is not a blob")?;
+  Ok(Some(
+    String::from_utf8_lossy(blob.data.as_ref()).to_string(),
+  ))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::test_support::run_git;
+
+  #[test]
+  fn stage_and_unstage_files() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let git = |args: &[&str]| run_git(td.path(), args);
+    git(&["init", "--quiet"]);
+    std::fs::write(td.path().join("a.txt"), "one\n").expect("write file");
+    git(&["add", "a.txt"]);
+    git(&["commit", "--quiet", "--no-gpg-sign", "-m", "init"]);
+    std::fs::write(td.path().join("a.txt"), "two\n").expect("write file");
+    std::fs::write(td.path().join("b.txt"), "new\n").expect("write file");
+    let repo = gix::open(td.path()).expect("open repo");
+
+    stage_file(&repo, "a.txt").expect("stage a");
+    stage_file(&repo, "b.txt").expect("stage b");
+    assert_eq!(
+      try_read_index(&repo, "a.txt").expect("read").as_deref(),
+      Some("two\n")
+    );
+    assert_eq!(git(&["diff", "--cached", "--name-only"]), "a.txt\nb.txt\n");
+    assert_eq!(git(&["diff", "--name-only"]), "");
+
+    unstage_file(&repo, "a.txt").expect("unstage a");
+    unstage_file(&repo, "b.txt").expect("unstage b");
+    assert_eq!(
+      try_read_index(&repo, "a.txt").expect("read").as_deref(),
+      Some("one\n")
+    );
+    assert!(try_read_index(&repo, "b.txt").expect("read").is_none());
+    assert_eq!(git(&["diff", "--cached", "--name-only"]), "");
+
+    write_index_entry(&repo, "a.txt", Some(b"one\nhalf\n")).expect("write entry");
+    assert_eq!(git(&["show", ":a.txt"]), "one\nhalf\n");
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn stage_file_keeps_links_and_modes_and_skips_conflicts() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let td = tempfile::tempdir().expect("tempdir");
+    let git = |args: &[&str]| run_git(td.path(), args);
+    git(&["init", "--quiet"]);
+    std::fs::write(td.path().join("run.sh"), "echo\n").expect("write file");
+    git(&["add", "run.sh"]);
+    git(&["commit", "--quiet", "--no-gpg-sign", "-m", "init"]);
+    let repo = gix::open(td.path()).expect("open repo");
+
+    std::os::unix::fs::symlink("run.sh", td.path().join("link")).expect("symlink");
+    let script = td.path().join("run.sh");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+    stage_file(&repo, "link").expect("stage link");
+    stage_file(&repo, "run.sh").expect("stage script");
+    assert_eq!(
+      git(&["ls-files", "--stage", "link", "run.sh"])
+        .lines()
+        .map(|line| line.split(' ').next().unwrap_or_default())
+        .collect::<Vec<_>>(),
+      vec!["120000", "100755"]
+    );
+    assert_eq!(git(&["show", ":link"]), "run.sh");
+
+    unstage_file(&repo, "run.sh").expect("unstage script");
+    assert!(git(&["ls-files", "--stage", "run.sh"]).starts_with("100644 "));
+
+    let blob = git(&["hash-object", "-w", "run.sh"]);
+    let info = ["1", "2", "3"]
+      .map(|stage| format!("100644 {} {stage}\tclash.txt\n", blob.trim()))
+      .concat();
+    let mut child = Command::new("git")
+      .arg("-C")
+      .arg(td.path())
+      .args(["update-index", "--index-info"])
+      .stdin(std::process::Stdio::piped())
+      .spawn()
+      .expect("spawn git");
+    std::io::Write::write_all(&mut child.stdin.take().expect("stdin"), info.as_bytes())
+      .expect("write index info");
+    assert!(child.wait().expect("wait").success());
+    std::fs::write(td.path().join("clash.txt"), "resolved\n").expect("write file");
+    assert!(stage_file(&repo, "clash.txt").is_err());
+    assert!(unstage_file(&repo, "clash.txt").is_err());
+  }
+
+  #[test]
+  fn snapshot_roundtrip() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+
+    let id = write_snapshot(&repo, "fn main() {}\n").expect("write snapshot");
+    let read = try_read_snapshot(&repo, &id.to_string()).expect("read snapshot");
+    assert_eq!(read.as_deref(), Some("fn main() {}\n"));
+
+    let missing = "0123456789012345678901234567890123456789";
+    assert!(try_read_snapshot(&repo, missing).expect("read").is_none());
+    assert!(
+      try_read_snapshot(&repo, "not-an-id")
+        .expect("read")
+        .is_none()
+    );
+  }
 
   #[test]
   fn list_unstaged_paths_includes_untracked_files() {
@@ -681,4 +1315,48 @@ mod tests {
     let paths = list_base_paths(&repo, "main").expect("list base paths");
     assert!(paths.is_empty());
   }
+
+  #[test]
+  fn parse_range_spec_defaults_to_head() {
+    assert_eq!(parse_range_spec("main..topic").unwrap(), ("main", "topic"));
+    assert_eq!(parse_range_spec("main..").unwrap(), ("main", "HEAD"));
+    assert_eq!(parse_range_spec("..topic").unwrap(), ("HEAD", "topic"));
+    assert!(parse_range_spec("main...topic").is_err());
+    assert!(parse_range_spec("main").is_err());
+    assert!(parse_range_spec("..").is_err());
+  }
+
+  #[test]
+  fn resolve_range_lists_commits_oldest_first() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let git = |args: &[&str]| run_git(td.path(), args);
+    git(&["init", "--quiet"]);
+    for (i, contents) in ["one\n", "two\n", "three\n"].iter().enumerate() {
+      std::fs::write(td.path().join("a.txt"), contents).expect("write file");
+      git(&["add", "a.txt"]);
+      let msg = format!("commit {i}");
+      git(&["commit", "--quiet", "--no-gpg-sign", "-m", &msg]);
+    }
+    let repo = gix::open(td.path()).expect("open repo");
+
+    let range = resolve_range(&repo, "HEAD~2..").expect("resolve range");
+    assert_eq!(range.view, ViewKind::Range);
+    let summaries: Vec<_> = range.commits.iter().map(|c| c.summary.as_str()).collect();
+    assert_eq!(summaries, ["commit 1", "commit 2"]);
+    assert_eq!(range.commits[1].parent, Some(range.commits[0].id));
+    assert_eq!(range.commits[0].parent, range.from);
+    assert_eq!(
+      list_commit_paths(&repo, range.from, range.to).expect("paths"),
+      ["a.txt"]
+    );
+
+    let root = resolve_commit(&repo, "HEAD~2").expect("resolve commit");
+    assert_eq!(root.from, None);
+    assert_eq!(
+      try_read_at(&repo, root.to, "a.txt")
+        .expect("read")
+        .as_deref(),
+      Some("one\n")
+    );
+  }
 }
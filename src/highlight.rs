@@ -9,25 +9,31 @@ use verdant::style::Style as SynStyle;
 use verdant::theme::ResolvedTheme;
 use verdant_parsers_git::{Lang, LanguageSetImpl};
 
+use crate::theme::SyntaxTheme;
+
 pub struct Highlighter {
   language_set: LanguageSetImpl,
-  theme: ResolvedTheme,
+  /// `None` when code is shown without colours.
+  theme: Option<ResolvedTheme>,
   tab_width: usize,
 }
 
 impl Highlighter {
-  pub fn new(tab_width: usize) -> Result<Self> {
+  pub fn new(tab_width: usize, theme: Option<SyntaxTheme>) -> Result<Self> {
     Ok(Self {
       language_set: LanguageSetImpl::new(),
-      theme: verdant_themes::catppuccin::mocha(),
+      theme: theme.map(SyntaxTheme::resolve),
       tab_width: tab_width.max(1),
     })
   }
 
   pub fn highlight_lang(&self, lang: Lang, text: &str) -> Result<Vec<Vec<Span<'static>>>> {
+    let Some(theme) = &self.theme else {
+      return Ok(self.plain_lines(text));
+    };
     let highlights = verdant::Processor::process_once(text, lang, &self.language_set)
       .context("verdant process")?;
-    let themed = verdant::renderer::resolve_styles(&highlights, &self.theme);
+    let themed = verdant::renderer::resolve_styles(&highlights, theme);
 
     let mut lines = Vec::with_capacity(themed.len());
     for line in themed {
@@ -50,6 +56,20 @@ impl Highlighter {
     Ok(lines)
   }
 
+  fn plain_lines(&self, text: &str) -> Vec<Vec<Span<'static>>> {
+    text
+      .lines()
+      .map(|line| {
+        let mut column = 0usize;
+        if line.is_empty() {
+          Vec::new()
+        } else {
+          vec![Span::raw(expand_tabs(line, &mut column, self.tab_width))]
+        }
+      })
+      .collect()
+  }
+
   pub fn highlight_diff(&self, text: &str) -> Result<Vec<Vec<Span<'static>>>> {
     self.highlight_lang(Lang::Diff, text)
   }
@@ -177,7 +197,7 @@ mod tests {
   fn proves_go_highlighting_expands_indent_tabs() {
     let language_set = LanguageSetImpl::new();
     let lang = resolve_lang_token("go", &language_set).expect("go language is detected");
-    let hl = Highlighter::new(2).expect("highlighter");
+    let hl = Highlighter::new(2, Some(SyntaxTheme::CATPPUCCIN_MOCHA)).expect("highlighter");
 
     let lines = hl
       .highlight_lang(lang, "func main() {\n\tif ok {\n\t\treturn\n\t}\n}\n")
@@ -202,7 +222,7 @@ mod tests {
   fn proves_go_alignment_tabs_use_the_configured_width() {
     let language_set = LanguageSetImpl::new();
     let lang = resolve_lang_token("go", &language_set).expect("go language is detected");
-    let hl = Highlighter::new(2).expect("highlighter");
+    let hl = Highlighter::new(2, Some(SyntaxTheme::CATPPUCCIN_MOCHA)).expect("highlighter");
 
     let lines = hl
       .highlight_lang(lang, "var (\n\tshort\t= 1\n\tlonger\t= 2\n)\n")
@@ -221,6 +241,20 @@ mod tests {
     assert_eq!(rendered[2], "  longer  = 2");
   }
 
+  #[test]
+  fn without_a_theme_code_is_plain_text() {
+    let language_set = LanguageSetImpl::new();
+    let lang = resolve_lang_token("go", &language_set).expect("go language is detected");
+    let hl = Highlighter::new(2, None).expect("highlighter");
+
+    let lines = hl
+      .highlight_lang(lang, "func main() {\n\treturn\n\n}")
+      .expect("highlight go");
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1], [Span::raw("  return")]);
+    assert!(lines[2].is_empty());
+  }
+
   #[test]
   fn resolves_ts_and_tsx() {
     let language_set = LanguageSetImpl::new();
@@ -237,7 +271,7 @@ mod tests {
     std::fs::create_dir_all(td.path().join("src")).expect("mkdir src");
     std::fs::write(td.path().join("src/example.ts"), "const x: number = 1;\n").expect("write ts");
 
-    let hl = Highlighter::new(2).expect("highlighter");
+    let hl = Highlighter::new(2, Some(SyntaxTheme::CATPPUCCIN_MOCHA)).expect("highlighter");
     let lang = hl.detect_file_lang(&repo, "src/example.ts");
     assert!(lang.is_some());
   }
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::config::{HookCli, HookCommand, HookInstallCli};
+
+/// Marks hook scripts written by `remark hook install`, so reinstalling may overwrite them.
+const HOOK_MARKER: &str = "# Installed by `remark hook install`.";
+
+pub fn run(repo: &gix::Repository, cmd: HookCli) -> Result<()> {
+  match cmd.command {
+    HookCommand::Install(cmd) => install(repo, cmd),
+  }
+}
+
+fn install(repo: &gix::Repository, cmd: HookInstallCli) -> Result<()> {
+  let dir = hooks_dir(repo)?;
+  std::fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+
+  // git passes the refs being pushed on stdin; `--pre-push` checks just what they change.
+  let mut hooks = vec![("pre-push", hook_script("remark check --pre-push"))];
+  if cmd.post_rewrite {
+    // Runs after the rewrite has happened, so it only reports (and re-anchors the notes).
+    hooks.push(("post-rewrite", hook_script("remark check || true")));
+  }
+
+  for (name, script) in hooks {
+    let path = dir.join(name);
+    write_hook(&path, &script, cmd.force)?;
+    println!("Installed {}", path.display());
+  }
+  Ok(())
+}
+
+/// `core.hooksPath` from any config level (system, global, local, `-c`), resolved against the
+/// worktree like git does, or else the hooks directory of the common git dir.
+fn hooks_dir(repo: &gix::Repository) -> Result<PathBuf> {
+  let config = repo.config_snapshot();
+  let configured = config
+    .trusted_path("core.hooksPath")
+    .transpose()
+    .context("resolve core.hooksPath")?;
+  Ok(match configured {
+    Some(path) if path.is_absolute() => path.into_owned(),
+    Some(path) => match repo.workdir() {
+      Some(wd) => wd.join(path),
+      None => repo.git_dir().join(path),
+    },
+    None => repo.common_dir().join("hooks"),
+  })
+}
+
+fn hook_script(command: &str) -> String {
+  format!("#!/bin/sh\n{HOOK_MARKER}\n{command}\n")
+}
+
+fn write_hook(path: &Path, script: &str, force: bool) -> Result<()> {
+  if let Ok(existing) = std::fs::read_to_string(path)
+    && !existing.contains(HOOK_MARKER)
+    && !force
+  {
+    anyhow::bail!(
+      "{} already exists and was not installed by remark (use --force to replace it)",
+      path.display()
+    );
+  }
+  std::fs::write(path, script).with_context(|| format!("write {}", path.display()))?;
+  make_executable(path)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+
+  let mut perms = std::fs::metadata(path)
+    .with_context(|| format!("stat {}", path.display()))?
+    .permissions();
+  perms.set_mode(perms.mode() | 0o755);
+  std::fs::set_permissions(path, perms).with_context(|| format!("chmod {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn write_hook_refuses_foreign_hooks_unless_forced() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let path = td.path().join("pre-push");
+
+    write_hook(&path, &hook_script("remark check"), false).expect("install");
+    write_hook(&path, &hook_script("remark check"), false).expect("reinstall over our own hook");
+
+    std::fs::write(&path, "#!/bin/sh\nmake lint\n").expect("write foreign hook");
+    assert!(write_hook(&path, &hook_script("remark check"), false).is_err());
+    write_hook(&path, &hook_script("remark check"), true).expect("forced install");
+    assert!(
+      std::fs::read_to_string(&path)
+        .expect("read hook")
+        .contains("remark check")
+    );
+  }
+
+  #[test]
+  fn hooks_dir_follows_included_config() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let git_dir = repo.git_dir().to_path_buf();
+    std::fs::write(
+      git_dir.join("shared.cfg"),
+      "[core]\n\thooksPath = .githooks\n",
+    )
+    .expect("write included config");
+    let mut config = std::fs::read_to_string(git_dir.join("config")).expect("read config");
+    config.push_str("[include]\n\tpath = shared.cfg\n");
+    std::fs::write(git_dir.join("config"), config).expect("write config");
+
+    let repo = gix::open(td.path()).expect("reopen repo");
+    let workdir = repo.workdir().expect("workdir").to_path_buf();
+    assert_eq!(
+      hooks_dir(&repo).expect("hooks dir"),
+      workdir.join(".githooks")
+    );
+  }
+}
@@ -0,0 +1,641 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use anyhow::{Context as _, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Where an action's keys apply. Keys of the focused pane win over global ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Context {
+  Global,
+  Files,
+  Diff,
+  CommentList,
+  CommentEditor,
+  PromptEditor,
+  /// The search prompt and the file filter's glob prompt.
+  Input,
+  FileFinder,
+  FileFilter,
+}
+
+impl Context {
+  pub(crate) fn title(self) -> &'static str {
+    match self {
+      Context::Global => "Global",
+      Context::Files => "Files",
+      Context::Diff => "Diff",
+      Context::CommentList => "Comment list",
+      Context::CommentEditor => "Comment editor",
+      Context::PromptEditor => "Prompt editor",
+      Context::Input => "Search and glob prompts",
+      Context::FileFinder => "File finder",
+      Context::FileFilter => "File filter",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Action {
+  Quit,
+  Cancel,
+  Help,
+  ToggleIgnored,
+  FocusFiles,
+  FocusDiff,
+  ViewAll,
+  ViewUnstaged,
+  ViewStaged,
+  ViewBase,
+  ViewRev,
+  PrevCommit,
+  NextCommit,
+  MoveUp,
+  MoveDown,
+  PageUp,
+  PageDown,
+  Search,
+  FindFile,
+  FilterFiles,
+  ToggleReviewed,
+  SinceReview,
+  Stage,
+  Unstage,
+  Comment,
+  Reply,
+  CycleDiffMode,
+  Reload,
+  CommentList,
+  Prompt,
+  Open,
+  ToggleDir,
+  ResolveAll,
+  SearchBackward,
+  Next,
+  PrevMatch,
+  NextUnreviewed,
+  PrevUnreviewed,
+  MarkViewed,
+  Visual,
+  PlaceComment,
+  ApplySuggestion,
+  DeleteComment,
+  ToggleResolved,
+  ResolveWithNote,
+  DiffPopup,
+  LessContext,
+  MoreContext,
+  ListClose,
+  ListUp,
+  ListDown,
+  ListPageUp,
+  ListPageDown,
+  ListFirst,
+  ListLast,
+  ListMark,
+  ListJump,
+  ListResolveFiles,
+  ListDiscard,
+  ListReanchor,
+  ListMarkChanged,
+  ListResolveSelected,
+  ListFilter,
+  EditorAccept,
+  EditorCancel,
+  PromptCopy,
+  PromptClose,
+  InputAccept,
+  InputCancel,
+  FinderOpen,
+  FinderUp,
+  FinderDown,
+  FinderCancel,
+  FilterUnreviewed,
+  FilterUnresolved,
+  FilterChange,
+  FilterGlob,
+  FilterClear,
+  FilterClose,
+}
+
+struct ActionInfo {
+  action: Action,
+  /// Name used in the `keymap` config section.
+  name: &'static str,
+  context: Context,
+  keys: &'static [&'static str],
+  help: &'static str,
+}
+
+const fn info(
+  action: Action,
+  name: &'static str,
+  context: Context,
+  keys: &'static [&'static str],
+  help: &'static str,
+) -> ActionInfo {
+  ActionInfo {
+    action,
+    name,
+    context,
+    keys,
+    help,
+  }
+}
+
+/// Every bindable action with its default keys, in the order the help lists them.
+#[rustfmt::skip]
+const ACTIONS: &[ActionInfo] = &[
+  info(Action::FocusFiles, "focus_files", Context::Global, &["h", "left"], "Focus the file list"),
+  info(Action::FocusDiff, "focus_diff", Context::Global, &["l", "right"], "Focus the diff"),
+  info(Action::ViewAll, "view_all", Context::Global, &["1"], "All view"),
+  info(Action::ViewUnstaged, "view_unstaged", Context::Global, &["2"], "Unstaged view"),
+  info(Action::ViewStaged, "view_staged", Context::Global, &["3"], "Staged view"),
+  info(Action::ViewBase, "view_base", Context::Global, &["4"], "Base view"),
+  info(Action::ViewRev, "view_rev", Context::Global, &["5"], "Commit or range view (--rev / --range)"),
+  info(Action::PrevCommit, "prev_commit", Context::Global, &["<"], "Previous commit in range"),
+  info(Action::NextCommit, "next_commit", Context::Global, &[">"], "Next commit in range"),
+  info(Action::MoveUp, "move_up", Context::Global, &["up", "k"], "Move up (focused pane)"),
+  info(Action::MoveDown, "move_down", Context::Global, &["down", "j"], "Move down (focused pane)"),
+  info(Action::PageUp, "page_up", Context::Global, &["pgup", "ctrl+u"], "Page up"),
+  info(Action::PageDown, "page_down", Context::Global, &["pgdn", "ctrl+d"], "Page down"),
+  info(Action::Search, "search", Context::Global, &["/"], "Search the diff (from the file list: all files)"),
+  info(Action::FindFile, "find_file", Context::Global, &["f"], "Find a file by fuzzy name"),
+  info(Action::FilterFiles, "filter_files", Context::Global, &["F"], "Filter the file list (kept across views)"),
+  info(Action::ToggleReviewed, "toggle_reviewed", Context::Global, &["v"], "Toggle reviewed (file or whole directory)"),
+  info(Action::SinceReview, "since_review", Context::Global, &["S"], "Show only changes since the file was last reviewed"),
+  info(Action::Stage, "stage", Context::Global, &["s"], "Stage file, directory, hunk or selected lines"),
+  info(Action::Unstage, "unstage", Context::Global, &["u"], "Unstage file, directory, hunk or selected lines"),
+  info(Action::Comment, "comment", Context::Global, &["c"], "Add/edit comment (file or line)"),
+  info(Action::Reply, "reply", Context::Global, &["a"], "Reply to comment thread"),
+  info(Action::CycleDiffMode, "cycle_diff_mode", Context::Global, &["i"], "Cycle view mode (decorated/side-by-side/unified)"),
+  info(Action::Reload, "reload", Context::Global, &["R"], "Reload file list"),
+  info(Action::ToggleIgnored, "toggle_ignored", Context::Global, &["I"], "Show/hide ignored files"),
+  info(Action::CommentList, "comment_list", Context::Global, &["C"], "Open comment list"),
+  info(Action::Prompt, "prompt", Context::Global, &["p"], "Open prompt editor"),
+  info(Action::Help, "help", Context::Global, &["?", "f1"], "Show/hide this help"),
+  info(Action::Cancel, "cancel", Context::Global, &["esc"], "Clear search or selection, dismiss overlay, or quit"),
+  info(Action::Quit, "quit", Context::Global, &["q", "Q"], "Quit"),
+  info(Action::Open, "open", Context::Files, &["enter"], "Move focus to diff, or collapse/expand directory"),
+  info(Action::ToggleDir, "toggle_dir", Context::Files, &["space"], "Collapse/expand directory"),
+  info(Action::ResolveAll, "resolve_all", Context::Files, &["r"], "Resolve all comments of the file or directory"),
+  info(Action::Next, "next", Context::Diff, &["n"], "Next unviewed hunk (next match while searching)"),
+  info(Action::PrevMatch, "prev_match", Context::Diff, &["N"], "Previous match"),
+  info(Action::SearchBackward, "search_backward", Context::Diff, &["?"], "Search backward in the diff"),
+  info(Action::NextUnreviewed, "next_unreviewed", Context::Diff, &["ctrl+n"], "Next unreviewed file"),
+  info(Action::PrevUnreviewed, "prev_unreviewed", Context::Diff, &["ctrl+p"], "Previous unreviewed file"),
+  info(Action::MarkViewed, "mark_viewed", Context::Diff, &["m"], "Mark hunk viewed / not viewed"),
+  info(Action::Visual, "visual", Context::Diff, &["V"], "Select lines for a range comment"),
+  info(Action::PlaceComment, "place_comment", Context::Diff, &["enter"], "Place the re-anchored comment on this line"),
+  info(Action::ApplySuggestion, "apply_suggestion", Context::Diff, &["A"], "Apply the comment's suggested change"),
+  info(Action::DeleteComment, "delete_comment", Context::Diff, &["d"], "Delete comment (file or line)"),
+  info(Action::ToggleResolved, "toggle_resolved", Context::Diff, &["r"], "Resolve/unresolve comment"),
+  info(Action::ResolveWithNote, "resolve_with_note", Context::Diff, &["x"], "Resolve comment with a note on how it was addressed"),
+  info(Action::DiffPopup, "diff_popup", Context::Diff, &["H"], "Show/hide diff popup"),
+  info(Action::LessContext, "less_context", Context::Diff, &["["], "Less diff context"),
+  info(Action::MoreContext, "more_context", Context::Diff, &["]"], "More diff context"),
+  info(Action::ListUp, "list_up", Context::CommentList, &["up", "k"], "Move selection up"),
+  info(Action::ListDown, "list_down", Context::CommentList, &["down", "j"], "Move selection down"),
+  info(Action::ListPageUp, "list_page_up", Context::CommentList, &["pgup"], "Page up"),
+  info(Action::ListPageDown, "list_page_down", Context::CommentList, &["pgdn"], "Page down"),
+  info(Action::ListFirst, "list_first", Context::CommentList, &["home"], "First comment"),
+  info(Action::ListLast, "list_last", Context::CommentList, &["end"], "Last comment"),
+  info(Action::ListMark, "list_mark", Context::CommentList, &["enter"], "Select/unselect"),
+  info(Action::ListJump, "list_jump", Context::CommentList, &["shift+enter"], "Jump to location"),
+  info(Action::ListResolveFiles, "list_resolve_files", Context::CommentList, &["R"], "Resolve file comments"),
+  info(Action::ListDiscard, "list_discard", Context::CommentList, &["delete"], "Discard file comments"),
+  info(Action::ListReanchor, "list_reanchor", Context::CommentList, &["o"], "Re-anchor an outdated comment"),
+  info(Action::ListMarkChanged, "list_mark_changed", Context::CommentList, &["a"], "Select comments whose code changed since they were written"),
+  info(Action::ListResolveSelected, "list_resolve_selected", Context::CommentList, &["y"], "Resolve the selected comments (not whole files)"),
+  info(Action::ListFilter, "list_filter", Context::CommentList, &["f"], "Filter by severity (all, suggestion+, issue+, blocker)"),
+  info(Action::ListClose, "list_close", Context::CommentList, &["esc"], "Close list"),
+  info(Action::EditorAccept, "editor_accept", Context::CommentEditor, &["shift+enter", "ctrl+s"], "Accept and close"),
+  info(Action::EditorCancel, "editor_cancel", Context::CommentEditor, &["esc"], "Cancel"),
+  info(Action::PromptCopy, "prompt_copy", Context::PromptEditor, &["shift+enter", "ctrl+s"], "Copy prompt and close"),
+  info(Action::PromptClose, "prompt_close", Context::PromptEditor, &["esc"], "Close prompt"),
+  info(Action::InputAccept, "input_accept", Context::Input, &["enter"], "Search, or apply the glob"),
+  info(Action::InputCancel, "input_cancel", Context::Input, &["esc"], "Cancel"),
+  info(Action::FinderOpen, "finder_open", Context::FileFinder, &["enter"], "Open the selected file"),
+  info(Action::FinderUp, "finder_up", Context::FileFinder, &["up", "ctrl+p"], "Previous match"),
+  info(Action::FinderDown, "finder_down", Context::FileFinder, &["down", "ctrl+n"], "Next match"),
+  info(Action::FinderCancel, "finder_cancel", Context::FileFinder, &["esc"], "Cancel"),
+  info(Action::FilterUnreviewed, "filter_unreviewed", Context::FileFilter, &["u"], "Only unreviewed files"),
+  info(Action::FilterUnresolved, "filter_unresolved", Context::FileFilter, &["c"], "Only files with unresolved comments"),
+  info(Action::FilterChange, "filter_change", Context::FileFilter, &["k"], "Cycle change kind (any, added, modified, deleted)"),
+  info(Action::FilterGlob, "filter_glob", Context::FileFilter, &["g"], "Edit glob (space separated, ! excludes)"),
+  info(Action::FilterClear, "filter_clear", Context::FileFilter, &["x"], "Clear all filters"),
+  info(Action::FilterClose, "filter_close", Context::FileFilter, &["esc", "enter", "F", "q"], "Close the filter"),
+];
+
+/// Keys for one action in the `keymap` config section: a single chord or a list of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl KeyBinding {
+  fn chords(&self) -> &[String] {
+    match self {
+      KeyBinding::One(chord) => std::slice::from_ref(chord),
+      KeyBinding::Many(chords) => chords,
+    }
+  }
+}
+
+/// A key with its modifiers, e.g. `ctrl+n`, `shift+enter`, `C` or `f1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct KeyChord {
+  code: KeyCode,
+  modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+  /// Terminals differ in whether a shifted character also reports Shift; characters carry it
+  /// in their case instead, so `C`, `shift+c` and Shift+C from either kind of terminal match.
+  fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+    let mut modifiers =
+      modifiers & (KeyModifiers::SHIFT | KeyModifiers::CONTROL | KeyModifiers::ALT);
+    let code = match code {
+      KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+        modifiers.remove(KeyModifiers::SHIFT);
+        KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+      }
+      code => code,
+    };
+    Self { code, modifiers }
+  }
+
+  pub(crate) fn parse(text: &str) -> Result<Self> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text;
+    // A lone `+` or a chord ending in `+` (`ctrl++`) names the plus key itself.
+    while let Some((modifier, tail)) = rest.split_once('+')
+      && !tail.is_empty()
+    {
+      modifiers |= match modifier.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => KeyModifiers::CONTROL,
+        "alt" | "meta" => KeyModifiers::ALT,
+        "shift" => KeyModifiers::SHIFT,
+        _ => anyhow::bail!("unknown modifier '{modifier}' in key '{text}'"),
+      };
+      rest = tail;
+    }
+    let code = match rest.to_ascii_lowercase().as_str() {
+      "enter" | "return" => KeyCode::Enter,
+      "esc" | "escape" => KeyCode::Esc,
+      "space" => KeyCode::Char(' '),
+      "tab" => KeyCode::Tab,
+      "backspace" => KeyCode::Backspace,
+      "delete" | "del" => KeyCode::Delete,
+      "up" => KeyCode::Up,
+      "down" => KeyCode::Down,
+      "left" => KeyCode::Left,
+      "right" => KeyCode::Right,
+      "pgup" | "pageup" => KeyCode::PageUp,
+      "pgdn" | "pagedown" => KeyCode::PageDown,
+      "home" => KeyCode::Home,
+      "end" => KeyCode::End,
+      lower => {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+          // Terminals report Ctrl+U and Alt+X with the lowercase character; only an explicit
+          // `shift+` asks for the shifted one.
+          (Some(c), None)
+            if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+              && !modifiers.contains(KeyModifiers::SHIFT) =>
+          {
+            KeyCode::Char(c.to_lowercase().next().unwrap_or(c))
+          }
+          (Some(c), None) => KeyCode::Char(c),
+          _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => anyhow::bail!("unknown key '{text}'"),
+          },
+        }
+      }
+    };
+    Ok(Self::normalized(code, modifiers))
+  }
+
+  fn matches(self, key: KeyEvent) -> bool {
+    self == Self::normalized(key.code, key.modifiers)
+  }
+}
+
+impl fmt::Display for KeyChord {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.modifiers.contains(KeyModifiers::CONTROL) {
+      f.write_str("Ctrl+")?;
+    }
+    if self.modifiers.contains(KeyModifiers::ALT) {
+      f.write_str("Alt+")?;
+    }
+    if self.modifiers.contains(KeyModifiers::SHIFT) {
+      f.write_str("Shift+")?;
+    }
+    match self.code {
+      KeyCode::Char(' ') => f.write_str("Space"),
+      // Control chords are shown the way people say them: Ctrl+N rather than Ctrl+n.
+      KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+        write!(f, "{}", c.to_ascii_uppercase())
+      }
+      KeyCode::Char(c) => write!(f, "{c}"),
+      KeyCode::F(n) => write!(f, "F{n}"),
+      KeyCode::PageUp => f.write_str("PgUp"),
+      KeyCode::PageDown => f.write_str("PgDn"),
+      KeyCode::Enter => f.write_str("Enter"),
+      KeyCode::Esc => f.write_str("Esc"),
+      KeyCode::Tab => f.write_str("Tab"),
+      KeyCode::Backspace => f.write_str("Backspace"),
+      KeyCode::Delete => f.write_str("Delete"),
+      KeyCode::Up => f.write_str("Up"),
+      KeyCode::Down => f.write_str("Down"),
+      KeyCode::Left => f.write_str("Left"),
+      KeyCode::Right => f.write_str("Right"),
+      KeyCode::Home => f.write_str("Home"),
+      KeyCode::End => f.write_str("End"),
+      code => write!(f, "{code:?}"),
+    }
+  }
+}
+
+/// Keys of every action: the defaults, with the actions named in the config rebound.
+#[derive(Debug, Clone)]
+pub(crate) struct Keymap {
+  bindings: HashMap<Action, Vec<KeyChord>>,
+}
+
+impl Default for Keymap {
+  fn default() -> Self {
+    let bindings = ACTIONS
+      .iter()
+      .map(|a| {
+        let chords = a
+          .keys
+          .iter()
+          .map(|k| KeyChord::parse(k).unwrap_or_else(|e| panic!("default key of {}: {e}", a.name)));
+        (a.action, chords.collect())
+      })
+      .collect();
+    Self { bindings }
+  }
+}
+
+impl Keymap {
+  /// The default keymap with the actions in `overrides` bound to the given keys instead. An
+  /// empty list unbinds an action. Two actions of one context can't share a key.
+  pub(crate) fn with_overrides(overrides: &BTreeMap<String, KeyBinding>) -> Result<Self> {
+    let mut keymap = Self::default();
+    for (name, binding) in overrides {
+      let Some(info) = ACTIONS.iter().find(|a| a.name == name) else {
+        anyhow::bail!("unknown action '{name}' in keymap");
+      };
+      let chords = binding
+        .chords()
+        .iter()
+        .map(|chord| KeyChord::parse(chord))
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("keymap entry '{name}'"))?;
+      keymap.bindings.insert(info.action, chords);
+    }
+
+    for (i, a) in ACTIONS.iter().enumerate() {
+      for b in ACTIONS[i + 1..].iter().filter(|b| b.context == a.context) {
+        if let Some(chord) = keymap
+          .chords(a.action)
+          .iter()
+          .find(|c| keymap.chords(b.action).contains(c))
+        {
+          anyhow::bail!(
+            "key {chord} is bound to both '{}' and '{}' in the keymap",
+            a.name,
+            b.name
+          );
+        }
+      }
+    }
+    Ok(keymap)
+  }
+
+  pub(crate) fn chords(&self, action: Action) -> &[KeyChord] {
+    self.bindings.get(&action).map_or(&[], Vec::as_slice)
+  }
+
+  /// The action `key` triggers in `context` itself, ignoring global keys.
+  pub(crate) fn action(&self, context: Context, key: KeyEvent) -> Option<Action> {
+    ACTIONS
+      .iter()
+      .filter(|a| a.context == context)
+      .find(|a| self.chords(a.action).iter().any(|c| c.matches(key)))
+      .map(|a| a.action)
+  }
+
+  /// The action `key` triggers with a pane focused: the pane's own keys, then the global ones.
+  pub(crate) fn lookup(&self, context: Context, key: KeyEvent) -> Option<Action> {
+    self
+      .action(context, key)
+      .or_else(|| self.action(Context::Global, key))
+  }
+
+  /// The keys of `action` as hints show them, e.g. `Shift+Enter/Ctrl+S`. An unbound action shows
+  /// its default keys.
+  pub(crate) fn keys(&self, action: Action) -> String {
+    let chords = self.chords(action);
+    let shown: Vec<String> = if chords.is_empty() {
+      ACTIONS
+        .iter()
+        .filter(|a| a.action == action)
+        .flat_map(|a| a.keys)
+        .filter_map(|k| KeyChord::parse(k).ok())
+        .map(|c| c.to_string())
+        .collect()
+    } else {
+      chords.iter().map(|c| c.to_string()).collect()
+    };
+    shown.join("/")
+  }
+
+  /// A hint such as `Enter select, Esc close`, naming each action by its current keys.
+  pub(crate) fn hint(&self, items: &[(Action, &str)]) -> String {
+    let parts: Vec<String> = items
+      .iter()
+      .map(|(action, what)| format!("{} {what}", self.keys(*action)))
+      .collect();
+    parts.join(", ")
+  }
+
+  /// Keys and description of each bound action of `context`, for the help overlay.
+  pub(crate) fn help(&self, context: Context) -> Vec<(String, &'static str)> {
+    ACTIONS
+      .iter()
+      .filter(|a| a.context == context)
+      .filter(|a| !self.chords(a.action).is_empty())
+      .map(|a| {
+        let keys: Vec<String> = self
+          .chords(a.action)
+          .iter()
+          .map(|c| c.to_string())
+          .collect();
+        (keys.join(" / "), a.help)
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+  }
+
+  #[test]
+  fn chords_parse_and_display() {
+    for (text, shown) in [
+      ("ctrl+n", "Ctrl+N"),
+      ("shift+enter", "Shift+Enter"),
+      ("shift+c", "C"),
+      ("C", "C"),
+      ("f1", "F1"),
+      ("space", "Space"),
+      ("pgdn", "PgDn"),
+      ("?", "?"),
+      ("ctrl++", "Ctrl++"),
+    ] {
+      let chord = KeyChord::parse(text).expect(text);
+      assert_eq!(chord.to_string(), shown, "{text}");
+    }
+    assert!(KeyChord::parse("hyper+x").is_err());
+    assert!(KeyChord::parse("nope").is_err());
+  }
+
+  #[test]
+  fn shifted_characters_match_either_way() {
+    let keymap = Keymap::default();
+    let shift_c = key(KeyCode::Char('C'), KeyModifiers::SHIFT);
+    let plain_c = key(KeyCode::Char('C'), KeyModifiers::NONE);
+    let kitty_c = key(KeyCode::Char('c'), KeyModifiers::SHIFT);
+    for k in [shift_c, plain_c, kitty_c] {
+      assert_eq!(keymap.lookup(Context::Files, k), Some(Action::CommentList));
+    }
+    let c = key(KeyCode::Char('c'), KeyModifiers::NONE);
+    assert_eq!(keymap.lookup(Context::Files, c), Some(Action::Comment));
+    let ctrl_u = key(KeyCode::Char('u'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.lookup(Context::Diff, ctrl_u), Some(Action::PageUp));
+  }
+
+  #[test]
+  fn control_and_alt_chords_ignore_character_case() {
+    let ctrl_u = KeyChord::parse("ctrl+U").expect("ctrl+U");
+    assert_eq!(ctrl_u, KeyChord::parse("ctrl+u").expect("ctrl+u"));
+    assert!(ctrl_u.matches(key(KeyCode::Char('u'), KeyModifiers::CONTROL)));
+    assert_eq!(ctrl_u.to_string(), "Ctrl+U");
+
+    let alt_x = KeyChord::parse("alt+X").expect("alt+X");
+    assert!(alt_x.matches(key(KeyCode::Char('x'), KeyModifiers::ALT)));
+    assert!(!alt_x.matches(key(
+      KeyCode::Char('X'),
+      KeyModifiers::ALT | KeyModifiers::SHIFT
+    )));
+  }
+
+  #[test]
+  fn pane_keys_win_over_global_ones() {
+    let keymap = Keymap::default();
+    let qmark = key(KeyCode::Char('?'), KeyModifiers::NONE);
+    assert_eq!(keymap.lookup(Context::Files, qmark), Some(Action::Help));
+    assert_eq!(
+      keymap.lookup(Context::Diff, qmark),
+      Some(Action::SearchBackward)
+    );
+  }
+
+  #[test]
+  fn overrides_replace_defaults() {
+    let overrides = BTreeMap::from([
+      (
+        "quit".to_string(),
+        KeyBinding::Many(vec!["ctrl+q".to_string()]),
+      ),
+      ("move_down".to_string(), KeyBinding::One("down".to_string())),
+      ("stage".to_string(), KeyBinding::Many(Vec::new())),
+    ]);
+    let keymap = Keymap::with_overrides(&overrides).expect("keymap");
+    let q = key(KeyCode::Char('q'), KeyModifiers::NONE);
+    assert_eq!(keymap.lookup(Context::Files, q), None);
+    let ctrl_q = key(KeyCode::Char('q'), KeyModifiers::CONTROL);
+    assert_eq!(keymap.lookup(Context::Files, ctrl_q), Some(Action::Quit));
+    let j = key(KeyCode::Char('j'), KeyModifiers::NONE);
+    assert_eq!(keymap.lookup(Context::Diff, j), None);
+    assert!(keymap.chords(Action::Stage).is_empty());
+    assert!(
+      keymap
+        .help(Context::Global)
+        .contains(&("Ctrl+Q".to_string(), "Quit"))
+    );
+
+    let unknown = BTreeMap::from([("fly".to_string(), KeyBinding::One("x".to_string()))]);
+    assert!(Keymap::with_overrides(&unknown).is_err());
+    let clash = BTreeMap::from([("stage".to_string(), KeyBinding::One("c".to_string()))]);
+    let err = Keymap::with_overrides(&clash).expect_err("clash");
+    assert!(err.to_string().contains("'stage' and 'comment'"), "{err}");
+  }
+
+  #[test]
+  fn hints_follow_rebound_keys() {
+    let overrides = BTreeMap::from([
+      (
+        "editor_accept".to_string(),
+        KeyBinding::One("ctrl+j".to_string()),
+      ),
+      ("list_mark".to_string(), KeyBinding::Many(Vec::new())),
+    ]);
+    let keymap = Keymap::with_overrides(&overrides).expect("keymap");
+    assert_eq!(keymap.keys(Action::EditorAccept), "Ctrl+J");
+    assert_eq!(
+      Keymap::default().keys(Action::EditorAccept),
+      "Shift+Enter/Ctrl+S"
+    );
+    // Nothing bound: the default key is the best hint there is.
+    assert_eq!(keymap.keys(Action::ListMark), "Enter");
+    assert_eq!(
+      keymap.hint(&[
+        (Action::ListMark, "toggles"),
+        (Action::ListResolveSelected, "resolves")
+      ]),
+      "Enter toggles, y resolves"
+    );
+  }
+
+  #[test]
+  fn overlay_keys_do_not_fall_through_to_global_ones() {
+    let keymap = Keymap::default();
+    let q = key(KeyCode::Char('q'), KeyModifiers::NONE);
+    assert_eq!(
+      keymap.action(Context::FileFilter, q),
+      Some(Action::FilterClose)
+    );
+    assert_eq!(keymap.action(Context::FileFinder, q), None);
+    let ctrl_s = key(KeyCode::Char('s'), KeyModifiers::CONTROL);
+    assert_eq!(
+      keymap.action(Context::CommentEditor, ctrl_s),
+      Some(Action::EditorAccept)
+    );
+    let shift_enter = key(KeyCode::Enter, KeyModifiers::SHIFT);
+    assert_eq!(
+      keymap.action(Context::PromptEditor, shift_enter),
+      Some(Action::PromptCopy)
+    );
+    let enter = key(KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(keymap.action(Context::CommentEditor, enter), None);
+  }
+
+  #[test]
+  fn default_keys_do_not_clash() {
+    Keymap::with_overrides(&BTreeMap::new()).expect("defaults");
+  }
+}
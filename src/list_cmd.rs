@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::config::{ListCli, OutputFormat};
+use crate::review::{
+  Comment, FileReview, LineKey, LineSide, Reply, Resolution, Review, Severity, Suggestion, Tags,
+};
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: ListCli,
+) -> Result<()> {
+  let paths = crate::prompt_cmd::view_paths(repo, cmd.filter, base_ref.as_deref())?;
+  let review = load_notes_review(repo, notes_ref, base_ref.as_deref(), &paths)?;
+  let entries: Vec<_> = review
+    .files
+    .iter()
+    .flat_map(|(path, file)| comment_entries(path, file))
+    .collect();
+
+  match cmd.format {
+    OutputFormat::Json => {
+      println!(
+        "{}",
+        serde_json::to_string_pretty(&entries).context("serialize comments")?
+      );
+    }
+    OutputFormat::Text if entries.is_empty() => println!("No comments."),
+    OutputFormat::Text => {
+      for entry in &entries {
+        println!("{}", entry.summary_line());
+      }
+    }
+  }
+  Ok(())
+}
+
+/// One comment thread as printed by `remark list` and `remark show`.
+#[derive(Debug, Serialize)]
+pub(crate) struct CommentEntry<'a> {
+  /// Stable thread id (see [`Comment::id`]).
+  pub(crate) id: String,
+  pub(crate) file: &'a str,
+  /// `None` for the file-level comment.
+  pub(crate) side: Option<LineSide>,
+  pub(crate) line: Option<u32>,
+  /// Last line of a range comment.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) end_line: Option<u32>,
+  pub(crate) resolved: bool,
+  /// How the comment was addressed, when that was recorded.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) resolution: Option<&'a Resolution>,
+  pub(crate) outdated: bool,
+  pub(crate) severity: Option<Severity>,
+  pub(crate) labels: &'a [String],
+  /// Severity used for ordering and thresholds (see [`Comment::rank`]).
+  #[serde(skip)]
+  pub(crate) rank: Severity,
+  pub(crate) author: Option<&'a str>,
+  pub(crate) created_at: Option<i64>,
+  pub(crate) body: &'a str,
+  pub(crate) replies: &'a [Reply],
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) suggestion: Option<&'a Suggestion>,
+}
+
+impl<'a> CommentEntry<'a> {
+  pub(crate) fn new(file: &'a str, key: Option<LineKey>, comment: &'a Comment) -> Self {
+    Self {
+      id: comment.id(file, key),
+      file,
+      side: key.map(|k| k.side),
+      line: key.map(|k| k.line),
+      end_line: key
+        .map(|k| comment.last_line(k))
+        .filter(|&end| Some(end) != key.map(|k| k.line)),
+      resolved: comment.resolved,
+      resolution: comment.resolution.as_ref(),
+      outdated: comment.outdated,
+      severity: comment.severity,
+      labels: &comment.labels,
+      rank: comment.rank(),
+      author: comment.author.as_deref(),
+      created_at: comment.created_at,
+      body: &comment.body,
+      replies: &comment.replies,
+      suggestion: comment.suggestion.as_ref(),
+    }
+  }
+
+  /// `path`, `path:42` or `path:10-12`, with ` (old)` for deleted-side comments.
+  pub(crate) fn location(&self) -> String {
+    let Some(line) = self.line else {
+      return self.file.to_string();
+    };
+    let mut out = match self.end_line {
+      Some(end) => format!("{}:{line}-{end}", self.file),
+      None => format!("{}:{line}", self.file),
+    };
+    if self.side == Some(LineSide::Old) {
+      out.push_str(" (old)");
+    }
+    out
+  }
+
+  pub(crate) fn state(&self) -> &'static str {
+    if self.resolved {
+      "resolved"
+    } else if self.outdated {
+      "outdated"
+    } else {
+      "open"
+    }
+  }
+
+  /// `[severity] #label ...` as written in drafts; empty when untagged.
+  pub(crate) fn tags(&self) -> String {
+    Tags {
+      severity: self.severity,
+      labels: self.labels.to_vec(),
+    }
+    .to_string()
+  }
+
+  /// `location [state] tags first body line`, plus a reply count when the thread has any and
+  /// how it was resolved when that was recorded.
+  pub(crate) fn summary_line(&self) -> String {
+    let first = self.body.lines().next().unwrap_or_default();
+    let mut out = format!("{} [{}] ", self.location(), self.state());
+    let tags = self.tags();
+    if !tags.is_empty() {
+      out.push_str(&tags);
+      out.push(' ');
+    }
+    out.push_str(first);
+    match self.replies.len() {
+      0 => {}
+      1 => out.push_str(" (1 reply)"),
+      n => out.push_str(&format!(" ({n} replies)")),
+    }
+    if let Some(resolution) = self.resolution {
+      out.push_str(&format!(" (resolved: {})", resolution.summary()));
+    }
+    out
+  }
+}
+
+/// The file comment first, then line comments in line order.
+pub(crate) fn comment_entries<'a>(path: &'a str, file: &'a FileReview) -> Vec<CommentEntry<'a>> {
+  let file_comment = file
+    .file_comment
+    .iter()
+    .map(|c| CommentEntry::new(path, None, c));
+  let lines = file
+    .comments
+    .iter()
+    .map(|(key, c)| CommentEntry::new(path, Some(*key), c));
+  file_comment.chain(lines).collect()
+}
+
+/// Notes for `paths` as recorded, without syncing the draft into them: reading must not turn
+/// half-written draft comments into notes. Notes left under an earlier HEAD are re-anchored
+/// first so they are found at all. Empty on an unborn branch.
+pub(crate) fn load_notes_review(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<&str>,
+  paths: &[String],
+) -> Result<Review> {
+  let mut review = Review::new();
+  if crate::git::head_commit_oid(repo).is_err() {
+    return Ok(review);
+  }
+  for path in paths {
+    if let Some(file) = crate::lsp::load_file_review(repo, notes_ref, path, base_ref)? {
+      review.files.insert(path.clone(), file);
+    }
+  }
+  Ok(review)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn comment(body: &str) -> Comment {
+    Comment {
+      body: body.to_string(),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn entries_list_file_comment_then_lines() {
+    let mut file = FileReview {
+      file_comment: Some(comment("overall\nmore")),
+      ..Default::default()
+    };
+    let mut range = comment("tighten this");
+    range.end_line = Some(12);
+    range.resolved = true;
+    range.severity = Some(Severity::Issue);
+    range.labels = vec!["perf".to_string()];
+    range.resolution = Some(Resolution {
+      author: "b <b@x>".to_string(),
+      at: 2,
+      message: Some("moved to a helper".to_string()),
+      commit: Some("abcdef0123456789".to_string()),
+    });
+    file.comments.insert(
+      LineKey {
+        side: LineSide::New,
+        line: 10,
+      },
+      range,
+    );
+    let mut gone = comment("why remove?");
+    gone.outdated = true;
+    gone.replies.push(Reply {
+      author: "a <a@x>".to_string(),
+      at: 1,
+      body: "because".to_string(),
+    });
+    file.comments.insert(
+      LineKey {
+        side: LineSide::Old,
+        line: 3,
+      },
+      gone,
+    );
+
+    let entries = comment_entries("src/lib.rs", &file);
+    let lines: Vec<_> = entries.iter().map(CommentEntry::summary_line).collect();
+    assert_eq!(
+      lines,
+      [
+        "src/lib.rs [open] overall",
+        "src/lib.rs:3 (old) [outdated] why remove? (1 reply)",
+        "src/lib.rs:10-12 [resolved] [issue] #perf tighten this (resolved: moved to a helper, abcdef0)",
+      ]
+    );
+
+    let json = serde_json::to_value(&entries).expect("serialize");
+    assert_eq!(json[0]["side"], serde_json::Value::Null);
+    assert_eq!(json[1]["side"], "old");
+    assert_eq!(json[1]["replies"][0]["body"], "because");
+    assert_eq!(json[2]["line"], 10);
+    assert_eq!(json[2]["end_line"], 12);
+    assert_eq!(json[2]["resolved"], true);
+    assert_eq!(json[2]["severity"], "issue");
+    assert_eq!(json[2]["labels"][0], "perf");
+    assert_eq!(json[0]["severity"], serde_json::Value::Null);
+    assert_eq!(json[2]["resolution"]["message"], "moved to a helper");
+    assert!(json[0].get("resolution").is_none());
+  }
+}
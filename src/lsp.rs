@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
@@ -15,13 +15,14 @@ use tower_lsp::lsp_types::{
   ExecuteCommandParams, Hover, HoverContents, InitializeParams, InitializeResult,
   InitializedParams, InlayHint, InlayHintLabel, InlayHintOptions, InlayHintParams,
   InlayHintServerCapabilities, MarkupContent, MarkupKind, MessageType, OneOf, Position, Range,
-  ServerCapabilities, ShowDocumentParams, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+  ServerCapabilities, ShowDocumentParams, TextDocumentContentChangeEvent,
+  TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
 };
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
 use crate::config::LspCli;
 use crate::git::{self, ViewKind};
-use crate::review::{Comment, FileReview, LineKey, LineSide};
+use crate::review::{Comment, FileReview, LineKey, LineSide, Severity, merge_file_review};
 
 const COMMAND_RESOLVE: &str = "remark.resolve";
 const COMMAND_UNRESOLVE: &str = "remark.unresolve";
@@ -73,7 +74,8 @@ struct Backend {
   enable_inlay_hints: bool,
   enable_diagnostics: bool,
   show_document: std::sync::RwLock<Option<bool>>,
-  open_documents: std::sync::RwLock<HashSet<Url>>,
+  /// Text of the open documents, kept current with unsaved edits.
+  open_documents: std::sync::RwLock<HashMap<Url, String>>,
   fs_watcher: std::sync::Mutex<Option<notify::RecommendedWatcher>>,
 }
 
@@ -119,7 +121,7 @@ impl Backend {
       enable_inlay_hints,
       enable_diagnostics,
       show_document: std::sync::RwLock::new(None),
-      open_documents: std::sync::RwLock::new(HashSet::new()),
+      open_documents: std::sync::RwLock::new(HashMap::new()),
       fs_watcher: std::sync::Mutex::new(None),
     }
   }
@@ -259,6 +261,20 @@ impl Backend {
     Some(rel.to_string_lossy().to_string())
   }
 
+  /// Moves anchored comments to where their code is in the open buffer, which may not be saved
+  /// yet.
+  fn follow_buffer(&self, uri: &Url, review: &mut FileReview) {
+    let Some(text) = self.buffer_text(uri) else {
+      return;
+    };
+    let lines: Vec<String> = text.lines().map(str::to_string).collect();
+    review.relocate(LineSide::New, &lines);
+  }
+
+  fn buffer_text(&self, uri: &Url) -> Option<String> {
+    self.open_documents.read().ok()?.get(uri).cloned()
+  }
+
   async fn publish_diagnostics(&self, uri: &Url) {
     if !self.enable_diagnostics {
       return;
@@ -289,6 +305,7 @@ impl Backend {
 
     let diagnostics = review
       .map(|mut review| {
+        self.follow_buffer(uri, &mut review);
         if !self.include_resolved {
           prune_resolved(&mut review);
         }
@@ -310,7 +327,7 @@ impl Backend {
       .open_documents
       .read()
       .ok()
-      .map(|set| set.iter().cloned().collect::<Vec<_>>())
+      .map(|docs| docs.keys().cloned().collect::<Vec<_>>())
       .unwrap_or_default();
     for uri in uris {
       if exclude.is_some_and(|excluded| excluded == &uri) {
@@ -331,8 +348,9 @@ impl Backend {
     let path = uri.to_file_path().ok()?;
     let rel_path = self.to_repo_relative(&path)?;
     let repo = self.open_repo().ok()?;
-    let review =
+    let mut review =
       load_file_review(&repo, &self.notes_ref, &rel_path, self.base_ref.as_deref()).ok()??;
+    self.follow_buffer(uri, &mut review);
 
     let line = position.line.saturating_add(1);
     let mut snippets = Vec::new();
@@ -341,10 +359,10 @@ impl Backend {
       if !self.include_resolved && comment.resolved {
         continue;
       }
-      if key.side == LineSide::New && key.line == line {
-        snippets.push(comment.body.clone());
-      } else if key.side == LineSide::Old && key.line == line {
-        snippets.push(format!("[old] {}", comment.body));
+      if comment.covers(*key, LineSide::New, line) {
+        snippets.push(hover_thread(comment, ""));
+      } else if comment.covers(*key, LineSide::Old, line) {
+        snippets.push(hover_thread(comment, "[old] "));
       }
     }
 
@@ -352,7 +370,7 @@ impl Backend {
       && let Some(comment) = &review.file_comment
       && (self.include_resolved || !comment.resolved)
     {
-      snippets.push(comment.body.clone());
+      snippets.push(hover_thread(comment, ""));
     }
 
     if snippets.is_empty() {
@@ -394,6 +412,7 @@ impl Backend {
     let Some(mut review) = review else {
       return Vec::new();
     };
+    self.follow_buffer(uri, &mut review);
     if !self.include_resolved {
       prune_resolved(&mut review);
     }
@@ -449,6 +468,7 @@ impl Backend {
       }
     };
     let mut review = review;
+    self.follow_buffer(uri, &mut review);
     if !self.include_resolved {
       prune_resolved(&mut review);
     }
@@ -486,6 +506,25 @@ impl Backend {
       actions.push(make_file_comment_action(&rel_path, comment.resolved));
     }
 
+    // Only offer suggestions that still fit the buffer (or the file on disk).
+    let content = self
+      .buffer_text(uri)
+      .or_else(|| std::fs::read_to_string(&path).ok())
+      .unwrap_or_default();
+    for (key, comment) in &review.comments {
+      if let Some(suggestion) = &comment.suggestion
+        && !comment.resolved
+        && comment.covers(*key, LineSide::New, line)
+        && suggestion
+          .apply_to(&content, key.line, comment.last_line(*key))
+          .is_ok()
+      {
+        actions.push(make_apply_suggestion_action(
+          uri, &rel_path, *key, comment, &content,
+        ));
+      }
+    }
+
     actions
   }
 
@@ -516,6 +555,8 @@ impl Backend {
       side,
       file_comment: args.file_comment,
       unresolve,
+      message: None,
+      commit: None,
     };
     if let Err(err) = crate::resolve_cmd::run(&repo, &self.notes_ref, self.base_ref.clone(), cmd) {
       self.log_error(err).await;
@@ -695,6 +736,36 @@ impl Backend {
   }
 }
 
+/// Applies one `didChange` event (a full replacement or an incremental range edit) to `text`.
+fn apply_content_change(text: &mut String, change: TextDocumentContentChangeEvent) {
+  let Some(range) = change.range else {
+    *text = change.text;
+    return;
+  };
+  let start = byte_offset(text, range.start);
+  let end = byte_offset(text, range.end).max(start);
+  text.replace_range(start..end, &change.text);
+}
+
+/// Byte offset of an LSP position (UTF-16 column) in `text`, clamped to the end of its line.
+fn byte_offset(text: &str, pos: Position) -> usize {
+  let mut offset = 0;
+  for (idx, line) in text.split_inclusive('\n').enumerate() {
+    if idx == pos.line as usize {
+      let mut units = 0;
+      for (i, ch) in line.char_indices() {
+        if units >= pos.character || ch == '\n' || ch == '\r' {
+          return offset + i;
+        }
+        units += ch.len_utf16() as u32;
+      }
+      return offset + line.len();
+    }
+    offset += line.len();
+  }
+  text.len()
+}
+
 fn line_end_utf16(path: &Path, line_idx: u32) -> Option<u32> {
   let content = std::fs::read_to_string(path).ok()?;
   let line = content.lines().nth(line_idx as usize)?;
@@ -784,6 +855,40 @@ fn should_sync_path(root: &Path, gitdir: Option<&Path>, path: &Path) -> bool {
   walker_includes_path(&root_abs, &abs)
 }
 
+/// Renders a comment and its replies as hover markdown.
+fn hover_thread(comment: &Comment, prefix: &str) -> String {
+  let mut out = format!("{prefix}{}", comment.body);
+  if let Some(author) = &comment.author {
+    out.push_str(&format!("\n\n*— {author}"));
+    if let Some(at) = comment.created_at {
+      out.push_str(&format!(", {}", crate::review::format_timestamp(at)));
+    }
+    out.push('*');
+  }
+  for reply in &comment.replies {
+    out.push_str(&format!(
+      "\n\n**{}** · {}\n\n{}",
+      reply.author,
+      crate::review::format_timestamp(reply.at),
+      reply.body
+    ));
+  }
+  if let Some(resolution) = &comment.resolution {
+    out.push_str(&format!(
+      "\n\n**Resolved** by {} · {}",
+      resolution.author,
+      crate::review::format_timestamp(resolution.at)
+    ));
+    if let Some(commit) = &resolution.commit {
+      out.push_str(&format!(" in `{}`", crate::review::short_commit(commit)));
+    }
+    if let Some(message) = &resolution.message {
+      out.push_str(&format!("\n\n{message}"));
+    }
+  }
+  out
+}
+
 fn inlay_label(comment: &Comment, side: Option<LineSide>) -> String {
   let mut snippet = comment.body.lines().next().unwrap_or("").trim().to_string();
   const MAX_LEN: usize = 80;
@@ -855,6 +960,80 @@ fn make_resolve_action(
   })
 }
 
+/// Replaces the covered lines of `content` (the buffer the edit applies to) with the suggestion,
+/// then resolves the comment. Line endings are kept the way
+/// [`crate::review::Suggestion::apply_to`] keeps them, and the last line of a file without a
+/// trailing newline is replaced up to its end, so none is added.
+fn make_apply_suggestion_action(
+  uri: &Url,
+  file: &str,
+  key: LineKey,
+  comment: &Comment,
+  content: &str,
+) -> CodeActionOrCommand {
+  let newline = crate::review::line_ending(content);
+  let replacement: &[String] = comment
+    .suggestion
+    .as_ref()
+    .map_or(&[], |s| s.replacement.as_slice());
+  let lines: Vec<&str> = content.lines().collect();
+  let start = key.line.saturating_sub(1);
+  let last = comment.last_line(key);
+  let utf16_len = |line: u32| {
+    lines
+      .get(line as usize)
+      .map_or(0, |text| text.encode_utf16().count() as u32)
+  };
+  let (range, new_text) = if last as usize >= lines.len() && !content.ends_with('\n') {
+    let end = Position::new(last.saturating_sub(1), utf16_len(last.saturating_sub(1)));
+    if replacement.is_empty() && start > 0 {
+      // Dropping the final lines also drops the newline that ended the line before them.
+      let start = Position::new(start - 1, utf16_len(start - 1));
+      (Range::new(start, end), String::new())
+    } else {
+      (
+        Range::new(Position::new(start, 0), end),
+        replacement.join(newline),
+      )
+    }
+  } else {
+    let new_text = replacement
+      .iter()
+      .map(|l| format!("{l}{newline}"))
+      .collect();
+    (
+      Range::new(Position::new(start, 0), Position::new(last, 0)),
+      new_text,
+    )
+  };
+  let edit = WorkspaceEdit {
+    changes: Some(
+      [(uri.clone(), vec![TextEdit { range, new_text }])]
+        .into_iter()
+        .collect(),
+    ),
+    ..Default::default()
+  };
+  let args = ResolveArgs {
+    file: file.to_string(),
+    line: Some(key.line),
+    side: Some("new".to_string()),
+    file_comment: false,
+  };
+  let title = "Remark: Apply suggested change".to_string();
+  CodeActionOrCommand::CodeAction(CodeAction {
+    title: title.clone(),
+    kind: Some(CodeActionKind::QUICKFIX),
+    edit: Some(edit),
+    command: Some(Command {
+      title,
+      command: COMMAND_RESOLVE.to_string(),
+      arguments: Some(vec![serde_json::to_value(args).unwrap_or(Value::Null)]),
+    }),
+    ..Default::default()
+  })
+}
+
 fn make_file_comment_action(file: &str, resolved: bool) -> CodeActionOrCommand {
   let title = if resolved {
     "Unresolve remark file comment".to_string()
@@ -1040,7 +1219,10 @@ impl LanguageServer for Backend {
 
   async fn did_open(&self, params: tower_lsp::lsp_types::DidOpenTextDocumentParams) {
     if let Ok(mut docs) = self.open_documents.write() {
-      docs.insert(params.text_document.uri.clone());
+      docs.insert(
+        params.text_document.uri.clone(),
+        params.text_document.text.clone(),
+      );
     }
     if self.sync_draft_if_needed(&params.text_document.uri).await {
       return;
@@ -1049,6 +1231,13 @@ impl LanguageServer for Backend {
   }
 
   async fn did_change(&self, params: tower_lsp::lsp_types::DidChangeTextDocumentParams) {
+    if let Ok(mut docs) = self.open_documents.write()
+      && let Some(text) = docs.get_mut(&params.text_document.uri)
+    {
+      for change in params.content_changes {
+        apply_content_change(text, change);
+      }
+    }
     self.publish_diagnostics(&params.text_document.uri).await;
   }
 
@@ -1141,12 +1330,16 @@ impl Drop for Backend {
   }
 }
 
-fn load_file_review(
+/// Notes for `path` from every view's note merged into one (the base view only when a base ref
+/// is set), after re-anchoring notes left under an earlier HEAD. The one read path for the LSP
+/// and the commands that report on notes.
+pub(crate) fn load_file_review(
   repo: &gix::Repository,
   notes_ref: &str,
   path: &str,
   base_ref: Option<&str>,
 ) -> Result<Option<FileReview>> {
+  crate::reanchor::reanchor_notes(repo, notes_ref, base_ref)?;
   let head = git::head_commit_oid(repo)?;
   let mut merged: Option<FileReview> = None;
   let mut views = vec![ViewKind::All, ViewKind::Staged, ViewKind::Unstaged];
@@ -1181,26 +1374,6 @@ fn load_file_review(
   Ok(merged)
 }
 
-fn merge_file_review(target: &mut FileReview, incoming: FileReview) {
-  match (&target.file_comment, &incoming.file_comment) {
-    (None, Some(_)) => target.file_comment = incoming.file_comment,
-    (Some(t), Some(i)) if t.resolved && !i.resolved => target.file_comment = incoming.file_comment,
-    _ => {}
-  }
-
-  for (k, v) in incoming.comments {
-    match target.comments.get(&k) {
-      None => {
-        target.comments.insert(k, v);
-      }
-      Some(existing) if existing.resolved && !v.resolved => {
-        target.comments.insert(k, v);
-      }
-      _ => {}
-    }
-  }
-}
-
 fn prune_resolved(review: &mut FileReview) {
   if matches!(review.file_comment, Some(Comment { resolved: true, .. })) {
     review.file_comment = None;
@@ -1213,7 +1386,8 @@ fn review_to_diagnostics(review: &FileReview) -> Vec<Diagnostic> {
   if let Some(comment) = &review.file_comment {
     diags.push(build_diag(1, None, comment, None));
   }
-  for (key, comment) in &review.comments {
+  // Outdated comments no longer point at the code they were written for.
+  for (key, comment) in review.comments.iter().filter(|(_, c)| !c.outdated) {
     diags.push(build_diag(key.line, Some(key.side), comment, Some(*key)));
   }
   diags
@@ -1226,7 +1400,11 @@ fn build_diag(
   key: Option<LineKey>,
 ) -> Diagnostic {
   let line_idx = line.saturating_sub(1);
-  let range = Range::new(Position::new(line_idx, 0), Position::new(line_idx, 0));
+  // Range comments span every covered line: end at the start of the line after the last one.
+  let range = match key.and_then(|key| comment.end_line.filter(|end| *end > key.line)) {
+    Some(end) => Range::new(Position::new(line_idx, 0), Position::new(end, 0)),
+    None => Range::new(Position::new(line_idx, 0), Position::new(line_idx, 0)),
+  };
   let mut message = comment.body.trim().to_string();
   if let Some(side) = side
     && side == LineSide::Old
@@ -1234,12 +1412,24 @@ fn build_diag(
     message = format!("[old] {message}");
   }
   if let Some(key) = key {
-    message = format!("{message} (line {})", key.line);
+    let last = comment.last_line(key);
+    message = if last > key.line {
+      format!("{message} (lines {}-{last})", key.line)
+    } else {
+      format!("{message} (line {})", key.line)
+    };
   }
-  let severity = if comment.resolved {
-    DiagnosticSeverity::HINT
-  } else {
-    DiagnosticSeverity::WARNING
+  let tags = crate::review::Tags::of(comment);
+  if !tags.is_empty() {
+    message = format!("{tags} {message}");
+  }
+  // Untagged comments weigh what `rank` says they do, as in the prompt.
+  let severity = match comment.rank() {
+    _ if comment.resolved => DiagnosticSeverity::HINT,
+    Severity::Blocker => DiagnosticSeverity::ERROR,
+    Severity::Issue => DiagnosticSeverity::WARNING,
+    Severity::Suggestion => DiagnosticSeverity::INFORMATION,
+    Severity::Nit => DiagnosticSeverity::HINT,
   };
   Diagnostic {
     range,
@@ -1270,11 +1460,38 @@ mod tests {
   use std::collections::BTreeMap;
   use std::sync::Once;
 
+  #[test]
+  fn hover_thread_lists_replies() {
+    let mut c = comment("Why?", false);
+    c.author = Some("Alice <alice@example.com>".to_string());
+    c.created_at = Some(0);
+    c.replies.push(crate::review::Reply {
+      author: "Bob <bob@example.com>".to_string(),
+      at: 60,
+      body: "Because.".to_string(),
+    });
+
+    c.resolution = Some(crate::review::Resolution {
+      author: "Alice <alice@example.com>".to_string(),
+      at: 120,
+      message: Some("Won't fix: intentional".to_string()),
+      commit: Some("0123456789abcdef".to_string()),
+    });
+
+    let hover = hover_thread(&c, "[old] ");
+    assert!(hover.starts_with("[old] Why?"));
+    assert!(hover.contains("Alice <alice@example.com>, 1970-01-01T00:00:00Z"));
+    assert!(hover.contains("**Bob <bob@example.com>** · 1970-01-01T00:01:00Z\n\nBecause."));
+    assert!(hover.ends_with(
+      "**Resolved** by Alice <alice@example.com> · 1970-01-01T00:02:00Z in `0123456`\n\nWon't fix: intentional"
+    ));
+  }
+
   fn comment(body: &str, resolved: bool) -> Comment {
     Comment {
       body: body.to_string(),
       resolved,
-      snippet_hash: None,
+      ..Default::default()
     }
   }
 
@@ -1414,7 +1631,131 @@ mod tests {
     assert_eq!(diag.range.end.line, 6);
     assert_eq!(diag.range.end.character, 0);
     assert_eq!(diag.source.as_deref(), Some("remark"));
-    assert_eq!(diag.severity, Some(DiagnosticSeverity::WARNING));
+    assert_eq!(diag.severity, Some(DiagnosticSeverity::INFORMATION));
+  }
+
+  #[test]
+  fn apply_suggestion_action_edits_covered_lines() {
+    let uri = Url::parse("file:///tmp/src/lib.rs").expect("uri");
+    let mut c = comment("Collapse", false);
+    c.end_line = Some(4);
+    c.suggestion = Some(crate::review::Suggestion {
+      original: Vec::new(),
+      replacement: vec!["one".to_string()],
+    });
+    let key = LineKey {
+      side: LineSide::New,
+      line: 2,
+    };
+
+    let edit = |content: &str| {
+      let CodeActionOrCommand::CodeAction(action) =
+        make_apply_suggestion_action(&uri, "src/lib.rs", key, &c, content)
+      else {
+        panic!("expected a code action");
+      };
+      assert_eq!(
+        action.command.map(|c| c.command).as_deref(),
+        Some(COMMAND_RESOLVE)
+      );
+      let mut changes = action.edit.and_then(|e| e.changes).expect("changes");
+      let mut edits = changes.remove(&uri).expect("edits");
+      assert_eq!(edits.len(), 1);
+      edits.remove(0)
+    };
+
+    let edit_lf = edit("a\nb\nc\nd\ne\n");
+    assert_eq!(edit_lf.range.start, Position::new(1, 0));
+    assert_eq!(edit_lf.range.end, Position::new(4, 0));
+    assert_eq!(edit_lf.new_text, "one\n");
+
+    let edit_crlf = edit("a\r\nb\r\nc\r\nd\r\ne\r\n");
+    assert_eq!(edit_crlf.new_text, "one\r\n");
+
+    // The covered lines run to the end of a file without a trailing newline.
+    let edit_eof = edit("a\nb\nc\nd");
+    assert_eq!(edit_eof.range.start, Position::new(1, 0));
+    assert_eq!(edit_eof.range.end, Position::new(3, 1));
+    assert_eq!(edit_eof.new_text, "one");
+  }
+
+  #[test]
+  fn apply_content_change_edits_utf16_ranges() {
+    let mut text = "fn é() {}\nlet x = 1;\n".to_string();
+    apply_content_change(
+      &mut text,
+      TextDocumentContentChangeEvent {
+        range: Some(Range {
+          start: Position::new(0, 3),
+          end: Position::new(0, 4),
+        }),
+        range_length: None,
+        text: "e".to_string(),
+      },
+    );
+    apply_content_change(
+      &mut text,
+      TextDocumentContentChangeEvent {
+        range: Some(Range {
+          start: Position::new(0, 0),
+          end: Position::new(0, 0),
+        }),
+        range_length: None,
+        text: "// new\n".to_string(),
+      },
+    );
+    assert_eq!(text, "// new\nfn e() {}\nlet x = 1;\n");
+
+    apply_content_change(
+      &mut text,
+      TextDocumentContentChangeEvent {
+        range: None,
+        range_length: None,
+        text: "replaced\n".to_string(),
+      },
+    );
+    assert_eq!(text, "replaced\n");
+  }
+
+  #[test]
+  fn build_diag_spans_range_comments() {
+    let mut c = comment("range note", false);
+    c.end_line = Some(12);
+    let key = LineKey {
+      side: LineSide::New,
+      line: 10,
+    };
+    let diag = build_diag(key.line, Some(key.side), &c, Some(key));
+
+    assert_eq!(diag.range.start.line, 9);
+    assert_eq!(diag.range.end.line, 12);
+    assert_eq!(diag.message, "range note (lines 10-12)");
+  }
+
+  #[test]
+  fn build_diag_follows_comment_severity() {
+    let severity_of = |severity| {
+      let mut c = comment("note", false);
+      c.severity = severity;
+      build_diag(1, None, &c, None).severity
+    };
+    let expected = [
+      (None, DiagnosticSeverity::INFORMATION),
+      (Some(Severity::Nit), DiagnosticSeverity::HINT),
+      (Some(Severity::Suggestion), DiagnosticSeverity::INFORMATION),
+      (Some(Severity::Issue), DiagnosticSeverity::WARNING),
+      (Some(Severity::Blocker), DiagnosticSeverity::ERROR),
+    ];
+    for (severity, level) in expected {
+      assert_eq!(severity_of(severity), Some(level), "{severity:?}");
+    }
+
+    let mut c = comment("slow", true);
+    c.severity = Some(Severity::Blocker);
+    c.labels = vec!["perf".to_string()];
+    let diag = build_diag(1, None, &c, None);
+    assert_eq!(diag.severity, Some(DiagnosticSeverity::HINT));
+    assert_eq!(diag.message, "[blocker] #perf slow");
   }
 
   fn init_repo_with_commit(path: &str, contents: &str) -> (tempfile::TempDir, gix::Repository) {
@@ -2022,7 +2363,7 @@ mod tests {
     assert_eq!(diagnostics.len(), 2);
 
     let draft_path = crate::add_cmd::draft_path(&repo).expect("draft path");
-    let empty = crate::review::render_prompt(&crate::review::Review::new(), |_, _| None);
+    let empty = crate::review::render_prompt(&crate::review::Review::new(), |_, _, _| None);
     std::fs::write(&draft_path, empty).expect("write draft");
     let draft_uri = Url::from_file_path(&draft_path).expect("draft uri");
     write_lsp_message(
@@ -1,19 +1,37 @@
 mod add_cmd;
 mod app;
+mod apply_suggestion_cmd;
+mod autoresolve_cmd;
+mod check_cmd;
 mod clipboard;
 mod config;
 mod diff;
+mod file_filter;
 mod file_tree;
 mod git;
 mod highlight;
+mod hook_cmd;
+mod keymap;
+mod list_cmd;
 mod lsp;
+mod mcp;
 mod new_cmd;
 mod notes;
 mod prompt_cmd;
 mod prompt_code;
+mod prompt_template;
+mod pull_cmd;
 mod purge_cmd;
+mod push_cmd;
+mod reanchor;
 mod resolve_cmd;
 mod review;
+mod revive_cmd;
+mod search;
+mod show_cmd;
+#[cfg(test)]
+mod test_support;
+mod theme;
 mod ui;
 
 #[global_allocator]
@@ -62,8 +80,42 @@ fn main() -> anyhow::Result<()> {
       let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
       let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
       let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      let template = config::resolve_prompt_template(&cfg, cmd.template.clone());
       maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
-      prompt_cmd::run(&repo, &notes_ref, cmd.filter, base_ref)
+      prompt_cmd::run(&repo, &notes_ref, cmd, base_ref, template)
+    }
+    Some(config::Command::List(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      list_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
+    Some(config::Command::Show(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      show_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
+    Some(config::Command::Check(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_for_ui(&repo, &cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      check_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
+    Some(config::Command::Hook(cmd)) => hook_cmd::run(&repo, cmd),
+    Some(config::Command::Mcp(_)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_for_ui(&repo, &cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      mcp::run(&repo, &notes_ref, base_ref)
     }
     Some(config::Command::Resolve(cmd)) => {
       let cfg = config::load_config(&global, &ui)?;
@@ -73,6 +125,34 @@ fn main() -> anyhow::Result<()> {
       maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
       resolve_cmd::run(&repo, &notes_ref, base_ref, cmd)
     }
+    Some(config::Command::Autoresolve(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_for_ui(&repo, &cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      autoresolve_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
+    Some(config::Command::Revive(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      revive_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
+    Some(config::Command::Push(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
+      push_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
+    Some(config::Command::Pull(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
+      pull_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
     Some(config::Command::Add(cmd)) => {
       let cfg = config::load_config(&global, &ui)?;
       let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
@@ -81,22 +161,49 @@ fn main() -> anyhow::Result<()> {
       maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
       add_cmd::run(&repo, &notes_ref, base_ref, cmd)
     }
+    Some(config::Command::ApplySuggestion(cmd)) => {
+      let cfg = config::load_config(&global, &ui)?;
+      let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
+      let base_ref = config::resolve_base_ref_optional(&cfg, global.base_ref.clone());
+      let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
+      maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
+      apply_suggestion_cmd::run(&repo, &notes_ref, base_ref, cmd)
+    }
     None => {
       let cfg = config::load_config(&global, &ui)?;
       let notes_ref = config::resolve_notes_ref(&repo, &cfg, global.notes_ref.clone());
       let base_ref = config::resolve_base_ref_for_ui(&repo, &cfg, global.base_ref.clone());
       let show_ignored = config::resolve_show_ignored(&cfg, ui.show_ignored);
       let fetch_notes = config::resolve_fetch_notes(&cfg, global.fetch_notes);
-      let view = ui.view.unwrap_or(git::ViewKind::All);
+      let revisions = match (ui.rev.as_deref(), ui.range.as_deref()) {
+        (Some(rev), _) => Some(git::resolve_commit(&repo, rev)?),
+        (None, Some(range)) => Some(git::resolve_range(&repo, range)?),
+        (None, None) if ui.view == Some(git::ViewKind::Commit) => {
+          Some(git::resolve_commit(&repo, "HEAD")?)
+        }
+        (None, None) => None,
+      };
+      let view = ui
+        .view
+        .or(revisions.as_ref().map(|r| r.view))
+        .unwrap_or(git::ViewKind::All);
       let jump_target = app::build_jump_target(&repo, ui.file.clone(), ui.line, ui.side)?;
+      let keymap = match &cfg.keymap {
+        Some(overrides) => keymap::Keymap::with_overrides(overrides).context("load keymap")?,
+        None => keymap::Keymap::default(),
+      };
       maybe_fetch_notes(&repo, &notes_ref, fetch_notes);
       let options = app::UiOptions {
         notes_ref,
         base_ref,
         show_ignored,
         view,
+        revisions,
         jump_target,
         tab_width: cfg.tab_width,
+        prompt_template: cfg.prompt_template.clone(),
+        keymap,
+        theme: config::resolve_theme(&cfg, ui.theme, ui.syntax_theme),
       };
       app::run(repo, options)
     }
@@ -0,0 +1,388 @@
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use crate::git::ViewKind;
+use crate::list_cmd::{CommentEntry, comment_entries};
+use crate::prompt_code::{LineSnippetResolver, language_for_path};
+use crate::review::{LineKey, Review};
+
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const DEFAULT_CONTEXT_LINES: u32 = 3;
+
+const TOOL_LIST: &str = "list_comments";
+const TOOL_CONTEXT: &str = "get_comment_context";
+const TOOL_RESOLVE: &str = "resolve_comment";
+const TOOL_REPLY: &str = "reply_to_comment";
+
+/// Serves the review notes to a Model Context Protocol client over stdio, one JSON-RPC message
+/// per line.
+pub fn run(repo: &gix::Repository, notes_ref: &str, base_ref: Option<String>) -> Result<()> {
+  let server = Server {
+    repo,
+    notes_ref,
+    base_ref,
+  };
+  let stdin = std::io::stdin();
+  let mut stdout = std::io::stdout().lock();
+  for line in stdin.lock().lines() {
+    let line = line.context("read stdin")?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let response = match serde_json::from_str::<Value>(&line) {
+      Ok(message) => server.handle(message),
+      Err(err) => Some(error_response(
+        Value::Null,
+        PARSE_ERROR,
+        &format!("parse error: {err}"),
+      )),
+    };
+    if let Some(response) = response {
+      writeln!(stdout, "{response}").context("write stdout")?;
+      stdout.flush().context("flush stdout")?;
+    }
+  }
+  Ok(())
+}
+
+struct Server<'a> {
+  repo: &'a gix::Repository,
+  notes_ref: &'a str,
+  base_ref: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ListArgs {
+  include_resolved: bool,
+  ignore_outdated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContextArgs {
+  id: String,
+  context_lines: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResolveArgs {
+  id: String,
+  #[serde(default)]
+  unresolve: bool,
+  note: Option<String>,
+  commit: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplyArgs {
+  id: String,
+  body: String,
+}
+
+impl Server<'_> {
+  /// The response to `message`, or `None` for notifications.
+  fn handle(&self, message: Value) -> Option<Value> {
+    let id = message.get("id").cloned()?;
+    let method = message
+      .get("method")
+      .and_then(Value::as_str)
+      .unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+      "initialize" => initialize_result(&params),
+      "ping" => json!({}),
+      "tools/list" => json!({ "tools": tool_definitions() }),
+      "tools/call" => self.call_tool(&params),
+      _ => {
+        return Some(error_response(
+          id,
+          METHOD_NOT_FOUND,
+          &format!("unknown method '{method}'"),
+        ));
+      }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+  }
+
+  /// Tool failures are reported in the result so the agent sees them.
+  fn call_tool(&self, params: &Value) -> Value {
+    let name = params
+      .get("name")
+      .and_then(Value::as_str)
+      .unwrap_or_default();
+    let args = params
+      .get("arguments")
+      .cloned()
+      .unwrap_or_else(|| json!({}));
+    let (text, is_error) = match self.run_tool(name, args) {
+      Ok(text) => (text, false),
+      Err(err) => (format!("{err:#}"), true),
+    };
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+  }
+
+  fn run_tool(&self, name: &str, args: Value) -> Result<String> {
+    match name {
+      TOOL_LIST => self.list_comments(parse_args(args)?),
+      TOOL_CONTEXT => self.comment_context(parse_args(args)?),
+      TOOL_RESOLVE => self.resolve_comment(parse_args(args)?),
+      TOOL_REPLY => self.reply_to_comment(parse_args(args)?),
+      _ => anyhow::bail!("unknown tool '{name}'"),
+    }
+  }
+
+  /// The notes for every changed file, after syncing the draft like the LSP does.
+  fn load_review(&self) -> Result<Review> {
+    let base_ref = self.base_ref.as_deref();
+    let paths = crate::check_cmd::check_paths(self.repo, None, base_ref)?;
+    if crate::git::head_commit_oid(self.repo).is_ok() {
+      crate::add_cmd::sync_draft_notes(self.repo, self.notes_ref, base_ref)?;
+    }
+    crate::list_cmd::load_notes_review(self.repo, self.notes_ref, base_ref, &paths)
+  }
+
+  fn list_comments(&self, args: ListArgs) -> Result<String> {
+    let review = self.load_review()?;
+    let entries: Vec<_> = review
+      .files
+      .iter()
+      .flat_map(|(path, file)| comment_entries(path, file))
+      .filter(|e| args.include_resolved || !e.resolved)
+      .filter(|e| !(args.ignore_outdated && e.outdated))
+      .collect();
+    serde_json::to_string_pretty(&entries).context("serialize comments")
+  }
+
+  fn comment_context(&self, args: ContextArgs) -> Result<String> {
+    let review = self.load_review()?;
+    let entry = find_entry(&review, &args.id)?;
+    let base_ref = self.base_ref.as_deref();
+    let base_tree = base_ref.and_then(|b| crate::git::merge_base_tree(self.repo, b).ok());
+    let mut view_order = vec![ViewKind::All, ViewKind::Staged, ViewKind::Unstaged];
+    if base_tree.is_some() {
+      view_order.push(ViewKind::Base);
+    }
+    let context_lines = args.context_lines.unwrap_or(DEFAULT_CONTEXT_LINES);
+    let mut resolver = LineSnippetResolver::new(self.repo, base_tree, context_lines, view_order);
+
+    let snippet = entry_key(&entry).and_then(|key| {
+      let last = entry.end_line.unwrap_or(key.line);
+      resolver.snippet_range(entry.file, key, last)
+    });
+    let context = json!({
+      "id": entry.id,
+      "file": entry.file,
+      "language": language_for_path(entry.file),
+      "side": entry.side,
+      "line": entry.line,
+      "end_line": entry.end_line,
+      "snippet": snippet,
+      "hunks": resolver.hunks(entry.file),
+    });
+    serde_json::to_string_pretty(&context).context("serialize context")
+  }
+
+  fn resolve_comment(&self, args: ResolveArgs) -> Result<String> {
+    let review = self.load_review()?;
+    let entry = find_entry(&review, &args.id)?;
+    let key = entry_key(&entry);
+    let cmd = crate::config::ResolveCli {
+      file: Some(entry.file.to_string()),
+      line: key.map(|k| k.line),
+      side: key.map(|k| k.side),
+      file_comment: key.is_none(),
+      unresolve: args.unresolve,
+      message: args.note,
+      commit: args.commit,
+    };
+    crate::resolve_cmd::run(self.repo, self.notes_ref, self.base_ref.clone(), cmd)?;
+    let state = if args.unresolve {
+      "Unresolved"
+    } else {
+      "Resolved"
+    };
+    Ok(format!("{state} {}", entry.location()))
+  }
+
+  fn reply_to_comment(&self, args: ReplyArgs) -> Result<String> {
+    let review = self.load_review()?;
+    let entry = find_entry(&review, &args.id)?;
+    crate::add_cmd::append_reply(
+      self.repo,
+      self.notes_ref,
+      self.base_ref.as_deref(),
+      entry.file,
+      entry_key(&entry),
+      args.body,
+    )?;
+    Ok(format!("Replied on {}", entry.location()))
+  }
+}
+
+fn parse_args<T: DeserializeOwned>(args: Value) -> Result<T> {
+  serde_json::from_value(args).context("invalid tool arguments")
+}
+
+fn find_entry<'a>(review: &'a Review, id: &str) -> Result<CommentEntry<'a>> {
+  review
+    .files
+    .iter()
+    .flat_map(|(path, file)| comment_entries(path, file))
+    .find(|e| e.id == id)
+    .with_context(|| format!("no comment with id '{id}'"))
+}
+
+fn entry_key(entry: &CommentEntry<'_>) -> Option<LineKey> {
+  Some(LineKey {
+    side: entry.side?,
+    line: entry.line?,
+  })
+}
+
+fn initialize_result(params: &Value) -> Value {
+  let requested = params
+    .get("protocolVersion")
+    .and_then(Value::as_str)
+    .unwrap_or_default();
+  let version = PROTOCOL_VERSIONS
+    .iter()
+    .find(|v| **v == requested)
+    .unwrap_or(&PROTOCOL_VERSIONS[0]);
+  json!({
+    "protocolVersion": version,
+    "capabilities": { "tools": {} },
+    "serverInfo": { "name": "remark", "version": env!("CARGO_PKG_VERSION") },
+    "instructions": "Review comments left on this repository's changes. List the unresolved \
+  comments, fetch the code around one with its id, make the change, then resolve it with a note \
+  saying what was done.",
+  })
+}
+
+fn tool_definitions() -> Value {
+  let id = json!({ "type": "string", "description": "Comment id from list_comments." });
+  json!([
+    {
+      "name": TOOL_LIST,
+      "description": "List review comments on the changed files (unresolved only by default).",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "include_resolved": { "type": "boolean", "description": "Also list resolved comments." },
+          "ignore_outdated": {
+            "type": "boolean",
+            "description": "Skip comments whose code has changed or disappeared.",
+          },
+        },
+      },
+    },
+    {
+      "name": TOOL_CONTEXT,
+      "description": "Fetch the code a comment is about, plus the diff hunks of its file.",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "id": id,
+          "context_lines": {
+            "type": "integer",
+            "minimum": 0,
+            "description": "Lines of context around the commented lines (default 3).",
+          },
+        },
+        "required": ["id"],
+      },
+    },
+    {
+      "name": TOOL_RESOLVE,
+      "description": "Resolve a comment (or reopen it), optionally recording how it was addressed.",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "id": id,
+          "note": { "type": "string", "description": "What was done about the comment." },
+          "commit": { "type": "string", "description": "Revision of the commit that addressed it." },
+          "unresolve": { "type": "boolean", "description": "Reopen instead of resolving." },
+        },
+        "required": ["id"],
+      },
+    },
+    {
+      "name": TOOL_REPLY,
+      "description": "Add a reply to a comment's thread.",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "id": id,
+          "body": { "type": "string" },
+        },
+        "required": ["id", "body"],
+      },
+    },
+  ])
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+  json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn handle_answers_requests_and_skips_notifications() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let server = Server {
+      repo: &repo,
+      notes_ref: crate::git::DEFAULT_NOTES_REF,
+      base_ref: None,
+    };
+
+    let init = server
+      .handle(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": { "protocolVersion": "2024-11-05", "capabilities": {} },
+      }))
+      .expect("initialize response");
+    assert_eq!(init["id"], 1);
+    assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+    assert!(init["result"]["capabilities"]["tools"].is_object());
+
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    assert!(server.handle(notification).is_none());
+
+    let tools = server
+      .handle(json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+      .expect("tools/list response");
+    let names: Vec<_> = tools["result"]["tools"]
+      .as_array()
+      .expect("tools")
+      .iter()
+      .filter_map(|t| t["name"].as_str())
+      .collect();
+    assert_eq!(names, [TOOL_LIST, TOOL_CONTEXT, TOOL_RESOLVE, TOOL_REPLY]);
+
+    let unknown = server
+      .handle(json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" }))
+      .expect("error response");
+    assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+    let bad_call = server
+      .handle(json!({
+        "jsonrpc": "2.0",
+        "id": 4,
+        "method": "tools/call",
+        "params": { "name": TOOL_REPLY, "arguments": { "id": "abc" } },
+      }))
+      .expect("tools/call response");
+    assert_eq!(bad_call["result"]["isError"], true);
+  }
+}
@@ -1,9 +1,12 @@
+use std::collections::BTreeMap;
+
 use anyhow::{Context, Result};
 use gix::Repository;
 use gix::bstr::ByteSlice;
 use gix::object::tree::EntryKind;
 use gix::objs;
 use gix_hash::ObjectId;
+use gix_ref::Target;
 use gix_ref::transaction::PreviousValue;
 use smallvec::SmallVec;
 
@@ -14,111 +17,416 @@ fn note_path(oid: &ObjectId) -> String {
 }
 
 pub fn read(repo: &Repository, notes_ref: &str, target: &ObjectId) -> Result<Option<String>> {
+  read_at(repo, current_tip(repo, notes_ref)?, target)
+}
+
+/// Reads `target` as of the notes commit `tip` (`None` when the ref does not exist yet).
+fn read_at(repo: &Repository, tip: Option<ObjectId>, target: &ObjectId) -> Result<Option<String>> {
+  let Some(tip) = tip else {
+    return Ok(None);
+  };
+  let tree = notes_commit_tree(repo, tip)?;
+  match note_blob(&tree, target)? {
+    Some(blob_id) => read_blob(repo, blob_id).map(Some),
+    None => Ok(None),
+  }
+}
+
+fn note_blob(tree: &gix::Tree<'_>, target: &ObjectId) -> Result<Option<ObjectId>> {
+  let p = note_path(target);
+  Ok(
+    tree
+      .lookup_entry_by_path(&p)
+      .context("lookup note entry")?
+      .map(|entry| entry.object_id()),
+  )
+}
+
+/// Lists every note under `notes_ref` as `target -> note blob`.
+pub fn list(repo: &Repository, notes_ref: &str) -> Result<BTreeMap<ObjectId, ObjectId>> {
   let Some(r) = repo
     .try_find_reference(notes_ref)
     .context("find notes ref")?
   else {
-    return Ok(None);
+    return Ok(BTreeMap::new());
   };
-  let commit = r
-    .id()
-    .object()
-    .context("read notes ref object")?
+  list_commit(repo, r.id().detach())
+}
+
+fn list_commit(repo: &Repository, commit_id: ObjectId) -> Result<BTreeMap<ObjectId, ObjectId>> {
+  let tree = notes_commit_tree(repo, commit_id)?;
+
+  let mut recorder = gix::traverse::tree::Recorder::default();
+  tree
+    .traverse()
+    .breadthfirst(&mut recorder)
+    .context("walk notes tree")?;
+
+  let mut out = BTreeMap::new();
+  for entry in recorder.records {
+    if entry.mode.is_tree() {
+      continue;
+    }
+    // Note paths are the target id split into fan-out directories (`xx/yyyy...`).
+    let hex = entry.filepath.to_str_lossy().replace('/', "");
+    if let Ok(target) = ObjectId::from_hex(hex.as_bytes()) {
+      out.insert(target, entry.oid);
+    }
+  }
+  Ok(out)
+}
+
+fn notes_commit_tree(repo: &Repository, commit_id: ObjectId) -> Result<gix::Tree<'_>> {
+  repo
+    .find_object(commit_id)
+    .context("read notes commit")?
     .peel_to_commit()
-    .context("notes ref is not a commit")?;
-  let tree = commit.tree().context("notes tree")?;
+    .context("notes ref is not a commit")?
+    .tree()
+    .context("notes tree")
+}
 
-  let p = note_path(target);
-  let Some(entry) = tree.lookup_entry_by_path(&p).context("lookup note entry")? else {
-    return Ok(None);
-  };
-  let blob = entry
-    .object()
+fn read_blob(repo: &Repository, blob_id: ObjectId) -> Result<String> {
+  let blob = repo
+    .find_object(blob_id)
     .context("load note blob")?
     .try_into_blob()
     .context("note entry is not a blob")?;
-  Ok(Some(
-    String::from_utf8_lossy(blob.data.as_ref()).to_string(),
-  ))
+  Ok(String::from_utf8_lossy(blob.data.as_ref()).to_string())
 }
 
-pub fn write(
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeOutcome {
+  UpToDate,
+  FastForward,
+  Merged,
+}
+
+/// Merges the notes commit `theirs` into `notes_ref`.
+///
+/// Notes changed on only one side since the merge base take that side. Notes changed on both
+/// sides are combined with `merge_note(base, ours, theirs)`, `base` being the note at the merge
+/// base if there was one; a note deleted on one side but changed on the other is kept. The ref is
+/// only moved if it still points where it did when we started.
+pub fn merge<F>(
   repo: &Repository,
   notes_ref: &str,
-  target: &ObjectId,
-  note: Option<&str>,
-) -> Result<()> {
-  let mut previous_commit: Option<ObjectId> = None;
-  let mut root_tree = repo.empty_tree();
-
-  if let Some(r) = repo
+  theirs: ObjectId,
+  mut merge_note: F,
+) -> Result<MergeOutcome>
+where
+  F: FnMut(Option<&str>, &str, &str) -> String,
+{
+  let ours = repo
     .try_find_reference(notes_ref)
     .context("find notes ref")?
-  {
-    let id = r.id();
-    previous_commit = Some(id.detach());
-    let commit = id
-      .object()
-      .context("read previous notes commit")?
-      .peel_to_commit()
-      .context("notes ref is not a commit")?;
-    root_tree = commit.tree().context("previous notes tree")?;
-  }
-
-  let mut editor = gix::object::tree::Editor::new(&root_tree).context("init notes tree editor")?;
-  let p = note_path(target);
+    .map(|r| r.id().detach());
+  let Some(ours) = ours else {
+    repo
+      .reference(
+        notes_ref,
+        theirs,
+        PreviousValue::MustNotExist,
+        "remark: fetch notes",
+      )
+      .context("create notes ref")?;
+    return Ok(MergeOutcome::FastForward);
+  };
+  if ours == theirs {
+    return Ok(MergeOutcome::UpToDate);
+  }
 
-  match note {
-    Some(text) if !text.trim().is_empty() => {
-      let blob_id = repo
-        .write_blob(text.as_bytes())
-        .context("write note blob")?
-        .detach();
-      editor
-        .upsert(p.as_bytes().as_bstr(), EntryKind::Blob, blob_id)
-        .context("upsert note path")?;
+  let base = repo.merge_base(ours, theirs).ok().map(|id| id.detach());
+  if base == Some(theirs) {
+    return Ok(MergeOutcome::UpToDate);
+  }
+  if base == Some(ours) {
+    repo
+      .reference(
+        notes_ref,
+        theirs,
+        PreviousValue::MustExistAndMatch(Target::Object(ours)),
+        "remark: fast-forward notes",
+      )
+      .context("fast-forward notes ref")?;
+    return Ok(MergeOutcome::FastForward);
+  }
+
+  let ours_notes = list_commit(repo, ours)?;
+  let theirs_notes = list_commit(repo, theirs)?;
+  let base_notes = match base {
+    Some(base) => list_commit(repo, base)?,
+    None => BTreeMap::new(),
+  };
+
+  let ours_tree = notes_commit_tree(repo, ours)?;
+  let mut editor = gix::object::tree::Editor::new(&ours_tree).context("init notes tree editor")?;
+  for (target, theirs_blob) in &theirs_notes {
+    let ours_blob = ours_notes.get(target);
+    if ours_blob == Some(theirs_blob) || base_notes.get(target) == Some(theirs_blob) {
+      // Unchanged on their side (or identical): our version stands, including our deletions.
+      continue;
     }
-    _ => {
+    let blob_id = match ours_blob {
+      Some(ours_blob) if base_notes.get(target) != Some(ours_blob) => {
+        let base_note = match base_notes.get(target) {
+          Some(base_blob) => Some(read_blob(repo, *base_blob)?),
+          None => None,
+        };
+        let merged = merge_note(
+          base_note.as_deref(),
+          &read_blob(repo, *ours_blob)?,
+          &read_blob(repo, *theirs_blob)?,
+        );
+        repo
+          .write_blob(merged.as_bytes())
+          .context("write merged note blob")?
+          .detach()
+      }
+      _ => *theirs_blob,
+    };
+    let p = note_path(target);
+    editor
+      .upsert(p.as_bytes().as_bstr(), EntryKind::Blob, blob_id)
+      .context("upsert merged note")?;
+  }
+  for target in ours_notes.keys() {
+    // Deleted on their side and untouched on ours.
+    if !theirs_notes.contains_key(target) && base_notes.get(target) == ours_notes.get(target) {
+      let p = note_path(target);
       editor
         .remove(p.as_bytes().as_bstr())
         .context("remove note")?;
     }
-  };
-
+  }
   let tree_id = editor.write().context("write notes tree")?.detach();
 
-  // Write commit object directly (no config dependency), then update the notes ref.
   let sig = default_signature();
-  let mut parents: SmallVec<[ObjectId; 1]> = Default::default();
-  if let Some(p) = previous_commit {
-    parents.push(p);
-  }
   let commit = objs::Commit {
     tree: tree_id,
-    parents,
+    parents: SmallVec::from_vec(vec![ours, theirs]),
     author: sig.clone(),
     committer: sig,
     encoding: None,
-    message: "remark: update notes\n".into(),
+    message: "remark: merge notes\n".into(),
     extra_headers: Default::default(),
   };
   let commit_id = repo
     .write_object(commit)
-    .context("write notes commit")?
+    .context("write notes merge commit")?
     .detach();
-
   repo
     .reference(
       notes_ref,
       commit_id,
-      PreviousValue::Any,
-      "remark: update notes",
+      PreviousValue::MustExistAndMatch(Target::Object(ours)),
+      "remark: merge notes",
     )
-    .context("update notes ref")?;
+    .context("update notes ref (it moved during the merge; retry)")?;
+
+  Ok(MergeOutcome::Merged)
+}
 
+pub fn write(
+  repo: &Repository,
+  notes_ref: &str,
+  target: &ObjectId,
+  note: Option<&str>,
+) -> Result<()> {
+  let mut tx = Transaction::new(repo, notes_ref)?;
+  tx.set(target, note);
+  tx.commit()?;
   Ok(())
 }
 
+const MAX_COMMIT_ATTEMPTS: usize = 8;
+
+/// A batch of note upserts/deletes written to `notes_ref` as a single commit.
+///
+/// Reads go through the notes commit the ref pointed at when the transaction started, and the
+/// ref is updated with compare-and-swap. If another writer (e.g. the LSP) moved it in the
+/// meantime, the staged changes are replayed on top of the new tip: their notes for other
+/// targets survive, and a staged note they changed too is combined with theirs like
+/// [`merge`] does, a deleted one keeping their version.
+pub struct Transaction<'r> {
+  repo: &'r Repository,
+  notes_ref: String,
+  /// Tip the staged changes were made against.
+  base: Option<ObjectId>,
+  changes: BTreeMap<ObjectId, Option<String>>,
+}
+
+impl<'r> Transaction<'r> {
+  pub fn new(repo: &'r Repository, notes_ref: &str) -> Result<Self> {
+    Ok(Self {
+      repo,
+      notes_ref: notes_ref.to_string(),
+      base: current_tip(repo, notes_ref)?,
+      changes: BTreeMap::new(),
+    })
+  }
+
+  /// Stages `note` for `target`; `None` (or a blank note) removes it.
+  pub fn set(&mut self, target: &ObjectId, note: Option<&str>) {
+    let note = note.filter(|text| !text.trim().is_empty());
+    self.changes.insert(*target, note.map(ToOwned::to_owned));
+  }
+
+  pub fn upsert(&mut self, target: &ObjectId, note: &str) {
+    self.set(target, Some(note));
+  }
+
+  pub fn remove(&mut self, target: &ObjectId) {
+    self.set(target, None);
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.changes.is_empty()
+  }
+
+  /// Reads `target` as it will look once this transaction commits.
+  pub fn read(&self, target: &ObjectId) -> Result<Option<String>> {
+    match self.changes.get(target) {
+      Some(staged) => Ok(staged.clone()),
+      None => read_at(self.repo, self.base, target),
+    }
+  }
+
+  /// Writes all staged changes as one notes commit. Returns `false` if nothing was staged.
+  pub fn commit(self) -> Result<bool> {
+    if self.changes.is_empty() {
+      return Ok(false);
+    }
+    for _ in 0..MAX_COMMIT_ATTEMPTS {
+      let parent = current_tip(self.repo, &self.notes_ref)?;
+      if self.try_commit(parent)?.is_some() {
+        return Ok(true);
+      }
+    }
+    anyhow::bail!(
+      "{} kept moving while writing notes; gave up after {MAX_COMMIT_ATTEMPTS} attempts",
+      self.notes_ref
+    )
+  }
+
+  /// Commits the staged changes on top of `parent`. Returns `None` if the ref no longer points
+  /// at `parent` (another writer won the race).
+  fn try_commit(&self, parent: Option<ObjectId>) -> Result<Option<ObjectId>> {
+    let root_tree = match parent {
+      Some(parent) => notes_commit_tree(self.repo, parent)?,
+      None => self.repo.empty_tree(),
+    };
+    // Only consulted when another writer moved the ref since we started.
+    let base_tree = match self.base {
+      Some(base) if parent != self.base => Some(notes_commit_tree(self.repo, base)?),
+      _ => None,
+    };
+    let mut editor =
+      gix::object::tree::Editor::new(&root_tree).context("init notes tree editor")?;
+    for (target, staged) in &self.changes {
+      let p = note_path(target);
+      let note = if parent == self.base {
+        staged.clone()
+      } else {
+        self.rebase_change(target, staged, base_tree.as_ref(), &root_tree)?
+      };
+      match note {
+        Some(text) => {
+          let blob_id = self
+            .repo
+            .write_blob(text.as_bytes())
+            .context("write note blob")?
+            .detach();
+          editor
+            .upsert(p.as_bytes().as_bstr(), EntryKind::Blob, blob_id)
+            .context("upsert note path")?;
+        }
+        None => {
+          editor
+            .remove(p.as_bytes().as_bstr())
+            .context("remove note")?;
+        }
+      }
+    }
+    let tree_id = editor.write().context("write notes tree")?.detach();
+
+    // Write commit object directly (no config dependency), then update the notes ref.
+    let sig = default_signature();
+    let commit = objs::Commit {
+      tree: tree_id,
+      parents: parent.into_iter().collect(),
+      author: sig.clone(),
+      committer: sig,
+      encoding: None,
+      message: "remark: update notes\n".into(),
+      extra_headers: Default::default(),
+    };
+    let commit_id = self
+      .repo
+      .write_object(commit)
+      .context("write notes commit")?
+      .detach();
+
+    let expected = match parent {
+      Some(parent) => PreviousValue::MustExistAndMatch(Target::Object(parent)),
+      None => PreviousValue::MustNotExist,
+    };
+    if let Err(err) = self.repo.reference(
+      self.notes_ref.as_str(),
+      commit_id,
+      expected,
+      "remark: update notes",
+    ) {
+      if current_tip(self.repo, &self.notes_ref)? != parent {
+        return Ok(None);
+      }
+      return Err(err).context("update notes ref");
+    }
+    Ok(Some(commit_id))
+  }
+
+  /// The note to write for `target` on top of `tip_tree`, given what was staged against the
+  /// transaction's base. A note the other writer left alone is written as staged.
+  fn rebase_change(
+    &self,
+    target: &ObjectId,
+    staged: &Option<String>,
+    base_tree: Option<&gix::Tree<'_>>,
+    tip_tree: &gix::Tree<'_>,
+  ) -> Result<Option<String>> {
+    let base_blob = match base_tree {
+      Some(tree) => note_blob(tree, target)?,
+      None => None,
+    };
+    let Some(theirs_blob) = note_blob(tip_tree, target)? else {
+      return Ok(staged.clone());
+    };
+    if base_blob == Some(theirs_blob) {
+      return Ok(staged.clone());
+    }
+    let theirs = read_blob(self.repo, theirs_blob)?;
+    Ok(Some(match staged {
+      Some(ours) => {
+        let base = match base_blob {
+          Some(base_blob) => Some(read_blob(self.repo, base_blob)?),
+          None => None,
+        };
+        crate::review::merge_note_text(base.as_deref(), ours, &theirs)
+      }
+      None => theirs,
+    }))
+  }
+}
+
+fn current_tip(repo: &Repository, notes_ref: &str) -> Result<Option<ObjectId>> {
+  Ok(
+    repo
+      .try_find_reference(notes_ref)
+      .context("find notes ref")?
+      .map(|r| r.id().detach()),
+  )
+}
+
 fn default_signature() -> gix_actor::Signature {
   use gix_date::Time;
   let name = std::env::var("GIT_AUTHOR_NAME")
@@ -141,3 +449,175 @@ fn default_signature() -> gix_actor::Signature {
     },
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn oid(hex: &str) -> ObjectId {
+    ObjectId::from_hex(hex.repeat(40).as_bytes()).expect("oid")
+  }
+
+  fn history_len(repo: &Repository, notes_ref: &str) -> usize {
+    let tip = current_tip(repo, notes_ref)
+      .expect("tip")
+      .expect("notes ref");
+    repo
+      .rev_walk([tip])
+      .all()
+      .expect("walk notes history")
+      .count()
+  }
+
+  #[test]
+  fn transaction_writes_one_commit() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    write(&repo, notes_ref, &oid("1"), Some("one")).expect("write");
+
+    let mut tx = Transaction::new(&repo, notes_ref).expect("transaction");
+    tx.upsert(&oid("2"), "two");
+    tx.upsert(&oid("3"), "three");
+    tx.remove(&oid("1"));
+    assert_eq!(
+      tx.read(&oid("2")).expect("read staged").as_deref(),
+      Some("two")
+    );
+    assert!(tx.commit().expect("commit"));
+
+    assert_eq!(history_len(&repo, notes_ref), 2);
+    let notes = list(&repo, notes_ref).expect("list");
+    assert_eq!(
+      notes.keys().copied().collect::<Vec<_>>(),
+      vec![oid("2"), oid("3")]
+    );
+  }
+
+  #[test]
+  fn transaction_replays_on_top_of_concurrent_writer() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    write(&repo, notes_ref, &oid("1"), Some("one")).expect("write");
+    let mut tx = Transaction::new(&repo, notes_ref).expect("transaction");
+    let stale = tx.base;
+
+    // Someone else writes after we started.
+    write(&repo, notes_ref, &oid("2"), Some("two")).expect("concurrent write");
+
+    tx.upsert(&oid("3"), "three");
+    assert!(tx.try_commit(stale).expect("try commit").is_none());
+    assert!(tx.commit().expect("commit"));
+
+    assert_eq!(
+      read(&repo, notes_ref, &oid("2")).expect("read").as_deref(),
+      Some("two")
+    );
+    assert_eq!(
+      read(&repo, notes_ref, &oid("3")).expect("read").as_deref(),
+      Some("three")
+    );
+  }
+
+  #[test]
+  fn transaction_merges_notes_the_concurrent_writer_changed() {
+    use crate::review::{
+      Comment, FileReview, LineKey, LineSide, decode_file_note, encode_file_note,
+    };
+
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    let with_comments = |lines: &[u32]| {
+      let mut file = FileReview::default();
+      for line in lines {
+        let key = LineKey {
+          side: LineSide::New,
+          line: *line,
+        };
+        let comment = Comment {
+          body: format!("line {line}"),
+          ..Default::default()
+        };
+        file.comments.insert(key, comment);
+      }
+      encode_file_note(&file)
+    };
+    write(&repo, notes_ref, &oid("1"), Some(&with_comments(&[1]))).expect("write");
+    write(&repo, notes_ref, &oid("2"), Some("two")).expect("write");
+
+    let mut tx = Transaction::new(&repo, notes_ref).expect("transaction");
+    tx.upsert(&oid("1"), &with_comments(&[1, 2]));
+    tx.remove(&oid("2"));
+
+    write(&repo, notes_ref, &oid("1"), Some(&with_comments(&[1, 3]))).expect("concurrent");
+    write(&repo, notes_ref, &oid("2"), Some("two, edited")).expect("concurrent");
+    assert!(tx.commit().expect("commit"));
+
+    let note = read(&repo, notes_ref, &oid("1"))
+      .expect("read")
+      .expect("note");
+    let lines: Vec<u32> = decode_file_note(&note)
+      .expect("decode")
+      .comments
+      .keys()
+      .map(|key| key.line)
+      .collect();
+    assert_eq!(lines, vec![1, 2, 3]);
+    // Their edit outlives our deletion.
+    assert_eq!(
+      read(&repo, notes_ref, &oid("2")).expect("read").as_deref(),
+      Some("two, edited")
+    );
+  }
+
+  #[test]
+  fn transaction_keeps_a_resolve_racing_an_unrelated_edit() {
+    use crate::review::{
+      Comment, FileReview, LineKey, LineSide, decode_file_note, encode_file_note,
+    };
+
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    let key = |line| LineKey {
+      side: LineSide::New,
+      line,
+    };
+    let mut base = FileReview::default();
+    for line in [1, 2] {
+      let comment = Comment {
+        body: format!("line {line}"),
+        ..Default::default()
+      };
+      base.comments.insert(key(line), comment);
+    }
+    write(&repo, notes_ref, &oid("1"), Some(&encode_file_note(&base))).expect("write");
+
+    let mut tx = Transaction::new(&repo, notes_ref).expect("transaction");
+    let mut resolved = base.clone();
+    resolved.comments.get_mut(&key(1)).unwrap().resolved = true;
+    resolved.comments.remove(&key(2));
+    tx.upsert(&oid("1"), &encode_file_note(&resolved));
+
+    let mut edited = base.clone();
+    edited.reviewed = true;
+    write(
+      &repo,
+      notes_ref,
+      &oid("1"),
+      Some(&encode_file_note(&edited)),
+    )
+    .expect("concurrent");
+    assert!(tx.commit().expect("commit"));
+
+    let note = read(&repo, notes_ref, &oid("1"))
+      .expect("read")
+      .expect("note");
+    let merged = decode_file_note(&note).expect("decode");
+    assert!(merged.reviewed);
+    assert!(merged.comments[&key(1)].resolved);
+    assert!(!merged.comments.contains_key(&key(2)));
+  }
+}
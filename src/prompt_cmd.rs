@@ -1,36 +1,24 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 
-use crate::config::PromptFilter;
+use crate::config::{PromptCli, PromptFilter, PromptFormat};
 use crate::git::ViewKind;
 use crate::prompt_code::{LineSnippetResolver, language_for_path};
+use crate::prompt_template::{CommentContext, PromptContext};
 use crate::review::PromptSnippet;
 
 pub fn run(
   repo: &gix::Repository,
   notes_ref: &str,
-  filter: PromptFilter,
+  cmd: PromptCli,
   base_ref: Option<String>,
+  template: Option<String>,
 ) -> Result<()> {
+  let filter = cmd.filter;
+  if cmd.template.is_some() && cmd.format != PromptFormat::Markdown {
+    anyhow::bail!("--template only applies to --format markdown");
+  }
   let head = crate::git::head_commit_oid(repo).ok();
-
-  let mut paths = match filter {
-    PromptFilter::All => {
-      let mut staged = crate::git::list_staged_paths(repo)?;
-      staged.extend(crate::git::list_unstaged_paths(repo, false)?);
-      staged
-    }
-    PromptFilter::Unstaged => crate::git::list_unstaged_paths(repo, false)?,
-    PromptFilter::Staged => crate::git::list_staged_paths(repo)?,
-    PromptFilter::Base => {
-      let Some(base) = base_ref.clone() else {
-        anyhow::bail!("--filter base requires --base <ref>")
-      };
-      crate::git::list_base_paths(repo, &base)?
-    }
-  };
-
-  paths.sort();
-  paths.dedup();
+  let paths = view_paths(repo, filter, base_ref.as_deref())?;
 
   if head.is_some() {
     crate::add_cmd::sync_draft_notes(repo, notes_ref, base_ref.as_deref())?;
@@ -39,6 +27,26 @@ pub fn run(
   let mut review = crate::add_cmd::load_review_from_draft(repo, notes_ref, base_ref.as_deref())?;
   let path_set: std::collections::HashSet<_> = paths.iter().cloned().collect();
   review.files.retain(|path, _| path_set.contains(path));
+  if cmd.include_outdated {
+    // The draft only carries live comments; outdated ones live in the notes alone.
+    for path in &paths {
+      let Some(notes_file) = crate::add_cmd::load_file_review(repo, notes_ref, path)? else {
+        continue;
+      };
+      let outdated: Vec<_> = notes_file
+        .comments
+        .into_iter()
+        .filter(|(_, comment)| comment.outdated && !comment.resolved)
+        .collect();
+      if outdated.is_empty() {
+        continue;
+      }
+      let file = review.files.entry(path.clone()).or_default();
+      for (key, comment) in outdated {
+        file.comments.entry(key).or_insert(comment);
+      }
+    }
+  }
 
   let diff_context = prompt_diff_context(repo);
   let view_order = prompt_view_order(filter, base_ref.is_some());
@@ -47,12 +55,35 @@ pub fn run(
     .and_then(|b| crate::git::merge_base_tree(repo, b).ok());
   let mut resolver = LineSnippetResolver::new(repo, base_tree, diff_context, view_order);
 
-  let prompt = crate::review::render_prompt(&review, |path, key| {
-    resolver.snippet(path, key).map(|code| PromptSnippet {
-      code,
-      lang: language_for_path(path),
-    })
-  });
+  let prompt = match (cmd.format, template.as_deref()) {
+    (PromptFormat::Json | PromptFormat::Xml, _) => {
+      let ctx = crate::prompt_template::build_context(
+        &review,
+        cmd.include_outdated,
+        |path, key, last| resolver.snippet_range(path, key, last),
+        |_| None,
+      );
+      match cmd.format {
+        PromptFormat::Xml => render_xml(&ctx),
+        _ => serde_json::to_string_pretty(&ctx).context("serialize prompt")?,
+      }
+    }
+    (PromptFormat::Markdown, Some(name)) => {
+      crate::prompt_template::render(repo, name, &review, cmd.include_outdated, &mut resolver)?
+    }
+    (PromptFormat::Markdown, None) => crate::review::render_prompt_with_outdated(
+      &review,
+      cmd.include_outdated,
+      |path, key, last| {
+        resolver
+          .snippet_range(path, key, last)
+          .map(|code| PromptSnippet {
+            code,
+            lang: language_for_path(path),
+          })
+      },
+    ),
+  };
   print!("{prompt}");
   if !prompt.ends_with('\n') {
     println!();
@@ -60,6 +91,116 @@ pub fn run(
   Ok(())
 }
 
+/// The prompt as XML: one `<file>` per file, one `<comment>` per thread, text in elements.
+fn render_xml(ctx: &PromptContext) -> String {
+  let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<review>\n");
+  for file in &ctx.files {
+    out.push_str(&format!(
+      "  <file path=\"{}\" language=\"{}\">\n",
+      xml_escape(&file.path),
+      xml_escape(&file.language)
+    ));
+    for comment in file.file_comment.iter().chain(&file.comments) {
+      push_xml_comment(&mut out, comment, &file.language);
+    }
+    out.push_str("  </file>\n");
+  }
+  out.push_str("</review>\n");
+  out
+}
+
+fn push_xml_comment(out: &mut String, comment: &CommentContext, language: &str) {
+  let mut attrs = format!("id=\"{}\"", comment.id);
+  if let (Some(side), Some(line)) = (comment.side, comment.line) {
+    attrs.push_str(&format!(" side=\"{}\" line=\"{line}\"", side.label()));
+    if let Some(end) = comment.end_line.filter(|end| *end > line) {
+      attrs.push_str(&format!(" end-line=\"{end}\""));
+    }
+  } else {
+    attrs.push_str(" scope=\"file\"");
+  }
+  if let Some(severity) = comment.severity {
+    attrs.push_str(&format!(" severity=\"{}\"", severity.as_str()));
+  }
+  if !comment.labels.is_empty() {
+    attrs.push_str(&format!(
+      " labels=\"{}\"",
+      xml_escape(&comment.labels.join(" "))
+    ));
+  }
+  if comment.outdated {
+    attrs.push_str(" outdated=\"true\"");
+  }
+  out.push_str(&format!("    <comment {attrs}>\n"));
+  out.push_str(&format!(
+    "      <body>{}</body>\n",
+    xml_escape(&comment.body)
+  ));
+  if let Some(snippet) = &comment.snippet {
+    out.push_str(&format!(
+      "      <snippet language=\"{}\">{}</snippet>\n",
+      xml_escape(language),
+      xml_escape(snippet)
+    ));
+  }
+  if let Some(suggestion) = &comment.suggestion {
+    out.push_str(&format!(
+      "      <suggestion>{}</suggestion>\n",
+      xml_escape(suggestion)
+    ));
+  }
+  for reply in &comment.replies {
+    out.push_str(&format!(
+      "      <reply author=\"{}\" at=\"{}\">{}</reply>\n",
+      xml_escape(&reply.author),
+      reply.at,
+      xml_escape(&reply.body)
+    ));
+  }
+  out.push_str("    </comment>\n");
+}
+
+fn xml_escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '"' => out.push_str("&quot;"),
+      _ => out.push(c),
+    }
+  }
+  out
+}
+
+/// Changed paths for `filter`, sorted and deduplicated.
+pub(crate) fn view_paths(
+  repo: &gix::Repository,
+  filter: PromptFilter,
+  base_ref: Option<&str>,
+) -> Result<Vec<String>> {
+  let mut paths = match filter {
+    PromptFilter::All => {
+      let mut staged = crate::git::list_staged_paths(repo)?;
+      staged.extend(crate::git::list_unstaged_paths(repo, false)?);
+      staged
+    }
+    PromptFilter::Unstaged => crate::git::list_unstaged_paths(repo, false)?,
+    PromptFilter::Staged => crate::git::list_staged_paths(repo)?,
+    PromptFilter::Base => {
+      let Some(base) = base_ref else {
+        anyhow::bail!("--filter base requires --base <ref>")
+      };
+      crate::git::list_base_paths(repo, base)?
+    }
+  };
+
+  paths.sort();
+  paths.dedup();
+  Ok(paths)
+}
+
 const DEFAULT_DIFF_CONTEXT: u32 = 3;
 const MIN_DIFF_CONTEXT: u32 = 0;
 const MAX_DIFF_CONTEXT: u32 = 20;
@@ -94,3 +235,37 @@ fn prompt_diff_context(repo: &gix::Repository) -> u32 {
     .map(|v| v.clamp(MIN_DIFF_CONTEXT, MAX_DIFF_CONTEXT))
     .unwrap_or(DEFAULT_DIFF_CONTEXT)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::review::{LineSide, Review};
+
+  #[test]
+  fn render_xml_escapes_and_ids_comments() {
+    let mut review = Review::new();
+    review.set_file_comment("src/a&b.rs", "Overall".to_string());
+    review.set_line_comment("src/a&b.rs", LineSide::New, 4, "Use <T>".to_string());
+    let ctx = crate::prompt_template::build_context(
+      &review,
+      false,
+      |_, _, _| Some("let x = \"y\";".to_string()),
+      |_| None,
+    );
+    let line_id = &ctx.files[0].comments[0].id;
+    assert_eq!(line_id.len(), 12);
+
+    let xml = render_xml(&ctx);
+    assert!(xml.contains("<file path=\"src/a&amp;b.rs\" language=\"rust\">"));
+    assert!(xml.contains("scope=\"file\""));
+    assert!(xml.contains(&format!(
+      "<comment id=\"{line_id}\" side=\"new\" line=\"4\">"
+    )));
+    assert!(xml.contains("<body>Use &lt;T&gt;</body>"));
+    assert!(xml.contains("<snippet language=\"rust\">let x = &quot;y&quot;;</snippet>"));
+
+    let json = serde_json::to_value(&ctx).expect("serialize");
+    assert_eq!(json["files"][0]["comments"][0]["id"], line_id.as_str());
+    assert!(json["files"][0].get("hunks").is_none());
+  }
+}
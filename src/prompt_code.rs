@@ -10,6 +10,15 @@ struct LineContentMap {
   after: Option<Vec<String>>,
 }
 
+impl LineContentMap {
+  fn side(&self, side: LineSide) -> Option<&Vec<String>> {
+    match side {
+      LineSide::Old => self.before.as_ref(),
+      LineSide::New => self.after.as_ref(),
+    }
+  }
+}
+
 pub struct LineSnippetResolver<'a> {
   repo: &'a gix::Repository,
   base_tree: Option<gix::Tree<'a>>,
@@ -34,17 +43,14 @@ impl<'a> LineSnippetResolver<'a> {
     }
   }
 
-  pub fn snippet(&mut self, path: &str, key: LineKey) -> Option<String> {
+  /// The code of `key.line..=last_line` plus context, from the first view that has it.
+  pub fn snippet_range(&mut self, path: &str, key: LineKey, last_line: u32) -> Option<String> {
     let views = self.view_order.clone();
     let context_lines = self.context_lines;
     for view in views {
       if let Some(map) = self.map_for_view_path(view, path) {
-        let lines = match key.side {
-          LineSide::Old => map.before.as_ref(),
-          LineSide::New => map.after.as_ref(),
-        };
-        if let Some(lines) = lines
-          && let Some(snippet) = snippet_from_lines(lines, key.line, context_lines)
+        if let Some(lines) = map.side(key.side)
+          && let Some(snippet) = snippet_from_lines(lines, key.line, last_line, context_lines)
         {
           return Some(snippet);
         }
@@ -53,6 +59,50 @@ impl<'a> LineSnippetResolver<'a> {
     None
   }
 
+  /// The current lines of `path` on `side`, from the first view in order that has the file.
+  ///
+  /// Comment anchors are re-resolved against these lines.
+  pub fn lines(&mut self, path: &str, side: LineSide) -> Option<&[String]> {
+    let views = self.view_order.clone();
+    let view = views.into_iter().find(|view| {
+      self
+        .map_for_view_path(*view, path)
+        .is_some_and(|map| map.side(side).is_some())
+    })?;
+    self
+      .map_for_view_path(view, path)
+      .and_then(|map| map.side(side))
+      .map(Vec::as_slice)
+  }
+
+  /// Unified diff hunks (with `@@` headers) for `path`, from the first view in order that
+  /// changes it.
+  pub fn hunks(&mut self, path: &str) -> Vec<String> {
+    let views = self.view_order.clone();
+    let context_lines = self.context_lines as usize;
+    for view in views {
+      let Some(map) = self.map_for_view_path(view, path) else {
+        continue;
+      };
+      if map.before.is_none() && map.after.is_none() {
+        continue;
+      }
+      let before = map.before.as_deref().map(join_lines).unwrap_or_default();
+      let after = map.after.as_deref().map(join_lines).unwrap_or_default();
+      let diff = similar::TextDiff::from_lines(&before, &after);
+      let hunks: Vec<String> = diff
+        .unified_diff()
+        .context_radius(context_lines)
+        .iter_hunks()
+        .map(|hunk| hunk.to_string())
+        .collect();
+      if !hunks.is_empty() {
+        return hunks;
+      }
+    }
+    Vec::new()
+  }
+
   fn map_for_view_path(&mut self, view: ViewKind, path: &str) -> Option<&LineContentMap> {
     let cache_key = (view, path.to_string());
     if !self.cache.contains_key(&cache_key) {
@@ -89,6 +139,8 @@ impl<'a> LineSnippetResolver<'a> {
           .flatten();
         (before, crate::git::try_read_head(self.repo, path)?)
       }
+      // Commit and range comments are never part of the worktree prompt.
+      ViewKind::Commit | ViewKind::Range => return Ok(LineContentMap::default()),
     };
 
     Ok(LineContentMap {
@@ -102,7 +154,15 @@ fn split_lines(content: &str) -> Vec<String> {
   content.lines().map(|line| line.to_string()).collect()
 }
 
-fn snippet_from_lines(lines: &[String], line: u32, context: u32) -> Option<String> {
+fn join_lines(lines: &[String]) -> String {
+  let mut out = lines.join("\n");
+  if !out.is_empty() {
+    out.push('\n');
+  }
+  out
+}
+
+fn snippet_from_lines(lines: &[String], line: u32, last_line: u32, context: u32) -> Option<String> {
   if line == 0 {
     return None;
   }
@@ -110,9 +170,11 @@ fn snippet_from_lines(lines: &[String], line: u32, context: u32) -> Option<Strin
   if idx >= lines.len() {
     return None;
   }
+  // A range running past the end of the file is clamped rather than dropped.
+  let last_idx = (last_line.max(line) - 1) as usize;
   let context = context as usize;
   let start = idx.saturating_sub(context);
-  let end = (idx + context + 1).min(lines.len());
+  let end = (last_idx + context + 1).min(lines.len());
   Some(lines[start..end].join("\n"))
 }
 
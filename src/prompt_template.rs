@@ -0,0 +1,242 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::prompt_code::{LineSnippetResolver, language_for_path};
+use crate::review::{Comment, LineKey, LineSide, Reply, Review, Severity};
+
+const TEMPLATE_DIR: &str = "templates";
+const TEMPLATE_EXT: &str = "j2";
+
+/// What a prompt template is rendered with.
+#[derive(Debug, Serialize)]
+pub(crate) struct PromptContext {
+  pub(crate) files: Vec<FileContext>,
+  /// Distinct languages of the files, sorted.
+  pub(crate) languages: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FileContext {
+  pub(crate) path: String,
+  pub(crate) language: String,
+  pub(crate) file_comment: Option<CommentContext>,
+  pub(crate) comments: Vec<CommentContext>,
+  /// Unified diff hunks for the file, each starting with its `@@` header. Templates always get
+  /// them; the structured prompt formats leave them out.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) hunks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CommentContext {
+  /// Stable id (see [`Comment::id`]) agents can report back.
+  pub(crate) id: String,
+  /// `None` for the file comment.
+  pub(crate) side: Option<LineSide>,
+  pub(crate) line: Option<u32>,
+  pub(crate) end_line: Option<u32>,
+  pub(crate) body: String,
+  pub(crate) severity: Option<Severity>,
+  pub(crate) labels: Vec<String>,
+  pub(crate) outdated: bool,
+  pub(crate) author: Option<String>,
+  pub(crate) replies: Vec<Reply>,
+  /// The suggested change as a diff of the lines it replaces.
+  pub(crate) suggestion: Option<String>,
+  /// The commented lines plus context; `None` when the code is gone.
+  pub(crate) snippet: Option<String>,
+}
+
+impl CommentContext {
+  fn new(path: &str, key: Option<LineKey>, comment: &Comment, snippet: Option<String>) -> Self {
+    Self {
+      id: comment.id(path, key),
+      side: key.map(|k| k.side),
+      line: key.map(|k| k.line),
+      end_line: key.map(|k| comment.last_line(k)),
+      body: comment.body.trim_end().to_string(),
+      severity: comment.severity,
+      labels: comment.labels.clone(),
+      outdated: comment.outdated,
+      author: comment.author.clone(),
+      replies: comment.replies.clone(),
+      suggestion: comment.suggestion.as_ref().map(|s| s.preview()),
+      snippet,
+    }
+  }
+}
+
+/// Collects the open comments of `review` the way [`crate::review::render_prompt`] lists them.
+pub(crate) fn build_context<S, H>(
+  review: &Review,
+  include_outdated: bool,
+  mut snippet: S,
+  mut hunks: H,
+) -> PromptContext
+where
+  S: FnMut(&str, LineKey, u32) -> Option<String>,
+  H: FnMut(&str) -> Option<Vec<String>>,
+{
+  let mut files = Vec::new();
+  for (path, file) in &review.files {
+    let file_comment = crate::review::prompt_file_comment(file);
+    let line_comments = crate::review::prompt_line_comments(file, include_outdated);
+    if file_comment.is_none() && line_comments.is_empty() {
+      continue;
+    }
+    let comments = line_comments
+      .into_iter()
+      .map(|(key, comment)| {
+        let code = (!comment.outdated)
+          .then(|| snippet(path, *key, comment.last_line(*key)))
+          .flatten();
+        CommentContext::new(path, Some(*key), comment, code)
+      })
+      .collect();
+    files.push(FileContext {
+      path: path.clone(),
+      language: language_for_path(path),
+      file_comment: file_comment.map(|c| CommentContext::new(path, None, c, None)),
+      comments,
+      hunks: hunks(path),
+    });
+  }
+  let languages: BTreeSet<_> = files.iter().map(|f| f.language.clone()).collect();
+  PromptContext {
+    files,
+    languages: languages.into_iter().collect(),
+  }
+}
+
+/// Renders `review` with the template called `name`, pulling snippets and hunks from `resolver`.
+pub(crate) fn render(
+  repo: &gix::Repository,
+  name: &str,
+  review: &Review,
+  include_outdated: bool,
+  resolver: &mut LineSnippetResolver<'_>,
+) -> Result<String> {
+  let source = load(repo, name)?;
+  let resolver = std::cell::RefCell::new(resolver);
+  let ctx = build_context(
+    review,
+    include_outdated,
+    |path, key, last| resolver.borrow_mut().snippet_range(path, key, last),
+    |path| Some(resolver.borrow_mut().hunks(path)),
+  );
+  render_source(&source, &ctx).with_context(|| format!("render prompt template '{name}'"))
+}
+
+/// Renders a template source. Besides the Jinja builtins, templates get a `fence(lang)` filter
+/// that wraps text in a code fence long enough for its contents.
+pub(crate) fn render_source(source: &str, ctx: &PromptContext) -> Result<String> {
+  let mut env = minijinja::Environment::new();
+  env.set_trim_blocks(true);
+  env.set_lstrip_blocks(true);
+  env.set_keep_trailing_newline(true);
+  env.add_filter("fence", |text: String, lang: Option<String>| {
+    crate::review::fenced_block(&text, lang.as_deref().unwrap_or(""))
+  });
+  env.add_template("prompt", source)?;
+  Ok(env.get_template("prompt")?.render(ctx)?)
+}
+
+/// Reads the template `name`: a path to a file, or `<name>.j2` (or `<name>`) in the repo's
+/// `.config/remark/templates` or the user's `remark/templates` config dir, in that order.
+fn load(repo: &gix::Repository, name: &str) -> Result<String> {
+  let path = find(repo, name).with_context(|| {
+    let dirs = template_dirs(repo)
+      .iter()
+      .map(|d| d.display().to_string())
+      .collect::<Vec<_>>()
+      .join(", ");
+    format!("prompt template '{name}' not found (looked in {dirs})")
+  })?;
+  std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))
+}
+
+fn find(repo: &gix::Repository, name: &str) -> Option<PathBuf> {
+  let direct = Path::new(name);
+  if direct.components().count() > 1 && direct.is_file() {
+    return Some(direct.to_path_buf());
+  }
+  template_dirs(repo).into_iter().find_map(|dir| {
+    [dir.join(format!("{name}.{TEMPLATE_EXT}")), dir.join(name)]
+      .into_iter()
+      .find(|path| path.is_file())
+  })
+}
+
+fn template_dirs(repo: &gix::Repository) -> Vec<PathBuf> {
+  let mut dirs = Vec::new();
+  if let Some(wd) = repo.workdir() {
+    dirs.push(wd.join(".config").join("remark").join(TEMPLATE_DIR));
+  }
+  if let Some(dir) = dirs::config_dir() {
+    dirs.push(dir.join("remark").join(TEMPLATE_DIR));
+  }
+  dirs
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render_source_exposes_files_comments_and_snippets() {
+    let mut review = Review::new();
+    review.set_file_comment("src/lib.rs", "Split this module".to_string());
+    review.set_line_comment(
+      "src/lib.rs",
+      LineSide::New,
+      3,
+      "Avoid ```unwrap```".to_string(),
+    );
+    review.set_line_comment("src/lib.rs", LineSide::New, 9, "Already fixed".to_string());
+    if let Some(file) = review.files.get_mut("src/lib.rs") {
+      for comment in file
+        .comments
+        .values_mut()
+        .filter(|c| c.body == "Already fixed")
+      {
+        comment.resolved = true;
+      }
+    }
+
+    let ctx = build_context(
+      &review,
+      false,
+      |_, key, _| Some(format!("code at {}", key.line)),
+      |_| Some(vec!["@@ -1 +1 @@\n-a\n+b\n".to_string()]),
+    );
+    let template = "\
+{% for file in files %}
+# {{ file.path }} ({{ file.language }}, {{ file.hunks | length }} hunk)
+{{ file.file_comment.body }}
+{% for c in file.comments %}
+* line {{ c.line }}: {{ c.snippet }}
+{{ c.body | fence('text') }}
+{% endfor %}
+{% endfor %}
+languages: {{ languages | join(', ') }}
+";
+    let out = render_source(template, &ctx).expect("render");
+    assert_eq!(
+      out,
+      "# src/lib.rs (rust, 1 hunk)\n\
+Split this module\n\
+* line 3: code at 3\n\
+````text\nAvoid ```unwrap```\n````\n\n\
+languages: rust\n"
+    );
+  }
+
+  #[test]
+  fn render_source_reports_template_errors() {
+    let ctx = build_context(&Review::new(), false, |_, _, _| None, |_| None);
+    assert!(render_source("{% for %}", &ctx).is_err());
+  }
+}
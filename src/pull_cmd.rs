@@ -0,0 +1,53 @@
+use anyhow::{Context, Result};
+
+use crate::config::SyncCli;
+use crate::notes::MergeOutcome;
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: SyncCli,
+) -> Result<()> {
+  let remote = resolve_remote(repo, cmd.remote)?;
+  let outcome = pull_notes(repo, &remote, notes_ref)?;
+  match outcome {
+    None => println!("{remote} has no {notes_ref}."),
+    Some(MergeOutcome::UpToDate) => println!("{notes_ref} is up to date with {remote}."),
+    Some(MergeOutcome::FastForward) => println!("Fast-forwarded {notes_ref} from {remote}."),
+    Some(MergeOutcome::Merged) => println!("Merged {remote}'s {notes_ref} into local notes."),
+  }
+
+  if matches!(
+    outcome,
+    Some(MergeOutcome::FastForward | MergeOutcome::Merged)
+  ) && crate::git::head_commit_oid(repo).is_ok()
+  {
+    crate::add_cmd::sync_draft_notes(repo, notes_ref, base_ref.as_deref())?;
+  }
+  Ok(())
+}
+
+pub(crate) fn resolve_remote(repo: &gix::Repository, remote: Option<String>) -> Result<String> {
+  match remote {
+    Some(remote) => Ok(remote),
+    None => crate::git::select_notes_remote(repo)?
+      .context("no git remote configured (pass --remote <name>)"),
+  }
+}
+
+/// Fetches `notes_ref` from `remote` and merges it into the local notes, file review by file review.
+pub(crate) fn pull_notes(
+  repo: &gix::Repository,
+  remote: &str,
+  notes_ref: &str,
+) -> Result<Option<MergeOutcome>> {
+  let Some(fetched) = crate::git::fetch_notes_ref(repo, remote, notes_ref)
+    .with_context(|| format!("fetch {notes_ref} from {remote}"))?
+  else {
+    return Ok(None);
+  };
+  let outcome = crate::notes::merge(repo, notes_ref, fetched, crate::review::merge_note_text)
+    .with_context(|| format!("merge {notes_ref} from {remote}"))?;
+  Ok(Some(outcome))
+}
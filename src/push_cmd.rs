@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+
+use crate::config::SyncCli;
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: SyncCli,
+) -> Result<()> {
+  let remote = crate::pull_cmd::resolve_remote(repo, cmd.remote)?;
+  if repo
+    .try_find_reference(notes_ref)
+    .context("find notes ref")?
+    .is_none()
+  {
+    anyhow::bail!("no local notes under {notes_ref}");
+  }
+
+  // Fold in whatever the remote has first so the push is a fast-forward.
+  if let Some(outcome) = crate::pull_cmd::pull_notes(repo, &remote, notes_ref)?
+    && outcome != crate::notes::MergeOutcome::UpToDate
+    && crate::git::head_commit_oid(repo).is_ok()
+  {
+    crate::add_cmd::sync_draft_notes(repo, notes_ref, base_ref.as_deref())?;
+  }
+
+  crate::git::push_notes_ref(repo, &remote, notes_ref)
+    .with_context(|| format!("push {notes_ref} to {remote}"))?;
+  println!("Pushed {notes_ref} to {remote}.");
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::review::{Comment, FileReview, LineKey, LineSide};
+  use crate::test_support::run_git;
+  use gix::ObjectId;
+
+  fn init_clone(remote: &std::path::Path) -> (tempfile::TempDir, gix::Repository) {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    run_git(
+      td.path(),
+      &[
+        "remote",
+        "add",
+        "origin",
+        remote.to_str().expect("utf8 path"),
+      ],
+    );
+    (td, repo)
+  }
+
+  fn add_line_comment(repo: &gix::Repository, target: &ObjectId, line: u32, body: &str) {
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    let mut file = crate::notes::read(repo, notes_ref, target)
+      .expect("read note")
+      .as_deref()
+      .and_then(crate::review::decode_file_note)
+      .unwrap_or_default();
+    file.comments.insert(
+      LineKey {
+        side: LineSide::New,
+        line,
+      },
+      Comment {
+        body: body.to_string(),
+        ..Default::default()
+      },
+    );
+    let note = crate::review::encode_file_note(&file);
+    crate::notes::write(repo, notes_ref, target, Some(&note)).expect("write note");
+  }
+
+  fn read_file(repo: &gix::Repository, target: &ObjectId) -> FileReview {
+    crate::notes::read(repo, crate::git::DEFAULT_NOTES_REF, target)
+      .expect("read note")
+      .as_deref()
+      .and_then(crate::review::decode_file_note)
+      .expect("file review")
+  }
+
+  #[test]
+  fn push_and_pull_merge_divergent_notes() {
+    let remote = tempfile::tempdir().expect("tempdir");
+    run_git(remote.path(), &["init", "--bare", "--quiet"]);
+    let (_a_td, a) = init_clone(remote.path());
+    let (_b_td, b) = init_clone(remote.path());
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+    let shared = ObjectId::from_hex(b"1111111111111111111111111111111111111111").expect("oid");
+    let only_b = ObjectId::from_hex(b"2222222222222222222222222222222222222222").expect("oid");
+
+    add_line_comment(&a, &shared, 1, "from a");
+    run(&a, notes_ref, None, SyncCli::default()).expect("push a");
+
+    add_line_comment(&b, &shared, 2, "from b");
+    add_line_comment(&b, &only_b, 1, "only b");
+    run(&b, notes_ref, None, SyncCli::default()).expect("push b merges remote notes first");
+
+    crate::pull_cmd::run(&a, notes_ref, None, SyncCli::default()).expect("pull a");
+
+    let merged = read_file(&a, &shared);
+    let bodies: Vec<&str> = merged.comments.values().map(|c| c.body.as_str()).collect();
+    assert_eq!(bodies, vec!["from a", "from b"]);
+    assert_eq!(
+      read_file(&a, &only_b)
+        .comments
+        .values()
+        .next()
+        .map(|c| c.body.as_str()),
+      Some("only b")
+    );
+  }
+}
@@ -0,0 +1,726 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use gix::ObjectId;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+use crate::git::ViewKind;
+use crate::review::{FileReview, LineKey, LineSide};
+
+// Note keys include the HEAD commit, so notes written before a commit, amend or rebase are not
+// found under the new HEAD. Re-anchoring looks them up under previous HEADs, remaps their line
+// keys through the content diff and copies them to the current key. The notes under the old keys
+// stay, so tools reading by an older HEAD still find them.
+
+const ANCHOR_DIR: &str = "remark";
+const ANCHOR_FILENAME: &str = "anchors.json";
+const MAX_PREVIOUS_HEADS: usize = 16;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AnchorState {
+  /// Last HEAD the notes of each notes ref were anchored to.
+  #[serde(default)]
+  heads: BTreeMap<String, String>,
+  /// Per notes ref, the paths whose notes were carried to each recent HEAD, oldest first. A path
+  /// already carried to HEAD is skipped, so coming back to a HEAD doesn't merge the older copies
+  /// into its notes again.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  carried: BTreeMap<String, Vec<CarriedPaths>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CarriedPaths {
+  head: String,
+  paths: BTreeSet<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ReanchorReport {
+  pub(crate) copied_notes: usize,
+  pub(crate) outdated_comments: usize,
+}
+
+/// Copies notes written under earlier HEAD commits to the current HEAD. Each file's note is
+/// taken from the most recent earlier HEAD that has one.
+///
+/// This is a no-op while HEAD stays where it was last anchored.
+pub(crate) fn reanchor_notes(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<&str>,
+) -> Result<ReanchorReport> {
+  let mut report = ReanchorReport::default();
+  let Ok(head) = crate::git::head_commit_oid(repo) else {
+    return Ok(report);
+  };
+
+  let state_path = repo.path().join(ANCHOR_DIR).join(ANCHOR_FILENAME);
+  let cache_key = (state_path.clone(), notes_ref.to_string());
+  if anchored_heads().get(&cache_key) == Some(&head) {
+    return Ok(report);
+  }
+  let mut state = load_anchor_state(&state_path);
+  let last_head = state
+    .heads
+    .get(notes_ref)
+    .and_then(|hex| ObjectId::from_hex(hex.as_bytes()).ok());
+  if last_head == Some(head) {
+    anchored_heads().insert(cache_key, head);
+    return Ok(report);
+  }
+
+  let head_hex = head.to_string();
+  let mut history = state.carried.remove(notes_ref).unwrap_or_default();
+  let mut current = match history.iter().position(|c| c.head == head_hex) {
+    Some(i) => history.remove(i),
+    None => CarriedPaths {
+      head: head_hex.clone(),
+      paths: BTreeSet::new(),
+    },
+  };
+  let carried_before = std::mem::take(&mut current.paths);
+  let targets: BTreeSet<ObjectId> = crate::notes::list(repo, notes_ref)?.into_keys().collect();
+  let mut tx = crate::notes::Transaction::new(repo, notes_ref)?;
+  if !targets.is_empty() {
+    // The last anchored HEAD may be on another branch now; only its own history counts.
+    let mut candidates: Vec<ObjectId> = last_head
+      .filter(|last| crate::git::is_ancestor(repo, *last, head))
+      .into_iter()
+      .collect();
+    for oid in crate::git::previous_head_commits(repo, head, MAX_PREVIOUS_HEADS) {
+      if !candidates.contains(&oid) {
+        candidates.push(oid);
+      }
+    }
+    candidates.retain(|oid| *oid != head);
+
+    let head_tree = crate::git::commit_tree(repo, head)?;
+    let head_base_tree = base_ref.and_then(|b| crate::git::merge_base_tree(repo, b).ok());
+    let mut paths = BTreeSet::new();
+    paths.extend(crate::add_cmd::list_tracked_paths(repo)?);
+    // (path, view) pairs already copied from a more recent HEAD.
+    let mut carried = HashSet::new();
+
+    for old_head in candidates {
+      let Ok(old_tree) = crate::git::commit_tree(repo, old_head) else {
+        continue;
+      };
+      let old_base_tree =
+        base_ref.and_then(|b| crate::git::merge_base_tree_for(repo, old_head, b).ok());
+      let mut candidate_paths = paths.clone();
+      candidate_paths.extend(crate::git::changed_paths_between(
+        repo, &old_tree, &head_tree,
+      )?);
+
+      let trees = AnchorTrees {
+        old_tree: &old_tree,
+        new_tree: &head_tree,
+        old_base_tree: old_base_tree.as_ref(),
+        new_base_tree: head_base_tree.as_ref(),
+      };
+      for path in &candidate_paths {
+        if carried_before.contains(path) {
+          continue;
+        }
+        for view in anchored_views(base_ref.is_some()) {
+          if carried.contains(&(path.clone(), view)) {
+            continue;
+          }
+          let base_for_key = match view {
+            ViewKind::Base => base_ref,
+            _ => None,
+          };
+          let old_key = crate::git::note_file_key_oid(repo, old_head, view, base_for_key, path)?;
+          if !targets.contains(&old_key) {
+            continue;
+          }
+          let note = tx
+            .read(&old_key)
+            .with_context(|| format!("read note for '{path}'"))?;
+          let Some(mut file) = note.as_deref().and_then(crate::review::decode_file_note) else {
+            continue;
+          };
+
+          let (old_map, new_map) = trees.line_maps(view, path)?;
+          let remapped = remap_file_review(&mut file, &old_map, &new_map);
+          report.outdated_comments += remapped.outdated;
+          for key in remapped.moved {
+            if let Some(comment) = file.comments.get_mut(&key)
+              && !comment.outdated
+              && comment.snippet_hash.is_some()
+            {
+              let last_line = comment.last_line(key);
+              comment.snippet_hash =
+                crate::add_cmd::current_snippet_hash(repo, base_ref, path, key, last_line);
+            }
+          }
+
+          let new_key = crate::git::note_file_key_oid(repo, head, view, base_for_key, path)?;
+          let existing = tx
+            .read(&new_key)
+            .with_context(|| format!("read note for '{path}'"))?
+            .as_deref()
+            .and_then(crate::review::decode_file_note);
+          let merged = match existing {
+            Some(mut existing) => {
+              crate::review::merge_file_review(&mut existing, file);
+              existing
+            }
+            None => file,
+          };
+          let note = crate::review::encode_file_note(&merged);
+          tx.upsert(&new_key, &note);
+          carried.insert((path.clone(), view));
+          current.paths.insert(path.clone());
+          report.copied_notes += 1;
+        }
+      }
+    }
+  }
+
+  tx.commit().context("write re-anchored notes")?;
+
+  state.heads.insert(notes_ref.to_string(), head_hex);
+  current.paths.extend(carried_before);
+  if !current.paths.is_empty() {
+    history.push(current);
+  }
+  let stale = history.len().saturating_sub(MAX_PREVIOUS_HEADS);
+  history.drain(..stale);
+  if !history.is_empty() {
+    state.carried.insert(notes_ref.to_string(), history);
+  }
+  write_anchor_state(&state_path, &state)?;
+  anchored_heads().insert(cache_key, head);
+  Ok(report)
+}
+
+type AnchorCacheKey = (std::path::PathBuf, String);
+
+/// HEAD each notes ref was last anchored to in this process, keyed by anchor state file, so the
+/// LSP's per-request reads skip the state file and reflog while HEAD stays put.
+fn anchored_heads() -> std::sync::MutexGuard<'static, HashMap<AnchorCacheKey, ObjectId>> {
+  static HEADS: OnceLock<Mutex<HashMap<AnchorCacheKey, ObjectId>>> = OnceLock::new();
+  HEADS
+    .get_or_init(Default::default)
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn anchored_views(include_base: bool) -> Vec<ViewKind> {
+  let mut views = vec![ViewKind::All, ViewKind::Staged, ViewKind::Unstaged];
+  if include_base {
+    views.push(ViewKind::Base);
+  }
+  views
+}
+
+fn load_anchor_state(path: &std::path::Path) -> AnchorState {
+  std::fs::read_to_string(path)
+    .ok()
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default()
+}
+
+fn write_anchor_state(path: &std::path::Path, state: &AnchorState) -> Result<()> {
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent).context("create anchor state directory")?;
+  }
+  let content = serde_json::to_string_pretty(state).context("serialize anchor state")?;
+  std::fs::write(path, content).context("write anchor state")?;
+  Ok(())
+}
+
+struct AnchorTrees<'a, 'repo> {
+  old_tree: &'a gix::Tree<'repo>,
+  new_tree: &'a gix::Tree<'repo>,
+  old_base_tree: Option<&'a gix::Tree<'repo>>,
+  new_base_tree: Option<&'a gix::Tree<'repo>>,
+}
+
+impl AnchorTrees<'_, '_> {
+  /// Line maps for the old and new side of `view` from the previous HEAD to the current one.
+  ///
+  /// Index and worktree content is not versioned with HEAD, so sides backed by them keep their
+  /// line numbers.
+  fn line_maps(&self, view: ViewKind, path: &str) -> Result<(LineMap, LineMap)> {
+    let head_map = || -> Result<LineMap> {
+      Ok(LineMap::between(
+        crate::git::try_read_tree(self.old_tree, path)?.as_deref(),
+        crate::git::try_read_tree(self.new_tree, path)?.as_deref(),
+      ))
+    };
+    match view {
+      ViewKind::All | ViewKind::Staged => Ok((head_map()?, LineMap::Identity)),
+      ViewKind::Unstaged => Ok((LineMap::Identity, LineMap::Identity)),
+      ViewKind::Base => {
+        let before = self
+          .old_base_tree
+          .map(|t| crate::git::try_read_tree(t, path))
+          .transpose()?
+          .flatten();
+        let after = self
+          .new_base_tree
+          .map(|t| crate::git::try_read_tree(t, path))
+          .transpose()?
+          .flatten();
+        Ok((
+          LineMap::between(before.as_deref(), after.as_deref()),
+          head_map()?,
+        ))
+      }
+      // Keyed to fixed commits rather than HEAD; never part of `anchored_views`.
+      ViewKind::Commit | ViewKind::Range => Ok((LineMap::Identity, LineMap::Identity)),
+    }
+  }
+}
+
+#[derive(Debug)]
+enum LineMap {
+  Identity,
+  /// 1-based old line -> 1-based new line for every unchanged line.
+  Lines(HashMap<u32, u32>),
+}
+
+impl LineMap {
+  fn between(before: Option<&str>, after: Option<&str>) -> Self {
+    let before = before.unwrap_or("");
+    let after = after.unwrap_or("");
+    if before == after {
+      return LineMap::Identity;
+    }
+
+    let diff = TextDiff::from_lines(before, after);
+    let mut lines = HashMap::new();
+    for op in diff.ops() {
+      for change in diff.iter_changes(op) {
+        if change.tag() == ChangeTag::Equal
+          && let (Some(old), Some(new)) = (change.old_index(), change.new_index())
+        {
+          lines.insert(old as u32 + 1, new as u32 + 1);
+        }
+      }
+    }
+    LineMap::Lines(lines)
+  }
+
+  fn map(&self, line: u32) -> Option<u32> {
+    match self {
+      LineMap::Identity => Some(line),
+      LineMap::Lines(lines) => lines.get(&line).copied(),
+    }
+  }
+
+  /// Where a line that could not be mapped ends up: right after the closest mapped line above.
+  fn fallback(&self, line: u32) -> u32 {
+    (1..line)
+      .rev()
+      .find_map(|prev| self.map(prev))
+      .map_or(1, |prev| prev + 1)
+  }
+}
+
+#[derive(Debug, Default)]
+struct Remapped {
+  /// Keys of live comments that now sit on a different line. Comments parked as outdated are left
+  /// out: their snippet hash must keep describing the code they were written against.
+  moved: Vec<LineKey>,
+  outdated: usize,
+}
+
+fn remap_file_review(file: &mut FileReview, old_map: &LineMap, new_map: &LineMap) -> Remapped {
+  let mut out = Remapped::default();
+  let mut unplaced = Vec::new();
+  let comments = std::mem::take(&mut file.comments);
+
+  for (key, mut comment) in comments {
+    let map = match key.side {
+      LineSide::Old => old_map,
+      LineSide::New => new_map,
+    };
+    match map.map(key.line) {
+      Some(line) if !file.comments.contains_key(&LineKey { line, ..key }) => {
+        let target = LineKey { line, ..key };
+        if target != key {
+          out.moved.push(target);
+        }
+        comment.end_line = remap_end(map, key.line, line, comment.end_line);
+        file.comments.insert(target, comment);
+      }
+      _ => unplaced.push((key, comment)),
+    }
+  }
+
+  // Comments whose line is gone are kept, flagged, and parked near where their code used to be.
+  for (key, mut comment) in unplaced {
+    let map = match key.side {
+      LineSide::Old => old_map,
+      LineSide::New => new_map,
+    };
+    let mut line = map.fallback(key.line);
+    while file.comments.contains_key(&LineKey { line, ..key }) {
+      line += 1;
+    }
+    let target = LineKey { line, ..key };
+    if !comment.outdated {
+      comment.outdated = true;
+      out.outdated += 1;
+    }
+    comment.end_line = remap_end(map, key.line, line, comment.end_line);
+    file.comments.insert(target, comment);
+  }
+
+  out
+}
+
+/// Moves the last line of a range comment along with its start, keeping the span length when the
+/// old end line no longer exists.
+fn remap_end(map: &LineMap, old_start: u32, new_start: u32, end: Option<u32>) -> Option<u32> {
+  let end = end.filter(|end| *end > old_start)?;
+  let mapped = map
+    .map(end)
+    .unwrap_or_else(|| new_start + (end - old_start));
+  (mapped > new_start).then_some(mapped)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::review::Comment;
+  use crate::test_support::run_git;
+
+  fn comment(body: &str) -> Comment {
+    Comment {
+      body: body.to_string(),
+      ..Default::default()
+    }
+  }
+
+  fn commit_file(dir: &std::path::Path, path: &str, contents: &str) {
+    std::fs::write(dir.join(path), contents).expect("write file");
+    run_git(dir, &["add", path]);
+    run_git(dir, &["commit", "--quiet", "--no-gpg-sign", "-m", "update"]);
+  }
+
+  #[test]
+  fn line_map_tracks_unchanged_lines() {
+    let map = LineMap::between(Some("a\nb\nc\n"), Some("x\na\nc\n"));
+    assert_eq!(map.map(1), Some(2));
+    assert_eq!(map.map(2), None);
+    assert_eq!(map.map(3), Some(3));
+    assert_eq!(map.fallback(2), 3);
+  }
+
+  #[test]
+  fn remap_keeps_unplaceable_comments_as_outdated() {
+    let mut file = FileReview::default();
+    file.comments.insert(
+      LineKey {
+        side: LineSide::Old,
+        line: 1,
+      },
+      comment("moves"),
+    );
+    file.comments.insert(
+      LineKey {
+        side: LineSide::Old,
+        line: 2,
+      },
+      comment("deleted"),
+    );
+    let old_map = LineMap::between(Some("a\nb\nc\n"), Some("x\na\nc\n"));
+
+    let remapped = remap_file_review(&mut file, &old_map, &LineMap::Identity);
+
+    assert_eq!(remapped.outdated, 1);
+    let moved = file
+      .comments
+      .get(&LineKey {
+        side: LineSide::Old,
+        line: 2,
+      })
+      .expect("moved comment");
+    assert_eq!(moved.body, "moves");
+    assert!(!moved.outdated);
+    let outdated = file
+      .comments
+      .get(&LineKey {
+        side: LineSide::Old,
+        line: 3,
+      })
+      .expect("outdated comment");
+    assert_eq!(outdated.body, "deleted");
+    assert!(outdated.outdated);
+  }
+
+  #[test]
+  fn remap_reports_only_live_comments_as_moved() {
+    let mut file = FileReview::default();
+    file.comments.insert(
+      LineKey {
+        side: LineSide::Old,
+        line: 1,
+      },
+      comment("moves"),
+    );
+    file.comments.insert(
+      LineKey {
+        side: LineSide::Old,
+        line: 2,
+      },
+      comment("deleted"),
+    );
+    let old_map = LineMap::between(Some("a\nb\nc\n"), Some("x\na\nc\n"));
+
+    let remapped = remap_file_review(&mut file, &old_map, &LineMap::Identity);
+
+    assert_eq!(
+      remapped.moved,
+      vec![LineKey {
+        side: LineSide::Old,
+        line: 2,
+      }]
+    );
+  }
+
+  #[test]
+  fn remap_moves_range_end_with_its_start() {
+    let mut file = FileReview::default();
+    file.comments.insert(
+      LineKey {
+        side: LineSide::New,
+        line: 1,
+      },
+      Comment {
+        end_line: Some(3),
+        ..comment("range")
+      },
+    );
+    let new_map = LineMap::between(Some("a\nb\nc\n"), Some("x\ny\na\nb\nc\n"));
+
+    remap_file_review(&mut file, &LineMap::Identity, &new_map);
+
+    let moved = file
+      .comments
+      .get(&LineKey {
+        side: LineSide::New,
+        line: 3,
+      })
+      .expect("moved range");
+    assert_eq!(moved.end_line, Some(5));
+  }
+
+  #[test]
+  fn reanchor_copies_notes_to_new_head() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let workdir = repo.workdir().expect("workdir").to_path_buf();
+    commit_file(&workdir, "lib.rs", "a\nb\nc\nd\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+
+    let old_head = crate::git::head_commit_oid(&repo).expect("head");
+    let mut file = FileReview {
+      reviewed: true,
+      ..Default::default()
+    };
+    file.comments.insert(
+      LineKey {
+        side: LineSide::Old,
+        line: 3,
+      },
+      comment("old side"),
+    );
+    file.comments.insert(
+      LineKey {
+        side: LineSide::New,
+        line: 4,
+      },
+      comment("new side"),
+    );
+    let old_key =
+      crate::git::note_file_key_oid(&repo, old_head, ViewKind::All, None, "lib.rs").expect("key");
+    let note = crate::review::encode_file_note(&file);
+    crate::notes::write(&repo, notes_ref, &old_key, Some(&note)).expect("write note");
+    reanchor_notes(&repo, notes_ref, None).expect("anchor initial head");
+
+    commit_file(&workdir, "lib.rs", "x\na\nb\nc\nd\n");
+    let report = reanchor_notes(&repo, notes_ref, None).expect("reanchor");
+    assert_eq!(report.copied_notes, 1);
+    assert_eq!(report.outdated_comments, 0);
+
+    let head = crate::git::head_commit_oid(&repo).expect("head");
+    let new_key =
+      crate::git::note_file_key_oid(&repo, head, ViewKind::All, None, "lib.rs").expect("key");
+    let moved = crate::notes::read(&repo, notes_ref, &new_key)
+      .expect("read note")
+      .as_deref()
+      .and_then(crate::review::decode_file_note)
+      .expect("moved note");
+    assert!(moved.reviewed);
+    assert_eq!(
+      moved
+        .comments
+        .get(&LineKey {
+          side: LineSide::Old,
+          line: 4,
+        })
+        .map(|c| c.body.as_str()),
+      Some("old side")
+    );
+    assert_eq!(
+      moved
+        .comments
+        .get(&LineKey {
+          side: LineSide::New,
+          line: 4,
+        })
+        .map(|c| c.body.as_str()),
+      Some("new side")
+    );
+    // The note stays under the old HEAD for tools that read by it.
+    assert_eq!(
+      crate::notes::read(&repo, notes_ref, &old_key).expect("read old note"),
+      Some(note)
+    );
+
+    let again = reanchor_notes(&repo, notes_ref, None).expect("reanchor again");
+    assert_eq!(again, ReanchorReport::default());
+  }
+
+  #[test]
+  fn reanchor_skips_paths_already_carried_to_head() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let workdir = repo.workdir().expect("workdir").to_path_buf();
+    commit_file(&workdir, "lib.rs", "a\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+
+    let old_head = crate::git::head_commit_oid(&repo).expect("head");
+    let old_key =
+      crate::git::note_file_key_oid(&repo, old_head, ViewKind::All, None, "lib.rs").expect("key");
+    let file = FileReview {
+      file_comment: Some(comment("fixed later")),
+      ..Default::default()
+    };
+    let note = crate::review::encode_file_note(&file);
+    crate::notes::write(&repo, notes_ref, &old_key, Some(&note)).expect("write note");
+    reanchor_notes(&repo, notes_ref, None).expect("anchor initial head");
+
+    commit_file(&workdir, "lib.rs", "a\nb\n");
+    let report = reanchor_notes(&repo, notes_ref, None).expect("reanchor");
+    assert_eq!(report.copied_notes, 1);
+
+    // The copy is deleted under the new HEAD, then HEAD leaves and comes back.
+    let head = crate::git::head_commit_oid(&repo).expect("head");
+    let new_key =
+      crate::git::note_file_key_oid(&repo, head, ViewKind::All, None, "lib.rs").expect("key");
+    crate::notes::write(&repo, notes_ref, &new_key, None).expect("delete note");
+    run_git(&workdir, &["checkout", "--quiet", "-b", "side", "HEAD~1"]);
+    reanchor_notes(&repo, notes_ref, None).expect("anchor side");
+    run_git(&workdir, &["checkout", "--quiet", "-"]);
+
+    let report = reanchor_notes(&repo, notes_ref, None).expect("reanchor back");
+    assert_eq!(report.copied_notes, 0);
+    assert!(
+      crate::notes::read(&repo, notes_ref, &new_key)
+        .expect("read note")
+        .is_none()
+    );
+  }
+
+  #[test]
+  fn reanchor_keeps_snippet_hash_of_outdated_comments() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let workdir = repo.workdir().expect("workdir").to_path_buf();
+    commit_file(&workdir, "lib.rs", "a\nb\nc\nd\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+
+    let old_head = crate::git::head_commit_oid(&repo).expect("head");
+    let mut file = FileReview::default();
+    file.comments.insert(
+      LineKey {
+        side: LineSide::New,
+        line: 2,
+      },
+      Comment {
+        snippet_hash: Some("original".to_string()),
+        ..comment("on b")
+      },
+    );
+    let old_key =
+      crate::git::note_file_key_oid(&repo, old_head, ViewKind::All, None, "lib.rs").expect("key");
+    let note = crate::review::encode_file_note(&file);
+    crate::notes::write(&repo, notes_ref, &old_key, Some(&note)).expect("write note");
+    reanchor_notes(&repo, notes_ref, None).expect("anchor initial head");
+
+    commit_file(&workdir, "lib.rs", "x\na\nc\nd\n");
+    let report = reanchor_notes(&repo, notes_ref, None).expect("reanchor");
+    assert_eq!(report.outdated_comments, 1);
+
+    let head = crate::git::head_commit_oid(&repo).expect("head");
+    let new_key =
+      crate::git::note_file_key_oid(&repo, head, ViewKind::All, None, "lib.rs").expect("key");
+    let moved = crate::notes::read(&repo, notes_ref, &new_key)
+      .expect("read note")
+      .as_deref()
+      .and_then(crate::review::decode_file_note)
+      .expect("moved note");
+    let (key, outdated) = moved.comments.iter().next().expect("outdated comment");
+    assert_ne!(key.line, 2);
+    assert!(outdated.outdated);
+    assert_eq!(outdated.snippet_hash.as_deref(), Some("original"));
+  }
+
+  #[test]
+  fn reanchor_leaves_other_branches_notes_alone() {
+    let td = tempfile::tempdir().expect("tempdir");
+    let repo = gix::init(td.path()).expect("init repo");
+    let workdir = repo.workdir().expect("workdir").to_path_buf();
+    commit_file(&workdir, "lib.rs", "a\n");
+    run_git(&workdir, &["branch", "other"]);
+    commit_file(&workdir, "lib.rs", "a\nb\n");
+    let notes_ref = crate::git::DEFAULT_NOTES_REF;
+
+    let head = crate::git::head_commit_oid(&repo).expect("head");
+    let key =
+      crate::git::note_file_key_oid(&repo, head, ViewKind::All, None, "lib.rs").expect("key");
+    let file = FileReview {
+      file_comment: Some(comment("only on this branch")),
+      ..Default::default()
+    };
+    let note = crate::review::encode_file_note(&file);
+    crate::notes::write(&repo, notes_ref, &key, Some(&note)).expect("write note");
+    reanchor_notes(&repo, notes_ref, None).expect("anchor initial head");
+
+    run_git(&workdir, &["checkout", "--quiet", "other"]);
+    let report = reanchor_notes(&repo, notes_ref, None).expect("reanchor");
+    assert_eq!(report.copied_notes, 0);
+    assert!(
+      crate::notes::read(&repo, notes_ref, &key)
+        .expect("read note")
+        .is_some()
+    );
+
+    // Amending replaces the commit in place, so its notes follow.
+    run_git(&workdir, &["checkout", "--quiet", "-"]);
+    reanchor_notes(&repo, notes_ref, None).expect("anchor branch head");
+    std::fs::write(workdir.join("lib.rs"), "a\nb\nc\n").expect("write file");
+    run_git(&workdir, &["add", "lib.rs"]);
+    run_git(
+      &workdir,
+      &[
+        "commit",
+        "--quiet",
+        "--no-gpg-sign",
+        "--amend",
+        "-m",
+        "amended",
+      ],
+    );
+    let report = reanchor_notes(&repo, notes_ref, None).expect("reanchor amend");
+    assert_eq!(report.copied_notes, 1);
+  }
+}
@@ -35,12 +35,24 @@ pub fn run(
     })
   };
 
+  let message = cmd.message.filter(|m| !m.trim().is_empty());
+  let commit = cmd
+    .commit
+    .as_deref()
+    .map(|rev| crate::git::resolve_commit_id(repo, rev).map(|id| id.to_string()))
+    .transpose()?;
+  let author = crate::git::author_identity(repo);
+  // Resolving an already resolved comment still records a new message or commit.
+  let records = message.is_some() || commit.is_some();
+
+  crate::reanchor::reanchor_notes(repo, notes_ref, base_ref.as_deref())?;
   let head = crate::git::head_commit_oid(repo)?;
   let mut views = vec![ViewKind::All, ViewKind::Staged, ViewKind::Unstaged];
   if base_ref.is_some() {
     views.push(ViewKind::Base);
   }
 
+  let mut tx = crate::notes::Transaction::new(repo, notes_ref)?;
   let mut changed_any = false;
   for view in views {
     let base_for_key = match view {
@@ -49,7 +61,7 @@ pub fn run(
     };
     let oid = crate::git::note_file_key_oid(repo, head, view, base_for_key, &file)
       .context("compute note key")?;
-    let note = crate::notes::read(repo, notes_ref, &oid).context("read file note")?;
+    let note = tx.read(&oid).context("read file note")?;
     let Some(note) = note.as_deref() else {
       continue;
     };
@@ -57,30 +69,25 @@ pub fn run(
       continue;
     };
 
-    let mut changed = false;
-    if let Some(key) = &line_key {
-      if let Some(c) = fr.comments.get_mut(key)
-        && c.resolved == cmd.unresolve
-      {
-        c.resolved = !cmd.unresolve;
-        changed = true;
-      }
-    } else if let Some(c) = fr.file_comment.as_mut()
-      && c.resolved == cmd.unresolve
-    {
-      c.resolved = !cmd.unresolve;
-      changed = true;
-    }
-
-    if !changed {
+    let Some(c) = fr.comment_mut(line_key) else {
       continue;
+    };
+    if cmd.unresolve {
+      if !c.resolved {
+        continue;
+      }
+      c.unresolve();
+    } else {
+      if c.resolved && !records {
+        continue;
+      }
+      c.resolve(author.clone(), message.clone(), commit.clone());
     }
 
-    if fr.file_comment.is_none() && fr.comments.is_empty() {
-      crate::notes::write(repo, notes_ref, &oid, None)?;
+    if fr.is_empty() {
+      tx.remove(&oid);
     } else {
-      let note = crate::review::encode_file_note(&fr);
-      crate::notes::write(repo, notes_ref, &oid, Some(&note))?;
+      tx.upsert(&oid, &crate::review::encode_file_note(&fr));
     }
     changed_any = true;
   }
@@ -89,6 +96,8 @@ pub fn run(
     anyhow::bail!("no matching comment found to resolve");
   }
 
-  crate::add_cmd::sync_draft_notes(repo, notes_ref, base_ref.as_deref())?;
+  let sync = crate::add_cmd::stage_draft_sync(&mut tx, repo, notes_ref, base_ref.as_deref())?;
+  tx.commit().context("write resolved state")?;
+  sync.finish(repo, notes_ref, base_ref.as_deref())?;
   Ok(())
 }
@@ -1,22 +1,499 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Comment {
   pub body: String,
   #[serde(default)]
   pub resolved: bool,
   #[serde(default, skip_serializing_if = "Option::is_none")]
   pub snippet_hash: Option<String>,
+  /// Last line (inclusive, same side as the key) of a range comment; unset for a single line.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub end_line: Option<u32>,
+  /// Set when re-anchoring could not place the comment on the current content.
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub outdated: bool,
+  /// Git identity (`Name <email>`) of whoever started the thread.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub author: Option<String>,
+  /// Unix timestamp (seconds) of when the thread was started.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<i64>,
+  /// Random salt picked when the thread was started, so threads started in the same second
+  /// by the same author still get distinct ids.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub nonce: Option<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub replies: Vec<Reply>,
+  /// Replacement proposed for the lines the comment covers (line comments only).
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub suggestion: Option<Suggestion>,
+  /// The code the comment was written about, used to follow it as the file changes.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub anchor: Option<Anchor>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub severity: Option<Severity>,
+  /// Free-form labels, without the leading `#`.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub labels: Vec<String>,
+  /// How the comment was addressed; only kept while it is resolved.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub resolution: Option<Resolution>,
+}
+
+/// Hex digits in [`Comment::id`].
+const COMMENT_ID_LEN: usize = 12;
+
+/// How much a comment matters, least first.
+#[derive(
+  Debug,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  PartialOrd,
+  Ord,
+  Hash,
+  Serialize,
+  Deserialize,
+  clap::ValueEnum
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Nit,
+  Suggestion,
+  Issue,
+  Blocker,
+}
+
+impl Severity {
+  pub const ALL: [Severity; 4] = [
+    Severity::Nit,
+    Severity::Suggestion,
+    Severity::Issue,
+    Severity::Blocker,
+  ];
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Severity::Nit => "nit",
+      Severity::Suggestion => "suggestion",
+      Severity::Issue => "issue",
+      Severity::Blocker => "blocker",
+    }
+  }
+
+  pub fn parse(s: &str) -> Option<Self> {
+    Self::ALL.into_iter().find(|v| v.as_str() == s)
+  }
+}
+
+/// A comment's severity and labels as written in the draft and the comment editor: a line of
+/// `[severity]` and `#label` tokens, e.g. `[issue] #perf #api`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags {
+  pub severity: Option<Severity>,
+  pub labels: Vec<String>,
+}
+
+impl Tags {
+  pub fn of(comment: &Comment) -> Self {
+    Self {
+      severity: comment.severity,
+      labels: comment.labels.clone(),
+    }
+  }
+
+  pub fn apply(self, comment: &mut Comment) {
+    comment.severity = self.severity;
+    comment.labels = self.labels;
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.severity.is_none() && self.labels.is_empty()
+  }
+
+  /// Parses a line made up only of tags; anything else yields `None`.
+  pub fn parse(line: &str) -> Option<Self> {
+    let mut tags = Self::default();
+    for token in line.split_whitespace() {
+      if let Some(severity) = token
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .and_then(Severity::parse)
+      {
+        tags.severity = Some(severity);
+      } else if let Some(label) = token.strip_prefix('#').filter(|l| is_label(l)) {
+        if !tags.labels.iter().any(|l| l == label) {
+          tags.labels.push(label.to_string());
+        }
+      } else {
+        return None;
+      }
+    }
+    (!tags.is_empty()).then_some(tags)
+  }
+}
+
+impl std::fmt::Display for Tags {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let severity = self.severity.map(|s| format!("[{}]", s.as_str()));
+    let labels = self.labels.iter().map(|l| format!("#{l}"));
+    let tokens: Vec<String> = severity.into_iter().chain(labels).collect();
+    f.write_str(&tokens.join(" "))
+  }
+}
+
+/// Labels start with a letter so issue references like `#123` stay part of the body.
+fn is_label(label: &str) -> bool {
+  label.starts_with(|c: char| c.is_ascii_alphabetic())
+    && label
+      .chars()
+      .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/' | '.'))
+}
+
+/// Lines of context kept on either side of the commented lines in an [`Anchor`].
+const ANCHOR_CONTEXT_LINES: usize = 2;
+/// Lowest similarity (0..1) at which changed lines still count as the commented code.
+const ANCHOR_FUZZY_THRESHOLD: f32 = 0.6;
+/// How much each matching context line adds to a candidate's similarity.
+const ANCHOR_CONTEXT_WEIGHT: f32 = 0.05;
+/// How far (in lines) from the old position edited lines are looked for.
+const ANCHOR_FUZZY_WINDOW: usize = 200;
+
+/// The lines a comment covers, plus a little context, as they read when it was anchored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Anchor {
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub before: Vec<String>,
+  #[serde(default)]
+  pub lines: Vec<String>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub after: Vec<String>,
+}
+
+/// Where [`Anchor::locate`] found the anchored lines (1-based start line).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Located {
+  /// The lines are there unchanged.
+  Exact(u32),
+  /// The lines were edited, but a close enough match is there.
+  Fuzzy(u32),
+}
+
+impl Anchor {
+  /// Captures lines `start..=last` (1-based) of `lines` with their context.
+  pub fn capture(lines: &[String], start: u32, last: u32) -> Option<Self> {
+    let start_idx = (start as usize).checked_sub(1)?;
+    if start_idx >= lines.len() {
+      return None;
+    }
+    let end_idx = (last.max(start) as usize).min(lines.len());
+    let after_idx = (end_idx + ANCHOR_CONTEXT_LINES).min(lines.len());
+    Some(Self {
+      before: lines[start_idx.saturating_sub(ANCHOR_CONTEXT_LINES)..start_idx].to_vec(),
+      lines: lines[start_idx..end_idx].to_vec(),
+      after: lines[end_idx..after_idx].to_vec(),
+    })
+  }
+
+  /// Finds the anchored lines in `lines`. Verbatim matches win over similar ones, which are only
+  /// looked for within [`ANCHOR_FUZZY_WINDOW`] lines of `hint`; among equals, the one with more
+  /// matching context, then the one closest to `hint`, is picked.
+  pub fn locate(&self, lines: &[String], hint: u32) -> Option<Located> {
+    let n = self.lines.len();
+    if n == 0 || n > lines.len() {
+      return None;
+    }
+    let hint_idx = hint.saturating_sub(1) as usize;
+    let starts = 0..=lines.len() - n;
+
+    let exact = starts
+      .clone()
+      .filter(|&i| lines[i..i + n] == self.lines[..])
+      .max_by_key(|&i| {
+        (
+          self.context_matches(lines, i),
+          std::cmp::Reverse(i.abs_diff(hint_idx)),
+        )
+      });
+    if let Some(i) = exact {
+      return Some(Located::Exact(i as u32 + 1));
+    }
+
+    let target = self.lines.join("\n");
+    let target_len = target.chars().count();
+    let nearby = hint_idx.saturating_sub(ANCHOR_FUZZY_WINDOW)
+      ..=(hint_idx + ANCHOR_FUZZY_WINDOW).min(*starts.end());
+    let mut best: Option<(f32, usize)> = None;
+    for i in nearby {
+      let window = lines[i..i + n].join("\n");
+      // The ratio is at most 2 * shorter / (sum of lengths), so lopsided candidates can be
+      // skipped without diffing them.
+      let window_len = window.chars().count();
+      let bound = 2.0 * target_len.min(window_len) as f32 / (target_len + window_len).max(1) as f32;
+      if bound < ANCHOR_FUZZY_THRESHOLD {
+        continue;
+      }
+      let ratio = similar::TextDiff::from_chars(target.as_str(), window.as_str()).ratio();
+      if ratio < ANCHOR_FUZZY_THRESHOLD {
+        continue;
+      }
+      let score = ratio + ANCHOR_CONTEXT_WEIGHT * self.context_matches(lines, i) as f32;
+      let better = best.is_none_or(|(best_score, j)| {
+        score > best_score || (score == best_score && i.abs_diff(hint_idx) < j.abs_diff(hint_idx))
+      });
+      if better {
+        best = Some((score, i));
+      }
+    }
+    best.map(|(_, i)| Located::Fuzzy(i as u32 + 1))
+  }
+
+  /// How many context lines match around a candidate starting at index `start`.
+  fn context_matches(&self, lines: &[String], start: usize) -> usize {
+    let before = self
+      .before
+      .iter()
+      .rev()
+      .zip(lines[..start].iter().rev())
+      .filter(|(a, b)| a == b)
+      .count();
+    let after = self
+      .after
+      .iter()
+      .zip(lines[start + self.lines.len()..].iter())
+      .filter(|(a, b)| a == b)
+      .count();
+    before + after
+  }
+}
+
+/// A suggested change: the covered lines as they read when it was written, and their replacement.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+  /// Empty when the original text is unknown (e.g. written by hand in the draft).
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub original: Vec<String>,
+  #[serde(default)]
+  pub replacement: Vec<String>,
+}
+
+impl Suggestion {
+  /// Diff-style preview: original lines prefixed with `-`, replacement lines with `+`.
+  pub fn preview(&self) -> String {
+    let removed = self.original.iter().map(|l| format!("-{l}"));
+    let added = self.replacement.iter().map(|l| format!("+{l}"));
+    removed.chain(added).collect::<Vec<_>>().join("\n")
+  }
+
+  /// Parses a [`Self::preview`] back; lines without a `-`/`+` prefix are ignored.
+  pub fn from_preview(text: &str) -> Self {
+    let mut out = Self::default();
+    for line in text.lines() {
+      if let Some(rest) = line.strip_prefix('-') {
+        out.original.push(rest.to_string());
+      } else if let Some(rest) = line.strip_prefix('+') {
+        out.replacement.push(rest.to_string());
+      }
+    }
+    out
+  }
+
+  /// Replaces lines `start..=last` (1-based) of `content` with the suggestion. Fails when the
+  /// original text is known and no longer matches what is there.
+  pub fn apply_to(&self, content: &str, start: u32, last: u32) -> anyhow::Result<String> {
+    let newline = line_ending(content);
+    let mut lines: Vec<&str> = content.lines().collect();
+    let start_idx = start.saturating_sub(1) as usize;
+    let end_idx = (last.max(start) as usize).min(lines.len());
+    if start == 0 || start_idx >= lines.len() {
+      anyhow::bail!("line {start} is past the end of the file");
+    }
+    if !self.original.is_empty() && lines[start_idx..end_idx] != self.original[..] {
+      anyhow::bail!("lines {start}-{last} changed since the suggestion was made");
+    }
+    lines.splice(
+      start_idx..end_idx,
+      self.replacement.iter().map(String::as_str),
+    );
+    let mut out = lines.join(newline);
+    if content.ends_with('\n') && !lines.is_empty() {
+      out.push_str(newline);
+    }
+    Ok(out)
+  }
+}
+
+/// The line ending `content` uses: `\r\n` if it has any, else `\n`.
+pub fn line_ending(content: &str) -> &'static str {
+  if content.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reply {
+  pub author: String,
+  /// Unix timestamp (seconds).
+  pub at: i64,
+  pub body: String,
+}
+
+/// Recorded when a comment is resolved with a message or a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+  /// Git identity of whoever resolved the comment.
+  pub author: String,
+  /// Unix timestamp (seconds).
+  pub at: i64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub message: Option<String>,
+  /// Full id of the commit that addressed the comment.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub commit: Option<String>,
+}
+
+impl Resolution {
+  /// One line for comment lists, e.g. `fixed the loop, abc1234`.
+  pub fn summary(&self) -> String {
+    let message = self
+      .message
+      .as_deref()
+      .and_then(|m| m.lines().next())
+      .unwrap_or("");
+    match self.commit.as_deref() {
+      Some(commit) if message.is_empty() => short_commit(commit).to_string(),
+      Some(commit) => format!("{message}, {}", short_commit(commit)),
+      None => message.to_string(),
+    }
+  }
+}
+
+/// The abbreviated form of a commit id.
+pub fn short_commit(commit: &str) -> &str {
+  // Notes pulled from elsewhere may carry anything here, so don't split a character.
+  commit
+    .char_indices()
+    .nth(7)
+    .map_or(commit, |(end, _)| &commit[..end])
+}
+
+impl Comment {
+  /// Resolves the comment. A message or commit is recorded along with who resolved it; without
+  /// either, an existing resolution is kept.
+  pub fn resolve(&mut self, author: String, message: Option<String>, commit: Option<String>) {
+    self.resolved = true;
+    if message.is_some() || commit.is_some() {
+      self.resolution = Some(Resolution {
+        author,
+        at: now_timestamp(),
+        message,
+        commit,
+      });
+    }
+  }
+
+  /// Reopens the comment, dropping how it was resolved.
+  pub fn unresolve(&mut self) {
+    self.resolved = false;
+    self.resolution = None;
+  }
+
+  pub fn push_reply(&mut self, author: String, body: String) {
+    self.replies.push(Reply {
+      author,
+      at: now_timestamp(),
+      body,
+    });
+  }
+
+  /// Short id for the thread that survives edits and line moves: derived from the file, the
+  /// author, the creation time and the nonce. Comments that predate those fall back to their
+  /// location.
+  pub fn id(&self, path: &str, key: Option<LineKey>) -> String {
+    let location = match key {
+      None => "file".to_string(),
+      Some(key) => format!("{}:{}", key.side.label(), key.line),
+    };
+    let origin = match (&self.author, self.created_at) {
+      (Some(author), Some(at)) => {
+        let kind = if key.is_some() { "line" } else { "file" };
+        match &self.nonce {
+          Some(nonce) => format!("{kind}\0{author}\0{at}\0{nonce}"),
+          None => format!("{kind}\0{author}\0{at}"),
+        }
+      }
+      _ => location,
+    };
+    let mut h = gix_hash::hasher(gix_hash::Kind::Sha1);
+    h.update(b"remark-comment-id-v1\0");
+    h.update(path.as_bytes());
+    h.update(b"\0");
+    h.update(origin.as_bytes());
+    h.try_finalize()
+      .map(|oid| oid.to_hex_with_len(COMMENT_ID_LEN).to_string())
+      .unwrap_or_default()
+  }
+
+  /// The last line this comment covers when anchored at `key`.
+  pub fn last_line(&self, key: LineKey) -> u32 {
+    self
+      .end_line
+      .filter(|end| *end > key.line)
+      .unwrap_or(key.line)
+  }
+
+  /// Whether the comment anchored at `key` covers `line` on `side`.
+  pub fn covers(&self, key: LineKey, side: LineSide, line: u32) -> bool {
+    key.side == side && (key.line..=self.last_line(key)).contains(&line)
+  }
+
+  /// Severity used for ordering and filtering; untagged comments count as suggestions.
+  pub fn rank(&self) -> Severity {
+    self.severity.unwrap_or(Severity::Suggestion)
+  }
+
+  /// Records `author` as the thread starter unless one is already set.
+  pub fn stamp_author(&mut self, author: &str) {
+    if self.author.is_none() {
+      self.author = Some(author.to_string());
+      self.created_at = Some(now_timestamp());
+      self.nonce = Some(new_nonce());
+    }
+  }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+#[derive(
+  Debug,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  PartialOrd,
+  Ord,
+  clap::ValueEnum,
+  Serialize
+)]
+#[serde(rename_all = "lowercase")]
 pub enum LineSide {
   Old,
   New,
 }
 
+impl LineSide {
+  pub fn label(self) -> &'static str {
+    match self {
+      LineSide::Old => "old",
+      LineSide::New => "new",
+    }
+  }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct LineKey {
   pub side: LineSide,
@@ -77,6 +554,138 @@ pub struct FileReview {
   pub reviewed: bool,
   #[serde(default, skip_serializing_if = "Option::is_none")]
   pub reviewed_hash: Option<String>,
+  /// Blob id of the content the file had when it was last marked reviewed. Kept when a change
+  /// un-reviews the file, so the next review can read just what changed since.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub reviewed_blob: Option<String>,
+  /// Hashes (see [`crate::diff::Hunk::hash`]) of the hunks marked viewed.
+  #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+  pub viewed_hunks: BTreeSet<String>,
+}
+
+impl FileReview {
+  /// Whether there is nothing worth keeping a note for.
+  pub fn is_empty(&self) -> bool {
+    self.file_comment.is_none()
+      && self.comments.is_empty()
+      && !self.reviewed
+      && self.reviewed_blob.is_none()
+      && self.viewed_hunks.is_empty()
+  }
+
+  /// The file comment for `None`, otherwise the line comment at `key`.
+  pub fn comment_mut(&mut self, key: Option<LineKey>) -> Option<&mut Comment> {
+    match key {
+      None => self.file_comment.as_mut(),
+      Some(key) => self.comments.get_mut(&key),
+    }
+  }
+
+  /// Re-anchors the outdated comment at `from` onto line `to` (same side) and makes it current
+  /// again. A range keeps its length.
+  pub fn revive(&mut self, from: LineKey, to: u32) -> anyhow::Result<&mut Comment> {
+    let target = LineKey { line: to, ..from };
+    if target != from && self.comments.contains_key(&target) {
+      anyhow::bail!("line {to} already has a comment");
+    }
+    let mut comment = match self.comments.remove(&from) {
+      Some(comment) if comment.outdated => comment,
+      Some(comment) => {
+        self.comments.insert(from, comment);
+        anyhow::bail!("comment on line {} is not outdated", from.line);
+      }
+      None => anyhow::bail!("no comment on line {}", from.line),
+    };
+    let span = comment.last_line(from) - from.line;
+    comment.end_line = (span > 0).then_some(to + span);
+    comment.outdated = false;
+    // The old anchor describes code that is gone; the next sync anchors it to what is there now.
+    comment.anchor = None;
+    Ok(self.comments.entry(target).or_insert(comment))
+  }
+
+  /// Moves anchored `side` line comments to wherever their code now sits in `lines`.
+  ///
+  /// A comment whose code can't be found becomes outdated and stays where it was; an outdated
+  /// comment whose code turns up again verbatim is revived. Comments without an anchor are left
+  /// alone.
+  pub fn relocate(&mut self, side: LineSide, lines: &[String]) -> Relocated {
+    let mut out = Relocated::default();
+    let keys: Vec<LineKey> = self
+      .comments
+      .iter()
+      .filter(|(key, comment)| key.side == side && comment.anchor.is_some())
+      .map(|(key, _)| *key)
+      .collect();
+    let pending: Vec<(LineKey, Comment)> = keys
+      .into_iter()
+      .filter_map(|key| self.comments.remove(&key).map(|comment| (key, comment)))
+      .collect();
+
+    for (key, mut comment) in pending {
+      let span = comment.last_line(key) - key.line;
+      let found = match comment
+        .anchor
+        .as_ref()
+        .and_then(|a| a.locate(lines, key.line))
+      {
+        Some(Located::Exact(line)) => Some((line, false)),
+        Some(Located::Fuzzy(line)) if !comment.outdated => Some((line, true)),
+        _ => None,
+      };
+      let found =
+        found.filter(|(line, _)| !self.comments.contains_key(&LineKey { line: *line, ..key }));
+
+      let Some((line, fuzzy)) = found else {
+        if !comment.outdated {
+          comment.outdated = true;
+          out.outdated.push(key);
+        }
+        let mut line = key.line;
+        while self.comments.contains_key(&LineKey { line, ..key }) {
+          line += 1;
+        }
+        self.comments.insert(LineKey { line, ..key }, comment);
+        continue;
+      };
+
+      let target = LineKey { line, ..key };
+      if fuzzy {
+        // Follow the edit: next time, match against the code as it reads now.
+        comment.anchor = Anchor::capture(lines, line, line + span);
+      }
+      comment.end_line = (span > 0).then_some(line + span);
+      if std::mem::take(&mut comment.outdated) {
+        out.revived.push(target);
+      } else if target != key || fuzzy {
+        out.moved.push((key, target));
+      }
+      if fuzzy {
+        out.edited.push(target);
+      }
+      self.comments.insert(target, comment);
+    }
+    out
+  }
+}
+
+/// What [`FileReview::relocate`] changed.
+#[derive(Debug, Default)]
+pub struct Relocated {
+  /// `(from, to)` for live comments that moved or whose anchor was refreshed in place.
+  pub moved: Vec<(LineKey, LineKey)>,
+  /// Comments that could not be found and were marked outdated (at their old key).
+  pub outdated: Vec<LineKey>,
+  /// Outdated comments that were found again, at their new key.
+  pub revived: Vec<LineKey>,
+  /// Live comments whose code was edited and that followed it, at their new key.
+  pub edited: Vec<LineKey>,
+}
+
+impl Relocated {
+  pub fn is_empty(&self) -> bool {
+    self.moved.is_empty() && self.outdated.is_empty() && self.revived.is_empty()
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -104,14 +713,11 @@ impl Review {
     if comment.trim().is_empty() {
       f.file_comment = None;
     } else {
-      let resolved = f.file_comment.as_ref().map(|c| c.resolved).unwrap_or(false);
-      f.file_comment = Some(Comment {
-        body: comment,
-        resolved,
-        snippet_hash: None,
-      });
+      let mut updated = f.file_comment.take().unwrap_or_default();
+      updated.body = comment;
+      f.file_comment = Some(updated);
     }
-    if f.file_comment.is_none() && f.comments.is_empty() && !f.reviewed {
+    if f.is_empty() {
       self.files.remove(path);
     }
   }
@@ -125,7 +731,7 @@ impl Review {
       return false;
     };
     let removed = f.file_comment.take().is_some();
-    if f.file_comment.is_none() && f.comments.is_empty() && !f.reviewed {
+    if f.is_empty() {
       self.files.remove(path);
     }
     removed
@@ -134,7 +740,11 @@ impl Review {
   pub fn toggle_file_comment_resolved(&mut self, path: &str) -> Option<bool> {
     let f = self.files.get_mut(path)?;
     let c = f.file_comment.as_mut()?;
-    c.resolved = !c.resolved;
+    if c.resolved {
+      c.unresolve();
+    } else {
+      c.resolved = true;
+    }
     Some(c.resolved)
   }
 
@@ -156,18 +766,10 @@ impl Review {
         side,
         line: line_1_based,
       };
-      let resolved = f.comments.get(&key).map(|c| c.resolved).unwrap_or(false);
-      let snippet_hash = f.comments.get(&key).and_then(|c| c.snippet_hash.clone());
-      f.comments.insert(
-        key,
-        Comment {
-          body: comment,
-          resolved,
-          snippet_hash,
-        },
-      );
+      let updated = f.comments.entry(key).or_default();
+      updated.body = comment;
     }
-    if f.file_comment.is_none() && f.comments.is_empty() && !f.reviewed {
+    if f.is_empty() {
       self.files.remove(path);
     }
   }
@@ -191,6 +793,48 @@ impl Review {
     }
   }
 
+  /// Sets the last line of the range covered by the comment at `line_1_based`.
+  pub fn set_line_comment_end(
+    &mut self,
+    path: &str,
+    side: LineSide,
+    line_1_based: u32,
+    end_line: Option<u32>,
+  ) {
+    let Some(f) = self.files.get_mut(path) else {
+      return;
+    };
+    let key = LineKey {
+      side,
+      line: line_1_based,
+    };
+    if let Some(comment) = f.comments.get_mut(&key) {
+      comment.end_line = end_line.filter(|end| *end > line_1_based);
+    }
+  }
+
+  /// The comment anchored at or whose range spans `line_1_based`, with its key.
+  pub fn line_comment_covering(
+    &self,
+    path: &str,
+    side: LineSide,
+    line_1_based: u32,
+  ) -> Option<(LineKey, &Comment)> {
+    let f = self.files.get(path)?;
+    let key = LineKey {
+      side,
+      line: line_1_based,
+    };
+    if let Some(comment) = f.comments.get(&key) {
+      return Some((key, comment));
+    }
+    f.comments
+      .range(..key)
+      .rev()
+      .find(|(k, c)| c.covers(**k, side, line_1_based))
+      .map(|(k, c)| (*k, c))
+  }
+
   pub fn line_comment(&self, path: &str, side: LineSide, line_1_based: u32) -> Option<&Comment> {
     self.files.get(path).and_then(|f| {
       f.comments.get(&LineKey {
@@ -211,7 +855,7 @@ impl Review {
         line: line_1_based,
       })
       .is_some();
-    if f.file_comment.is_none() && f.comments.is_empty() && !f.reviewed {
+    if f.is_empty() {
       self.files.remove(path);
     }
     removed
@@ -228,11 +872,17 @@ impl Review {
       side,
       line: line_1_based,
     })?;
-    c.resolved = !c.resolved;
+    if c.resolved {
+      c.unresolve();
+    } else {
+      c.resolved = true;
+    }
     Some(c.resolved)
   }
 
-  pub fn prune_line_comments(
+  /// Flags line comments whose line is not in `valid_old`/`valid_new` as outdated. Nothing is
+  /// removed; returns whether any comment changed.
+  pub fn mark_outdated_line_comments(
     &mut self,
     path: &str,
     valid_old: &HashSet<u32>,
@@ -242,21 +892,17 @@ impl Review {
       return false;
     };
 
-    let before = f.comments.len();
-    f.comments.retain(|k, _| match k.side {
-      LineSide::Old => valid_old.contains(&k.line),
-      LineSide::New => valid_new.contains(&k.line),
-    });
-    let mut changed = f.comments.len() != before;
-
-    if f.file_comment.is_none()
-      && f.comments.is_empty()
-      && !f.reviewed
-      && self.files.remove(path).is_some()
-    {
-      changed = true;
+    let mut changed = false;
+    for (k, c) in f.comments.iter_mut() {
+      let valid = match k.side {
+        LineSide::Old => valid_old.contains(&k.line),
+        LineSide::New => valid_new.contains(&k.line),
+      };
+      if !valid && !c.outdated {
+        c.outdated = true;
+        changed = true;
+      }
     }
-
     changed
   }
 
@@ -271,7 +917,8 @@ impl Review {
         return CommentState::HasUnresolved;
       }
     }
-    for c in f.comments.values() {
+    // Outdated comments are history, not open work.
+    for c in f.comments.values().filter(|c| !c.outdated) {
       saw_any = true;
       if !c.resolved {
         return CommentState::HasUnresolved;
@@ -285,19 +932,173 @@ impl Review {
   }
 }
 
+/// Merges `incoming` into `target`, preferring unresolved comments when both sides have one.
+pub fn merge_file_review(target: &mut FileReview, incoming: FileReview) {
+  if incoming.reviewed {
+    target.reviewed = true;
+    if target.reviewed_hash.is_none() {
+      target.reviewed_hash = incoming.reviewed_hash;
+    }
+  }
+  if target.reviewed_blob.is_none() {
+    target.reviewed_blob = incoming.reviewed_blob;
+  }
+  target.viewed_hunks.extend(incoming.viewed_hunks);
+  if let Some(incoming) = incoming.file_comment {
+    merge_comment(&mut target.file_comment, incoming);
+  }
+
+  for (k, v) in incoming.comments {
+    let mut slot = target.comments.remove(&k);
+    merge_comment(&mut slot, v);
+    if let Some(comment) = slot {
+      target.comments.insert(k, comment);
+    }
+  }
+}
+
+/// Three-way merge of two versions of a file's note that both changed since `base`. Whatever
+/// only one side changed wins, including a resolve or a deletion the other side left alone;
+/// comments both sides changed are combined like [`merge_file_review`] does.
+pub fn merge_file_review_with_base(base: &FileReview, ours: &mut FileReview, theirs: FileReview) {
+  let review_state = |f: &FileReview| (f.reviewed, f.reviewed_hash.clone());
+  if review_state(ours) == review_state(base) {
+    ours.reviewed = theirs.reviewed;
+    ours.reviewed_hash = theirs.reviewed_hash.clone();
+  } else if theirs.reviewed && review_state(&theirs) != review_state(base) {
+    ours.reviewed = true;
+    if ours.reviewed_hash.is_none() {
+      ours.reviewed_hash = theirs.reviewed_hash.clone();
+    }
+  }
+  if ours.reviewed_blob == base.reviewed_blob || ours.reviewed_blob.is_none() {
+    ours.reviewed_blob = theirs.reviewed_blob;
+  }
+  let viewed_hunks = std::mem::take(&mut ours.viewed_hunks);
+  ours.viewed_hunks = viewed_hunks
+    .iter()
+    .chain(&theirs.viewed_hunks)
+    .filter(|hash| {
+      !base.viewed_hunks.contains(*hash)
+        || (viewed_hunks.contains(*hash) && theirs.viewed_hunks.contains(*hash))
+    })
+    .cloned()
+    .collect();
+
+  ours.file_comment = merge_comment_with_base(
+    base.file_comment.as_ref(),
+    ours.file_comment.take(),
+    theirs.file_comment,
+  );
+  let mut theirs_comments = theirs.comments;
+  let keys: BTreeSet<LineKey> = ours
+    .comments
+    .keys()
+    .chain(theirs_comments.keys())
+    .copied()
+    .collect();
+  for key in keys {
+    let merged = merge_comment_with_base(
+      base.comments.get(&key),
+      ours.comments.remove(&key),
+      theirs_comments.remove(&key),
+    );
+    if let Some(comment) = merged {
+      ours.comments.insert(key, comment);
+    }
+  }
+}
+
+/// Merges two encoded file notes with [`merge_file_review_with_base`], against an empty note
+/// when there is no `base` to compare with; a note that does not decode loses to one that does.
+pub fn merge_note_text(base: Option<&str>, ours: &str, theirs: &str) -> String {
+  match (decode_file_note(ours), decode_file_note(theirs)) {
+    (Some(mut ours_file), Some(theirs_file)) => {
+      let base_file = base.and_then(decode_file_note).unwrap_or_default();
+      merge_file_review_with_base(&base_file, &mut ours_file, theirs_file);
+      encode_file_note(&ours_file)
+    }
+    (None, Some(_)) => theirs.to_string(),
+    _ => ours.to_string(),
+  }
+}
+
+/// Adds the replies `target` doesn't have yet, keeping the thread in time order.
+pub fn merge_replies(target: &mut Vec<Reply>, incoming: &[Reply]) {
+  for reply in incoming {
+    if !target.contains(reply) {
+      target.push(reply.clone());
+    }
+  }
+  target.sort_by_key(|r| r.at);
+}
+
+/// Thread replies from both sides are always kept, so concurrent conversations don't lose turns.
+fn merge_comment(target: &mut Option<Comment>, incoming: Comment) {
+  let Some(existing) = target.as_mut() else {
+    *target = Some(incoming);
+    return;
+  };
+  let mut replies = std::mem::take(&mut existing.replies);
+  merge_replies(&mut replies, &incoming.replies);
+  if existing.resolved && !incoming.resolved {
+    *existing = incoming;
+  }
+  existing.replies = replies;
+}
+
+/// One comment slot of [`merge_file_review_with_base`]. A comment edited on one side and deleted
+/// on the other is kept; when both sides edited it, a resolve or reopen only one of them made
+/// still wins.
+fn merge_comment_with_base(
+  base: Option<&Comment>,
+  ours: Option<Comment>,
+  theirs: Option<Comment>,
+) -> Option<Comment> {
+  if ours.as_ref() == base {
+    return theirs;
+  }
+  if theirs.as_ref() == base || ours == theirs {
+    return ours;
+  }
+  let (ours, theirs) = match (ours, theirs) {
+    (Some(ours), Some(theirs)) => (ours, theirs),
+    (ours, theirs) => return ours.or(theirs),
+  };
+  let only_resolved_by = |side: &Comment, other: &Comment| {
+    base.is_some_and(|b| side.resolved != b.resolved && other.resolved == b.resolved)
+  };
+  let resolution = if only_resolved_by(&ours, &theirs) {
+    Some((ours.resolved, ours.resolution.clone()))
+  } else if only_resolved_by(&theirs, &ours) {
+    Some((theirs.resolved, theirs.resolution.clone()))
+  } else {
+    None
+  };
+  let mut merged = Some(ours);
+  merge_comment(&mut merged, theirs);
+  if let (Some(comment), Some((resolved, resolution))) = (merged.as_mut(), resolution) {
+    comment.resolved = resolved;
+    comment.resolution = resolution;
+  }
+  merged
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct FileNote {
   version: u8,
   file: FileReview,
 }
 
+const FILE_NOTE_VERSION: u8 = 3;
+
 pub fn encode_file_note(file: &FileReview) -> String {
   let json = serde_json::to_string_pretty(&FileNote {
-    version: 2,
+    version: FILE_NOTE_VERSION,
     file: file.clone(),
   })
   .unwrap_or_else(|_| "{}".to_string());
-  format!("<!-- remark-file:2 -->\n```json\n{json}\n```\n")
+  format!("<!-- remark-file:{FILE_NOTE_VERSION} -->\n```json\n{json}\n```\n")
 }
 
 pub fn decode_file_note(note: &str) -> Option<FileReview> {
@@ -312,8 +1113,10 @@ pub fn decode_file_note(note: &str) -> Option<FileReview> {
         json_lines.push(l);
       }
       let json = json_lines.join("\n");
+      // v3 only added thread fields (author/created_at/replies), so v2 notes decode as v3
+      // threads without replies.
       if let Ok(v) = serde_json::from_str::<FileNote>(&json)
-        && v.version == 2
+        && matches!(v.version, 2 | FILE_NOTE_VERSION)
       {
         return Some(v.file);
       }
@@ -338,8 +1141,7 @@ pub fn decode_file_note(note: &str) -> Option<FileReview> {
       return Some(FileReview {
         file_comment: v1.file.file_comment.map(|body| Comment {
           body,
-          resolved: false,
-          snippet_hash: None,
+          ..Default::default()
         }),
         comments: v1
           .file
@@ -350,23 +1152,39 @@ pub fn decode_file_note(note: &str) -> Option<FileReview> {
               k,
               Comment {
                 body,
-                resolved: false,
-                snippet_hash: None,
+                ..Default::default()
               },
             )
           })
           .collect(),
         reviewed: false,
         reviewed_hash: None,
+        reviewed_blob: None,
+        viewed_hunks: BTreeSet::new(),
       });
     }
   }
   None
 }
 
-pub fn render_prompt<F>(review: &Review, mut line_code: F) -> String
+/// `line_code(path, key, last_line)` returns the code a line (or range) comment is about.
+/// Outdated comments are left out; see [`render_prompt_with_outdated`].
+pub fn render_prompt<F>(review: &Review, line_code: F) -> String
 where
-  F: FnMut(&str, LineKey) -> Option<PromptSnippet>,
+  F: FnMut(&str, LineKey, u32) -> Option<PromptSnippet>,
+{
+  render_prompt_with_outdated(review, false, line_code)
+}
+
+/// Like [`render_prompt`]; with `include_outdated`, outdated comments are listed too, marked
+/// `(outdated)` and without a snippet since their code is gone.
+pub fn render_prompt_with_outdated<F>(
+  review: &Review,
+  include_outdated: bool,
+  mut line_code: F,
+) -> String
+where
+  F: FnMut(&str, LineKey, u32) -> Option<PromptSnippet>,
 {
   let mut out = String::new();
   if review.files.is_empty() {
@@ -382,19 +1200,8 @@ These notes are grouped by file. Every comment belongs to the file section it ap
   );
 
   for (path, f) in &review.files {
-    let file_comment = f
-      .file_comment
-      .as_ref()
-      .filter(|c| !c.resolved)
-      .map(|c| c.body.trim_end())
-      .filter(|s| !s.is_empty());
-
-    let line_comments = f
-      .comments
-      .iter()
-      .filter(|(_, c)| !c.resolved && !c.body.trim().is_empty())
-      .map(|(k, c)| (k, c.body.trim_end()))
-      .collect::<Vec<_>>();
+    let file_comment = prompt_file_comment(f);
+    let line_comments = prompt_line_comments(f, include_outdated);
 
     if file_comment.is_none() && line_comments.is_empty() {
       continue;
@@ -405,22 +1212,32 @@ These notes are grouped by file. Every comment belongs to the file section it ap
 
     if let Some(fc) = file_comment {
       out.push_str("### File comment\n");
-      push_fenced_block(&mut out, fc);
+      push_thread(&mut out, fc);
       out.push('\n');
     }
 
     if !line_comments.is_empty() {
       out.push_str("### Line comments\n");
       for (line, comment) in line_comments {
-        out.push_str(&format!("- line {}", line.line));
+        let last = comment.last_line(*line);
+        if last > line.line {
+          out.push_str(&format!("- lines {}-{last}", line.line));
+        } else {
+          out.push_str(&format!("- line {}", line.line));
+        }
         if line.side == LineSide::Old {
           out.push_str(" (old)");
         }
+        if comment.outdated {
+          out.push_str(" (outdated)");
+        }
         out.push('\n');
-        if let Some(snippet) = line_code(path, *line) {
+        if !comment.outdated
+          && let Some(snippet) = line_code(path, *line, last)
+        {
           push_fenced_block_with_lang(&mut out, &snippet.code, &snippet.lang);
         }
-        push_fenced_block(&mut out, comment);
+        push_thread(&mut out, comment);
       }
     }
     out.push('\n');
@@ -433,6 +1250,141 @@ These notes are grouped by file. Every comment belongs to the file section it ap
   out
 }
 
+/// The file comment as the prompt shows it: only when open and non-empty.
+pub(crate) fn prompt_file_comment(file: &FileReview) -> Option<&Comment> {
+  file
+    .file_comment
+    .as_ref()
+    .filter(|c| !c.resolved && !c.body.trim().is_empty())
+}
+
+/// The open line comments the prompt lists, most severe first.
+pub(crate) fn prompt_line_comments(
+  file: &FileReview,
+  include_outdated: bool,
+) -> Vec<(&LineKey, &Comment)> {
+  let mut comments = file
+    .comments
+    .iter()
+    .filter(|(_, c)| !c.resolved && !c.body.trim().is_empty())
+    .filter(|(_, c)| include_outdated || !c.outdated)
+    .collect::<Vec<_>>();
+  // The sort is stable, so equal severities keep line order.
+  comments.sort_by_key(|(_, c)| std::cmp::Reverse(c.rank()));
+  comments
+}
+
+/// `text` in a fenced block tagged `lang`, with enough backticks to survive fences inside it.
+pub(crate) fn fenced_block(text: &str, lang: &str) -> String {
+  let mut out = String::new();
+  push_fenced_block_with_lang(&mut out, text, lang);
+  out
+}
+
+/// Fence info string for thread replies; draft parsing keys off it to tell replies from comments.
+pub(crate) const REPLY_FENCE_LANG: &str = "reply";
+pub(crate) const REPLY_MARKER: &str = "- reply from ";
+pub(crate) const SUGGESTION_FENCE_LANG: &str = "suggestion";
+pub(crate) const SUGGESTION_MARKER: &str = "- suggested change";
+
+/// The comment body as presented for editing: a suggestion is appended as a ```suggestion block,
+/// which [`take_suggestion`] splits back out.
+pub fn body_with_suggestion(comment: &Comment) -> String {
+  let mut out = body_with_tags(comment);
+  if let Some(suggestion) = &comment.suggestion {
+    if !out.is_empty() {
+      out.push_str("\n\n");
+    }
+    push_fenced_block_with_lang(
+      &mut out,
+      &suggestion.replacement.join("\n"),
+      SUGGESTION_FENCE_LANG,
+    );
+  }
+  out
+}
+
+/// The comment body with its [`Tags`] on a first line of their own, which [`take_tags`] splits
+/// back out.
+pub fn body_with_tags(comment: &Comment) -> String {
+  let body = comment.body.trim_end();
+  let tags = Tags::of(comment);
+  if tags.is_empty() {
+    body.to_string()
+  } else if body.is_empty() {
+    tags.to_string()
+  } else {
+    format!("{tags}\n{body}")
+  }
+}
+
+/// Splits a leading line of tags (`[issue] #perf`) off a comment body.
+pub fn take_tags(body: &str) -> (String, Tags) {
+  let trimmed = body.trim_start_matches('\n');
+  let (first, rest) = trimmed.split_once('\n').unwrap_or((trimmed, ""));
+  match Tags::parse(first) {
+    Some(tags) => (rest.trim_start_matches('\n').to_string(), tags),
+    None => (body.to_string(), Tags::default()),
+  }
+}
+
+/// Splits a GitHub-style ```suggestion block out of a comment body, returning the remaining body
+/// and the suggested replacement lines. A body that was only a suggestion gets a stock sentence so
+/// the comment is not mistaken for an empty one.
+pub fn take_suggestion(body: &str) -> (String, Option<Vec<String>>) {
+  let lines: Vec<&str> = body.lines().collect();
+  let Some(open) = lines.iter().position(|l| {
+    let t = l.trim_start();
+    t.starts_with("```") && t.trim_start_matches('`').trim() == SUGGESTION_FENCE_LANG
+  }) else {
+    return (body.to_string(), None);
+  };
+  let ticks = lines[open]
+    .trim_start()
+    .chars()
+    .take_while(|c| *c == '`')
+    .count();
+  let fence = "`".repeat(ticks);
+  let Some(close) = lines[open + 1..]
+    .iter()
+    .position(|l| l.trim() == fence)
+    .map(|i| open + 1 + i)
+  else {
+    return (body.to_string(), None);
+  };
+  let replacement = lines[open + 1..close]
+    .iter()
+    .map(|l| l.to_string())
+    .collect();
+  let rest = lines[..open]
+    .iter()
+    .chain(&lines[close + 1..])
+    .copied()
+    .collect::<Vec<_>>()
+    .join("\n");
+  let rest = match rest.trim() {
+    "" => "Suggested change.".to_string(),
+    rest => rest.to_string(),
+  };
+  (rest, Some(replacement))
+}
+
+fn push_thread(out: &mut String, comment: &Comment) {
+  push_fenced_block(out, &body_with_tags(comment));
+  if let Some(suggestion) = &comment.suggestion {
+    out.push_str(&format!("{SUGGESTION_MARKER}\n"));
+    push_fenced_block_with_lang(out, &suggestion.preview(), "diff");
+  }
+  for reply in &comment.replies {
+    out.push_str(&format!(
+      "{REPLY_MARKER}{} at {}\n",
+      reply.author,
+      format_timestamp(reply.at)
+    ));
+    push_fenced_block_with_lang(out, reply.body.trim_end(), REPLY_FENCE_LANG);
+  }
+}
+
 fn push_fenced_block(out: &mut String, text: &str) {
   push_fenced_block_with_lang(out, text, "text");
 }
@@ -458,6 +1410,47 @@ fn is_false(v: &bool) -> bool {
   !*v
 }
 
+pub fn now_timestamp() -> i64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs() as i64
+}
+
+/// A value that differs between calls, even within a process in the same nanosecond.
+fn new_nonce() -> String {
+  static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+  let nanos = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_nanos();
+  let count = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+  let mut h = gix_hash::hasher(gix_hash::Kind::Sha1);
+  h.update(&nanos.to_le_bytes());
+  h.update(&count.to_le_bytes());
+  h.update(&std::process::id().to_le_bytes());
+  h.try_finalize()
+    .map(|oid| oid.to_hex_with_len(8).to_string())
+    .unwrap_or_else(|_| format!("{count:08x}"))
+}
+
+/// How timestamps are shown and written to the draft: ISO 8601 in UTC.
+const TIMESTAMP_FORMAT: gix_date::time::format::CustomFormat =
+  gix_date::time::format::CustomFormat::new("%Y-%m-%dT%H:%M:%SZ");
+
+/// Formats a unix timestamp as `YYYY-MM-DDTHH:MM:SSZ` (UTC).
+pub fn format_timestamp(secs: i64) -> String {
+  gix_date::Time::new(secs, 0).format_or_unix(TIMESTAMP_FORMAT)
+}
+
+/// Parses the output of [`format_timestamp`].
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+  let utc = s.trim().strip_suffix('Z')?;
+  // Spelled as an offset, it is git's strict ISO 8601 date format.
+  let time = gix_date::parse(&format!("{utc}+00:00"), None).ok()?;
+  Some(time.seconds)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -488,10 +1481,11 @@ mod tests {
       file_comment: Some(Comment {
         body: "file-level".to_string(),
         resolved: true,
-        snippet_hash: None,
+        ..Default::default()
       }),
       reviewed: true,
       reviewed_hash: Some("abc123".to_string()),
+      reviewed_blob: Some("def456".to_string()),
       ..Default::default()
     };
     fr.comments.insert(
@@ -502,7 +1496,7 @@ mod tests {
       Comment {
         body: "hello".to_string(),
         resolved: false,
-        snippet_hash: None,
+        ..Default::default()
       },
     );
     fr.comments.insert(
@@ -513,7 +1507,7 @@ mod tests {
       Comment {
         body: "restore this".to_string(),
         resolved: true,
-        snippet_hash: None,
+        ..Default::default()
       },
     );
 
@@ -526,6 +1520,7 @@ mod tests {
     assert!(decoded.file_comment.as_ref().unwrap().resolved);
     assert!(decoded.reviewed);
     assert_eq!(decoded.reviewed_hash.as_deref(), Some("abc123"));
+    assert_eq!(decoded.reviewed_blob.as_deref(), Some("def456"));
     assert_eq!(decoded.comments.len(), 2);
     assert_eq!(
       decoded
@@ -550,6 +1545,21 @@ mod tests {
     );
   }
 
+  #[test]
+  fn file_note_roundtrip_v3_viewed_hunks() {
+    let fr = FileReview {
+      viewed_hunks: BTreeSet::from(["h1".to_string(), "h2".to_string()]),
+      ..Default::default()
+    };
+    assert!(!fr.is_empty());
+
+    let note = encode_file_note(&fr);
+    assert!(note.starts_with("<!-- remark-file:3 -->"));
+    let decoded = decode_file_note(&note).unwrap();
+    assert_eq!(decoded.viewed_hunks, fr.viewed_hunks);
+    assert!(!decoded.reviewed);
+  }
+
   #[test]
   fn file_note_v1_upgrades_to_v2_comments_unresolved() {
     let note = r#"<!-- remark-file:1 -->
@@ -593,6 +1603,173 @@ mod tests {
     );
   }
 
+  #[test]
+  fn file_note_v2_upgrades_to_threads_without_replies() {
+    let note = r#"<!-- remark-file:2 -->
+```json
+{
+  "version": 2,
+  "file": {
+    "comments": {
+      "n:4": { "body": "why?", "resolved": false }
+    }
+  }
+}
+```"#;
+
+    let fr = decode_file_note(note).unwrap();
+    let c = fr
+      .comments
+      .get(&LineKey {
+        side: LineSide::New,
+        line: 4,
+      })
+      .unwrap();
+    assert_eq!(c.body, "why?");
+    assert!(c.author.is_none());
+    assert!(c.replies.is_empty());
+    assert!(encode_file_note(&fr).starts_with("<!-- remark-file:3 -->"));
+  }
+
+  #[test]
+  fn merge_keeps_replies_from_both_sides() {
+    let key = LineKey {
+      side: LineSide::New,
+      line: 1,
+    };
+    let reply = |author: &str, at: i64| Reply {
+      author: author.to_string(),
+      at,
+      body: format!("from {author}"),
+    };
+    let thread = |replies: Vec<Reply>| {
+      let mut fr = FileReview::default();
+      fr.comments.insert(
+        key,
+        Comment {
+          body: "root".to_string(),
+          replies,
+          ..Default::default()
+        },
+      );
+      fr
+    };
+
+    let mut ours = thread(vec![reply("a", 10), reply("b", 30)]);
+    merge_file_review(&mut ours, thread(vec![reply("a", 10), reply("c", 20)]));
+    let authors: Vec<&str> = ours.comments[&key]
+      .replies
+      .iter()
+      .map(|r| r.author.as_str())
+      .collect();
+    assert_eq!(authors, vec!["a", "c", "b"]);
+  }
+
+  fn merge_fixture() -> (LineKey, LineKey, FileReview) {
+    let first = LineKey {
+      side: LineSide::New,
+      line: 1,
+    };
+    let second = LineKey {
+      side: LineSide::New,
+      line: 5,
+    };
+    let mut base = FileReview::default();
+    for (key, body) in [(first, "first"), (second, "second")] {
+      base.comments.insert(
+        key,
+        Comment {
+          body: body.to_string(),
+          ..Default::default()
+        },
+      );
+    }
+    (first, second, base)
+  }
+
+  #[test]
+  fn merge_with_base_keeps_a_resolve_against_an_unrelated_edit() {
+    let (first, second, base) = merge_fixture();
+    let mut ours = base.clone();
+    ours.comments.get_mut(&first).unwrap().resolved = true;
+    let mut theirs = base.clone();
+    theirs.comments.get_mut(&second).unwrap().body = "second, edited".to_string();
+
+    let merged = merge_note_text(
+      Some(&encode_file_note(&base)),
+      &encode_file_note(&ours),
+      &encode_file_note(&theirs),
+    );
+    let merged = decode_file_note(&merged).unwrap();
+    assert!(merged.comments[&first].resolved);
+    assert_eq!(merged.comments[&second].body, "second, edited");
+
+    // Same outcome with the sides swapped.
+    let mut swapped = theirs;
+    merge_file_review_with_base(&base, &mut swapped, ours);
+    assert!(swapped.comments[&first].resolved);
+    assert_eq!(swapped.comments[&second].body, "second, edited");
+  }
+
+  #[test]
+  fn merge_with_base_keeps_a_deletion_against_an_unrelated_edit() {
+    let (first, second, base) = merge_fixture();
+    let mut ours = base.clone();
+    ours.comments.remove(&first);
+    let mut theirs = base.clone();
+    theirs.comments.get_mut(&second).unwrap().body = "second, edited".to_string();
+
+    let mut merged = ours.clone();
+    merge_file_review_with_base(&base, &mut merged, theirs.clone());
+    assert!(!merged.comments.contains_key(&first));
+    assert_eq!(merged.comments[&second].body, "second, edited");
+
+    let mut swapped = theirs;
+    merge_file_review_with_base(&base, &mut swapped, ours);
+    assert!(!swapped.comments.contains_key(&first));
+    assert_eq!(swapped.comments[&second].body, "second, edited");
+  }
+
+  #[test]
+  fn merge_with_base_keeps_a_resolve_when_both_sides_touch_the_thread() {
+    let (first, _, base) = merge_fixture();
+    let reply = Reply {
+      author: "b".to_string(),
+      at: 10,
+      body: "reply".to_string(),
+    };
+    let mut ours = base.clone();
+    ours.comments.get_mut(&first).unwrap().resolved = true;
+    let mut theirs = base.clone();
+    theirs
+      .comments
+      .get_mut(&first)
+      .unwrap()
+      .replies
+      .push(reply.clone());
+
+    merge_file_review_with_base(&base, &mut ours, theirs);
+    let merged = &ours.comments[&first];
+    assert!(merged.resolved);
+    assert_eq!(merged.replies, vec![reply]);
+  }
+
+  #[test]
+  fn short_commit_keeps_whole_characters() {
+    assert_eq!(short_commit("0123456789abcdef"), "0123456");
+    assert_eq!(short_commit("abc"), "abc");
+    assert_eq!(short_commit("äöüäöüäöü"), "äöüäöüä");
+  }
+
+  #[test]
+  fn timestamp_roundtrip() {
+    assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+    assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    assert_eq!(parse_timestamp("2023-11-14T22:13:20Z"), Some(1_700_000_000));
+    assert_eq!(parse_timestamp("yesterday"), None);
+    assert_eq!(parse_timestamp("2023-13-14T22:13:20Z"), None);
+  }
+
   #[test]
   fn prompt_omits_resolved_comments() {
     let mut r = Review::new();
@@ -609,7 +1786,7 @@ mod tests {
     r.set_file_comment("b.rs", "All done".to_string());
     r.toggle_file_comment_resolved("b.rs");
 
-    let p = render_prompt(&r, |_, _| None);
+    let p = render_prompt(&r, |_, _, _| None);
     assert!(!p.contains("Target:"));
     assert!(!p.contains("Base:"));
     assert!(p.contains("## a.rs"));
@@ -622,7 +1799,7 @@ mod tests {
   }
 
   #[test]
-  fn prune_line_comments_removes_unreachable() {
+  fn mark_outdated_line_comments_keeps_unreachable() {
     let mut r = Review::new();
     r.set_line_comment("a.rs", LineSide::New, 10, "Fix this".to_string());
     r.set_line_comment("a.rs", LineSide::Old, 2, "Restore this".to_string());
@@ -632,9 +1809,158 @@ mod tests {
     let mut valid_new = HashSet::new();
     valid_new.insert(11);
 
-    assert!(r.prune_line_comments("a.rs", &valid_old, &valid_new));
-    assert!(r.line_comment("a.rs", LineSide::Old, 2).is_some());
-    assert!(r.line_comment("a.rs", LineSide::New, 10).is_none());
+    assert!(r.mark_outdated_line_comments("a.rs", &valid_old, &valid_new));
+    assert!(!r.line_comment("a.rs", LineSide::Old, 2).unwrap().outdated);
+    assert!(r.line_comment("a.rs", LineSide::New, 10).unwrap().outdated);
+    assert!(!r.mark_outdated_line_comments("a.rs", &valid_old, &valid_new));
+    assert_eq!(r.comment_state("a.rs"), CommentState::HasUnresolved);
+  }
+
+  #[test]
+  fn prompt_leaves_out_outdated_comments_by_default() {
+    let mut r = Review::new();
+    r.set_line_comment("a.rs", LineSide::New, 3, "Gone now".to_string());
+    r.files
+      .get_mut("a.rs")
+      .and_then(|f| f.comments.values_mut().next())
+      .expect("comment")
+      .outdated = true;
+
+    assert!(render_prompt(&r, |_, _, _| None).contains("No comments."));
+    let p = render_prompt_with_outdated(&r, true, |_, _, _| panic!("no snippet for outdated"));
+    assert!(p.contains("- line 3 (outdated)\n"));
+    assert!(p.contains("Gone now"));
+  }
+
+  #[test]
+  fn revive_reanchors_outdated_comment() {
+    let from = LineKey {
+      side: LineSide::New,
+      line: 3,
+    };
+    let mut f = FileReview::default();
+    f.comments.insert(
+      from,
+      Comment {
+        body: "Still relevant".to_string(),
+        end_line: Some(5),
+        outdated: true,
+        ..Default::default()
+      },
+    );
+    f.comments.insert(
+      LineKey {
+        side: LineSide::New,
+        line: 9,
+      },
+      Comment {
+        body: "Other".to_string(),
+        ..Default::default()
+      },
+    );
+
+    assert!(f.revive(from, 9).is_err());
+    let revived = f.revive(from, 7).expect("revive");
+    assert!(!revived.outdated);
+    assert_eq!(revived.end_line, Some(9));
+    assert!(!f.comments.contains_key(&from));
+    assert!(
+      f.revive(
+        LineKey {
+          side: LineSide::New,
+          line: 9
+        },
+        1
+      )
+      .is_err()
+    );
+  }
+
+  fn lines(text: &str) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+  }
+
+  #[test]
+  fn anchor_locates_moved_edited_and_lost_code() {
+    let original = lines("fn a() {\n  let total = items.len();\n}\nfn b() {\n  return;\n}");
+    let anchor = Anchor::capture(&original, 2, 2).expect("capture");
+    assert_eq!(anchor.lines, vec!["  let total = items.len();"]);
+    assert_eq!(anchor.before, vec!["fn a() {"]);
+
+    let moved = lines("// header\n\nfn a() {\n  let total = items.len();\n}");
+    assert_eq!(anchor.locate(&moved, 2), Some(Located::Exact(4)));
+
+    let edited = lines("fn a() {\n  let total = items.iter().count();\n}");
+    assert_eq!(anchor.locate(&edited, 2), Some(Located::Fuzzy(2)));
+
+    let gone = lines("fn a() {\n}\nfn b() {\n  return;\n}");
+    assert_eq!(anchor.locate(&gone, 2), None);
+  }
+
+  #[test]
+  fn anchor_looks_for_edited_code_near_its_old_line_only() {
+    let original = lines("fn a() {\n  let total = items.len();\n}");
+    let anchor = Anchor::capture(&original, 2, 2).expect("capture");
+    let filler = "\n".repeat(ANCHOR_FUZZY_WINDOW + 10);
+    let far_edited = lines(&format!(
+      "{filler}fn a() {{\n  let total = items.iter().count();\n}}"
+    ));
+    assert_eq!(anchor.locate(&far_edited, 2), None);
+    let far_moved = lines(&format!(
+      "{filler}fn a() {{\n  let total = items.len();\n}}"
+    ));
+    let moved_to = ANCHOR_FUZZY_WINDOW as u32 + 12;
+    assert_eq!(anchor.locate(&far_moved, 2), Some(Located::Exact(moved_to)));
+  }
+
+  #[test]
+  fn anchor_prefers_matching_context_over_distance() {
+    let original = lines("let a = 0;\nx += 1;\nlet b = 0;\nlet c = 0;\nx += 1;\nlet d = 0;");
+    let anchor = Anchor::capture(&original, 5, 5).expect("capture");
+    let swapped = lines("let c = 0;\nx += 1;\nlet d = 0;\nlet a = 0;\nx += 1;\nlet b = 0;");
+    assert_eq!(anchor.locate(&swapped, 5), Some(Located::Exact(2)));
+  }
+
+  #[test]
+  fn relocate_follows_code_and_flags_lost_comments() {
+    let original = lines("one\ntwo\nthree\nfour\nfive");
+    let key = |line| LineKey {
+      side: LineSide::New,
+      line,
+    };
+    let mut f = FileReview::default();
+    f.comments.insert(
+      key(2),
+      Comment {
+        body: "about two and three".to_string(),
+        end_line: Some(3),
+        anchor: Anchor::capture(&original, 2, 3),
+        ..Default::default()
+      },
+    );
+    f.comments.insert(
+      key(5),
+      Comment {
+        body: "about five".to_string(),
+        anchor: Anchor::capture(&original, 5, 5),
+        ..Default::default()
+      },
+    );
+
+    let current = lines("zero\none\ntwo\nthree\nfour");
+    let relocated = f.relocate(LineSide::New, &current);
+    assert_eq!(relocated.moved, vec![(key(2), key(3))]);
+    assert_eq!(relocated.outdated, vec![key(5)]);
+    let moved = &f.comments[&key(3)];
+    assert_eq!(moved.end_line, Some(4));
+    assert!(!moved.outdated);
+    assert!(f.comments[&key(5)].outdated);
+
+    // The lost code comes back unchanged: the comment is revived where it now is.
+    let restored = lines("zero\none\ntwo\nthree\nfour\nfive");
+    let relocated = f.relocate(LineSide::New, &restored);
+    assert_eq!(relocated.revived, vec![key(6)]);
+    assert!(!f.comments[&key(6)].outdated);
   }
 
   #[test]
@@ -642,7 +1968,7 @@ mod tests {
     let mut r = Review::new();
     r.set_file_comment("a.rs", "done".to_string());
     r.toggle_file_comment_resolved("a.rs");
-    let p = render_prompt(&r, |_, _| None);
+    let p = render_prompt(&r, |_, _, _| None);
     assert!(p.contains("No comments."));
   }
 
@@ -650,19 +1976,218 @@ mod tests {
   fn prompt_does_not_duplicate_file_comments_as_todos() {
     let mut r = Review::new();
     r.set_file_comment("a.rs", "Do thing".to_string());
-    let p = render_prompt(&r, |_, _| None);
+    let p = render_prompt(&r, |_, _, _| None);
     assert!(p.contains("## a.rs"));
     assert!(p.contains("### File comment"));
     assert!(p.contains("Do thing"));
     assert!(!p.contains("## TODOs"));
   }
 
+  #[test]
+  fn prompt_renders_thread_replies() {
+    let mut r = Review::new();
+    r.set_line_comment("a.rs", LineSide::New, 3, "Rename this".to_string());
+    r.files
+      .get_mut("a.rs")
+      .and_then(|f| {
+        f.comment_mut(Some(LineKey {
+          side: LineSide::New,
+          line: 3,
+        }))
+      })
+      .unwrap()
+      .replies
+      .push(Reply {
+        author: "Bob <bob@example.com>".to_string(),
+        at: 0,
+        body: "Done in the next commit".to_string(),
+      });
+
+    let p = render_prompt(&r, |_, _, _| None);
+    assert!(p.contains("Rename this"));
+    assert!(p.contains("- reply from Bob <bob@example.com> at 1970-01-01T00:00:00Z"));
+    assert!(p.contains("```reply\nDone in the next commit\n```"));
+  }
+
+  #[test]
+  fn prompt_renders_range_comments() {
+    let mut r = Review::new();
+    r.set_line_comment("a.rs", LineSide::New, 10, "Split this function".to_string());
+    r.set_line_comment_end("a.rs", LineSide::New, 10, Some(24));
+
+    let mut asked = None;
+    let p = render_prompt(&r, |_, key, last| {
+      asked = Some((key.line, last));
+      None
+    });
+    assert!(p.contains("- lines 10-24\n"));
+    assert_eq!(asked, Some((10, 24)));
+    assert_eq!(
+      r.line_comment_covering("a.rs", LineSide::New, 17)
+        .map(|(k, _)| k.line),
+      Some(10)
+    );
+    assert!(r.line_comment_covering("a.rs", LineSide::New, 25).is_none());
+    assert!(r.line_comment_covering("a.rs", LineSide::Old, 17).is_none());
+  }
+
+  #[test]
+  fn suggestion_roundtrips_through_body_and_preview() {
+    let comment = Comment {
+      body: "Use a constant".to_string(),
+      suggestion: Some(Suggestion {
+        original: vec!["let x = 1;".to_string()],
+        replacement: vec!["const X: i32 = 1;".to_string()],
+      }),
+      ..Default::default()
+    };
+
+    let (body, replacement) = take_suggestion(&body_with_suggestion(&comment));
+    assert_eq!(body, "Use a constant");
+    assert_eq!(replacement, Some(vec!["const X: i32 = 1;".to_string()]));
+
+    let preview = comment.suggestion.as_ref().expect("suggestion").preview();
+    assert_eq!(preview, "-let x = 1;\n+const X: i32 = 1;");
+    assert_eq!(
+      Suggestion::from_preview(&preview),
+      comment.suggestion.unwrap()
+    );
+  }
+
+  #[test]
+  fn tags_round_trip_through_body() {
+    let comment = Comment {
+      body: "Allocates per call\n\n#123 has context".to_string(),
+      severity: Some(Severity::Issue),
+      labels: vec!["perf".to_string(), "hot-path".to_string()],
+      ..Default::default()
+    };
+    let text = body_with_tags(&comment);
+    assert_eq!(
+      text,
+      "[issue] #perf #hot-path\nAllocates per call\n\n#123 has context"
+    );
+
+    let (body, tags) = take_tags(&text);
+    assert_eq!(body, comment.body);
+    assert_eq!(tags, Tags::of(&comment));
+
+    // A first line that isn't only tags stays part of the body.
+    let (body, tags) = take_tags("#123 is related");
+    assert_eq!(body, "#123 is related");
+    assert!(tags.is_empty());
+    assert_eq!(Tags::parse("[nit] fix"), None);
+  }
+
+  #[test]
+  fn resolution_is_kept_until_reopened() {
+    let key = LineKey {
+      side: LineSide::New,
+      line: 4,
+    };
+    let mut review = Review::new();
+    review.set_line_comment("src/lib.rs", key.side, key.line, "Off by one".to_string());
+    let comment = review
+      .files
+      .get_mut("src/lib.rs")
+      .and_then(|f| f.comment_mut(Some(key)))
+      .expect("comment");
+    comment.resolve(
+      "a <a@x>".to_string(),
+      Some("fixed the bound".to_string()),
+      Some("0123456789abcdef".to_string()),
+    );
+    // Resolving again without a message keeps what was recorded.
+    comment.resolve("b <b@x>".to_string(), None, None);
+    assert!(comment.resolved);
+    let resolution = comment.resolution.as_ref().expect("resolution");
+    assert_eq!(resolution.author, "a <a@x>");
+    assert_eq!(resolution.summary(), "fixed the bound, 0123456");
+
+    assert_eq!(
+      review.toggle_line_comment_resolved("src/lib.rs", key.side, key.line),
+      Some(false)
+    );
+    let reopened = review
+      .line_comment("src/lib.rs", key.side, key.line)
+      .expect("comment");
+    assert_eq!(reopened.resolution, None);
+  }
+
+  #[test]
+  fn comment_id_survives_edits_and_moves() {
+    let mut comment = Comment::default();
+    comment.stamp_author("a <a@x>");
+    let key = |line| {
+      Some(LineKey {
+        side: LineSide::New,
+        line,
+      })
+    };
+    let id = comment.id("src/lib.rs", key(3));
+
+    comment.body = "edited".to_string();
+    assert_eq!(comment.id("src/lib.rs", key(7)), id);
+    assert_ne!(comment.id("src/main.rs", key(3)), id);
+    assert_ne!(comment.id("src/lib.rs", None), id);
+
+    // Threads started by the same author in the same second still tell apart.
+    let mut twin = Comment::default();
+    twin.stamp_author("a <a@x>");
+    twin.created_at = comment.created_at;
+    assert_ne!(twin.id("src/lib.rs", key(3)), id);
+
+    // Without an author the location is all there is to go by.
+    let legacy = Comment::default();
+    assert_ne!(
+      legacy.id("src/lib.rs", key(3)),
+      legacy.id("src/lib.rs", key(4))
+    );
+  }
+
+  #[test]
+  fn prompt_lists_severe_comments_first() {
+    let mut r = Review::new();
+    for (line, body) in [(1, "plain"), (2, "nit"), (3, "blocker")] {
+      r.set_line_comment("a.rs", LineSide::New, line, body.to_string());
+    }
+    let file = r.files.get_mut("a.rs").expect("file");
+    for (line, severity) in [(2, Severity::Nit), (3, Severity::Blocker)] {
+      file
+        .comments
+        .get_mut(&LineKey {
+          side: LineSide::New,
+          line,
+        })
+        .expect("comment")
+        .severity = Some(severity);
+    }
+
+    let p = render_prompt(&r, |_, _, _| None);
+    let pos = |needle: &str| p.find(needle).expect(needle);
+    assert!(pos("- line 3") < pos("- line 1"));
+    assert!(pos("- line 1") < pos("- line 2"));
+  }
+
+  #[test]
+  fn suggestion_apply_keeps_line_endings() {
+    let suggestion = Suggestion {
+      original: vec!["b".to_string()],
+      replacement: vec!["x".to_string(), "y".to_string()],
+    };
+    assert_eq!(
+      suggestion.apply_to("a\r\nb\r\nc\r\n", 2, 2).expect("apply"),
+      "a\r\nx\r\ny\r\nc\r\n"
+    );
+    assert!(suggestion.apply_to("a\nz\n", 2, 2).is_err());
+  }
+
   #[test]
   fn prompt_includes_line_code_when_available() {
     let mut r = Review::new();
     r.set_line_comment("a.rs", LineSide::New, 10, "Fix this".to_string());
 
-    let p = render_prompt(&r, |path, key| {
+    let p = render_prompt(&r, |path, key, _| {
       if path == "a.rs" && key.side == LineSide::New && key.line == 10 {
         Some(PromptSnippet {
           code: "let x = 1;".to_string(),
@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+
+use crate::config::ReviveCli;
+use crate::review::{LineKey, LineSide};
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: ReviveCli,
+) -> Result<()> {
+  let file = crate::git::normalize_repo_path(repo, &cmd.file);
+  let from = LineKey {
+    side: cmd.side.unwrap_or(LineSide::New),
+    line: cmd.line,
+  };
+  let to = cmd.to.unwrap_or(cmd.line);
+
+  crate::reanchor::reanchor_notes(repo, notes_ref, base_ref.as_deref())?;
+  let mut file_review = crate::add_cmd::load_file_review(repo, notes_ref, &file)?
+    .with_context(|| format!("no comments on {file}"))?;
+  let target = LineKey { line: to, ..from };
+  let comment = file_review
+    .revive(from, to)
+    .with_context(|| format!("revive comment on {file}:{}", cmd.line))?;
+  comment.snippet_hash = crate::add_cmd::current_snippet_hash(
+    repo,
+    base_ref.as_deref(),
+    &file,
+    target,
+    comment.last_line(target),
+  );
+  crate::add_cmd::persist_file_review(repo, notes_ref, &file, Some(&file_review))?;
+  crate::add_cmd::sync_draft_notes(repo, notes_ref, base_ref.as_deref())?;
+  println!("Re-anchored comment to {file}:{to}.");
+  Ok(())
+}
@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+use crate::app::RenderRow;
+
+/// Byte ranges of the non-overlapping matches of `pattern` in `text`, left to right. Case is
+/// ignored unless the pattern has an uppercase letter.
+pub(crate) fn find(text: &str, pattern: &str) -> Vec<Range<usize>> {
+  if pattern.is_empty() {
+    return Vec::new();
+  }
+  let ignore_case = !pattern.chars().any(char::is_uppercase);
+  let pattern: Vec<char> = pattern.chars().collect();
+  let same = |a: char, b: char| a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()));
+
+  let chars: Vec<(usize, char)> = text.char_indices().collect();
+  let mut out = Vec::new();
+  let mut i = 0;
+  while i + pattern.len() <= chars.len() {
+    let hit = pattern
+      .iter()
+      .zip(&chars[i..])
+      .all(|(&p, &(_, c))| same(c, p));
+    if hit {
+      let start = chars[i].0;
+      let end = chars
+        .get(i + pattern.len())
+        .map_or(text.len(), |&(at, _)| at);
+      out.push(start..end);
+      i += pattern.len();
+    } else {
+      i += 1;
+    }
+  }
+  out
+}
+
+pub(crate) fn contains(text: &str, pattern: &str) -> bool {
+  !find(text, pattern).is_empty()
+}
+
+/// Whether the code shown on `row` matches. Headers and hunk separators are not searched.
+pub(crate) fn row_matches(row: &RenderRow, pattern: &str) -> bool {
+  let spans_match = |spans: &[ratatui::text::Span<'static>]| {
+    let text: String = spans.iter().map(|s| s.content.as_ref()).collect();
+    contains(&text, pattern)
+  };
+  match row {
+    RenderRow::Unified(r) => spans_match(&r.spans),
+    RenderRow::Decorated(r) => spans_match(&r.spans),
+    RenderRow::SideBySide(r) => spans_match(&r.left_spans) || spans_match(&r.right_spans),
+    RenderRow::FileHeader { .. } | RenderRow::Section { .. } => false,
+  }
+}
+
+/// How well `candidate` matches the fuzzy `query`, or `None` when the query's characters don't
+/// all appear in it in order. Runs of consecutive characters and characters that start a path
+/// segment or word score higher, as do matches in the file name; shorter candidates win ties.
+/// Case is handled as in [`find`].
+pub(crate) fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+  let ignore_case = !query.chars().any(char::is_uppercase);
+  let fold = |c: char| {
+    if ignore_case {
+      c.to_lowercase().next().unwrap_or(c)
+    } else {
+      c
+    }
+  };
+  let query: Vec<char> = query.chars().map(fold).collect();
+  let chars: Vec<char> = candidate.chars().collect();
+  if query.is_empty() {
+    return Some(-(chars.len() as i64));
+  }
+  let name_start = chars.iter().rposition(|&c| c == '/').map_or(0, |at| at + 1);
+  let bonus = |at: usize| -> i64 {
+    let start = match at.checked_sub(1).map(|prev| chars[prev]) {
+      None | Some('/') => 8,
+      Some('_' | '-' | '.' | ' ') => 6,
+      Some(prev) if prev.is_lowercase() && chars[at].is_uppercase() => 6,
+      _ => 0,
+    };
+    start + if at >= name_start { 2 } else { 0 }
+  };
+
+  // best[at]: the best score of the query so far with its last character matched at `at`.
+  let mut best: Vec<Option<i64>> = vec![None; chars.len()];
+  for (qi, &q) in query.iter().enumerate() {
+    let mut next = vec![None; chars.len()];
+    // Best score of the previous query character at any position before `at - 1`; coming from
+    // there skips characters, which costs a little.
+    let mut earlier: Option<i64> = None;
+    for at in 0..chars.len() {
+      let before = if qi == 0 {
+        Some(0)
+      } else {
+        let adjacent = at.checked_sub(1).and_then(|prev| best[prev]).map(|s| s + 5);
+        adjacent.max(earlier.map(|s| s - 6))
+      };
+      if qi > 0 && at > 0 {
+        earlier = earlier.max(best[at - 1]);
+      }
+      if fold(chars[at]) == q
+        && let Some(before) = before
+      {
+        next[at] = Some(before + 16 + bonus(at));
+      }
+    }
+    best = next;
+  }
+  let top = best.into_iter().flatten().max()?;
+  Some(top - chars.len() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn find_uses_smart_case() {
+    assert_eq!(find("Foo foo FOO", "foo"), [0..3, 4..7, 8..11]);
+    assert_eq!(find("Foo foo FOO", "Foo"), [0..3]);
+    assert_eq!(find("aaaa", "aa"), [0..2, 2..4]);
+    assert_eq!(find("ÄPFEL äpfel", "äpfel"), [0..6, 7..13]);
+    assert!(find("abc", "").is_empty());
+    assert!(!contains("abc", "abcd"));
+  }
+
+  #[test]
+  fn fuzzy_score_prefers_tight_and_boundary_matches() {
+    let rank = |query: &str, candidates: &[&'static str]| {
+      let mut scored: Vec<_> = candidates
+        .iter()
+        .filter_map(|c| fuzzy_score(c, query).map(|score| (score, *c)))
+        .collect();
+      scored.sort_by(|a, b| b.0.cmp(&a.0));
+      scored.into_iter().map(|(_, c)| c).collect::<Vec<_>>()
+    };
+    assert_eq!(
+      rank(
+        "fitr",
+        &["src/fixtures/intro.rs", "src/file_tree.rs", "README.md"]
+      ),
+      ["src/file_tree.rs", "src/fixtures/intro.rs"]
+    );
+    assert_eq!(
+      rank(
+        "app",
+        &["src/apply_suggestion_cmd.rs", "src/app.rs", "a/p/p.rs"]
+      ),
+      ["src/app.rs", "a/p/p.rs", "src/apply_suggestion_cmd.rs"]
+    );
+    assert_eq!(fuzzy_score("src/app.rs", "App"), None);
+    assert!(fuzzy_score("src/App.rs", "App").is_some());
+    assert!(fuzzy_score("src/app.rs", "").is_some());
+  }
+}
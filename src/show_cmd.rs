@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::config::{OutputFormat, ShowCli};
+use crate::list_cmd::{CommentEntry, comment_entries};
+use crate::review::FileReview;
+
+#[derive(Debug, Serialize)]
+struct FileEntry<'a> {
+  file: &'a str,
+  reviewed: bool,
+  comments: Vec<CommentEntry<'a>>,
+}
+
+pub fn run(
+  repo: &gix::Repository,
+  notes_ref: &str,
+  base_ref: Option<String>,
+  cmd: ShowCli,
+) -> Result<()> {
+  let path = crate::git::normalize_repo_path(repo, &cmd.file);
+  let review = crate::list_cmd::load_notes_review(
+    repo,
+    notes_ref,
+    base_ref.as_deref(),
+    std::slice::from_ref(&path),
+  )?;
+  let empty = FileReview::default();
+  let file = review.files.get(&path).unwrap_or(&empty);
+  let entry = FileEntry {
+    file: &path,
+    reviewed: file.reviewed,
+    comments: comment_entries(&path, file),
+  };
+
+  match cmd.format {
+    OutputFormat::Json => {
+      println!(
+        "{}",
+        serde_json::to_string_pretty(&entry).context("serialize comments")?
+      );
+    }
+    OutputFormat::Text => print!("{}", render_text(&entry)),
+  }
+  Ok(())
+}
+
+fn render_text(entry: &FileEntry<'_>) -> String {
+  let mut out = String::new();
+  out.push_str(entry.file);
+  if entry.reviewed {
+    out.push_str(" (reviewed)");
+  }
+  out.push('\n');
+  if entry.comments.is_empty() {
+    out.push_str("\nNo comments.\n");
+  }
+
+  for comment in &entry.comments {
+    out.push('\n');
+    let what = match comment.line {
+      None => "file comment".to_string(),
+      Some(_) => comment.location(),
+    };
+    out.push_str(&format!("{what} [{}]", comment.state()));
+    let tags = comment.tags();
+    if !tags.is_empty() {
+      out.push_str(&format!(" {tags}"));
+    }
+    if let Some(author) = comment.author {
+      out.push_str(&format!(" by {author}"));
+    }
+    out.push('\n');
+    for line in comment.body.lines() {
+      out.push_str(&format!("  {line}\n"));
+    }
+    if let Some(suggestion) = comment.suggestion {
+      out.push_str("  suggestion:\n");
+      for line in suggestion.preview().lines() {
+        out.push_str(&format!("    {line}\n"));
+      }
+    }
+    for reply in comment.replies {
+      let mut lines = reply.body.lines();
+      let first = lines.next().unwrap_or_default();
+      out.push_str(&format!("  > {}: {first}\n", reply.author));
+      for line in lines {
+        out.push_str(&format!("    {line}\n"));
+      }
+    }
+    if let Some(resolution) = comment.resolution {
+      out.push_str(&format!("  resolved by {}", resolution.author));
+      if let Some(commit) = &resolution.commit {
+        out.push_str(&format!(" in {}", crate::review::short_commit(commit)));
+      }
+      out.push('\n');
+      for line in resolution.message.as_deref().unwrap_or_default().lines() {
+        out.push_str(&format!("    {line}\n"));
+      }
+    }
+  }
+  out
+}
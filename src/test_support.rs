@@ -0,0 +1,20 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Runs `git -C dir args` as the test identity, asserts it succeeded and returns its stdout.
+pub(crate) fn run_git(dir: &Path, args: &[&str]) -> String {
+  let out = Command::new("git")
+    .arg("-C")
+    .arg(dir)
+    .args([
+      "-c",
+      "user.name=remark-test",
+      "-c",
+      "user.email=remark-test@localhost",
+    ])
+    .args(args)
+    .output()
+    .expect("spawn git");
+  assert!(out.status.success(), "git {args:?} failed");
+  String::from_utf8(out.stdout).expect("utf8")
+}
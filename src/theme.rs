@@ -0,0 +1,313 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use verdant::theme::ResolvedTheme;
+
+/// Colours of the TUI itself: panes, diff markers and backgrounds, popups.
+#[derive(
+  Debug,
+  Clone,
+  Copy,
+  PartialEq,
+  Eq,
+  Default,
+  Deserialize,
+  clap::ValueEnum
+)]
+#[serde(rename_all = "lowercase")]
+pub enum UiTheme {
+  #[default]
+  Dark,
+  Light,
+  /// No colours at all, only bold, dim, underline and reverse video.
+  Mono,
+}
+
+/// Colours of the highlighted code: one of the themes verdant-themes ships, by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SyntaxTheme(&'static str);
+
+impl SyntaxTheme {
+  pub(crate) const CATPPUCCIN_LATTE: Self = Self("catppuccin::latte");
+  pub(crate) const CATPPUCCIN_MOCHA: Self = Self("catppuccin::mocha");
+
+  /// Every theme name, as `--syntax-theme` and `syntax_theme` take it (`catppuccin-mocha` for
+  /// verdant's `catppuccin::mocha`).
+  pub(crate) fn names() -> impl Iterator<Item = String> {
+    verdant_themes::THEMES
+      .iter()
+      .map(|name| name.replace("::", "-"))
+  }
+
+  pub(crate) fn resolve(self) -> ResolvedTheme {
+    verdant_themes::from_str(self.0).expect("syntax theme names come from verdant-themes")
+  }
+}
+
+impl std::str::FromStr for SyntaxTheme {
+  type Err = String;
+
+  /// Takes the name in either form, `catppuccin-mocha` or `catppuccin::mocha`.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let wanted = s.trim().replace("::", "-");
+    verdant_themes::THEMES
+      .iter()
+      .copied()
+      .find(|name| name.replace("::", "-").eq_ignore_ascii_case(&wanted))
+      .map(Self)
+      .ok_or_else(|| {
+        let names: Vec<String> = Self::names().collect();
+        format!(
+          "unknown syntax theme {s:?}; expected one of: {}",
+          names.join(", ")
+        )
+      })
+  }
+}
+
+impl TryFrom<String> for SyntaxTheme {
+  type Error = String;
+
+  fn try_from(s: String) -> Result<Self, Self::Error> {
+    s.parse()
+  }
+}
+
+impl std::fmt::Display for SyntaxTheme {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(&self.0.replace("::", "-"))
+  }
+}
+
+/// The palette and syntax theme the TUI draws with.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Theme {
+  pub(crate) palette: Palette,
+  /// `None` leaves code uncoloured.
+  pub(crate) syntax: Option<SyntaxTheme>,
+}
+
+impl Theme {
+  /// The syntax theme defaults to one that suits the UI theme. `no_color` (the `NO_COLOR`
+  /// convention) turns every colour off, whatever was picked.
+  pub(crate) fn new(ui: UiTheme, syntax: Option<SyntaxTheme>, no_color: bool) -> Self {
+    let ui = if no_color { UiTheme::Mono } else { ui };
+    let syntax = match ui {
+      UiTheme::Dark => Some(syntax.unwrap_or(SyntaxTheme::CATPPUCCIN_MOCHA)),
+      UiTheme::Light => Some(syntax.unwrap_or(SyntaxTheme::CATPPUCCIN_LATTE)),
+      UiTheme::Mono => None,
+    };
+    Self {
+      palette: Palette::for_theme(ui),
+      syntax,
+    }
+  }
+}
+
+impl Default for Theme {
+  fn default() -> Self {
+    Self::new(UiTheme::Dark, None, false)
+  }
+}
+
+/// Whether `NO_COLOR` is set to something non-empty (see no-color.org).
+pub(crate) fn no_color_requested() -> bool {
+  std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Styles of everything the TUI draws around the highlighted code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Palette {
+  /// Focused borders, directories, file names in headers and popup frames.
+  pub(crate) accent: Style,
+  /// Line numbers, separators, counts and other secondary text.
+  pub(crate) muted: Style,
+  /// Added lines and files.
+  pub(crate) added: Style,
+  /// Removed lines and files.
+  pub(crate) removed: Style,
+  /// Modified lines and files, and hunk headers.
+  pub(crate) modified: Style,
+  pub(crate) untracked: Style,
+  /// Renamed, copied and type-changed files.
+  pub(crate) renamed: Style,
+  /// Background of added lines.
+  pub(crate) added_bg: Style,
+  /// Background of removed lines.
+  pub(crate) removed_bg: Style,
+  pub(crate) unresolved: Style,
+  pub(crate) resolved: Style,
+  /// The file header row of the diff.
+  pub(crate) file_header: Style,
+  /// The separator row between hunks.
+  pub(crate) hunk_header: Style,
+  /// Lines of a visual selection.
+  pub(crate) selection: Style,
+  pub(crate) search_match: Style,
+  /// Laid over reviewed files and viewed hunks.
+  pub(crate) reviewed: Style,
+  /// Background of the diff popup.
+  pub(crate) popup: Style,
+  /// The comment editor's frame.
+  pub(crate) editor: Style,
+  pub(crate) footer: Style,
+  /// The cursor of the comment and prompt editors.
+  pub(crate) cursor: Style,
+}
+
+impl Palette {
+  pub(crate) fn for_theme(theme: UiTheme) -> Self {
+    match theme {
+      UiTheme::Dark => Self::dark(),
+      UiTheme::Light => Self::light(),
+      UiTheme::Mono => Self::mono(),
+    }
+  }
+
+  fn dark() -> Self {
+    let fg = |color: Color| Style::default().fg(color);
+    let bg = |color: Color| Style::default().bg(color);
+    Self {
+      accent: fg(Color::Cyan),
+      muted: fg(Color::DarkGray),
+      added: fg(Color::Green),
+      removed: fg(Color::Red),
+      modified: fg(Color::Yellow),
+      untracked: fg(Color::Magenta),
+      renamed: fg(Color::Cyan),
+      added_bg: bg(Color::Rgb(18, 40, 24)),
+      removed_bg: bg(Color::Rgb(48, 20, 22)),
+      unresolved: fg(Color::Yellow),
+      resolved: fg(Color::Green),
+      file_header: bg(Color::Rgb(25, 25, 25)),
+      hunk_header: fg(Color::Cyan)
+        .bg(Color::Rgb(30, 30, 30))
+        .add_modifier(Modifier::BOLD),
+      selection: bg(Color::Rgb(40, 50, 80)),
+      search_match: fg(Color::Black).bg(Color::Yellow),
+      reviewed: Style::default().add_modifier(Modifier::DIM),
+      popup: bg(Color::Black),
+      editor: fg(Color::Yellow),
+      footer: fg(Color::Gray),
+      cursor: fg(Color::Black).bg(Color::Yellow),
+    }
+  }
+
+  /// For terminals with a light background: yellow and the dark backgrounds of the dark palette
+  /// are unreadable there.
+  fn light() -> Self {
+    let fg = |color: Color| Style::default().fg(color);
+    let bg = |color: Color| Style::default().bg(color);
+    let amber = Color::Rgb(170, 110, 0);
+    Self {
+      accent: fg(Color::Blue),
+      muted: fg(Color::Rgb(120, 120, 120)),
+      added: fg(Color::Rgb(20, 130, 40)),
+      removed: fg(Color::Rgb(190, 30, 40)),
+      modified: fg(amber),
+      untracked: fg(Color::Magenta),
+      renamed: fg(Color::Blue),
+      added_bg: bg(Color::Rgb(222, 245, 225)),
+      removed_bg: bg(Color::Rgb(252, 228, 228)),
+      unresolved: fg(amber),
+      resolved: fg(Color::Rgb(20, 130, 40)),
+      file_header: bg(Color::Rgb(235, 235, 235)),
+      hunk_header: fg(Color::Blue)
+        .bg(Color::Rgb(228, 232, 242))
+        .add_modifier(Modifier::BOLD),
+      selection: bg(Color::Rgb(205, 218, 245)),
+      search_match: fg(Color::Black).bg(Color::Rgb(255, 222, 110)),
+      reviewed: Style::default().add_modifier(Modifier::DIM),
+      popup: Style::default(),
+      editor: fg(amber),
+      footer: fg(Color::Rgb(90, 90, 90)),
+      cursor: fg(Color::White).bg(Color::Rgb(60, 60, 60)),
+    }
+  }
+
+  fn mono() -> Self {
+    let plain = Style::default();
+    let bold = plain.add_modifier(Modifier::BOLD);
+    Self {
+      accent: bold,
+      muted: plain,
+      added: bold,
+      removed: plain,
+      modified: plain,
+      untracked: plain.add_modifier(Modifier::ITALIC),
+      renamed: plain,
+      added_bg: plain,
+      removed_bg: plain,
+      unresolved: bold,
+      resolved: plain,
+      file_header: bold,
+      hunk_header: bold,
+      selection: plain.add_modifier(Modifier::UNDERLINED),
+      search_match: plain.add_modifier(Modifier::REVERSED),
+      reviewed: plain.add_modifier(Modifier::DIM),
+      popup: plain,
+      editor: bold,
+      footer: plain,
+      cursor: plain.add_modifier(Modifier::REVERSED),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn no_color_wins_over_the_chosen_themes() {
+    let theme = Theme::new(UiTheme::Light, Some(SyntaxTheme::CATPPUCCIN_MOCHA), true);
+    assert_eq!(theme.palette, Palette::for_theme(UiTheme::Mono));
+    assert_eq!(theme.syntax, None);
+
+    let theme = Theme::new(UiTheme::Light, None, false);
+    assert_eq!(theme.syntax, Some(SyntaxTheme::CATPPUCCIN_LATTE));
+    let frappe: SyntaxTheme = "catppuccin-frappe".parse().unwrap();
+    let theme = Theme::new(UiTheme::Light, Some(frappe), false);
+    assert_eq!(theme.syntax, Some(frappe));
+    assert_eq!(Theme::default().syntax, Some(SyntaxTheme::CATPPUCCIN_MOCHA));
+  }
+
+  #[test]
+  fn syntax_themes_are_verdants() {
+    assert!(SyntaxTheme::names().count() > 4);
+    for name in SyntaxTheme::names() {
+      let theme: SyntaxTheme = name.parse().unwrap();
+      assert_eq!(theme.to_string(), name);
+      theme.resolve();
+    }
+    assert_eq!(
+      "catppuccin::mocha".parse::<SyntaxTheme>(),
+      Ok(SyntaxTheme::CATPPUCCIN_MOCHA)
+    );
+    assert_eq!(
+      "catppuccin-latte".parse::<SyntaxTheme>(),
+      Ok(SyntaxTheme::CATPPUCCIN_LATTE)
+    );
+
+    let err = "nope".parse::<SyntaxTheme>().unwrap_err();
+    assert!(err.contains("\"nope\""), "{err}");
+    assert!(err.contains("catppuccin-mocha"), "{err}");
+  }
+
+  #[test]
+  fn mono_palette_has_no_colours() {
+    let palette = Palette::for_theme(UiTheme::Mono);
+    for style in [
+      palette.accent,
+      palette.added,
+      palette.removed,
+      palette.added_bg,
+      palette.hunk_header,
+      palette.selection,
+      palette.search_match,
+      palette.popup,
+      palette.cursor,
+    ] {
+      assert_eq!((style.fg, style.bg), (None, None), "{style:?}");
+    }
+  }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{Stdout, stdout};
 
 use anyhow::{Context, Result};
@@ -13,19 +13,22 @@ use crossterm::terminal::{
 use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use ratatui_textarea::TextArea;
 use unicode_width::UnicodeWidthChar;
 
 use crate::app::{
-  CommentListEntry, CommentLocator, CommentTarget, DiffViewMode, FileEntry, Focus, Mode, RenderRow,
-  SideBySideRow,
+  CommentListEntry, CommentLocator, CommentTarget, DiffViewMode, FileEntry, FileFinder, Focus,
+  Mode, RenderRow, SideBySideRow,
 };
+use crate::file_filter::FileFilter;
 use crate::file_tree::FileTreeRow;
 use crate::git::ViewKind;
+use crate::keymap::{Action, Context as KeyContext, Keymap};
 use crate::review::{CommentState, Review};
+use crate::theme::Palette;
 
 pub struct Ui {
   pub terminal: Terminal<CrosstermBackend<Stdout>>,
@@ -103,6 +106,8 @@ pub fn layout(area: Rect) -> LayoutRects {
 pub struct DrawState<'a> {
   pub view: ViewKind,
   pub base_ref: Option<&'a str>,
+  /// Commit or range under review in the `commit`/`range` views.
+  pub rev_label: Option<String>,
   pub focus: Focus,
   pub mode: Mode,
   pub diff_view_mode: DiffViewMode,
@@ -110,6 +115,12 @@ pub struct DrawState<'a> {
   pub review: &'a Review,
 
   pub files: &'a [FileEntry],
+  /// Number of files in the view before filtering.
+  pub listed_files: usize,
+  pub file_filter: &'a FileFilter,
+  /// Glob being typed in the filter menu.
+  pub filter_glob_input: Option<&'a str>,
+  pub file_finder: Option<&'a FileFinder>,
   pub file_rows: &'a [FileTreeRow],
   pub file_selected: usize,
   pub file_row_selected: Option<usize>,
@@ -119,19 +130,39 @@ pub struct DrawState<'a> {
   pub diff_cursor: usize,
   pub diff_scroll: u16,
   pub diff_cursor_visual: u32,
+  /// Diff rows covered by the active visual selection (inclusive), if any.
+  pub visual_range: Option<(usize, usize)>,
+  /// An outdated comment is waiting to be re-anchored to the cursor line.
+  pub reanchoring: bool,
   pub reviewed_files: &'a HashSet<String>,
+  /// The diff's old side is the file as last reviewed rather than a git side.
+  pub interdiff: bool,
+  /// Whether each diff row belongs to a hunk marked viewed.
+  pub viewed_rows: &'a [bool],
+  /// Viewed and total hunks of partly viewed files.
+  pub hunk_progress: &'a HashMap<String, (usize, usize)>,
+  /// Active search; its matches are highlighted in the diff.
+  pub search_pattern: Option<&'a str>,
+  /// The search being typed, as shown in the footer (`/pattern`).
+  pub search_prompt: Option<String>,
 
   pub editor_target: Option<&'a CommentTarget>,
+  pub editor_reply: bool,
+  pub editor_resolve: bool,
+  pub editor_range_end: Option<u32>,
   pub editor_buffer: &'a TextArea<'static>,
   pub prompt_buffer: &'a TextArea<'static>,
 
   pub status: &'a str,
   pub show_help: bool,
+  pub keymap: &'a Keymap,
+  pub palette: &'a Palette,
   pub show_prompt: bool,
   pub show_diff_popup: bool,
   pub comment_list: &'a [CommentListEntry],
   pub comment_list_selected: usize,
   pub comment_list_marked: &'a HashSet<usize>,
+  pub comment_list_severity: Option<crate::review::Severity>,
 
   // Diff data for popup
   pub current_diff_lines: &'a [crate::diff::Line],
@@ -153,7 +184,7 @@ pub fn draw(f: &mut ratatui::Frame, s: DrawState<'_>) {
   draw_footer(f, footer, &s);
 
   if s.show_help && s.mode == Mode::Browse {
-    draw_help(f, outer);
+    draw_help(f, outer, s.keymap);
   }
   if s.show_prompt {
     draw_prompt(f, outer, &s);
@@ -164,6 +195,12 @@ pub fn draw(f: &mut ratatui::Frame, s: DrawState<'_>) {
   if s.mode == Mode::CommentList {
     draw_comment_list(f, outer, &s);
   }
+  if s.mode == Mode::FileFinder {
+    draw_file_finder(f, outer, &s);
+  }
+  if s.mode == Mode::FileFilter {
+    draw_file_filter(f, outer, &s);
+  }
   if s.show_diff_popup && s.mode == Mode::Browse {
     draw_diff_popup(f, outer, &s);
   }
@@ -176,11 +213,25 @@ fn draw_files(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
     (ViewKind::Staged, _) => "Files (staged)",
     (ViewKind::Base, Some(_)) => "Files (base)",
     (ViewKind::Base, None) => "Files (base: unset)",
+    (ViewKind::Commit, _) if s.rev_label.is_some() => "Files (commit)",
+    (ViewKind::Range, _) if s.rev_label.is_some() => "Files (range)",
+    (ViewKind::Commit | ViewKind::Range, _) => "Files (commit: unset)",
+  };
+
+  let title = if s.file_filter.is_active() {
+    format!(
+      "{title} [{}] {}/{}",
+      s.file_filter.describe(),
+      s.files.len(),
+      s.listed_files
+    )
+  } else {
+    title.to_string()
   };
 
   let mut block = Block::default().borders(Borders::ALL).title(title);
   if s.mode == Mode::Browse && s.focus == Focus::Files {
-    block = block.border_style(Style::default().fg(Color::Cyan));
+    block = block.border_style(s.palette.accent);
   }
 
   let inner = block.inner(area);
@@ -193,12 +244,27 @@ fn draw_files(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   let mut items = Vec::with_capacity(end.saturating_sub(scroll));
   for row in &s.file_rows[scroll..end] {
     if row.is_dir {
-      items.push(ListItem::new(Line::from(vec![Span::styled(
-        row.label.clone(),
-        Style::default()
-          .fg(Color::Cyan)
-          .add_modifier(Modifier::BOLD),
-      )])));
+      let label = if row.collapsed {
+        format!("{}…", row.label)
+      } else {
+        row.label.clone()
+      };
+      let paths = || row.dir_files.iter().filter_map(|&idx| s.files.get(idx));
+      let reviewed = paths()
+        .filter(|e| s.reviewed_files.contains(&e.path))
+        .count();
+      let open: usize = paths().map(|e| open_comment_count(s.review, &e.path)).sum();
+      let mut spans = vec![
+        Span::styled(label, s.palette.accent.add_modifier(Modifier::BOLD)),
+        Span::styled(
+          format!(" {reviewed}/{}", row.dir_files.len()),
+          s.palette.muted,
+        ),
+      ];
+      if open > 0 {
+        spans.push(Span::styled(format!(" 💬{open}"), s.palette.muted));
+      }
+      items.push(ListItem::new(Line::from(spans)));
       continue;
     }
 
@@ -211,18 +277,18 @@ fn draw_files(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
 
     let state = s.review.comment_state(&e.path);
 
-    let mut name_style = git_status_style(e.git_xy);
+    let mut name_style = git_status_style(e.git_xy, s.palette);
     match state {
       CommentState::HasUnresolved => {
         name_style = name_style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
       }
       CommentState::ResolvedOnly => {
-        name_style = name_style.add_modifier(Modifier::DIM);
+        name_style = name_style.patch(s.palette.reviewed);
       }
       CommentState::None => {
-        // Only apply DIM for reviewed files without unresolved comments
+        // Only dim reviewed files without unresolved comments
         if s.reviewed_files.contains(&e.path) {
-          name_style = name_style.add_modifier(Modifier::DIM);
+          name_style = name_style.patch(s.palette.reviewed);
         }
       }
     }
@@ -231,9 +297,11 @@ fn draw_files(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
     } else {
       row.label.clone()
     };
-    items.push(ListItem::new(Line::from(vec![Span::styled(
-      label, name_style,
-    )])));
+    let mut spans = vec![Span::styled(label, name_style)];
+    if let Some((viewed, total)) = s.hunk_progress.get(&e.path) {
+      spans.push(Span::styled(format!(" {viewed}/{total}"), s.palette.muted));
+    }
+    items.push(ListItem::new(Line::from(spans)));
   }
 
   let list = List::new(items)
@@ -250,35 +318,44 @@ fn draw_files(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   f.render_stateful_widget(list, inner, &mut state);
 }
 
-fn git_status_style(xy: [char; 2]) -> Style {
+/// Unresolved comments of `path`, not counting outdated ones.
+fn open_comment_count(review: &Review, path: &str) -> usize {
+  review.files.get(path).map_or(0, |f| {
+    f.file_comment
+      .iter()
+      .chain(f.comments.values().filter(|c| !c.outdated))
+      .filter(|c| !c.resolved)
+      .count()
+  })
+}
+
+fn git_status_style(xy: [char; 2], palette: &Palette) -> Style {
   let [x, y] = xy;
   if x == '-' && y == '-' {
-    return Style::default().fg(Color::DarkGray);
+    return palette.muted;
   }
   if y == 'N' {
-    return Style::default().fg(Color::Magenta);
+    return palette.untracked;
   }
   if y == 'I' {
-    return Style::default().fg(Color::DarkGray);
+    return palette.muted;
   }
   if x == 'A' || y == 'A' {
-    return Style::default().fg(Color::Green);
+    return palette.added;
   }
   if x == 'D' || y == 'D' {
-    return Style::default().fg(Color::Red);
+    return palette.removed;
   }
   if x == 'R' || y == 'R' || x == 'C' || y == 'C' {
-    return Style::default().fg(Color::Cyan);
+    return palette.renamed;
   }
   if x == 'T' || y == 'T' {
-    return Style::default().fg(Color::Blue);
+    return palette.renamed;
   }
   if x == 'U' || y == 'U' {
-    return Style::default()
-      .fg(Color::Yellow)
-      .add_modifier(Modifier::BOLD);
+    return palette.modified.add_modifier(Modifier::BOLD);
   }
-  Style::default().fg(Color::Yellow)
+  palette.modified
 }
 
 fn mark_reviewed_label(label: &str) -> String {
@@ -295,17 +372,24 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   } else {
     s.files
       .get(s.file_selected)
-      .map(|e| match s.review.comment_state(&e.path) {
-        CommentState::HasUnresolved => format!("Diff — {} 💬", e.path),
-        CommentState::ResolvedOnly => format!("Diff — {} ✓", e.path),
-        CommentState::None => format!("Diff — {}", e.path),
+      .map(|e| {
+        let path = if s.interdiff {
+          format!("{} (since last review)", e.path)
+        } else {
+          e.path.clone()
+        };
+        match s.review.comment_state(&e.path) {
+          CommentState::HasUnresolved => format!("Diff — {path} 💬"),
+          CommentState::ResolvedOnly => format!("Diff — {path} ✓"),
+          CommentState::None => format!("Diff — {path}"),
+        }
       })
       .unwrap_or_else(|| "Diff".to_string())
   };
 
   let mut block = Block::default().borders(Borders::ALL).title(title);
   if s.mode == Mode::Browse && s.focus == Focus::Diff {
-    block = block.border_style(Style::default().fg(Color::Cyan));
+    block = block.border_style(s.palette.accent);
   }
 
   let inner = block.inner(area);
@@ -389,6 +473,17 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
       }
       _ => None,
     };
+    // Old-side comments belong to the base, not to the snapshot an interdiff shows.
+    let locator = locator.filter(|l| {
+      !(s.interdiff
+        && matches!(
+          l,
+          CommentLocator::Line {
+            side: crate::review::LineSide::Old,
+            ..
+          }
+        ))
+    });
 
     let marker_state = match (path, locator) {
       (Some(p), Some(CommentLocator::File)) => s.review.file_comment(p).and_then(|c| {
@@ -402,7 +497,7 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
       }),
       (Some(p), Some(CommentLocator::Line { side, line })) => {
         s.review.line_comment(p, side, line).and_then(|c| {
-          if c.body.trim().is_empty() {
+          if c.body.trim().is_empty() || c.outdated {
             None
           } else if c.resolved {
             Some(CommentState::ResolvedOnly)
@@ -413,15 +508,36 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
       }
       _ => None,
     };
+    // Lines inside a range comment (after its first line) get a continuation marker.
+    let continued = match (path, locator) {
+      (Some(p), Some(CommentLocator::Line { side, line })) if marker_state.is_none() => s
+        .review
+        .line_comment_covering(p, side, line)
+        .filter(|(key, c)| key.line != line && !c.body.trim().is_empty() && !c.outdated)
+        .map(|(_, c)| {
+          if c.resolved {
+            CommentState::ResolvedOnly
+          } else {
+            CommentState::HasUnresolved
+          }
+        }),
+      _ => None,
+    };
+    let outdated = match (path, locator) {
+      (Some(p), Some(CommentLocator::Line { side, line })) => s
+        .review
+        .line_comment(p, side, line)
+        .is_some_and(|c| c.outdated && !c.body.trim().is_empty()),
+      _ => false,
+    };
+    let (marker, marker_style) = if outdated && marker_state.is_none() && continued.is_none() {
+      ("◌ ", s.palette.muted)
+    } else {
+      comment_marker(marker_state, continued, s.palette)
+    };
 
     match row {
       RenderRow::FileHeader { path } => {
-        // Keep this gutter column a fixed width (emoji are often 2 cells).
-        let (marker, marker_style) = match marker_state {
-          Some(CommentState::HasUnresolved) => ("💬", Style::default().fg(Color::Yellow)),
-          Some(CommentState::ResolvedOnly) => ("✓ ", Style::default().fg(Color::Green)),
-          _ => ("  ", Style::default().fg(Color::Reset)),
-        };
         let old_s = " ".repeat(old_w);
         let new_s = " ".repeat(new_w);
 
@@ -429,56 +545,45 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
           Span::styled(marker.to_string(), marker_style),
           Span::raw(" "),
           Span::raw(" "),
-          Span::styled(old_s, Style::default().fg(Color::DarkGray)),
+          Span::styled(old_s, s.palette.muted),
           Span::raw(" "),
-          Span::styled(new_s, Style::default().fg(Color::DarkGray)),
-          Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
-          Span::styled(
-            path.clone(),
-            Style::default()
-              .fg(Color::Cyan)
-              .add_modifier(Modifier::BOLD),
-          ),
+          Span::styled(new_s, s.palette.muted),
+          Span::styled(" │ ", s.palette.muted),
+          Span::styled(path.clone(), s.palette.accent.add_modifier(Modifier::BOLD)),
         ];
 
-        let mut style = Style::default().bg(Color::Rgb(25, 25, 25));
+        let mut style = s.palette.file_header;
         if abs_idx == s.diff_cursor {
           style = style.add_modifier(Modifier::REVERSED);
         }
         rendered.push(Line::from(spans).style(style));
       }
       RenderRow::Section { text } => {
-        let mut deco = format!("┄┄ {text} ┄┄");
+        let mut deco = if s.viewed_rows.get(abs_idx).copied().unwrap_or(false) {
+          format!("┄┄ ✓ {text} ┄┄")
+        } else {
+          format!("┄┄ {text} ┄┄")
+        };
         let w = inner.width.max(1) as usize;
         let len = deco.chars().count();
         if len < w {
           deco.push_str(&" ".repeat(w - len));
         }
-        let mut style = Style::default()
-          .fg(Color::Cyan)
-          .bg(Color::Rgb(30, 30, 30))
-          .add_modifier(Modifier::BOLD);
+        let mut style = s.palette.hunk_header;
         if abs_idx == s.diff_cursor {
           style = style.add_modifier(Modifier::REVERSED);
         }
         rendered.push(Line::from(Span::styled(deco, style)));
       }
       RenderRow::Unified(r) => {
-        // Keep this gutter column a fixed width (emoji are often 2 cells).
-        let (marker, marker_style) = match marker_state {
-          Some(CommentState::HasUnresolved) => ("💬", Style::default().fg(Color::Yellow)),
-          Some(CommentState::ResolvedOnly) => ("✓ ", Style::default().fg(Color::Green)),
-          _ => ("  ", Style::default().fg(Color::Reset)),
-        };
-
         // Color line numbers instead of using +/-
         let old_line_style = match r.kind {
-          crate::diff::Kind::Remove => Style::default().fg(Color::Red),
-          _ => Style::default().fg(Color::DarkGray),
+          crate::diff::Kind::Remove => s.palette.removed,
+          _ => s.palette.muted,
         };
         let new_line_style = match r.kind {
-          crate::diff::Kind::Add => Style::default().fg(Color::Green),
-          _ => Style::default().fg(Color::DarkGray),
+          crate::diff::Kind::Add => s.palette.added,
+          _ => s.palette.muted,
         };
 
         let old_s = r
@@ -496,42 +601,61 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
         spans.push(Span::styled(old_s, old_line_style));
         spans.push(Span::raw(" "));
         spans.push(Span::styled(new_s, new_line_style));
-        spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
-        spans.extend(r.spans.iter().cloned());
+        spans.push(Span::styled(" │ ", s.palette.muted));
+        spans.extend(highlight_matches(
+          &r.spans,
+          s.search_pattern,
+          s.palette.search_match,
+        ));
 
-        let mut style = Style::default();
+        let mut style = match r.kind {
+          crate::diff::Kind::Add => s.palette.added_bg,
+          crate::diff::Kind::Remove => s.palette.removed_bg,
+          _ => Style::default(),
+        };
         if abs_idx == s.diff_cursor {
           style = style.add_modifier(Modifier::REVERSED);
         }
         rendered.push(Line::from(spans).style(style));
       }
       RenderRow::SideBySide(r) => {
+        let highlighted;
+        let r = match s.search_pattern {
+          Some(_) => {
+            highlighted = SideBySideRow {
+              left_spans: highlight_matches(
+                &r.left_spans,
+                s.search_pattern,
+                s.palette.search_match,
+              ),
+              right_spans: highlight_matches(
+                &r.right_spans,
+                s.search_pattern,
+                s.palette.search_match,
+              ),
+              ..r.clone()
+            };
+            &highlighted
+          }
+          None => r,
+        };
         rendered.push(render_side_by_side_line(
           r,
           abs_idx == s.diff_cursor,
-          matches!(
-            marker_state,
-            Some(CommentState::HasUnresolved | CommentState::ResolvedOnly)
-          ),
-          matches!(marker_state, Some(CommentState::ResolvedOnly)),
+          (marker, marker_style),
           old_w,
           new_w,
           inner.width as usize,
+          s.palette,
         ));
       }
       RenderRow::Decorated(r) => {
-        let (marker, marker_style) = match marker_state {
-          Some(CommentState::HasUnresolved) => ("💬", Style::default().fg(Color::Yellow)),
-          Some(CommentState::ResolvedOnly) => ("✓ ", Style::default().fg(Color::Green)),
-          _ => ("  ", Style::default().fg(Color::Reset)),
-        };
-
         // Git status marker and line number
         let (git_marker, git_style) = match r.status {
-          crate::diff::LineStatus::Unchanged => (" ", Style::default().fg(Color::Reset)),
-          crate::diff::LineStatus::Added => ("+", Style::default().fg(Color::Green)),
-          crate::diff::LineStatus::Removed => ("-", Style::default().fg(Color::Red)),
-          crate::diff::LineStatus::Modified => ("~", Style::default().fg(Color::Yellow)),
+          crate::diff::LineStatus::Unchanged => (" ", Style::default()),
+          crate::diff::LineStatus::Added => ("+", s.palette.added),
+          crate::diff::LineStatus::Removed => ("-", s.palette.removed),
+          crate::diff::LineStatus::Modified => ("~", s.palette.modified),
         };
 
         let line_s = if r.line_number > 0 {
@@ -543,19 +667,39 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
         let mut spans: Vec<Span<'static>> = Vec::with_capacity(5 + r.spans.len());
         spans.push(Span::styled(marker.to_string(), marker_style));
         spans.push(Span::raw(" "));
-        spans.push(Span::styled(line_s, Style::default().fg(Color::DarkGray)));
+        spans.push(Span::styled(line_s, s.palette.muted));
         spans.push(Span::raw(" "));
         spans.push(Span::styled(git_marker.to_string(), git_style));
-        spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
-        spans.extend(r.spans.iter().cloned());
+        spans.push(Span::styled(" │ ", s.palette.muted));
+        spans.extend(highlight_matches(
+          &r.spans,
+          s.search_pattern,
+          s.palette.search_match,
+        ));
 
-        let mut style = Style::default();
+        let mut style = match r.status {
+          crate::diff::LineStatus::Added => s.palette.added_bg,
+          crate::diff::LineStatus::Removed => s.palette.removed_bg,
+          _ => Style::default(),
+        };
         if abs_idx == s.diff_cursor {
           style = style.add_modifier(Modifier::REVERSED);
         }
         rendered.push(Line::from(spans).style(style));
       }
     }
+
+    if s.viewed_rows.get(abs_idx).copied().unwrap_or(false)
+      && let Some(line) = rendered.last_mut()
+    {
+      line.style = line.style.patch(s.palette.reviewed);
+    }
+    if let Some((start, end)) = s.visual_range
+      && (start..=end).contains(&abs_idx)
+      && let Some(line) = rendered.last_mut()
+    {
+      line.style = line.style.patch(s.palette.selection);
+    }
   }
 
   let para = Paragraph::new(Text::from(rendered))
@@ -564,15 +708,68 @@ fn draw_diff(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   f.render_widget(para, inner);
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Splits `spans` so the matches of `pattern` can be styled on their own.
+fn highlight_matches(
+  spans: &[Span<'static>],
+  pattern: Option<&str>,
+  hit: Style,
+) -> Vec<Span<'static>> {
+  let Some(pattern) = pattern else {
+    return spans.to_vec();
+  };
+  let text: String = spans.iter().map(|s| s.content.as_ref()).collect();
+  let matches = crate::search::find(&text, pattern);
+  if matches.is_empty() {
+    return spans.to_vec();
+  }
+  let mut out = Vec::with_capacity(spans.len() + 2 * matches.len());
+  let mut at = 0;
+  for span in spans {
+    let end = at + span.content.len();
+    // Cut the span at every match boundary inside it.
+    let mut cuts = vec![at, end];
+    for m in &matches {
+      cuts.extend([m.start, m.end].into_iter().filter(|&c| c > at && c < end));
+    }
+    cuts.sort_unstable();
+    cuts.dedup();
+    for pair in cuts.windows(2) {
+      let (from, to) = (pair[0], pair[1]);
+      let style = if matches.iter().any(|m| m.start <= from && to <= m.end) {
+        span.style.patch(hit)
+      } else {
+        span.style
+      };
+      out.push(Span::styled(text[from..to].to_string(), style));
+    }
+    at = end;
+  }
+  out
+}
+
+/// Gutter marker for a diff row. Kept a fixed width (emoji are often 2 cells).
+fn comment_marker(
+  state: Option<CommentState>,
+  continued: Option<CommentState>,
+  palette: &Palette,
+) -> (&'static str, Style) {
+  match (state, continued) {
+    (Some(CommentState::HasUnresolved), _) => ("💬", palette.unresolved),
+    (Some(CommentState::ResolvedOnly), _) => ("✓ ", palette.resolved),
+    (_, Some(CommentState::HasUnresolved)) => ("┃ ", palette.unresolved),
+    (_, Some(CommentState::ResolvedOnly)) => ("┃ ", palette.resolved),
+    _ => ("  ", Style::default()),
+  }
+}
+
 fn render_side_by_side_line(
   row: &SideBySideRow,
   selected: bool,
-  has_comment: bool,
-  comment_resolved: bool,
+  (marker, marker_style): (&'static str, Style),
   old_w: usize,
   new_w: usize,
   total_width: usize,
+  palette: &Palette,
 ) -> Line<'static> {
   fn str_width(s: &str) -> usize {
     s.chars()
@@ -612,21 +809,13 @@ fn render_side_by_side_line(
     Span::styled(" ".repeat(width), style)
   }
 
-  let (marker, marker_style) = if has_comment && comment_resolved {
-    ("✓ ", Style::default().fg(Color::Green))
-  } else if has_comment {
-    ("💬", Style::default().fg(Color::Yellow))
-  } else {
-    ("  ", Style::default().fg(Color::Reset))
-  };
-
   let old_line_style = match row.left_kind {
-    Some(crate::diff::Kind::Remove) => Style::default().fg(Color::Red),
-    _ => Style::default().fg(Color::DarkGray),
+    Some(crate::diff::Kind::Remove) => palette.removed,
+    _ => palette.muted,
   };
   let new_line_style = match row.right_kind {
-    Some(crate::diff::Kind::Add) => Style::default().fg(Color::Green),
-    _ => Style::default().fg(Color::DarkGray),
+    Some(crate::diff::Kind::Add) => palette.added,
+    _ => palette.muted,
   };
 
   let old_s = row
@@ -638,8 +827,25 @@ fn render_side_by_side_line(
     .map(|n| format!("{n:>new_w$}"))
     .unwrap_or_else(|| " ".repeat(new_w));
 
-  let left_spans = row.left_spans.clone();
-  let right_spans = row.right_spans.clone();
+  // The line background goes under the code's own styles, so search matches stay visible.
+  let left_bg = match row.left_kind {
+    Some(crate::diff::Kind::Remove) => palette.removed_bg,
+    _ => Style::default(),
+  };
+  let right_bg = match row.right_kind {
+    Some(crate::diff::Kind::Add) => palette.added_bg,
+    _ => Style::default(),
+  };
+  let left_spans: Vec<Span<'static>> = row
+    .left_spans
+    .iter()
+    .map(|span| Span::styled(span.content.clone(), left_bg.patch(span.style)))
+    .collect();
+  let right_spans: Vec<Span<'static>> = row
+    .right_spans
+    .iter()
+    .map(|span| Span::styled(span.content.clone(), right_bg.patch(span.style)))
+    .collect();
 
   // Allocate fixed-width columns so the right side never "slides" into the left side.
   // Layout:
@@ -653,11 +859,11 @@ fn render_side_by_side_line(
 
   let (mut left_code, left_used) = spans_truncate_to_width(&left_spans, left_code_w);
   if left_used < left_code_w {
-    left_code.push(pad_spaces(left_code_w - left_used, Style::default()));
+    left_code.push(pad_spaces(left_code_w - left_used, left_bg));
   }
   let (mut right_code, right_used) = spans_truncate_to_width(&right_spans, right_code_w);
   if right_used < right_code_w {
-    right_code.push(pad_spaces(right_code_w - right_used, Style::default()));
+    right_code.push(pad_spaces(right_code_w - right_used, right_bg));
   }
 
   let mut spans: Vec<Span<'static>> = Vec::with_capacity(12 + left_code.len() + right_code.len());
@@ -668,7 +874,7 @@ fn render_side_by_side_line(
   spans.push(Span::raw(" "));
   spans.extend(left_code);
 
-  spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+  spans.push(Span::styled(" │ ", palette.muted));
 
   spans.push(Span::styled(new_s, new_line_style));
   spans.push(Span::raw(" "));
@@ -737,14 +943,10 @@ fn draw_diff_popup(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   // Create the popup
   let block = Block::default()
     .borders(Borders::ALL)
-    .border_style(Style::default().fg(Color::Cyan))
+    .border_style(s.palette.accent)
     .title(" Diff Hunk ")
-    .title_style(
-      Style::default()
-        .fg(Color::Cyan)
-        .add_modifier(Modifier::BOLD),
-    )
-    .style(Style::default().bg(Color::Black));
+    .title_style(s.palette.accent.add_modifier(Modifier::BOLD))
+    .style(s.palette.popup);
 
   let inner = block.inner(popup_area);
   f.render_widget(block, popup_area);
@@ -805,15 +1007,13 @@ fn draw_diff_popup(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
           .unwrap_or(&diff_line.text);
         lines.push(Line::from(vec![Span::styled(
           path.to_string(),
-          Style::default()
-            .fg(Color::Cyan)
-            .add_modifier(Modifier::BOLD),
+          s.palette.accent.add_modifier(Modifier::BOLD),
         )]));
       }
       crate::diff::Kind::HunkHeader => {
         lines.push(Line::from(vec![Span::styled(
           diff_line.text.clone(),
-          Style::default().fg(Color::Yellow),
+          s.palette.modified,
         )]));
       }
       crate::diff::Kind::Remove | crate::diff::Kind::Add | crate::diff::Kind::Context => {
@@ -821,12 +1021,12 @@ fn draw_diff_popup(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
 
         // Line number styling
         let old_line_style = match kind {
-          crate::diff::Kind::Remove => Style::default().fg(Color::Red),
-          _ => Style::default().fg(Color::DarkGray),
+          crate::diff::Kind::Remove => s.palette.removed,
+          _ => s.palette.muted,
         };
         let new_line_style = match kind {
-          crate::diff::Kind::Add => Style::default().fg(Color::Green),
-          _ => Style::default().fg(Color::DarkGray),
+          crate::diff::Kind::Add => s.palette.added,
+          _ => s.palette.muted,
         };
 
         let old_s = diff_line
@@ -845,20 +1045,19 @@ fn draw_diff_popup(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
           .unwrap_or(&diff_line.text);
 
         // Color the content based on change type
-        let content_color = match kind {
-          crate::diff::Kind::Add => Color::Green,
-          crate::diff::Kind::Remove => Color::Red,
-          crate::diff::Kind::Context => Color::Reset,
-          _ => Color::Reset,
+        let content_style = match kind {
+          crate::diff::Kind::Add => s.palette.added.patch(s.palette.added_bg),
+          crate::diff::Kind::Remove => s.palette.removed.patch(s.palette.removed_bg),
+          _ => Style::default(),
         };
 
         let spans: Vec<Span<'static>> = vec![
           Span::raw("  "), // No comment marker in popup
           Span::styled(old_s, old_line_style),
-          Span::styled(" ", Style::default().fg(Color::DarkGray)),
+          Span::styled(" ", s.palette.muted),
           Span::styled(new_s, new_line_style),
-          Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
-          Span::styled(content_text.to_string(), Style::default().fg(content_color)),
+          Span::styled(" │ ", s.palette.muted),
+          Span::styled(content_text.to_string(), content_style),
         ];
 
         lines.push(Line::from(spans));
@@ -880,6 +1079,8 @@ fn draw_footer(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
     (ViewKind::Staged, _) => "staged".to_string(),
     (ViewKind::Base, Some(b)) => format!("base({b})"),
     (ViewKind::Base, None) => "base(unset)".to_string(),
+    (ViewKind::Commit, _) => format!("commit({})", s.rev_label.as_deref().unwrap_or("unset")),
+    (ViewKind::Range, _) => format!("range({})", s.rev_label.as_deref().unwrap_or("unset")),
   };
 
   let diff_mode = match s.diff_view_mode {
@@ -888,7 +1089,28 @@ fn draw_footer(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
     DiffViewMode::Decorated => "decorated",
   };
 
+  let keymap = s.keymap;
   let mut left = match s.mode {
+    Mode::Browse if s.visual_range.is_some() => {
+      let s = format!(
+        "visual selection  ({})",
+        keymap.hint(&[
+          (Action::Comment, "comment on range"),
+          (Action::Cancel, "cancel"),
+        ])
+      );
+      fit_with_ellipsis(&s, area.width as usize)
+    }
+    Mode::Browse if s.reanchoring => {
+      let s = format!(
+        "re-anchor outdated comment  ({})",
+        keymap.hint(&[
+          (Action::PlaceComment, "place on this line"),
+          (Action::Cancel, "cancel"),
+        ])
+      );
+      fit_with_ellipsis(&s, area.width as usize)
+    }
     Mode::Browse => {
       // Intentionally minimal: rely on `?` for keybinding help.
       fit_with_ellipsis(
@@ -897,15 +1119,68 @@ fn draw_footer(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
       )
     }
     Mode::EditComment => {
-      let s = "comment editor  (Shift+Enter/Ctrl+S accept)  (Esc cancel)".to_string();
+      let s = format!(
+        "comment editor  ({})",
+        keymap.hint(&[
+          (Action::EditorAccept, "accept"),
+          (Action::EditorCancel, "cancel"),
+        ])
+      );
       fit_with_ellipsis(&s, area.width as usize)
     }
     Mode::EditPrompt => {
-      let s = "prompt editor  (Shift+Enter/Ctrl+S copy)  (Esc close)".to_string();
+      let s = format!("prompt editor  ({})", prompt_hint(keymap));
+      fit_with_ellipsis(&s, area.width as usize)
+    }
+    Mode::Search => {
+      let prompt = s.search_prompt.as_deref().unwrap_or("/");
+      let s = format!(
+        "{prompt}  ({})",
+        keymap.hint(&[
+          (Action::InputAccept, "search"),
+          (Action::InputCancel, "cancel"),
+        ])
+      );
+      fit_with_ellipsis(&s, area.width as usize)
+    }
+    Mode::FileFinder => {
+      let s = format!(
+        "find file  ({})",
+        keymap.hint(&[
+          (Action::FinderOpen, "open"),
+          (Action::FinderUp, "previous"),
+          (Action::FinderDown, "next"),
+          (Action::FinderCancel, "cancel"),
+        ])
+      );
+      fit_with_ellipsis(&s, area.width as usize)
+    }
+    Mode::FileFilter if s.filter_glob_input.is_some() => {
+      let s = format!(
+        "file filter glob  ({})",
+        keymap.hint(&[
+          (Action::InputAccept, "apply"),
+          (Action::InputCancel, "cancel"),
+        ])
+      );
+      fit_with_ellipsis(&s, area.width as usize)
+    }
+    Mode::FileFilter => {
+      let s = format!(
+        "file filter  ({})",
+        keymap.hint(&[
+          (Action::FilterUnreviewed, "unreviewed"),
+          (Action::FilterUnresolved, "unresolved"),
+          (Action::FilterChange, "change kind"),
+          (Action::FilterGlob, "glob"),
+          (Action::FilterClear, "clear"),
+          (Action::FilterClose, "close"),
+        ])
+      );
       fit_with_ellipsis(&s, area.width as usize)
     }
     Mode::CommentList => {
-      let s = "comment list  (Enter select, Shift+Enter jump, Shift+R resolve, Delete discard, Esc close)".to_string();
+      let s = format!("comment list  ({})", comment_list_hint(keymap));
       fit_with_ellipsis(&s, area.width as usize)
     }
   };
@@ -919,7 +1194,7 @@ fn draw_footer(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   }
 
   let para = Paragraph::new(left)
-    .style(Style::default().fg(Color::Gray))
+    .style(s.palette.footer)
     .alignment(Alignment::Left);
   f.render_widget(para, area);
 }
@@ -942,61 +1217,46 @@ fn fit_with_ellipsis(s: &str, width: usize) -> String {
   out
 }
 
-fn draw_help(f: &mut ratatui::Frame, area: Rect) {
-  let help = Text::from(vec![
-    Line::from("Focus"),
-    Line::from("  h / l or Left/Right   Switch between files and diff"),
-    Line::from(""),
-    Line::from("Views"),
-    Line::from("  1 / 2 / 3 / 4     All / Unstaged / Staged / Base"),
-    Line::from(""),
-    Line::from("Files"),
-    Line::from("  Up/Down, j/k      Select file"),
-    Line::from("  Ctrl+U / Ctrl+D   Page up / down"),
-    Line::from("  Enter             Move focus to diff"),
-    Line::from("  c                 Add/edit file comment"),
-    Line::from("  v                 Toggle reviewed"),
-    Line::from(""),
-    Line::from("Diff"),
-    Line::from("  Up/Down, j/k      Navigate diff"),
-    Line::from("  PgUp/Dn           Page up / down"),
-    Line::from("  Ctrl+U / Ctrl+D   Page up / down"),
-    Line::from("  Ctrl+N / Ctrl+P   Next/prev unreviewed file"),
-    Line::from("  n                 Next hunk"),
-    Line::from("  i                 Cycle view mode (decorated/side-by-side/unified)"),
-    Line::from("  H                 Show/hide diff popup"),
-    Line::from("  [ / ]             Less/more diff context"),
-    Line::from("  R                 Reload file list"),
-    Line::from("  c                 Add/edit comment (file or line)"),
-    Line::from("  d                 Delete comment (file or line)"),
-    Line::from("  r                 Resolve/unresolve comment"),
-    Line::from("  Shift+C           Open comment list"),
-    Line::from(""),
-    Line::from("Review"),
-    Line::from("  p                 Open prompt editor"),
-    Line::from("  q / Q             Quit"),
-    Line::from("  Esc               Dismiss overlay or quit"),
-    Line::from(""),
-    Line::from("Comment editor"),
-    Line::from("  Shift+Enter/Ctrl+S  Accept and close"),
-    Line::from("  Enter             Newline"),
-    Line::from("  Esc               Cancel"),
-    Line::from(""),
-    Line::from("Prompt editor"),
-    Line::from("  Shift+Enter/Ctrl+S  Copy prompt and close"),
-    Line::from("  Esc               Close prompt"),
-    Line::from(""),
-    Line::from("Comment list"),
-    Line::from("  Up/Down, j/k      Move selection"),
-    Line::from("  Enter             Select/unselect"),
-    Line::from("  Shift+Enter       Jump to location"),
-    Line::from("  Shift+R           Resolve file comments"),
-    Line::from("  Delete            Discard file comments"),
-    Line::from("  Esc               Close list"),
-    Line::from(""),
-    Line::from("Help"),
-    Line::from("  ? or Esc          Close this help"),
-  ]);
+/// The prompt editor's keys, for its footer and title.
+fn prompt_hint(keymap: &Keymap) -> String {
+  keymap.hint(&[(Action::PromptCopy, "copy"), (Action::PromptClose, "close")])
+}
+
+/// The comment list's keys, for its footer and title.
+fn comment_list_hint(keymap: &Keymap) -> String {
+  keymap.hint(&[
+    (Action::ListMark, "select"),
+    (Action::ListJump, "jump"),
+    (Action::ListResolveFiles, "resolve"),
+    (Action::ListDiscard, "discard"),
+    (Action::ListReanchor, "re-anchor"),
+    (Action::ListMarkChanged, "changed"),
+    (Action::ListResolveSelected, "resolve selected"),
+    (Action::ListFilter, "filter"),
+    (Action::ListClose, "close"),
+  ])
+}
+
+fn draw_help(f: &mut ratatui::Frame, area: Rect, keymap: &Keymap) {
+  let mut lines = vec![Line::from("Keys of the focused pane win over global keys.")];
+  for context in [
+    KeyContext::Global,
+    KeyContext::Files,
+    KeyContext::Diff,
+    KeyContext::CommentList,
+    KeyContext::CommentEditor,
+    KeyContext::PromptEditor,
+    KeyContext::Input,
+    KeyContext::FileFinder,
+    KeyContext::FileFilter,
+  ] {
+    lines.push(Line::from(""));
+    lines.push(Line::from(context.title()));
+    for (keys, help) in keymap.help(context) {
+      lines.push(Line::from(format!("  {keys:<18}{help}")));
+    }
+  }
+  let help = Text::from(lines);
 
   let popup = centered_rect(78, 80, area);
   f.render_widget(Clear, popup);
@@ -1013,31 +1273,44 @@ fn draw_prompt(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   let popup = prompt_popup_rect(area);
   f.render_widget(Clear, popup);
   let title = match s.mode {
-    Mode::EditPrompt => "LLM Prompt (editable)  (Shift+Enter/Ctrl+S copy, Esc close)".to_string(),
+    Mode::EditPrompt => format!("LLM Prompt (editable)  ({})", prompt_hint(s.keymap)),
     _ => "LLM Prompt Preview (collated)".to_string(),
   };
   let block = Block::default().borders(Borders::ALL).title(title);
   let inner = block.inner(popup);
   f.render_widget(block, popup);
-  render_wrapped_textarea(f, inner, s.prompt_buffer);
+  render_wrapped_textarea(f, inner, s.prompt_buffer, s.palette.cursor);
 }
 
 fn draw_comment_list(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   let popup = centered_rect(80, 80, area);
   f.render_widget(Clear, popup);
-  let block = Block::default().borders(Borders::ALL).title(
-    "Comments  (Enter select, Shift+Enter jump, Shift+R resolve, Delete discard, Esc close)",
-  );
+  let filter = match s.comment_list_severity {
+    Some(min) => format!(" [{}+]", min.as_str()),
+    None => String::new(),
+  };
+  let block = Block::default().borders(Borders::ALL).title(format!(
+    "Comments{filter}  ({})",
+    comment_list_hint(s.keymap)
+  ));
 
   let inner = block.inner(popup);
   let max_width = inner.width.max(1) as usize;
   let height = inner.height.max(1) as usize;
 
+  // Outdated entries come last; a header row separates them from the live comments.
+  let outdated_from = s.comment_list.iter().position(|entry| entry.outdated);
   let mut items = Vec::new();
   if s.comment_list.is_empty() {
     items.push(ListItem::new(Line::from("No comments.")));
   } else {
     for (idx, entry) in s.comment_list.iter().enumerate() {
+      if outdated_from == Some(idx) {
+        items.push(ListItem::new(Line::from(Span::styled(
+          "── Outdated ──",
+          s.palette.muted.add_modifier(Modifier::BOLD),
+        ))));
+      }
       let mark = if s.comment_list_marked.contains(&idx) {
         "[x]"
       } else {
@@ -1055,12 +1328,25 @@ fn draw_comment_list(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
         }
       };
       let preview = entry.body.lines().next().unwrap_or("").trim_end();
-      let mut line = format!("{mark} {status} {loc} {} - {preview}", entry.path);
+      let mut line = format!("{mark} {status} {loc} {} - ", entry.path);
+      if !entry.tags.is_empty() {
+        line.push_str(&format!("{} ", entry.tags));
+      }
+      line.push_str(preview);
+      if entry.replies > 0 {
+        line.push_str(&format!(" (+{} replies)", entry.replies));
+      }
+      if entry.has_suggestion {
+        line.push_str(" (suggestion)");
+      }
+      if !entry.resolution.is_empty() {
+        line.push_str(&format!(" (resolved: {})", entry.resolution));
+      }
       if line.len() > max_width {
         line.truncate(max_width);
       }
-      let style = if entry.resolved {
-        Style::default().fg(Color::DarkGray)
+      let style = if entry.resolved || entry.outdated {
+        s.palette.muted
       } else {
         Style::default()
       };
@@ -1071,10 +1357,10 @@ fn draw_comment_list(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   let selected = if s.comment_list.is_empty() {
     None
   } else {
-    Some(
-      s.comment_list_selected
-        .min(s.comment_list.len().saturating_sub(1)),
-    )
+    let sel = s
+      .comment_list_selected
+      .min(s.comment_list.len().saturating_sub(1));
+    Some(sel + usize::from(outdated_from.is_some_and(|from| sel >= from)))
   };
   let scroll = selected
     .map(|sel| sel.saturating_add(1).saturating_sub(height))
@@ -1088,11 +1374,131 @@ fn draw_comment_list(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
   f.render_stateful_widget(list, popup, &mut state);
 }
 
+fn draw_file_finder(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
+  let Some(finder) = s.file_finder else {
+    return;
+  };
+  let popup = centered_rect(70, 60, area);
+  f.render_widget(Clear, popup);
+  let block = Block::default().borders(Borders::ALL).title(format!(
+    "Find file ({}/{})",
+    finder.matches.len(),
+    s.listed_files
+  ));
+  let inner = block.inner(popup);
+  f.render_widget(block, popup);
+  if inner.height == 0 {
+    return;
+  }
+
+  let prompt = Line::from(vec![
+    Span::styled("> ", s.palette.accent),
+    Span::raw(finder.query.clone()),
+  ]);
+  f.render_widget(Paragraph::new(prompt), Rect { height: 1, ..inner });
+  let list_area = Rect {
+    y: inner.y + 1,
+    height: inner.height.saturating_sub(1),
+    ..inner
+  };
+
+  let items: Vec<ListItem> = if finder.matches.is_empty() {
+    vec![ListItem::new(Line::from(Span::styled(
+      "No matching files.",
+      s.palette.muted,
+    )))]
+  } else {
+    finder
+      .matches
+      .iter()
+      .map(|path| {
+        let hidden = !s.files.iter().any(|e| e.path == *path);
+        let mut spans = vec![Span::raw(path.clone())];
+        if hidden {
+          spans.push(Span::styled("  (filtered out)", s.palette.muted));
+        }
+        ListItem::new(Line::from(spans))
+      })
+      .collect()
+  };
+  let height = list_area.height.max(1) as usize;
+  let selected = (!finder.matches.is_empty()).then_some(finder.selected);
+  let scroll = selected
+    .map(|sel| sel.saturating_add(1).saturating_sub(height))
+    .unwrap_or(0);
+  let mut state = ListState::default()
+    .with_selected(selected)
+    .with_offset(scroll);
+  let list = List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+  f.render_stateful_widget(list, list_area, &mut state);
+}
+
+fn draw_file_filter(f: &mut ratatui::Frame, area: Rect, s: &DrawState<'_>) {
+  let filter = s.file_filter;
+  let check = |on: bool| if on { "[x]" } else { "[ ]" };
+  let change = match filter.change {
+    Some(kind) => crate::file_filter::change_label(kind),
+    None => "any",
+  };
+  let glob = match s.filter_glob_input {
+    Some(input) => format!("{input}▏"),
+    None => filter.glob().unwrap_or("none").to_string(),
+  };
+  let keys = |action| s.keymap.keys(action);
+  let text = Text::from(vec![
+    Line::from(format!(
+      "  {:<6} {} Only unreviewed files",
+      keys(Action::FilterUnreviewed),
+      check(filter.unreviewed)
+    )),
+    Line::from(format!(
+      "  {:<6} {} Only files with unresolved comments",
+      keys(Action::FilterUnresolved),
+      check(filter.unresolved)
+    )),
+    Line::from(format!(
+      "  {:<10} Change kind: {change}",
+      keys(Action::FilterChange)
+    )),
+    Line::from(format!("  {:<10} Glob: {glob}", keys(Action::FilterGlob))),
+    Line::from(format!(
+      "  {:<10} Clear all filters",
+      keys(Action::FilterClear)
+    )),
+    Line::from(""),
+    Line::from(Span::styled(
+      format!("  Showing {} of {} files", s.files.len(), s.listed_files),
+      s.palette.muted,
+    )),
+  ]);
+
+  let popup = centered_rect(60, 40, area);
+  f.render_widget(Clear, popup);
+  let block = Block::default().borders(Borders::ALL).title(format!(
+    "Filter files  ({})",
+    s.keymap.hint(&[(Action::FilterClose, "close")])
+  ));
+  let para = Paragraph::new(text).block(block);
+  f.render_widget(para, popup);
+}
+
 fn draw_comment_editor(f: &mut ratatui::Frame, diff_area: Rect, s: &DrawState<'_>) {
   let Some(target) = s.editor_target else {
     return;
   };
-  let popup_h = 6u16.min(diff_area.height.saturating_sub(2)).max(3);
+  let comment = match target.locator {
+    CommentLocator::File => s.review.file_comment(&target.path),
+    CommentLocator::Line { side, line } => s.review.line_comment(&target.path, side, line),
+  };
+  // When replying or resolving, the textarea does not hold the comment, so show it with the
+  // replies.
+  let thread = comment
+    .map(|c| thread_lines(c, s.editor_reply || s.editor_resolve, s.palette))
+    .unwrap_or_default();
+  let thread_h = (thread.len() as u16).min(diff_area.height.saturating_sub(8) / 2);
+  let popup_h = (6u16 + thread_h)
+    .min(diff_area.height.saturating_sub(2))
+    .max(3);
   let popup_w = (diff_area.width.saturating_sub(4)).clamp(20, 90);
 
   let inner = diff_area.inner(ratatui::layout::Margin {
@@ -1122,31 +1528,131 @@ fn draw_comment_editor(f: &mut ratatui::Frame, diff_area: Rect, s: &DrawState<'_
   };
 
   f.render_widget(Clear, popup);
+  let verb = if s.editor_reply {
+    "Reply to"
+  } else if s.editor_resolve {
+    "Resolve"
+  } else {
+    "Comment"
+  };
+  let accept = s.keymap.hint(&[(Action::EditorAccept, "accept")]);
   let title = match target.locator {
     CommentLocator::File => {
-      format!(
-        "Comment {} (file)  (Shift+Enter/Ctrl+S accept)",
-        target.path
-      )
+      format!("{verb} {} (file)  ({accept})", target.path)
     }
     CommentLocator::Line { side, line } => {
+      let last = s
+        .editor_range_end
+        .or_else(|| {
+          s.review
+            .line_comment(&target.path, side, line)
+            .and_then(|c| c.end_line)
+        })
+        .filter(|end| *end > line);
+      let lines = match last {
+        Some(last) => format!("{line}-{last}"),
+        None => line.to_string(),
+      };
       let side = match side {
         crate::review::LineSide::Old => "old",
         crate::review::LineSide::New => "new",
       };
-      format!(
-        "Comment {}:{} ({side})  (Shift+Enter/Ctrl+S accept)",
-        target.path, line
-      )
+      format!("{verb} {}:{lines} ({side})  ({accept})", target.path)
     }
   };
   let block = Block::default()
     .borders(Borders::ALL)
     .title(title)
-    .border_style(Style::default().fg(Color::Yellow));
+    .border_style(s.palette.editor);
   let inner = block.inner(popup);
   f.render_widget(block, popup);
-  render_wrapped_textarea(f, inner, s.editor_buffer);
+  if thread_h == 0 {
+    render_wrapped_textarea(f, inner, s.editor_buffer, s.palette.cursor);
+    return;
+  }
+
+  let chunks = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([Constraint::Length(thread_h), Constraint::Min(1)])
+    .split(inner);
+  // Keep the latest turns visible when the thread is taller than its pane.
+  let scroll = (thread.len() as u16).saturating_sub(thread_h);
+  f.render_widget(Paragraph::new(thread).scroll((scroll, 0)), chunks[0]);
+  render_wrapped_textarea(f, chunks[1], s.editor_buffer, s.palette.cursor);
+}
+
+fn thread_lines(
+  comment: &crate::review::Comment,
+  include_root: bool,
+  palette: &Palette,
+) -> Vec<Line<'static>> {
+  let byline_style = palette.muted;
+  let mut lines = Vec::new();
+  let push_turn =
+    |lines: &mut Vec<Line<'static>>, author: Option<&str>, at: Option<i64>, body: &str| {
+      let byline = match (author, at) {
+        (Some(author), Some(at)) => {
+          format!("{author} · {}", crate::review::format_timestamp(at))
+        }
+        (Some(author), None) => author.to_string(),
+        _ => "(unknown author)".to_string(),
+      };
+      lines.push(Line::from(Span::styled(byline, byline_style)));
+      for l in body.lines() {
+        lines.push(Line::from(format!("  {l}")));
+      }
+    };
+  if include_root {
+    push_turn(
+      &mut lines,
+      comment.author.as_deref(),
+      comment.created_at,
+      &comment.body,
+    );
+  }
+  if let Some(suggestion) = &comment.suggestion {
+    lines.push(Line::from(Span::styled(
+      "Suggested change (A to apply)",
+      byline_style,
+    )));
+    for l in &suggestion.original {
+      lines.push(Line::from(Span::styled(format!("  -{l}"), palette.removed)));
+    }
+    for l in &suggestion.replacement {
+      lines.push(Line::from(Span::styled(format!("  +{l}"), palette.added)));
+    }
+  }
+  for reply in &comment.replies {
+    push_turn(
+      &mut lines,
+      Some(reply.author.as_str()),
+      Some(reply.at),
+      &reply.body,
+    );
+  }
+  if let Some(resolution) = &comment.resolution {
+    lines.push(Line::from(Span::styled(
+      format!(
+        "Resolved by {} · {}",
+        resolution.author,
+        crate::review::format_timestamp(resolution.at)
+      ),
+      palette.resolved,
+    )));
+    for l in resolution.message.as_deref().unwrap_or_default().lines() {
+      lines.push(Line::from(format!("  {l}")));
+    }
+    if let Some(commit) = &resolution.commit {
+      lines.push(Line::from(format!(
+        "  in {}",
+        crate::review::short_commit(commit)
+      )));
+    }
+  }
+  if !lines.is_empty() {
+    lines.push(Line::from(Span::styled("─".repeat(20), byline_style)));
+  }
+  lines
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -1175,9 +1681,7 @@ pub fn textarea_from_string(text: &str) -> TextArea<'static> {
   } else {
     trimmed.split('\n').map(|l| l.to_string()).collect()
   };
-  let mut textarea = TextArea::new(lines);
-  configure_textarea(&mut textarea);
-  textarea
+  TextArea::new(lines)
 }
 
 pub fn empty_textarea() -> TextArea<'static> {
@@ -1188,15 +1692,15 @@ pub fn textarea_contents(textarea: &TextArea<'_>) -> String {
   textarea.lines().join("\n")
 }
 
-fn configure_textarea(textarea: &mut TextArea<'static>) {
-  textarea.set_cursor_line_style(Style::default().bg(Color::DarkGray));
-  textarea.set_cursor_style(Style::default().fg(Color::Black).bg(Color::Yellow));
-}
-
-fn render_wrapped_textarea(f: &mut ratatui::Frame, area: Rect, textarea: &TextArea<'_>) {
+fn render_wrapped_textarea(
+  f: &mut ratatui::Frame,
+  area: Rect,
+  textarea: &TextArea<'_>,
+  cursor_style: Style,
+) {
   let width = area.width.max(1);
   let height = area.height.max(1) as usize;
-  let (lines, cursor_row, cursor_col) = wrap_textarea_lines(textarea, width);
+  let (lines, cursor_row, cursor_col) = wrap_textarea_lines(textarea, width, cursor_style);
 
   let mut scroll = 0usize;
   if cursor_row >= scroll + height {
@@ -1226,10 +1730,13 @@ fn render_wrapped_textarea(f: &mut ratatui::Frame, area: Rect, textarea: &TextAr
   }
 }
 
-fn wrap_textarea_lines(textarea: &TextArea<'_>, width: u16) -> (Vec<Line<'static>>, usize, usize) {
+fn wrap_textarea_lines(
+  textarea: &TextArea<'_>,
+  width: u16,
+  cursor_style: Style,
+) -> (Vec<Line<'static>>, usize, usize) {
   let width = width.max(1) as usize;
   let ratatui_textarea::DataCursor(cursor_row, cursor_col) = textarea.cursor();
-  let cursor_style = textarea.cursor_style();
 
   let mut out: Vec<Line<'static>> = Vec::new();
   let mut spans: Vec<Span<'static>> = Vec::new();